@@ -0,0 +1,575 @@
+//! The binary document store: the second on-disk form, beside the
+//! JSON one, for documents whose weight is in blobs — a CAD mesh
+//! hex-encoded and rewritten whole on every save is the case it
+//! exists for. Blobs are stored raw, cells are addressed by their
+//! 16-byte id, and a save APPENDS only what changed since the file
+//! was last read or written.
+//!
+//! The file is a header (magic, then the format as a little-endian
+//! u32) followed by frames. Each frame is a length, a payload of
+//! records, and an FNV-1a checksum of the payload; reading applies
+//! the frames in order from the empty document. The first frame is
+//! the BASE — the whole document, as compaction writes it — and the
+//! rest is the LOG of amendments. Once the log outweighs the base,
+//! the next save compacts: the document is rewritten as one frame,
+//! write-then-rename, as the JSON store does.
+//!
+//! An append is not atomic, so a torn FINAL frame reads as the save
+//! that never happened: the file says what its last complete frame
+//! says. A bad frame anywhere else is damage, and refuses: one whose
+//! end lies inside the file, or one followed by a complete frame that
+//! runs to the file's end — a length corrupted past the end must not
+//! pass for a tear and silently drop the frames after it. The base is
+//! only ever written by rename, so it is never torn.
+//!
+//! Lists are written as sequences; positions are session-only and
+//! are minted afresh at read, as JSON loading does.
+
+use crate::raw::Document;
+use crate::store::LibraryRef;
+use progred_graph::{Atom, CellId, Cells, Label, Value};
+use std::io::Write;
+use std::path::Path;
+
+/// Sniffed by the store ahead of the format. The high first byte
+/// keeps it from ever reading as text — a JSON document cannot
+/// start with it.
+pub const MAGIC: &[u8; 8] = b"\x89PRGRD\r\n";
+pub const FORMAT: u32 = 2;
+
+const HEADER: usize = MAGIC.len() + 4;
+
+// Records, the payload of a frame. A frame may hold any number, in
+// any order; each states one part of the document outright. A cell's
+// name and value are separate records, so renaming a mesh doesn't
+// rewrite it.
+const ROOT: u8 = 1;
+const LIBRARIES: u8 = 2;
+const NAME: u8 = 3;
+const VALUE: u8 = 4;
+const GONE: u8 = 5;
+
+// Value and label tags.
+const LINK: u8 = 0;
+const STRING: u8 = 1;
+const BLOB: u8 = 2;
+const LIST: u8 = 3;
+const RECORD: u8 = 4;
+
+/// What a binary file on disk says, and where its frames end — the
+/// base the next save diffs against. Held beside the document path;
+/// clones are O(1), as the document's are.
+#[derive(Clone, Debug)]
+pub struct Journal {
+    written: Document,
+    /// The file's readable length. A file of any other length has
+    /// been touched since (or ends in a torn frame), and the next
+    /// save rewrites it rather than append after the unknown.
+    len: u64,
+    /// Bytes through the base frame; the rest of `len` is the log.
+    base: u64,
+}
+
+pub fn read(bytes: &[u8]) -> Result<(Document, Journal), String> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("not a binary document")?;
+    let format = rest
+        .first_chunk::<4>()
+        .map(|format| u32::from_le_bytes(*format))
+        .ok_or("truncated header")?;
+    if format != FORMAT {
+        return Err(format!("binary format {format} (this build reads {FORMAT})"));
+    }
+    let mut doc = empty();
+    let mut at = HEADER;
+    let mut base = None;
+    while at < bytes.len() {
+        match frame(bytes, at) {
+            Some((payload, end)) => {
+                apply(&mut doc, payload).map_err(|error| format!("at byte {at}: {error}"))?;
+                base.get_or_insert(end as u64);
+                at = end;
+            }
+            // Bytes after a bad frame are damage, not a torn append.
+            None if frame_end(bytes, at).is_some_and(|end| end < bytes.len())
+                || ends_in_a_frame(bytes, at) =>
+            {
+                return Err(format!("damaged frame at byte {at}"));
+            }
+            None => break,
+        }
+    }
+    let base = base.ok_or("no complete frame")?;
+    let journal = Journal {
+        written: doc.clone(),
+        len: at as u64,
+        base,
+    };
+    Ok((doc, journal))
+}
+
+/// Writes `doc` at `path`: appends what changed since `journal` when
+/// the file is still the one it describes and the log stays lighter
+/// than the base, and compacts otherwise — which is also how a
+/// document first becomes binary (`journal` None). A save that
+/// changed nothing writes nothing.
+pub fn write(path: &Path, doc: &Document, journal: Option<&Journal>) -> Result<Journal, String> {
+    let current = journal.filter(|journal| {
+        std::fs::metadata(path).is_ok_and(|meta| meta.len() == journal.len)
+    });
+    if let Some(journal) = current {
+        let mut payload = Vec::new();
+        changes(&mut payload, &journal.written, doc);
+        if payload.is_empty() {
+            return Ok(journal.clone());
+        }
+        let framed = framed(&payload)?;
+        let len = journal.len + framed.len() as u64;
+        if len - journal.base <= journal.base - HEADER as u64 {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(|error| error.to_string())?;
+            file.write_all(&framed).map_err(|error| error.to_string())?;
+            file.sync_data().map_err(|error| error.to_string())?;
+            return Ok(Journal {
+                written: doc.clone(),
+                len,
+                base: journal.base,
+            });
+        }
+    }
+    compact(path, doc)
+}
+
+fn compact(path: &Path, doc: &Document) -> Result<Journal, String> {
+    let mut payload = Vec::new();
+    changes(&mut payload, &empty(), doc);
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT.to_le_bytes());
+    bytes.extend_from_slice(&framed(&payload)?);
+    let tmp = path.with_extension("progredb.tmp");
+    std::fs::write(&tmp, &bytes).map_err(|error| error.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|error| error.to_string())?;
+    Ok(Journal {
+        written: doc.clone(),
+        len: bytes.len() as u64,
+        base: bytes.len() as u64,
+    })
+}
+
+fn empty() -> Document {
+    Document {
+        root: None,
+        cells: Cells::new(),
+        libraries: Vec::new(),
+    }
+}
+
+/// The records taking `from` to `to`, cells in id order so the same
+/// document always writes the same bytes. Entries are compared, not
+/// tracked: a table that shares its root with the last save is
+/// skipped outright, and otherwise comparing is far cheaper than
+/// writing what didn't change.
+fn changes(out: &mut Vec<u8>, from: &Document, to: &Document) {
+    if from.root != to.root {
+        out.push(ROOT);
+        optional(out, to.root.as_ref(), value);
+    }
+    if from.libraries != to.libraries {
+        out.push(LIBRARIES);
+        length(out, to.libraries.len());
+        for library in &to.libraries {
+            string(out, &library.path);
+            optional(out, library.hash.as_deref(), string);
+        }
+    }
+    if from.cells.ptr_eq(&to.cells) {
+        return;
+    }
+    let mut stated: Vec<_> = to
+        .cells
+        .iter()
+        .filter(|(cell, entry)| from.cells.entry(**cell) != Some(*entry))
+        .collect();
+    stated.sort_by_key(|(cell, _)| **cell);
+    for (cell, entry) in stated {
+        let held = from.cells.entry(*cell);
+        if held.and_then(|held| held.name()) != entry.name() {
+            out.push(NAME);
+            out.extend_from_slice(cell.as_bytes());
+            optional(out, entry.name(), string);
+        }
+        if held.and_then(|held| held.value()) != entry.value() {
+            out.push(VALUE);
+            out.extend_from_slice(cell.as_bytes());
+            optional(out, entry.value(), value);
+        }
+    }
+    let mut gone: Vec<_> = from
+        .cells
+        .cells()
+        .filter(|cell| to.cells.entry(**cell).is_none())
+        .collect();
+    gone.sort();
+    for cell in gone {
+        out.push(GONE);
+        out.extend_from_slice(cell.as_bytes());
+    }
+}
+
+fn framed(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(payload.len() + 12);
+    out.extend_from_slice(&frame_len(payload.len())?);
+    out.extend_from_slice(payload);
+    out.extend_from_slice(&crate::store::fnv1a64(payload).to_le_bytes());
+    Ok(out)
+}
+
+/// A frame's length prefix. Refuses a payload past the prefix's 4 GiB
+/// rather than truncate it: the reader would take the short length
+/// for a torn tail and drop the document.
+fn frame_len(len: usize) -> Result<[u8; 4], String> {
+    u32::try_from(len)
+        .map(u32::to_le_bytes)
+        .map_err(|_| format!("a {len}-byte frame is past the format's 4 GiB limit"))
+}
+
+fn frame_end(bytes: &[u8], at: usize) -> Option<usize> {
+    let len = bytes.get(at..)?.first_chunk::<4>()?;
+    Some(at + 4 + u32::from_le_bytes(*len) as usize + 8)
+}
+
+/// The payload of the frame at `at` and where it ends; None when the
+/// frame runs past the file or fails its checksum.
+fn frame(bytes: &[u8], at: usize) -> Option<(&[u8], usize)> {
+    let end = frame_end(bytes, at)?;
+    let payload = bytes.get(at + 4..end - 8)?;
+    let sum = bytes.get(end - 8..end)?.first_chunk::<8>()?;
+    (crate::store::fnv1a64(payload) == u64::from_le_bytes(*sum)).then_some((payload, end))
+}
+
+/// Whether a complete frame starting past `at` ends exactly where the
+/// file does. An append only ever tears the last frame, so a bad frame
+/// this follows is damage whatever its length says. Only a start
+/// whose length lands on the end is checksummed, so the scan is one
+/// pass.
+fn ends_in_a_frame(bytes: &[u8], at: usize) -> bool {
+    (at + 1..bytes.len())
+        .any(|start| frame_end(bytes, start) == Some(bytes.len()) && frame(bytes, start).is_some())
+}
+
+fn apply(doc: &mut Document, payload: &[u8]) -> Result<(), String> {
+    let mut input = Input { bytes: payload };
+    while !input.bytes.is_empty() {
+        match input.byte()? {
+            ROOT => doc.root = input.optional(Input::value)?,
+            LIBRARIES => {
+                let count = input.length()?;
+                doc.libraries = (0..count)
+                    .map(|_| {
+                        Ok(LibraryRef {
+                            path: input.string()?,
+                            hash: input.optional(Input::string)?,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?;
+            }
+            NAME => {
+                let cell = input.cell()?;
+                match input.optional(Input::string)? {
+                    // Strict, as the JSON table's reads are: parsable
+                    // means canonical.
+                    Some(name) if name.is_empty() => {
+                        return Err("an empty name is spelled by omission".into());
+                    }
+                    name => doc.cells.set_name(cell, name.as_deref().unwrap_or("")),
+                }
+            }
+            VALUE => {
+                let cell = input.cell()?;
+                match input.optional(Input::value)? {
+                    Some(value) => doc.cells.set_value(cell, value),
+                    None => doc.cells.clear_value(cell),
+                }
+            }
+            GONE => doc.cells.remove(input.cell()?),
+            tag => return Err(format!("unknown record {tag}")),
+        }
+    }
+    Ok(())
+}
+
+fn length(out: &mut Vec<u8>, mut n: usize) {
+    // LEB128: seven bits a byte, high bit set on all but the last.
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn string(out: &mut Vec<u8>, s: &str) {
+    length(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn optional<T: ?Sized>(out: &mut Vec<u8>, item: Option<&T>, write: fn(&mut Vec<u8>, &T)) {
+    match item {
+        None => out.push(0),
+        Some(item) => {
+            out.push(1);
+            write(out, item);
+        }
+    }
+}
+
+fn label(out: &mut Vec<u8>, label: &Label) {
+    match label {
+        Label::Cell(cell) => {
+            out.push(LINK);
+            out.extend_from_slice(cell.as_bytes());
+        }
+        Label::String(s) => {
+            out.push(STRING);
+            string(out, s);
+        }
+    }
+}
+
+fn value(out: &mut Vec<u8>, v: &Value) {
+    match v {
+        Value::Atom(Atom::Cell(cell)) => {
+            out.push(LINK);
+            out.extend_from_slice(cell.as_bytes());
+        }
+        Value::Atom(Atom::String(s)) => {
+            out.push(STRING);
+            string(out, s);
+        }
+        Value::Atom(Atom::Blob(bytes)) => {
+            out.push(BLOB);
+            length(out, bytes.len());
+            out.extend_from_slice(bytes);
+        }
+        Value::List(elements) => {
+            out.push(LIST);
+            length(out, elements.len());
+            for element in elements.values() {
+                value(out, element);
+            }
+        }
+        Value::Record(fields) => {
+            out.push(RECORD);
+            length(out, fields.len());
+            for (key, field) in fields {
+                label(out, key);
+                value(out, field);
+            }
+        }
+    }
+}
+
+/// A cursor over a payload; every read consumes.
+struct Input<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if n > self.bytes.len() {
+            return Err("record runs past its frame".into());
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        let mut n = 0_usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            n |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err("length overflows".into())
+    }
+
+    fn cell(&mut self) -> Result<CellId, String> {
+        CellId::from_slice(self.take(16)?).map_err(|error| error.to_string())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let n = self.length()?;
+        String::from_utf8(self.take(n)?.to_vec()).map_err(|error| error.to_string())
+    }
+
+    fn optional<T>(
+        &mut self,
+        read: fn(&mut Self) -> Result<T, String>,
+    ) -> Result<Option<T>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            tag => Err(format!("unknown option tag {tag}")),
+        }
+    }
+
+    fn label(&mut self) -> Result<Label, String> {
+        match self.byte()? {
+            LINK => self.cell().map(Label::Cell),
+            STRING => self.string().map(Label::String),
+            tag => Err(format!("unknown label tag {tag}")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.byte()? {
+            LINK => self.cell().map(Value::from),
+            STRING => self.string().map(Value::from),
+            BLOB => {
+                let n = self.length()?;
+                Ok(Value::from(self.take(n)?.to_vec()))
+            }
+            LIST => {
+                let n = self.length()?;
+                let elements = (0..n).map(|_| self.value()).collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::list(elements))
+            }
+            RECORD => {
+                let n = self.length()?;
+                let fields = (0..n)
+                    .map(|_| Ok((self.label()?, self.value()?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(Value::record(fields))
+            }
+            tag => Err(format!("unknown value tag {tag}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::new_cell_id;
+
+    fn temp(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("progred-binary-{name}-{}.progredb", std::process::id()))
+    }
+
+    fn same(a: &Document, b: &Document) -> bool {
+        serde_json::to_string(a).unwrap() == serde_json::to_string(b).unwrap()
+    }
+
+    #[test]
+    fn blobs_are_raw_and_saves_append_only_what_changed() {
+        let path = temp("append");
+        let mesh: Vec<u8> = (0..4096_u32).map(|i| (i * 7 % 251) as u8).collect();
+        let part = new_cell_id();
+        let mut doc = crate::raw::sample_document();
+        doc.cells.set_name(part, "mesh");
+        doc.cells.set_value(part, Value::from(mesh.clone()));
+        let journal = write(&path, &doc, None).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.windows(mesh.len()).any(|window| window == mesh.as_slice()));
+        let (read_back, _) = read(&bytes).unwrap();
+        assert!(same(&read_back, &doc));
+
+        // Renaming one cell appends a frame holding just its new name —
+        // the mesh isn't rewritten.
+        let before = bytes.len();
+        doc.cells.set_name(part, "hull");
+        let journal = write(&path, &doc, Some(&journal)).unwrap();
+        let grown = std::fs::read(&path).unwrap();
+        assert!(grown.len() > before && grown.len() - before < 64);
+        assert_eq!(&grown[..before], &bytes[..]);
+        // Unchanged, nothing is written.
+        let journal = write(&path, &doc, Some(&journal)).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap().len(), grown.len());
+
+        // Removal and a new root amend too, and read back in order.
+        doc.cells.remove(part);
+        doc.root = Some(Value::from("replaced"));
+        let journal = write(&path, &doc, Some(&journal)).unwrap();
+        let (read_back, _) = read(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(same(&read_back, &doc));
+
+        // Once the log outweighs the base, the save compacts: the mesh
+        // is gone by then, so the rewritten file is smaller than the
+        // one that first held it.
+        let other = new_cell_id();
+        let mut journal = journal;
+        for i in 0.. {
+            doc.cells.set_name(other, &format!("draft {i}"));
+            journal = write(&path, &doc, Some(&journal)).unwrap();
+            if journal.len == journal.base {
+                break;
+            }
+            assert!(i < 1000, "the log never compacted");
+        }
+        let compacted = std::fs::read(&path).unwrap();
+        assert!(compacted.len() < before);
+        let (read_back, reread) = read(&compacted).unwrap();
+        assert!(same(&read_back, &doc));
+        assert_eq!(reread.len, journal.len);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn a_torn_final_frame_is_the_save_that_never_happened() {
+        let path = temp("torn");
+        let mut doc = crate::raw::sample_document();
+        let journal = write(&path, &doc, None).unwrap();
+        let saved = doc.clone();
+        let cell = new_cell_id();
+        doc.cells.set_name(cell, "late");
+        write(&path, &doc, Some(&journal)).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let torn = &bytes[..bytes.len() - 3];
+        let (read_back, journal) = read(torn).unwrap();
+        assert!(same(&read_back, &saved));
+        assert_eq!(journal.len, journal.base);
+        // The torn tail is never appended after: the next save
+        // rewrites the file whole.
+        std::fs::write(&path, torn).unwrap();
+        let journal = write(&path, &doc, Some(&journal)).unwrap();
+        assert_eq!(journal.len, journal.base);
+        let (read_back, _) = read(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(read_back.cells.name(cell), Some("late"));
+
+        // Damage with a frame after it refuses, as does a future format.
+        let mut damaged = bytes.clone();
+        damaged[HEADER + 6] ^= 0xff;
+        assert!(read(&damaged).unwrap_err().contains("damaged"));
+        let mut future = bytes.clone();
+        future[MAGIC.len()..HEADER].copy_from_slice(&7_u32.to_le_bytes());
+        assert!(read(&future).unwrap_err().contains("format 7"));
+
+        // A middle frame whose length is corrupted to run past the end
+        // is damage too, not a tear: the frame after it is complete.
+        doc.cells.set_name(cell, "later");
+        let journal = read(&bytes).unwrap().1;
+        std::fs::write(&path, &bytes).unwrap();
+        write(&path, &doc, Some(&journal)).unwrap();
+        let mut damaged = std::fs::read(&path).unwrap();
+        let middle = journal.base as usize;
+        damaged[middle..middle + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read(&damaged).unwrap_err().contains("damaged frame"));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn a_frame_past_the_length_prefix_refuses_to_write() {
+        let limit = u32::MAX as usize;
+        assert_eq!(frame_len(limit).unwrap(), u32::MAX.to_le_bytes());
+        assert!(frame_len(limit + 1).unwrap_err().contains("4 GiB"));
+    }
+}
@@ -0,0 +1,470 @@
+//! The structural checker: where a document that follows the schema
+//! conventions ([`crate::schema`]) doesn't. One pass over [`Sources`]
+//! from the root, then from every valued cell the root never reaches
+//! — floating definitions are checked too, just addressed from their
+//! own cell. Every finding is keyed by the path it sits at, so the
+//! projection can underline the offending row and the problems list
+//! can select it.
+//!
+//! The pass never blocks anything. Schema data that doesn't read is
+//! no type, untyped slots are never wrong, and each cell is walked
+//! ONCE — at the first path that reaches it — so cycles through
+//! `Follow` end where the projection's cycle re-entry does, and a
+//! cell shared between two typed slots answers to the first.
+
+use crate::conventions::{Names, display_name};
+use crate::panel::{Border, HOVER, ground};
+use crate::raw::{Path, RawStyles, short_id};
+use crate::schema::{self, Type};
+use crate::sources::Sources;
+use progred_graph::{Atom, CellId, Label, Step, Value};
+use puri::draw::Canvas;
+use puri::handler::HasHandler;
+use puri::layout::{HAlign, Node, col, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::collections::HashSet;
+use std::rc::Rc;
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect};
+use vello::peniko::Color;
+
+/// What is wrong at a diagnostic's path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The record's type requires this field and it is absent.
+    MissingField(CellId),
+    /// The record's type has no field by this label.
+    UnexpectedLabel(Label),
+    /// The value is not of the type the slot expects: a string where
+    /// a link to a record belongs, a blob where a list does.
+    WrongKind(Type),
+    /// A link to a cell that was never given a value.
+    RedLink(CellId),
+    /// Something outside the document gone wrong, as it was reported:
+    /// a declared library missing, drifted, or contradicting another,
+    /// or a settings file that didn't read. Not at any path — the
+    /// document opened regardless.
+    Reported(String),
+}
+
+/// One finding. `path` is from the root when `origin` is `None`;
+/// otherwise from the value of `origin`, a cell the root never
+/// reaches (and so one only the graph can show).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub origin: Option<CellId>,
+    pub path: Path,
+    pub problem: Problem,
+}
+
+/// Every diagnostic in the sources, the root's first, then the
+/// floating cells' in id order. Library cells are the library's
+/// business: links into them are checked, their values are not.
+pub fn check(sources: &Sources) -> Vec<Diagnostic> {
+    let mut walk = Walk {
+        sources,
+        seen: HashSet::new(),
+        origin: None,
+        out: Vec::new(),
+    };
+    if let Some(root) = sources.root() {
+        walk.value(&mut Vec::new(), root, None);
+    }
+    let mut floating: Vec<CellId> = sources.doc.cells.cells().copied().collect();
+    floating.sort();
+    for cell in floating {
+        let Some(value) = sources.doc.cells.value(cell) else {
+            continue;
+        };
+        if walk.seen.insert(cell) {
+            walk.origin = Some(cell);
+            walk.value(&mut Vec::new(), value, None);
+        }
+    }
+    walk.out
+}
+
+/// Problems reported from outside the document as diagnostics, to
+/// list before the document's own.
+pub fn reported<'a>(problems: impl IntoIterator<Item = &'a String>) -> Vec<Diagnostic> {
+    (problems.into_iter())
+        .map(|problem| Diagnostic {
+            origin: None,
+            path: Vec::new(),
+            problem: Problem::Reported(problem.clone()),
+        })
+        .collect()
+}
+
+/// The root paths to underline: every rooted diagnostic's.
+pub fn flagged(diagnostics: &[Diagnostic]) -> HashSet<Path> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.origin.is_none())
+        .filter(|diagnostic| !matches!(diagnostic.problem, Problem::Reported(_)))
+        .map(|diagnostic| diagnostic.path.clone())
+        .collect()
+}
+
+struct Walk<'a, 's> {
+    sources: &'a Sources<'s>,
+    seen: HashSet<CellId>,
+    origin: Option<CellId>,
+    out: Vec<Diagnostic>,
+}
+
+impl Walk<'_, '_> {
+    fn report(&mut self, path: &[Step], problem: Problem) {
+        self.out.push(Diagnostic {
+            origin: self.origin,
+            path: path.to_vec(),
+            problem,
+        });
+    }
+
+    fn value(&mut self, path: &mut Path, value: &Value, expected: Option<&Type>) {
+        let sources = self.sources;
+        if let Value::Atom(Atom::Cell(cell)) = value {
+            // A link is its cell: checked once, at the Follow step of
+            // the first path to reach it. Cells not walked here — the
+            // library's, or ones already seen — still answer for
+            // their kind at the link.
+            let Some(held) = sources.value(*cell) else {
+                if !schema::vocabulary_cell(*cell) {
+                    self.report(path, Problem::RedLink(*cell));
+                }
+                return;
+            };
+            if !sources.external(*cell) && self.seen.insert(*cell) {
+                path.push(Step::Follow);
+                self.value(path, held, expected);
+                path.pop();
+            } else if let Some(ty) = expected
+                && !schema::conforms(sources, value, ty)
+            {
+                self.report(path, Problem::WrongKind(ty.clone()));
+            }
+            return;
+        }
+        let fits = expected.is_none_or(|ty| schema::conforms(sources, value, ty));
+        if let (false, Some(ty)) = (fits, expected) {
+            self.report(path, Problem::WrongKind(ty.clone()));
+        }
+        match value {
+            Value::Atom(_) => {}
+            Value::List(elements) => {
+                let element = match (fits, expected) {
+                    (true, Some(Type::List(Some(element)))) => Some(&**element),
+                    _ => None,
+                };
+                for (position, item) in elements {
+                    path.push(Step::Element(position.clone()));
+                    self.value(path, item, element);
+                    path.pop();
+                }
+            }
+            Value::Record(fields) => {
+                // The record's own tag decides its fields, whatever
+                // the slot expected — a wrong tag is already reported.
+                let known = schema::isa(sources, value)
+                    .filter(|tag| {
+                        matches!(
+                            schema::read(sources, &Value::from(*tag)),
+                            Some(Type::Record(_))
+                        )
+                    })
+                    .map(|tag| schema::fields(sources, tag));
+                if let Some(known) = &known {
+                    for field in known {
+                        if field.required && !fields.contains_key(&Label::Cell(field.label)) {
+                            self.report(path, Problem::MissingField(field.label));
+                        }
+                    }
+                }
+                for (label, item) in fields {
+                    path.push(Step::Key(label.clone()));
+                    let ty = match (&known, label) {
+                        (_, Label::Cell(schema::ISA)) | (None, _) => None,
+                        (Some(known), label) => {
+                            match known.iter().find(|field| Label::Cell(field.label) == *label) {
+                                Some(field) => field.ty.as_ref(),
+                                None => {
+                                    self.report(path, Problem::UnexpectedLabel(label.clone()));
+                                    None
+                                }
+                            }
+                        }
+                    };
+                    self.value(path, item, ty);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// A diagnostic in words: what, and where — the keys and list
+/// indices down from its origin.
+pub fn message(sources: &Sources, names: &Names, raw: bool, diagnostic: &Diagnostic) -> String {
+    let name = |cell: CellId| display_name(sources, names, raw, cell).unwrap_or(short_id(cell));
+    let what = match &diagnostic.problem {
+        Problem::MissingField(field) => format!("missing {}", name(*field)),
+        Problem::UnexpectedLabel(Label::Cell(field)) => format!("unexpected {}", name(*field)),
+        Problem::UnexpectedLabel(Label::String(label)) => format!("unexpected \"{label}\""),
+        Problem::WrongKind(ty) => format!("expected {}", schema::describe(sources, ty)),
+        Problem::RedLink(cell) => format!("red link {}", name(*cell)),
+        Problem::Reported(problem) => return problem.clone(),
+    };
+    let origin = match diagnostic.origin {
+        Some(cell) => name(cell),
+        None => "root".to_string(),
+    };
+    let value = match diagnostic.origin {
+        Some(cell) => sources.value(cell),
+        None => sources.root(),
+    };
+    let at = trail(sources, names, raw, value, &diagnostic.path);
+    format!("{what} — {origin}{at}")
+}
+
+/// A path in words, down from `value`: " › key › [index]" per step,
+/// links passed through silently — how rows say where a site is.
+pub fn trail<'a>(
+    sources: &Sources<'a>,
+    names: &Names,
+    raw: bool,
+    mut value: Option<&'a Value>,
+    path: &[Step],
+) -> String {
+    let name = |cell: CellId| display_name(sources, names, raw, cell).unwrap_or(short_id(cell));
+    let mut at = String::new();
+    for step in path {
+        match step {
+            Step::Key(Label::Cell(field)) => at.push_str(&format!(" › {}", name(*field))),
+            Step::Key(Label::String(label)) => at.push_str(&format!(" › {label}")),
+            Step::Element(position) => {
+                let index = value
+                    .and_then(Value::as_list)
+                    .and_then(|list| list.keys().position(|key| key == position));
+                match index {
+                    Some(index) => at.push_str(&format!(" › [{index}]")),
+                    None => at.push_str(" › […]"),
+                }
+            }
+            Step::Follow | Step::Name => {}
+        }
+        value = match step {
+            Step::Follow => value.and_then(Value::as_cell).and_then(|cell| sources.value(cell)),
+            Step::Key(label) => value.and_then(Value::as_record).and_then(|r| r.get(label)),
+            Step::Element(position) => value.and_then(Value::as_list).and_then(|l| l.get(position)),
+            Step::Name => None,
+        };
+    }
+    at
+}
+
+/// The pane's window rectangle: a strip along the bottom of the
+/// document, under the body only — the side panels keep their height.
+pub fn panel(height: f64, right: f64) -> Rect {
+    Rect::new(0.0, (height * 0.78).round(), right, height)
+}
+
+/// Dispatch-time callbacks the shell injects: the strip reports a
+/// row clicked or hovered, and the shell acts on it.
+pub struct Hooks<C> {
+    pub jump: Rc<dyn Fn(&mut C, Diagnostic)>,
+    /// The pointer's resting claim inside the panel: the row under
+    /// it, or `None` for the pane's own ground.
+    pub hover: Rc<dyn Fn(&mut C, Option<usize>)>,
+}
+
+/// The underline's red, shared with the projection's.
+pub const FLAG: [f32; 4] = [0.86, 0.2, 0.18, 0.85];
+
+/// One pure pass over the diagnostics: a count, then a row per
+/// finding as far as the strip reaches — clicking one jumps the
+/// selection to it, on a [`ground`] bordering the body above.
+#[allow(clippy::too_many_arguments)]
+pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
+    sources: &Sources,
+    diagnostics: &[Diagnostic],
+    hover: Option<usize>,
+    names: &Names,
+    raw: bool,
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let heading = match diagnostics.len() {
+        0 => "No problems".to_string(),
+        1 => "1 problem".to_string(),
+        n => format!("{n} problems"),
+    };
+    let mut rows: Vec<Node<P>> = vec![text(tcx, &heading, &styles.label)];
+    let mut used = rows[0].extent.height();
+    let inner = panel.width() - 16.0 * scale;
+    for (index, diagnostic) in diagnostics.iter().enumerate() {
+        let content = min_width(
+            inner,
+            pad(
+                Insets::new(4.0 * scale, 1.0 * scale, 4.0 * scale, 1.0 * scale),
+                row(
+                    6.0 * scale,
+                    vec![
+                        text(tcx, "●", &styles.dim),
+                        text(tcx, &message(sources, names, raw, diagnostic), &styles.name),
+                    ],
+                ),
+            ),
+        );
+        used += content.extent.height() + 2.0 * scale;
+        if used > panel.height() - 16.0 * scale {
+            let rest = diagnostics.len() - index;
+            rows.push(text(tcx, &format!("… {rest} more"), &styles.dim));
+            break;
+        }
+        let lit = hover == Some(index);
+        let diagnostic = diagnostic.clone();
+        let jump = hooks.jump.clone();
+        let hover = hooks.hover.clone();
+        rows.push(decorate(content, move |p: &mut P, rect| {
+            if lit {
+                p.fill(
+                    RoundedRect::from_rect(rect, 4.0 * scale),
+                    Color::new(HOVER),
+                    Affine::IDENTITY,
+                );
+            }
+            p.handler().on_pointer_move(move |ctx, update| {
+                let point = Point::new(update.current.position.x, update.current.position.y);
+                rect.contains(point) && {
+                    hover(ctx, Some(index));
+                    false
+                }
+            });
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        jump(ctx, diagnostic.clone());
+                        true
+                    }
+            });
+        }));
+    }
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(8.0 * scale),
+            col(HAlign::Start, 0, 2.0 * scale, rows),
+        ),
+    );
+
+    let hover = hooks.hover.clone();
+    ground(content, panel, Border::Top, scale, move |ctx| hover(ctx, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::Document;
+    use crate::schema::{FIELDS, ISA, STRING, TYPE};
+    use progred_graph::{Cells, new_cell_id};
+
+    #[test]
+    fn reports_fields_kinds_and_red_links_through_cycles() {
+        let mut lib = schema::vocabulary();
+        let [point, x, y] = [(); 3].map(|_| new_cell_id());
+        lib.set_name(x, "x");
+        lib.set_value(x, Value::record([(Label::from(TYPE), Value::from(STRING))]));
+        lib.set_name(y, "y");
+        lib.set_value(y, Value::record([(Label::from(TYPE), Value::from(STRING))]));
+        lib.set_name(point, "point");
+        lib.set_value(
+            point,
+            Value::record([(
+                Label::from(FIELDS),
+                Value::list([Value::from(x), Value::from(y)]),
+            )]),
+        );
+        let [here, red, floating] = [(); 3].map(|_| new_cell_id());
+        let mut cells = Cells::new();
+        // `here` is a point missing y, with x a blob, a stray label,
+        // and a link back to itself.
+        cells.set_value(
+            here,
+            Value::record([
+                (Label::from(ISA), Value::from(point)),
+                (Label::from(x), Value::from(vec![0xff_u8])),
+                (Label::from("self"), Value::from(here)),
+            ]),
+        );
+        cells.set_name(red, "someday");
+        cells.set_value(floating, Value::list([Value::from(red)]));
+        let doc = Document {
+            root: Some(Value::list([Value::from(here)])),
+            cells,
+            libraries: Vec::new(),
+        };
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        let found = check(&sources);
+        let element = |value: &Value| value.as_list().unwrap().keys().next().cloned().unwrap();
+        let at_here = vec![Step::Element(element(doc.root.as_ref().unwrap())), Step::Follow];
+        let under = |step: Step| {
+            let mut path = at_here.clone();
+            path.push(step);
+            path
+        };
+        let rooted = |path: Path, problem: Problem| Diagnostic {
+            origin: None,
+            path,
+            problem,
+        };
+        // The self-link re-enters `here` and ends there; the red link
+        // inside the floating cell is addressed from that cell.
+        for expected in [
+            rooted(at_here.clone(), Problem::MissingField(y)),
+            rooted(
+                under(Step::Key(Label::from("self"))),
+                Problem::UnexpectedLabel(Label::from("self")),
+            ),
+            rooted(under(Step::Key(Label::from(x))), Problem::WrongKind(Type::String)),
+            Diagnostic {
+                origin: Some(floating),
+                path: vec![Step::Element(element(doc.cells.value(floating).unwrap()))],
+                problem: Problem::RedLink(red),
+            },
+        ] {
+            assert!(found.contains(&expected), "{expected:?} in {found:?}");
+        }
+        assert_eq!(found.len(), 4);
+        assert!(flagged(&found).contains(&at_here));
+        assert_eq!(
+            message(&sources, &Names::table(), false, &found[0]),
+            "missing y — root › [0]"
+        );
+    }
+
+    #[test]
+    fn reported_problems_read_as_said_and_underline_nothing() {
+        let doc = Document {
+            root: None,
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        let lib = Cells::new();
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        let said = vec!["library ./gone.progred: not found".to_string()];
+        let found = reported(&said);
+        assert_eq!(message(&sources, &Names::table(), false, &found[0]), said[0]);
+        assert!(flagged(&found).is_empty());
+    }
+}
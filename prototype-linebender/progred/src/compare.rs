@@ -0,0 +1,194 @@
+//! Compare mode: the document read against another state of itself —
+//! a file on disk, or a step of its own history — through the
+//! structural diff ([`progred_graph::diff`]), overlaid on the raw
+//! projection rather than shown beside it. What the other state
+//! lacked is tinted where it stands; what it had and this one doesn't
+//! is drawn struck through where it stood, in its container, so the
+//! change reads in the document's own shape.
+//!
+//! The overlay is keyed the way writes land: by OWNER — the root, or
+//! the cell whose value holds the spot — and the value spine inside
+//! it, so every projection of a changed cell shows the change, however
+//! the path reached it. The diff runs every pass, like the checker:
+//! it follows the document with nothing to invalidate.
+
+use crate::history::History;
+use crate::raw::Document;
+use progred_graph::diff::{self, CellDiff, Change, Diff};
+use progred_graph::merge::Side;
+pub use progred_graph::mentions::Owner;
+use progred_graph::Step;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What is new to the base: a light green wash.
+pub const ADDED: [f32; 4] = [0.20, 0.70, 0.35, 0.16];
+/// What the base had: the struck-through ghost's ink.
+pub const GONE: [f32; 4] = [0.78, 0.22, 0.20, 1.0];
+
+/// The state the document is compared with.
+pub enum Against {
+    /// A document read from disk, held as it was read: later changes
+    /// to the file don't move it.
+    File { path: PathBuf, doc: Document },
+    /// A state of the history, `offset` steps from the present —
+    /// negative back through undo, positive forward through redo.
+    /// It follows the present, so an edit keeps comparing with the
+    /// state the same number of steps away.
+    History(isize),
+}
+
+impl Against {
+    /// The base document, or None when the history no longer
+    /// reaches the offset.
+    pub fn base<'a>(&'a self, history: &'a History) -> Option<&'a Document> {
+        match self {
+            Against::File { doc, .. } => Some(doc),
+            Against::History(offset) => history.state(*offset),
+        }
+    }
+
+    /// How the title names the base.
+    pub fn describe(&self) -> String {
+        match self {
+            Against::File { path, .. } => path
+                .file_name()
+                .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into()),
+            Against::History(-1) => "1 step back".into(),
+            Against::History(1) => "1 step ahead".into(),
+            Against::History(offset) if *offset < 0 => format!("{} steps back", -offset),
+            Against::History(offset) => format!("{offset} steps ahead"),
+        }
+    }
+}
+
+/// The diff, indexed for the projection: each owner's changes by the
+/// spine they sit at.
+#[derive(Default)]
+pub struct Overlay {
+    at: HashMap<Owner, HashMap<Vec<Step>, Vec<Change>>>,
+    /// Cells added, removed, renamed, and revalued — the title's
+    /// summary. A renamed and revalued cell counts as both.
+    pub counts: [usize; 4],
+}
+
+impl Overlay {
+    /// What changed from `base` to `doc`.
+    pub fn new(base: &Document, doc: &Document) -> Self {
+        fn side(doc: &Document) -> Side<'_> {
+            Side {
+                cells: &doc.cells,
+                root: doc.root.as_ref(),
+            }
+        }
+        let found = diff::diff(side(base), side(doc));
+        Self::from_diff(found, doc)
+    }
+
+    fn from_diff(found: Diff, doc: &Document) -> Self {
+        let mut overlay = Overlay::default();
+        overlay.add(Owner::Root, found.root);
+        for (cell, change) in found.cells {
+            match change {
+                // A new cell is new throughout: its value, wherever
+                // it renders, reads as added.
+                CellDiff::Added => {
+                    overlay.counts[0] += 1;
+                    if let Some(value) = doc.cells.value(cell) {
+                        let edit = diff::Edit {
+                            spine: Vec::new(),
+                            change: Change::Added(value.clone()),
+                        };
+                        overlay.add(Owner::Cell(cell), vec![edit]);
+                    }
+                }
+                CellDiff::Removed(_) => overlay.counts[1] += 1,
+                CellDiff::Changed { renamed, revalued } => {
+                    overlay.counts[2] += usize::from(renamed.is_some());
+                    overlay.counts[3] += usize::from(!revalued.is_empty());
+                    overlay.add(Owner::Cell(cell), revalued);
+                }
+            }
+        }
+        overlay
+    }
+
+    fn add(&mut self, owner: Owner, edits: Vec<diff::Edit>) {
+        for edit in edits {
+            let spot = self.at.entry(owner).or_default().entry(edit.spine).or_default();
+            spot.push(edit.change);
+        }
+    }
+
+    /// The changes at `spine` in `owner`'s value: the value's own
+    /// (added, replaced) and its container's (fields and elements
+    /// gone from it).
+    pub fn at(&self, owner: Owner, spine: &[Step]) -> &[Change] {
+        self.at
+            .get(&owner)
+            .and_then(|spines| spines.get(spine))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.at.is_empty() && self.counts.iter().all(|count| *count == 0)
+    }
+
+    /// The title's account: "no changes", or the cell counts.
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no changes".into();
+        }
+        let [added, removed, renamed, revalued] = self.counts;
+        let mut parts: Vec<String> = [
+            (added, "added"),
+            (removed, "removed"),
+            (renamed, "renamed"),
+            (revalued, "revalued"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{count} {what}"))
+        .collect();
+        if parts.is_empty() {
+            parts.push("root changed".into());
+        }
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::{Label, Value};
+
+    #[test]
+    fn overlay_keys_changes_by_owner_and_spine() {
+        let base = crate::raw::sample_document();
+        let mut doc = base.clone();
+        let cell = doc
+            .cells
+            .iter()
+            .find_map(|(cell, entry)| (entry.name() == Some("origin")).then_some(*cell))
+            .unwrap();
+        let mut fields = doc.cells.value(cell).unwrap().as_record().unwrap().clone();
+        let at = fields.remove(&Label::from("at")).unwrap();
+        fields.insert(Label::from("near"), Value::from("top"));
+        doc.cells.set_value(cell, Value::Record(fields));
+        doc.cells.set_name(cell, "start");
+
+        let overlay = Overlay::new(&base, &doc);
+        assert_eq!(overlay.counts, [0, 0, 1, 1]);
+        assert_eq!(overlay.summary(), "1 renamed, 1 revalued");
+        assert_eq!(
+            overlay.at(Owner::Cell(cell), &[]),
+            [Change::FieldRemoved(Label::from("at"), at)]
+        );
+        assert_eq!(
+            overlay.at(Owner::Cell(cell), &[Step::Key(Label::from("near"))]),
+            [Change::Added(Value::from("top"))]
+        );
+        assert!(overlay.at(Owner::Root, &[]).is_empty());
+        assert!(Overlay::new(&doc, &doc).is_empty());
+    }
+}
@@ -9,11 +9,13 @@ use progred_graph::{CellId, Cells};
 use std::rc::Rc;
 
 /// The built-in library: read under every document through
-/// [`Sources`] — never written, never saved. Empty since names moved
-/// into the table; real loadable libraries will compose here (they
-/// are read-only, so composition is `Cells::merge`).
+/// [`Sources`] — never written, never saved. Holds the schema
+/// vocabulary ([`crate::schema::vocabulary`]) and nothing else since
+/// names moved into the table; a document's declared libraries
+/// compose over it (read-only, so composition is `Cells::merge`; see
+/// [`crate::store::libraries`]).
 pub fn library() -> Cells {
-    Cells::new()
+    crate::schema::vocabulary()
 }
 
 /// The editor's name policy: every display-name lookup goes through
@@ -0,0 +1,166 @@
+//! The git merge driver: `progred merge BASE OURS THEIRS` merges three
+//! document files structurally (see [`progred_graph::merge`]) and
+//! writes the result over OURS, in the form OURS was found in — the
+//! contract git's `%O %A %B` placeholders expect. It exits 0 on a
+//! clean merge; with conflicts it still writes the whole merged
+//! document (ours at each conflict), reports them one per line on
+//! stderr, and exits 1 so git marks the file conflicted. A file that
+//! doesn't read exits 2 and writes nothing. Wired up with:
+//!
+//! ```text
+//! # .git/config (or ~/.gitconfig)
+//! [merge "progred"]
+//!     name = progred structural merge
+//!     driver = progred merge %O %A %B
+//! # .gitattributes
+//! *.progred merge=progred
+//! ```
+//!
+//! Library declarations are not cells; they merge as one list, and
+//! two different changes to them are one more conflict.
+
+use crate::raw::Document;
+use crate::store;
+use progred_graph::merge::{self, Conflict, Part, Segment, Side};
+use progred_graph::{Cells, Value};
+use std::path::Path;
+
+pub fn run(args: &[String]) -> i32 {
+    let [base, ours, theirs] = args else {
+        eprintln!("usage: progred merge BASE OURS THEIRS");
+        return 2;
+    };
+    match merge_files(Path::new(base), Path::new(ours), Path::new(theirs)) {
+        Ok(report) if report.is_empty() => 0,
+        Ok(report) => {
+            for line in report {
+                eprintln!("{line}");
+            }
+            1
+        }
+        Err(error) => {
+            eprintln!("progred merge: {error}");
+            2
+        }
+    }
+}
+
+/// Merges into `ours`, returning the conflict report — empty when
+/// the merge was clean.
+fn merge_files(base: &Path, ours: &Path, theirs: &Path) -> Result<Vec<String>, String> {
+    let read = |path: &Path| {
+        store::open(path).map_err(|error| format!("{}: {error}", path.display()))
+    };
+    let base_doc = read(base)?.doc;
+    let opened = read(ours)?;
+    let theirs_doc = read(theirs)?.doc;
+    let merged = merge::merge(side(&base_doc), side(&opened.doc), side(&theirs_doc));
+    let mut report: Vec<String> = merged
+        .conflicts
+        .iter()
+        .map(|conflict| describe(conflict, &merged.cells))
+        .collect();
+    let (base_libraries, our_libraries, their_libraries) =
+        (base_doc.libraries, opened.doc.libraries, theirs_doc.libraries);
+    let libraries = if our_libraries == their_libraries || base_libraries == their_libraries {
+        our_libraries
+    } else if base_libraries == our_libraries {
+        their_libraries
+    } else {
+        report.push("conflict in library declarations: both sides changed them".into());
+        our_libraries
+    };
+    let doc = Document {
+        root: merged.root,
+        cells: merged.cells,
+        libraries,
+    };
+    store::write(ours, &doc, &opened.form)?;
+    Ok(report)
+}
+
+fn side(doc: &Document) -> Side<'_> {
+    Side {
+        cells: &doc.cells,
+        root: doc.root.as_ref(),
+    }
+}
+
+/// One report line: where, then each side's statement there.
+fn describe(conflict: &Conflict, cells: &Cells) -> String {
+    let cell = |cell| match cells.name(cell) {
+        Some(name) => format!("cell {cell} ({name})"),
+        None => format!("cell {cell}"),
+    };
+    let mut place = match conflict.part {
+        Part::Root => "root".to_string(),
+        Part::Name(id) => format!("{} name", cell(id)),
+        Part::Value(id) => cell(id),
+    };
+    for segment in &conflict.path {
+        match segment {
+            Segment::Field(label) => place.push_str(&format!(" › {label}")),
+            Segment::Element(index) => place.push_str(&format!(" › [{index}]")),
+        }
+    }
+    let side = |value: &Option<Value>| match value {
+        Some(value) => value.to_string(),
+        None => "absent".to_string(),
+    };
+    format!(
+        "conflict in {place}: base {}, ours {}, theirs {}",
+        side(&conflict.base),
+        side(&conflict.ours),
+        side(&conflict.theirs)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::{Label, new_cell_id};
+
+    #[test]
+    fn merges_into_ours_and_reports_conflicts() {
+        let dir = std::env::temp_dir().join(format!("progred-driver-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cell = new_cell_id();
+        let doc = |x: &str, y: &str| {
+            let mut cells = Cells::new();
+            cells.set_value(
+                cell,
+                Value::record([
+                    (Label::from("x"), Value::from(x)),
+                    (Label::from("y"), Value::from(y)),
+                ]),
+            );
+            Document {
+                root: Some(Value::from(cell)),
+                cells,
+                libraries: Vec::new(),
+            }
+        };
+        let paths = ["base", "ours", "theirs"].map(|name| dir.join(format!("{name}.progred")));
+        let write = |docs: [Document; 3]| {
+            for (path, doc) in paths.iter().zip(docs) {
+                store::save(path, &doc).unwrap();
+            }
+        };
+
+        write([doc("0", "0"), doc("1", "0"), doc("0", "2")]);
+        let report = merge_files(&paths[0], &paths[1], &paths[2]).unwrap();
+        assert!(report.is_empty());
+        let merged = store::open(&paths[1]).unwrap().doc;
+        assert_eq!(merged.cells.value(cell), doc("1", "2").cells.value(cell));
+
+        write([doc("0", "0"), doc("1", "0"), doc("2", "0")]);
+        let report = merge_files(&paths[0], &paths[1], &paths[2]).unwrap();
+        assert_eq!(
+            report,
+            [format!("conflict in cell {cell} › x: base \"0\", ours \"1\", theirs \"2\"")]
+        );
+        let merged = store::open(&paths[1]).unwrap().doc;
+        assert_eq!(merged.cells.value(cell), doc("1", "0").cells.value(cell));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -135,6 +135,36 @@ pub fn rank<A>(items: Vec<A>, key: impl Fn(&A) -> &str, needle: &str) -> Vec<Ran
     ranked
 }
 
+/// Every non-overlapping occurrence of `needle`, left to right: the
+/// exact ones when there are any, else the case-insensitive ones —
+/// the substring tiers' reading of a match, taken past the first.
+/// What replacement rewrites; a fuzzy tier's scattered characters
+/// name nothing to replace, so they are not occurrences.
+pub fn occurrences(needle: &str, haystack: &str) -> Vec<Match> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let every = |eq: CharEq| {
+        let mut found = Vec::new();
+        let mut from = 0;
+        while let Some(spans) = substring(needle, &haystack[from..], eq) {
+            let span = Match {
+                start: from + spans[0].start,
+                len: spans[0].len,
+            };
+            from = span.start + span.len;
+            found.push(span);
+        }
+        found
+    };
+    let found = every(exact);
+    if found.is_empty() {
+        every(case_insensitive)
+    } else {
+        found
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,4 +219,17 @@ mod tests {
         let ranked = rank(vec!["État"], |w| w, "ét");
         assert_eq!(ranked[0].matches, vec![Match { start: 0, len: 3 }]);
     }
+
+    #[test]
+    fn occurrences_prefer_exact_and_never_overlap() {
+        let at = |needle, haystack| -> Vec<usize> {
+            occurrences(needle, haystack).iter().map(|m| m.start).collect()
+        };
+        assert_eq!(at("aa", "aaaa"), vec![0, 2]);
+        // An exact occurrence shadows the case-folded ones.
+        assert_eq!(at("ab", "AB ab Ab"), vec![3]);
+        assert_eq!(at("ab", "AB Ab"), vec![0, 3]);
+        assert_eq!(occurrences("ét", "xÉT")[0], Match { start: 1, len: 3 });
+        assert!(at("", "abc").is_empty());
+    }
 }
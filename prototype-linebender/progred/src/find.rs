@@ -0,0 +1,577 @@
+//! Find and replace: every string the document states — atoms, cell
+//! names, string labels — matched against a query through the
+//! completion popup's tiers ([`crate::filter::rank`]), so a find reads
+//! the way completion does: exact prefix first, fuzzy subsequence
+//! last.
+//!
+//! The search walks like the checker ([`crate::check`]): from the
+//! root, then from every cell the root never reaches, each cell ONCE
+//! at the first path to reach it, so cycles end and a shared cell is
+//! found where the tree first shows it. A hit is addressed by a
+//! [`Path`] from the root, or — for a cell only the graph can show —
+//! from that cell, as if a link to it were the root: its name at
+//! `[Name]`, its value under `[Follow]`. Library cells are the
+//! library's business and are not searched.
+//!
+//! Replace rewrites through the same writes the editor makes —
+//! [`raw::set_value`], [`raw::set_name`], [`raw::rename_field`] — and
+//! only what a substring tier found: a fuzzy hit's scattered
+//! characters name nothing to replace. The shell records the batch as
+//! one undo step.
+
+use crate::filter::{self, Match};
+use crate::panel::{Border, SEPARATOR, ground};
+use crate::raw::{self, Document, Path, RawStyles};
+use crate::sources::Sources;
+use progred_graph::{Atom, CellId, Cells, Label, Step, Value};
+use puri::draw::Canvas;
+use puri::edit::{EditCtx, LineEditState, text_edit};
+use puri::handler::HasHandler;
+use puri::layout::{Node, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::collections::HashSet;
+use std::rc::Rc;
+use ui_events::keyboard::{Key, KeyboardEvent, NamedKey};
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect, Stroke};
+use vello::peniko::{Brush, Color};
+
+/// What a hit's text is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// A string atom; the path is the atom's.
+    Value,
+    /// A cell's name; the path ends in its Name step.
+    Name,
+    /// A string label; the path ends in its field's Key step.
+    Label,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit {
+    /// `None` when `path` is from the root; otherwise the unreached
+    /// cell it is from.
+    pub origin: Option<CellId>,
+    pub path: Path,
+    pub field: Field,
+    pub text: String,
+    pub matches: Vec<Match>,
+    /// Found by a fuzzy tier only: listed, never replaced.
+    pub fuzzy: bool,
+}
+
+/// Every hit for `needle`, best tier first — none for an empty one.
+pub fn find(sources: &Sources, needle: &str) -> Vec<Hit> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut walk = Walk {
+        sources,
+        seen: HashSet::new(),
+        origin: None,
+        out: Vec::new(),
+    };
+    if let Some(root) = sources.root() {
+        walk.value(&mut Vec::new(), root);
+    }
+    let mut floating: Vec<CellId> = sources.doc.cells.cells().copied().collect();
+    floating.sort();
+    for cell in floating {
+        walk.origin = Some(cell);
+        walk.value(&mut Vec::new(), &Value::from(cell));
+    }
+    filter::rank(walk.out, |hit| &hit.text, needle)
+        .into_iter()
+        .map(|ranked| {
+            let fuzzy = ranked.fuzzy();
+            Hit {
+                matches: ranked.matches,
+                fuzzy,
+                ..ranked.item
+            }
+        })
+        .collect()
+}
+
+struct Walk<'a, 's> {
+    sources: &'a Sources<'s>,
+    seen: HashSet<CellId>,
+    origin: Option<CellId>,
+    out: Vec<Hit>,
+}
+
+impl Walk<'_, '_> {
+    fn found(&mut self, path: &[Step], field: Field, text: &str) {
+        self.out.push(Hit {
+            origin: self.origin,
+            path: path.to_vec(),
+            field,
+            text: text.to_string(),
+            matches: Vec::new(),
+            fuzzy: false,
+        });
+    }
+
+    fn value(&mut self, path: &mut Path, value: &Value) {
+        match value {
+            Value::Atom(Atom::Cell(cell)) => {
+                let cell = *cell;
+                if self.sources.external(cell) || !self.seen.insert(cell) {
+                    return;
+                }
+                if let Some(name) = self.sources.name(cell) {
+                    path.push(Step::Name);
+                    self.found(path, Field::Name, name);
+                    path.pop();
+                }
+                if let Some(held) = self.sources.value(cell) {
+                    path.push(Step::Follow);
+                    self.value(path, held);
+                    path.pop();
+                }
+            }
+            Value::Atom(Atom::String(string)) => self.found(path, Field::Value, string),
+            Value::Atom(Atom::Blob(_)) => {}
+            Value::List(elements) => {
+                for (position, item) in elements {
+                    path.push(Step::Element(position.clone()));
+                    self.value(path, item);
+                    path.pop();
+                }
+            }
+            Value::Record(fields) => {
+                for (label, item) in fields {
+                    path.push(Step::Key(label.clone()));
+                    if let Label::String(string) = label {
+                        self.found(path, Field::Label, string);
+                    }
+                    self.value(path, item);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// `text` with every occurrence of `needle` rewritten to `with`, or
+/// None when nothing occurs.
+pub fn replaced(text: &str, needle: &str, with: &str) -> Option<String> {
+    let spans = filter::occurrences(needle, text);
+    if spans.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    let mut from = 0;
+    for span in spans {
+        out.push_str(&text[from..span.start]);
+        out.push_str(with);
+        from = span.start + span.len;
+    }
+    out.push_str(&text[from..]);
+    Some(out)
+}
+
+/// Rewrites every hit's occurrences of `needle` to `with`; how many
+/// hits changed. Deepest paths go first, and a field's value before
+/// its label at the same path, so nothing is re-keyed out from under
+/// a write still to come. A write the editor would decline — a label
+/// already taken, a cell not the document's — declines here too.
+pub fn replace(
+    doc: &mut Document,
+    library: &Cells,
+    hits: &[Hit],
+    needle: &str,
+    with: &str,
+) -> usize {
+    let mut order: Vec<&Hit> = hits.iter().filter(|hit| !hit.fuzzy).collect();
+    order.sort_by_key(|hit| (std::cmp::Reverse(hit.path.len()), hit.field == Field::Label));
+    let mut count = 0;
+    for hit in order {
+        let Some(text) = replaced(&hit.text, needle, with).filter(|text| *text != hit.text) else {
+            continue;
+        };
+        let wrote = rooted(doc, hit.origin, |doc| match hit.field {
+            Field::Value => raw::set_value(doc, library, &hit.path, Value::from(text.as_str())),
+            Field::Name => raw::set_name(doc, library, &hit.path, &text),
+            Field::Label => match hit.path.split_last() {
+                Some((Step::Key(old), parent)) => {
+                    raw::rename_field(doc, library, parent, old, Label::from(text.as_str()))
+                }
+                _ => false,
+            },
+        });
+        count += usize::from(wrote);
+    }
+    count
+}
+
+/// Runs a write with the root standing in as a link to `origin`, so a
+/// path from that cell lands where the same path from the root would.
+fn rooted(
+    doc: &mut Document,
+    origin: Option<CellId>,
+    write: impl FnOnce(&mut Document) -> bool,
+) -> bool {
+    let Some(cell) = origin else {
+        return write(doc);
+    };
+    let root = doc.root.replace(Value::from(cell));
+    let wrote = write(doc);
+    doc.root = root;
+    wrote
+}
+
+/// Which of the bar's two fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Query,
+    Replace,
+}
+
+/// The find bar's state: its two fields, which one types, and the hit
+/// last stepped to. Editor state, not document state — it survives
+/// document swaps like the name policy.
+pub struct FindBar {
+    pub query: LineEditState,
+    pub replace: LineEditState,
+    pub input: Input,
+    /// Whether keys go to the bar. A press elsewhere hands them back
+    /// to the document; a press in a field takes them again.
+    pub focused: bool,
+    /// Index into the current hits. The hits are found afresh after
+    /// every edit, so one can shift what it names — stepping goes on
+    /// from wherever it lands.
+    pub current: Option<usize>,
+    /// The hits last found, and what they were found in.
+    found: Option<Found>,
+}
+
+/// Hits kept across passes: an unchanged table is a pointer
+/// comparison away, so an open bar doesn't re-walk and re-rank the
+/// document every frame.
+struct Found {
+    cells: Cells,
+    root: Option<Value>,
+    query: String,
+    hits: Rc<[Hit]>,
+}
+
+impl FindBar {
+    pub fn new() -> Self {
+        let field = || LineEditState::new("", 14.0, Brush::from(Color::new(INK)));
+        Self {
+            query: field(),
+            replace: field(),
+            input: Input::Query,
+            focused: true,
+            current: None,
+            found: None,
+        }
+    }
+
+    /// The query's hits over `sources`, found afresh only when the
+    /// table, the root, or the query changed since they last were.
+    pub fn hits(&mut self, sources: &Sources) -> Rc<[Hit]> {
+        let (doc, query) = (sources.doc, self.query.text());
+        if let Some(found) = &self.found
+            && found.cells.ptr_eq(&doc.cells)
+            && found.root == doc.root
+            && found.query == query
+        {
+            return found.hits.clone();
+        }
+        let hits: Rc<[Hit]> = find(sources, query).into();
+        self.found = Some(Found {
+            cells: doc.cells.clone(),
+            root: doc.root.clone(),
+            query: query.to_string(),
+            hits: hits.clone(),
+        });
+        hits
+    }
+
+    /// The hits as [`FindBar::hits`] last found them — what a pass
+    /// shows, once the shell has brought them up to the document.
+    pub fn found(&self) -> &[Hit] {
+        self.found.as_ref().map_or(&[], |found| &found.hits)
+    }
+
+    pub fn field_mut(&mut self, input: Input) -> &mut LineEditState {
+        match input {
+            Input::Query => &mut self.query,
+            Input::Replace => &mut self.replace,
+        }
+    }
+
+    /// Steps to the next hit of `count` — or the previous, wrapping
+    /// either way — and returns its index.
+    pub fn step(&mut self, count: usize, forward: bool) -> Option<usize> {
+        if count == 0 {
+            self.current = None;
+            return None;
+        }
+        let next = match (self.current.filter(|at| *at < count), forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(at), true) => (at + 1) % count,
+            (Some(at), false) => (at + count - 1) % count,
+        };
+        self.current = Some(next);
+        self.current
+    }
+}
+
+impl Default for FindBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a key asks of a focused bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Next,
+    Previous,
+    /// Type into the other field.
+    Switch,
+    ReplaceAll,
+    Close,
+}
+
+/// The bar's own keys, over whatever its fields decline: Return
+/// steps forward and Shift+Return back, the command chord on Return
+/// replaces everything, Tab switches fields, Escape closes.
+pub fn command(event: &KeyboardEvent) -> Option<Command> {
+    if !event.state.is_down() {
+        return None;
+    }
+    let action_mod = if cfg!(target_os = "macos") {
+        event.modifiers.meta()
+    } else {
+        event.modifiers.ctrl()
+    };
+    match &event.key {
+        Key::Named(NamedKey::Enter) if action_mod => Some(Command::ReplaceAll),
+        Key::Named(NamedKey::Enter) if event.modifiers.shift() => Some(Command::Previous),
+        Key::Named(NamedKey::Enter) => Some(Command::Next),
+        Key::Named(NamedKey::Tab) => Some(Command::Switch),
+        Key::Named(NamedKey::Escape) => Some(Command::Close),
+        _ => None,
+    }
+}
+
+/// The bar's window rectangle: a strip across the body, standing on
+/// whatever is below it at `bottom`.
+pub fn panel(scale: f64, bottom: f64, right: f64) -> Rect {
+    Rect::new(0.0, (bottom - 36.0 * scale).round(), right, bottom)
+}
+
+/// Dispatch-time callbacks the shell injects, mirroring the panes:
+/// the bar reports, the shell transitions.
+pub struct Hooks<C> {
+    /// A press in a field, at a point relative to its text origin:
+    /// focus it and land the caret there.
+    pub press: Rc<dyn Fn(&mut C, Input, Point, u8)>,
+    /// None when the field isn't the focused one — retained-frame
+    /// dispatch can outlive the focus, and absent state declines.
+    pub edit: Rc<dyn for<'a> Fn(&'a mut C, Input) -> Option<EditCtx<'a>>>,
+    /// Keys the fields decline, while the bar is focused.
+    pub key: Rc<dyn Fn(&mut C, &KeyboardEvent) -> bool>,
+    pub act: Rc<dyn Fn(&mut C, Command)>,
+}
+
+const FIELD_BG: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const FOCUS: [f32; 4] = [0.0, 0.48, 1.0, 0.55];
+const INK: [f32; 4] = [0.13, 0.14, 0.16, 1.0];
+
+/// One pure pass over the bar: the two fields, where stepping stands
+/// among the hits, and the replace-all button, on a [`ground`]. The
+/// bar takes the keys the fields decline while focused, so typing
+/// never leaks into the document.
+pub fn bar<C: 'static, P: Canvas + HasHandler<C>>(
+    state: &FindBar,
+    hits: &[Hit],
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let field = |tcx: &mut TextCtx, input: Input, line: &LineEditState, ghost: &str| {
+        let focused = state.focused && state.input == input;
+        let edit = hooks.edit.clone();
+        let content = text_edit(
+            line,
+            focused,
+            &styles.edit,
+            Some((ghost, &styles.dim)),
+            tcx,
+            move |c| edit(c, input),
+        );
+        let press = hooks.press.clone();
+        decorate(
+            min_width(
+                panel.width() * 0.25,
+                pad(Insets::new(6.0 * scale, 3.0 * scale, 6.0 * scale, 3.0 * scale), content),
+            ),
+            move |p: &mut P, rect: Rect| {
+                let shape = RoundedRect::from_rect(rect, 4.0 * scale);
+                p.fill(shape, Color::new(FIELD_BG), Affine::IDENTITY);
+                let edge = if focused { FOCUS } else { SEPARATOR };
+                p.stroke(shape, Stroke::new(1.0 * scale), Color::new(edge), Affine::IDENTITY);
+                let origin = Point::new(rect.x0 + 6.0 * scale, rect.y0 + 3.0 * scale);
+                p.handler().on_pointer_down(move |ctx, event| {
+                    let point = Point::new(event.state.position.x, event.state.position.y);
+                    event.button == Some(PointerButton::Primary)
+                        && rect.contains(point)
+                        && {
+                            let count = event.state.count.max(1);
+                            press(ctx, input, point - origin.to_vec2(), count);
+                            true
+                        }
+                });
+            },
+        )
+    };
+    let place = match (state.current.filter(|at| *at < hits.len()), hits.len()) {
+        _ if state.query.text().is_empty() => String::new(),
+        (_, 0) => "No matches".into(),
+        (Some(at), count) => format!("{} of {count}", at + 1),
+        (None, 1) => "1 match".into(),
+        (None, count) => format!("{count} matches"),
+    };
+    let act = hooks.act.clone();
+    let button = decorate(
+        pad(
+            Insets::new(6.0 * scale, 3.0 * scale, 6.0 * scale, 3.0 * scale),
+            text(tcx, "Replace All", &styles.name),
+        ),
+        move |p: &mut P, rect: Rect| {
+            let shape = RoundedRect::from_rect(rect, 4.0 * scale);
+            p.fill(shape, Color::new(FIELD_BG), Affine::IDENTITY);
+            p.stroke(shape, Stroke::new(1.0 * scale), Color::new(SEPARATOR), Affine::IDENTITY);
+            let act = act.clone();
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        act(ctx, Command::ReplaceAll);
+                        true
+                    }
+            });
+        },
+    );
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(6.0 * scale),
+            row(
+                8.0 * scale,
+                vec![
+                    field(tcx, Input::Query, &state.query, "Find"),
+                    field(tcx, Input::Replace, &state.replace, "Replace"),
+                    button,
+                    text(tcx, &place, &styles.dim),
+                ],
+            ),
+        ),
+    );
+
+    let key = hooks.key.clone();
+    let focused = state.focused;
+    // Outside the ground, the key handler registers before the
+    // fields' too, so they win.
+    decorate(ground(content, panel, Border::Top, scale, |_| {}), move |p: &mut P, _| {
+        if focused {
+            p.handler().on_key(move |ctx, event| key(ctx, event));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::new_cell_id;
+
+    #[test]
+    fn hits_span_atoms_names_and_labels_and_replace_rewrites_them() {
+        let library = Cells::new();
+        let mut doc = Document {
+            root: None,
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        let (shape, stray) = (new_cell_id(), new_cell_id());
+        doc.cells.set_name(shape, "red shape");
+        doc.cells.set_value(
+            shape,
+            Value::record([
+                (Label::from("red"), Value::from("dark red")),
+                (Label::from("size"), Value::from("2")),
+            ]),
+        );
+        doc.cells.set_name(stray, "Redundant");
+        doc.root = Some(Value::list([Value::from(shape), Value::from(shape)]));
+        let position = doc.root.as_ref().unwrap().as_list().unwrap().keys().next().unwrap().clone();
+        let sources = Sources {
+            doc: &doc,
+            library: &library,
+        };
+
+        let hits = find(&sources, "red");
+        let at = |text: &str| hits.iter().find(|hit| hit.text == text).unwrap();
+        // The shared cell is found once, where the tree first shows it.
+        assert_eq!(hits.len(), 4);
+        let red = Label::from("red");
+        assert_eq!(at("red").path, [Step::Element(position.clone()), Step::Follow, Step::Key(red)]);
+        assert_eq!(at("red shape").field, Field::Name);
+        assert_eq!(at("dark red").field, Field::Value);
+        // The stray cell the root never reaches is addressed from
+        // itself, and its case-folded match ranks last.
+        assert_eq!(at("Redundant").origin, Some(stray));
+        assert_eq!(at("Redundant").path, [Step::Name]);
+        assert_eq!(hits.last().unwrap().text, "Redundant");
+        assert!(find(&sources, "").is_empty());
+        assert!(find(&sources, "rsz").iter().all(|hit| hit.fuzzy));
+
+        assert_eq!(replace(&mut doc, &library, &hits, "red", "blue"), 4);
+        assert_eq!(doc.cells.name(shape), Some("blue shape"));
+        assert_eq!(doc.cells.name(stray), Some("blueundant"));
+        assert_eq!(
+            doc.cells.value(shape),
+            Some(&Value::record([
+                (Label::from("blue"), Value::from("dark blue")),
+                (Label::from("size"), Value::from("2")),
+            ]))
+        );
+        // The stand-in root is put back.
+        assert_eq!(doc.root.as_ref().unwrap().as_list().unwrap().len(), 2);
+
+        // The bar keeps its hits until the document or the query
+        // changes.
+        let sources = Sources {
+            doc: &doc,
+            library: &library,
+        };
+        let mut bar = FindBar::new();
+        bar.query.set_text("blue");
+        let hits = bar.hits(&sources);
+        assert_eq!(hits.len(), 4);
+        assert!(Rc::ptr_eq(&hits, &bar.hits(&sources)));
+        assert_eq!(bar.found(), &hits[..]);
+        bar.query.set_text("dark");
+        assert_eq!(bar.hits(&sources).len(), 1);
+        let mut edited = doc.clone();
+        edited.cells.set_name(stray, "dark");
+        let sources = Sources {
+            doc: &edited,
+            library: &library,
+        };
+        assert_eq!(bar.hits(&sources).len(), 2);
+
+        let mut bar = FindBar::new();
+        assert_eq!(bar.step(3, false), Some(2));
+        assert_eq!(bar.step(3, true), Some(0));
+        assert_eq!(bar.step(0, true), None);
+    }
+}
@@ -1,31 +1,71 @@
-//! The graph view: the document's REFERENCE TOPOLOGY, for demos on
-//! small graphs. Nodes are cells (plus one synthetic node for a root
-//! value that is not a link); an arrow means "this node's value
+//! The graph view: the document's REFERENCE TOPOLOGY. Nodes are
+//! cells (plus one synthetic node for a root value that is not a
+//! link); an arrow means "this node's value
 //! mentions that cell", deduplicated — which field or element holds
 //! the link is the tree's business, so edges carry no labels and
 //! cannot be selected. What the picture shows is what the tree
 //! hides: cycles, sharing, floaters, and red links (valueless cells,
-//! dashed). Layout is the force simulation carried from the
+//! dashed). LABELED EDGES turn the picture into the tree's dual, the
+//! bubbles-and-arrows projection: every mention its own arrow, named
+//! by the field or element holding it, clicked to select that place
+//! in the tree and deleted to detach it there.
+//!
+//! Layout is the force simulation carried from the
 //! TypeScript/egui/Haskell prototypes (same constants), stepped
 //! every frame while the view is open; positions and velocities are
-//! explicit model state, seeded deterministically per node.
+//! explicit model state, seeded deterministically per node. A
+//! simulation reshuffles whenever the document changes, so the
+//! LAYERED layout ([`crate::layered`]) stands in when the picture
+//! must come out the same twice: rows down from the root's node,
+//! computed once per snapshot, the same positions for the same
+//! document, with no window needed to get them. EXPORT takes the
+//! picture out: as SVG, the pane's own drawing recorded and written
+//! whole, or as Graphviz DOT, the shown snapshot by display names.
 //! Rendering and hit-testing are one pure pass: build geometry from
 //! state, draw it, register handlers over it.
+//!
+//! Documents run to thousands of cells, so nothing per frame is
+//! all-pairs. Repulsion is Barnes-Hut ([`crate::spatial`]): a far
+//! cluster pushes as one body from its centroid, and beyond
+//! `REPULSION_CUTOFF` — where the push is a fraction of a pixel a
+//! frame — not at all, so distant clusters drift together under
+//! gravity until they meet at the cutoff: a looser picture than exact
+//! repulsion, never a wrong one. The snapshot is kept until the
+//! document changes, the pane lays out only the nodes the viewport
+//! shows, and hit-testing goes through a grid of their rectangles.
+//!
+//! Past a few hundred nodes the whole picture stops saying anything,
+//! so the view narrows to a NEIGHBORHOOD: the nodes within a radius of
+//! mention-hops around the selection — the graph's node, or the nodes
+//! the tree's selected values sit at — arrows followed either way.
+//! A node with neighbors left out wears a "+k" stub; clicking it
+//! brings them in. Nodes entering land beside the neighbor that
+//! brought them and the rest stay where they stood, so moving the
+//! selection re-centers the picture without re-scattering it.
 
 use crate::conventions::Names;
+use crate::layered;
+use crate::panel::{PANEL_BG, SEPARATOR};
 use crate::raw::{Document, Selection, command, short_id};
 use crate::sources::Sources;
+use crate::spatial::{Grid, Mass, Quads};
 use parley::style::GenericFamily;
 use parley::{Layout, StyleProperty};
-use progred_graph::{Atom, CellId, Value};
-use puri::draw::Canvas;
-use puri::handler::HasHandler;
-use puri::layout::{Extent, Node, leaf};
+use progred_graph::{Atom, CellId, Cells, Step, Value};
+use puri::draw::{self, Canvas, DrawList, GlyphRun};
+use puri::handler::{Handler, HasHandler};
+use puri::layout::{Extent, Node, leaf, place_top_left};
 use puri::text::{TextCtx, draw_layout};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f64::consts::TAU;
 use std::rc::Rc;
 use ui_events::pointer::PointerButton;
-use vello::kurbo::{Affine, BezPath, Point, Rect, RoundedRect, Stroke, Vec2};
+use vello::kurbo::{
+    Affine, BezPath, CubicBez, ParamCurve, ParamCurveNearest, PathSeg, Point, QuadBez, Rect,
+    RoundedRect, Shape, Stroke, Vec2,
+};
 use vello::peniko::{Brush, Color};
 
 const REPULSION_K: f64 = 8000.0;
@@ -34,13 +74,16 @@ const REST_LENGTH: f64 = 120.0;
 const DAMPING: f64 = 0.85;
 const MAX_FORCE: f64 = 10.0;
 const GRAVITY_K: f64 = 0.005;
+/// Beyond this, nodes don't repel: at the cutoff the exact push is
+/// 0.05 a frame against gravity's pull.
+const REPULSION_CUTOFF: f64 = 400.0;
+/// Barnes-Hut's opening angle: a quad narrower than this fraction of
+/// its distance pushes as one body from its centroid.
+const THETA: f64 = 1.0;
 const PARALLEL_SPACING: f64 = 50.0;
 const CLICK_SLOP: f64 = 2.0;
-
-/// The pane's window rectangle: the right 40% of the viewport.
-pub fn panel(width: f64, height: f64) -> Rect {
-    Rect::new((width * 0.6).round(), 0.0, width, height)
-}
+/// How near an edge's curve a press hits it, in logical pixels.
+const EDGE_SLOP: f64 = 4.0;
 
 /// A drawn node: a cell, or the document's root value when the root
 /// is not a link (a link root tints its cell instead).
@@ -50,17 +93,21 @@ pub enum GraphNode {
     Cell(CellId),
 }
 
-/// The graph's selection: nodes only — edges are anonymous mentions,
-/// deduplicated, with nothing per-edge to select or delete.
-#[derive(Clone, Copy, PartialEq)]
+/// The graph's selection: a node, or — with labeled edges — one
+/// mention, by the node whose value holds it and the spine of the
+/// field or element that does. A mention the tree can reach selects
+/// there instead; this is for the ones only the graph shows.
+#[derive(Clone, PartialEq)]
 pub enum GraphSelection {
     Node(GraphNode),
+    Edge(GraphNode, Vec<Step>),
 }
 
 /// An in-progress drag: a node being moved (with where the pointer
 /// grabbed it, as a world offset from the node position), or the
 /// background panning the viewport. Either way, an unmoved release is
 /// a click, reported through [`Release`].
+#[derive(Clone)]
 enum Drag {
     Node {
         node: GraphNode,
@@ -76,14 +123,50 @@ enum Drag {
 }
 
 /// The graph view's explicit state: per-node world positions and
-/// velocities, the viewport (world-space pan, zoom), and any drag in
-/// progress. Selection lives in the shell's one slot, not here.
+/// velocities, the viewport (world-space pan, zoom), any drag in
+/// progress, and the neighborhood when the view is narrowed to one.
+/// Selection lives in the shell's one slot, not here.
+#[derive(Clone)]
 pub struct GraphView {
     positions: HashMap<GraphNode, Point>,
     velocities: HashMap<GraphNode, Vec2>,
     drag: Option<Drag>,
     pan: Vec2,
     zoom: f64,
+    neighborhood: Option<Neighborhood>,
+    /// Every mention its own edge, labeled and selectable, rather than
+    /// one anonymous arrow per linked pair.
+    labeled: bool,
+    /// Positions from the layered layout, recomputed per snapshot,
+    /// rather than the simulation.
+    layered: bool,
+    /// The last snapshot, with the table and root it was taken of.
+    taken: Option<Taken>,
+}
+
+/// The neighborhood's radius when it is first turned on.
+pub const RADIUS: usize = 2;
+
+/// The view narrowed to what is near the focus: the nodes within
+/// `radius` mention-hops of it, following arrows either way, plus
+/// the neighbors of every node the user expanded.
+#[derive(Clone)]
+struct Neighborhood {
+    radius: usize,
+    focus: Vec<GraphNode>,
+    expanded: HashSet<GraphNode>,
+}
+
+/// A snapshot kept across frames: an unchanged table is a pointer
+/// comparison away, so a settling layout doesn't re-walk the
+/// document every step. `shown` is the part the neighborhood shows
+/// — the whole when there is none — and goes stale with it.
+#[derive(Clone)]
+struct Taken {
+    cells: Cells,
+    root: Option<Value>,
+    whole: Rc<Snapshot>,
+    shown: Option<Rc<Snapshot>>,
 }
 
 impl Default for GraphView {
@@ -94,6 +177,10 @@ impl Default for GraphView {
             drag: None,
             pan: Vec2::ZERO,
             zoom: 1.0,
+            neighborhood: None,
+            labeled: false,
+            layered: false,
+            taken: None,
         }
     }
 }
@@ -109,24 +196,60 @@ pub enum Release {
 
 struct Snapshot {
     nodes: Vec<GraphNode>,
-    /// Mentions, deduplicated per (from, to) pair.
+    /// Mentions: deduplicated per (from, to) pair, or with labeled
+    /// edges one per mention.
     edges: Vec<(GraphNode, CellId)>,
+    /// With labeled edges, each edge's spine in its `from` node's
+    /// value: the field or element holding the mention. Empty
+    /// otherwise.
+    spines: Vec<Vec<Step>>,
+    /// The edges again, as indices into `nodes`.
+    ends: Vec<(usize, usize)>,
+    /// Per node, how many of its neighbors the neighborhood hides:
+    /// the count its "+k" stub shows.
+    hidden: Vec<usize>,
+    /// The root's node — its link's cell, or the root value's own —
+    /// where a neighborhood with nothing yet to follow centers.
+    home: Option<GraphNode>,
+}
+
+impl Snapshot {
+    /// Each node's neighbors by index, arrows either way, each once.
+    fn around(&self) -> Vec<Vec<usize>> {
+        let mut around = vec![Vec::new(); self.nodes.len()];
+        for (from, to) in self.ends.iter().copied() {
+            if from != to {
+                around[from].push(to);
+                around[to].push(from);
+            }
+        }
+        for neighbors in &mut around {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+        around
+    }
 }
 
 /// Every cell a value links, labels included — a label is a mention
-/// too.
-fn links(value: &Value, out: &mut Vec<CellId>) {
+/// too — each with the spine of the field or element holding it: a
+/// label's is its own field's.
+fn links(value: &Value, spine: &mut Vec<Step>, out: &mut Vec<(CellId, Vec<Step>)>) {
     match value {
-        Value::Atom(atom) => out.extend(atom.as_cell()),
+        Value::Atom(atom) => out.extend(atom.as_cell().map(|cell| (cell, spine.clone()))),
         Value::List(elements) => {
-            for element in elements.values() {
-                links(element, out);
+            for (position, element) in elements {
+                spine.push(Step::Element(position.clone()));
+                links(element, spine, out);
+                spine.pop();
             }
         }
         Value::Record(fields) => {
             for (label, field) in fields {
-                out.extend(label.as_cell());
-                links(field, out);
+                spine.push(Step::Key(label.clone()));
+                out.extend(label.as_cell().map(|cell| (cell, spine.clone())));
+                links(field, spine, out);
+                spine.pop();
             }
         }
     }
@@ -134,18 +257,26 @@ fn links(value: &Value, out: &mut Vec<CellId>) {
 
 /// The document's reference topology: every cell its table or links
 /// mention (valueless cells included — a link is a mention), each
-/// value's mentions as deduplicated edges. Library facts enrich
-/// display only; the snapshot — what the graph SHOWS — stays the
-/// document's own.
-fn snapshot(doc: &Document) -> Snapshot {
+/// value's mentions as deduplicated edges — or `labeled`, as one
+/// edge per mention, in the order the value states them. Library
+/// facts enrich display only; the snapshot — what the graph SHOWS —
+/// stays the document's own.
+fn snapshot(doc: &Document, labeled: bool) -> Snapshot {
     let mut nodes: Vec<GraphNode> = Vec::new();
     let mut edges: Vec<(GraphNode, CellId)> = Vec::new();
-    let mention = |from: GraphNode, value: &Value, edges: &mut Vec<(GraphNode, CellId)>| {
+    let mut spines: Vec<Vec<Step>> = Vec::new();
+    let mut mention = |from: GraphNode, value: &Value, edges: &mut Vec<(GraphNode, CellId)>| {
         let mut found = Vec::new();
-        links(value, &mut found);
-        found.sort();
-        found.dedup();
-        edges.extend(found.into_iter().map(|to| (from, to)));
+        links(value, &mut Vec::new(), &mut found);
+        if labeled {
+            edges.extend(found.iter().map(|(to, _)| (from, *to)));
+            spines.extend(found.into_iter().map(|(_, spine)| spine));
+        } else {
+            let mut found: Vec<CellId> = found.into_iter().map(|(to, _)| to).collect();
+            found.sort();
+            found.dedup();
+            edges.extend(found.into_iter().map(|to| (from, to)));
+        }
     };
     let mut cells: Vec<CellId> = doc.cells.cells().copied().collect();
     cells.sort();
@@ -173,7 +304,21 @@ fn snapshot(doc: &Document) -> Snapshot {
     );
     nodes.sort();
     nodes.dedup();
-    Snapshot { nodes, edges }
+    let at = |node: &GraphNode| nodes.binary_search(node).expect("edges end at nodes");
+    let ends = edges.iter().map(|(from, to)| (at(from), at(&GraphNode::Cell(*to)))).collect();
+    let hidden = vec![0; nodes.len()];
+    let home = doc
+        .root
+        .as_ref()
+        .map(|root| root.as_cell().map_or(GraphNode::Root, GraphNode::Cell));
+    Snapshot {
+        nodes,
+        edges,
+        spines,
+        ends,
+        hidden,
+        home,
+    }
 }
 
 /// FNV-1a for deterministic seeding.
@@ -197,20 +342,22 @@ fn id_hash(node: &GraphNode) -> u32 {
 impl GraphView {
     /// Sync positions with the document (seed new nodes, drop stale
     /// ones) and advance the simulation one step. Runs every frame
-    /// while the view is open; a dragged node is pinned.
+    /// while the view is open; a dragged node is pinned. Laid out in
+    /// layers, nothing moves but what the user drags.
     pub fn step(&mut self, doc: &Document) {
-        let snapshot = snapshot(doc);
-        self.sync(&snapshot);
+        let snapshot = self.take(doc);
+        if self.layered {
+            return;
+        }
         let forces = self.forces(&snapshot);
         let dragged = match &self.drag {
             Some(Drag::Node { node, .. }) => Some(*node),
             _ => None,
         };
-        for id in &snapshot.nodes {
+        for (id, force) in snapshot.nodes.iter().zip(forces) {
             if dragged.as_ref() == Some(id) {
                 continue;
             }
-            let force = forces.get(id).copied().unwrap_or_default();
             let velocity = self.velocities.entry(*id).or_default();
             *velocity = (*velocity + force) * DAMPING;
             let position = self.positions.entry(*id).or_default();
@@ -218,7 +365,234 @@ impl GraphView {
         }
     }
 
+    /// The view as it would draw `doc`: itself, when its layout is of
+    /// `doc` already; otherwise a copy stepped once to seed one. The
+    /// view itself, and the simulation it runs, stay as they are.
+    pub fn laid_out(&self, doc: &Document) -> Cow<'_, GraphView> {
+        if self.taken(doc).is_some() {
+            return Cow::Borrowed(self);
+        }
+        let mut stepped = self.clone();
+        stepped.step(doc);
+        Cow::Owned(stepped)
+    }
+
+    /// Brings `node` to the panel's center, seeding its position
+    /// first if the simulation hasn't met it yet.
+    pub fn center_on(&mut self, doc: &Document, node: &GraphNode) {
+        self.take(doc);
+        if let Some(position) = self.positions.get(node) {
+            self.pan = -position.to_vec2();
+        }
+    }
+
+    /// Shows a merge: the survivor's node moves to the midpoint of the
+    /// pair and the loser's goes, so the loser's mentions visibly swing
+    /// over as the layout relaxes.
+    pub fn collapse(&mut self, survivor: CellId, loser: CellId) {
+        let (survivor, loser) = (GraphNode::Cell(survivor), GraphNode::Cell(loser));
+        if let Some(gone) = self.positions.remove(&loser) {
+            self.velocities.remove(&loser);
+            if let Some(position) = self.positions.get_mut(&survivor) {
+                *position = position.midpoint(gone);
+                self.velocities.insert(survivor, Vec2::ZERO);
+            }
+        }
+    }
+
+    pub fn labeled(&self) -> bool {
+        self.labeled
+    }
+
+    /// Switches between one labeled edge per mention and one arrow
+    /// per linked pair.
+    pub fn set_labeled(&mut self, labeled: bool) {
+        self.labeled = labeled;
+        self.taken = None;
+    }
+
+    pub fn layered(&self) -> bool {
+        self.layered
+    }
+
+    /// Switches between the layered layout and the simulation, which
+    /// picks up from wherever the layers left the nodes.
+    pub fn set_layered(&mut self, layered: bool) {
+        self.layered = layered;
+        self.reshow();
+    }
+
+    /// The neighborhood's radius, when the view is narrowed to one.
+    pub fn radius(&self) -> Option<usize> {
+        self.neighborhood.as_ref().map(|neighborhood| neighborhood.radius)
+    }
+
+    /// Narrows the view to `radius` hops around the focus, or widens
+    /// it back to the whole document. A changed radius keeps the
+    /// focus and what was expanded.
+    pub fn set_radius(&mut self, radius: Option<usize>) {
+        self.neighborhood = radius.map(|radius| {
+            let (focus, expanded) = match self.neighborhood.take() {
+                Some(old) => (old.focus, old.expanded),
+                None => (Vec::new(), HashSet::new()),
+            };
+            Neighborhood {
+                radius,
+                focus,
+                expanded,
+            }
+        });
+        self.reshow();
+    }
+
+    /// Moves the neighborhood's focus, folding away what the old one
+    /// had expanded. An empty focus — a selection with no node to
+    /// speak of — leaves it where it was.
+    pub fn focus(&mut self, focus: Vec<GraphNode>) {
+        match &mut self.neighborhood {
+            Some(neighborhood) if !focus.is_empty() && neighborhood.focus != focus => {
+                neighborhood.focus = focus;
+                neighborhood.expanded.clear();
+            }
+            _ => return,
+        }
+        self.reshow();
+    }
+
+    /// Opens a node's "+k" stub: its hidden neighbors join the view.
+    pub fn expand(&mut self, node: GraphNode) {
+        if let Some(neighborhood) = &mut self.neighborhood
+            && neighborhood.expanded.insert(node)
+        {
+            self.reshow();
+        }
+    }
+
+    fn reshow(&mut self) {
+        if let Some(taken) = &mut self.taken {
+            taken.shown = None;
+        }
+    }
+
+    /// What the view shows of `whole`: all of it, or the
+    /// neighborhood's part — nodes in `whole`'s order, the edges
+    /// among them, and each one's count of neighbors left out.
+    fn narrow(&self, whole: &Rc<Snapshot>) -> Rc<Snapshot> {
+        let Some(neighborhood) = &self.neighborhood else {
+            return whole.clone();
+        };
+        let around = whole.around();
+        let mut distance = vec![usize::MAX; whole.nodes.len()];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        let focus = match neighborhood.focus.as_slice() {
+            [] => whole.home.as_slice(),
+            focus => focus,
+        };
+        for node in focus {
+            if let Ok(index) = whole.nodes.binary_search(node) {
+                distance[index] = 0;
+                queue.push_back(index);
+            }
+        }
+        while let Some(index) = queue.pop_front() {
+            // An expanded node opens a hop past the radius, where the
+            // nodes it reveals are stubs again until expanded too.
+            if distance[index] >= neighborhood.radius
+                && !neighborhood.expanded.contains(&whole.nodes[index])
+            {
+                continue;
+            }
+            for next in &around[index] {
+                if distance[*next] == usize::MAX {
+                    distance[*next] = distance[index] + 1;
+                    queue.push_back(*next);
+                }
+            }
+        }
+        let shown = |index: &usize| distance[*index] != usize::MAX;
+        let kept: Vec<usize> = (0..whole.nodes.len()).filter(shown).collect();
+        let mut renumber = vec![usize::MAX; whole.nodes.len()];
+        for (new, old) in kept.iter().enumerate() {
+            renumber[*old] = new;
+        }
+        let among: Vec<usize> = (0..whole.edges.len())
+            .filter(|edge| shown(&whole.ends[*edge].0) && shown(&whole.ends[*edge].1))
+            .collect();
+        Rc::new(Snapshot {
+            nodes: kept.iter().map(|index| whole.nodes[*index]).collect(),
+            edges: among.iter().map(|edge| whole.edges[*edge]).collect(),
+            spines: (among.iter())
+                .filter_map(|edge| whole.spines.get(*edge).cloned())
+                .collect(),
+            ends: among
+                .iter()
+                .map(|edge| (renumber[whole.ends[*edge].0], renumber[whole.ends[*edge].1]))
+                .collect(),
+            hidden: kept
+                .iter()
+                .map(|index| around[*index].iter().filter(|next| !shown(next)).count())
+                .collect(),
+            home: whole.home,
+        })
+    }
+
+    /// The shown snapshot if it is of `doc` and still current.
+    fn taken(&self, doc: &Document) -> Option<Rc<Snapshot>> {
+        self.taken
+            .as_ref()
+            .filter(|taken| taken.cells.ptr_eq(&doc.cells) && taken.root == doc.root)
+            .and_then(|taken| taken.shown.clone())
+    }
+
+    /// `doc`'s shown snapshot, retaken and synced to only when the
+    /// document or the neighborhood changed — and laid out in layers
+    /// afresh only when what it shows is connected differently.
+    fn take(&mut self, doc: &Document) -> Rc<Snapshot> {
+        if let Some(snapshot) = self.taken(doc) {
+            return snapshot;
+        }
+        let whole = match &self.taken {
+            Some(taken) if taken.cells.ptr_eq(&doc.cells) && taken.root == doc.root => {
+                taken.whole.clone()
+            }
+            _ => Rc::new(snapshot(doc, self.labeled)),
+        };
+        let shown = self.narrow(&whole);
+        // Layers depend on topology alone: an edit to a string, or a
+        // link moved between fields of one cell, keeps them.
+        let before = self.taken.as_ref().and_then(|taken| taken.shown.as_ref());
+        let same = before.is_some_and(|before| {
+            before.nodes == shown.nodes && before.ends == shown.ends && before.home == shown.home
+        });
+        if !self.layered {
+            self.sync(&shown);
+        } else if !same {
+            self.arrange(&shown);
+        }
+        self.taken = Some(Taken {
+            cells: doc.cells.clone(),
+            root: doc.root.clone(),
+            whole,
+            shown: Some(shown.clone()),
+        });
+        shown
+    }
+
+    /// Seeds the nodes entering the view and forgets the ones leaving.
+    /// A node entering a standing layout lands beside a neighbor
+    /// already placed, so the picture grows from where it is rather
+    /// than re-scattering; a fresh layout, or a node nothing placed
+    /// reaches, is seeded by its id around the origin.
     fn sync(&mut self, snapshot: &Snapshot) {
+        let keep: HashSet<&GraphNode> = snapshot.nodes.iter().collect();
+        self.positions.retain(|id, _| keep.contains(id));
+        self.velocities.retain(|id, _| keep.contains(id));
+        let fresh = self.positions.is_empty();
+        let around = if fresh { Vec::new() } else { snapshot.around() };
+        let placed: Vec<usize> = (0..snapshot.nodes.len())
+            .filter(|index| self.positions.contains_key(&snapshot.nodes[*index]))
+            .collect();
+        self.spread(snapshot, &around, placed);
         for (index, id) in snapshot.nodes.iter().enumerate() {
             if !self.positions.contains_key(id) {
                 let position = if self.positions.is_empty() {
@@ -231,16 +605,42 @@ impl GraphView {
                 };
                 self.positions.insert(*id, position);
                 self.velocities.insert(*id, Vec2::ZERO);
+                if !fresh {
+                    self.spread(snapshot, &around, vec![index]);
+                }
+            }
+        }
+    }
+
+    /// Replaces every position with the snapshot's layered layout,
+    /// at rest.
+    fn arrange(&mut self, snapshot: &Snapshot) {
+        self.positions = snapshot.nodes.iter().copied().zip(arrangement(snapshot)).collect();
+        self.velocities = snapshot.nodes.iter().map(|node| (*node, Vec2::ZERO)).collect();
+    }
+
+    /// Places every unplaced node `from`'s placed nodes reach, each
+    /// half a rest length from the neighbor that reached it.
+    fn spread(&mut self, snapshot: &Snapshot, around: &[Vec<usize>], from: Vec<usize>) {
+        let mut queue = VecDeque::from(from);
+        while let Some(placed) = queue.pop_front() {
+            let beside = self.positions[&snapshot.nodes[placed]];
+            for next in around.get(placed).into_iter().flatten() {
+                let id = snapshot.nodes[*next];
+                if let Entry::Vacant(slot) = self.positions.entry(id) {
+                    let angle = f64::from(id_hash(&id) & 0xFFFF) / 65535.0 * TAU;
+                    slot.insert(beside + Vec2::from_angle(angle) * (REST_LENGTH / 2.0));
+                    self.velocities.insert(id, Vec2::ZERO);
+                    queue.push_back(*next);
+                }
             }
         }
-        let keep: std::collections::HashSet<&GraphNode> = snapshot.nodes.iter().collect();
-        self.positions.retain(|id, _| keep.contains(id));
-        self.velocities.retain(|id, _| keep.contains(id));
     }
 
-    fn forces(&self, snapshot: &Snapshot) -> HashMap<GraphNode, Vec2> {
-        let mut forces: HashMap<GraphNode, Vec2> =
-            snapshot.nodes.iter().map(|id| (*id, Vec2::ZERO)).collect();
+    /// The force on each of the snapshot's nodes, in its order.
+    fn forces(&self, snapshot: &Snapshot) -> Vec<Vec2> {
+        let positions: Vec<Point> = snapshot.nodes.iter().map(|id| self.positions[id]).collect();
+        let mut forces = vec![Vec2::ZERO; positions.len()];
         let unit = |delta: Vec2| {
             let length = delta.hypot();
             if length < 1e-6 {
@@ -249,28 +649,34 @@ impl GraphView {
                 delta / length
             }
         };
-        for i in 0..snapshot.nodes.len() {
-            for j in (i + 1)..snapshot.nodes.len() {
-                let (a, b) = (&snapshot.nodes[i], &snapshot.nodes[j]);
-                let delta = self.positions[a] - self.positions[b];
-                let force =
-                    unit(delta) * (REPULSION_K / delta.hypot2().max(1.0)).min(MAX_FORCE);
-                *forces.get_mut(a).unwrap() += force;
-                *forces.get_mut(b).unwrap() -= force;
-            }
+        let tree = Quads::new(&positions);
+        for (i, (at, force)) in positions.iter().zip(&mut forces).enumerate() {
+            tree.visit(*at, THETA, REPULSION_CUTOFF, &mut |mass| {
+                let (from, weight) = match mass {
+                    Mass::Item(j, _) if j == i => return,
+                    Mass::Item(_, point) => (point, 1.0),
+                    Mass::Cluster(point, weight) => (point, weight),
+                };
+                let delta = *at - from;
+                let distance2 = delta.hypot2();
+                if distance2 > REPULSION_CUTOFF * REPULSION_CUTOFF {
+                    return;
+                }
+                let magnitude = (REPULSION_K / distance2.max(1.0)).min(MAX_FORCE);
+                *force += unit(delta) * weight * magnitude;
+            });
         }
-        for (source, to) in &snapshot.edges {
-            let target = &GraphNode::Cell(*to);
-            let delta = self.positions[target] - self.positions[source];
+        for (source, target) in snapshot.ends.iter().copied() {
+            let delta = positions[target] - positions[source];
             let distance = delta.hypot().max(0.1);
             let magnitude =
                 (ATTRACTION_K * (distance - REST_LENGTH)).clamp(-MAX_FORCE, MAX_FORCE);
             let force = unit(delta) * magnitude;
-            *forces.get_mut(source).unwrap() += force;
-            *forces.get_mut(target).unwrap() -= force;
+            forces[source] += force;
+            forces[target] -= force;
         }
-        for id in &snapshot.nodes {
-            *forces.get_mut(id).unwrap() += self.positions[id].to_vec2() * -GRAVITY_K;
+        for (force, position) in forces.iter_mut().zip(&positions) {
+            *force += position.to_vec2() * -GRAVITY_K;
         }
         forces
     }
@@ -388,6 +794,13 @@ impl GraphView {
     }
 }
 
+/// The snapshot's layered layout, one point per node in its order,
+/// rooted at the root's node when it is shown.
+fn arrangement(snapshot: &Snapshot) -> Vec<Point> {
+    let root = (snapshot.home.as_ref()).and_then(|home| snapshot.nodes.binary_search(home).ok());
+    layered::layout(snapshot.nodes.len(), &snapshot.ends, root)
+}
+
 /// The value with every link to `cell` removed: the field or element
 /// holding the link drops, containers purge recursively, and a value
 /// that IS the link strips to nothing. Labels keep referencing — a
@@ -415,6 +828,43 @@ fn strip(value: &Value, cell: CellId) -> Option<Value> {
     }
 }
 
+/// The node a tree selection at `path` centers the neighborhood on:
+/// the cell its value links, else the node whose value holds it —
+/// the cell the path's last Follow crosses into, or the root's.
+pub fn focus_of(sources: &Sources, path: &[Step]) -> Option<GraphNode> {
+    let path = match path.split_last() {
+        Some((Step::Name, parent)) => parent,
+        _ => path,
+    };
+    if let Some(cell) = sources.resolve(path).and_then(Value::as_cell) {
+        return Some(GraphNode::Cell(cell));
+    }
+    site_of(sources, path).map(|(node, _)| node)
+}
+
+/// Where a tree path sits in the graph: the node whose value holds it
+/// — the cell the path's last Follow crosses into, or the root's —
+/// and the spine inside that value.
+fn site_of<'p>(sources: &Sources, path: &'p [Step]) -> Option<(GraphNode, &'p [Step])> {
+    match path.iter().rposition(|step| matches!(step, Step::Follow)) {
+        Some(index) => sources
+            .resolve(&path[..index])
+            .and_then(Value::as_cell)
+            .map(|cell| (GraphNode::Cell(cell), &path[index + 1..])),
+        None => sources.doc.root.as_ref().map(|_| (GraphNode::Root, path)),
+    }
+}
+
+/// `value` without the field or element at `spine` — or, for the
+/// empty spine, without the value itself; `None` when nothing is
+/// there to detach.
+fn detach(value: Option<&Value>, spine: &[Step]) -> Option<Option<Value>> {
+    match spine {
+        [] => value.map(|_| None),
+        spine => value.and_then(|value| progred_graph::spine::without(value, spine)).map(Some),
+    }
+}
+
 /// The value a node stands for: a cell's link, or the root value.
 pub fn node_value(doc: &Document, node: &GraphNode) -> Option<Value> {
     match node {
@@ -423,12 +873,23 @@ pub fn node_value(doc: &Document, node: &GraphNode) -> Option<Value> {
     }
 }
 
-/// Deletes the selected node from the graph: a cell is fully
-/// detached — its table entry (name and value) removed, the root
-/// cleared if it is the root link, and every link to it anywhere
-/// unlinked; a cell whose whole value was such a link keeps its name
-/// and goes valueless. The root node empties the root. Unreferenced
-/// cells simply stop appearing.
+/// The value an edge's field or element holds: the link itself, or
+/// for a label's mention its field's value.
+pub fn edge_value(doc: &Document, from: &GraphNode, spine: &[Step]) -> Option<Value> {
+    let holder = match from {
+        GraphNode::Cell(cell) => doc.cells.value(*cell),
+        GraphNode::Root => doc.root.as_ref(),
+    };
+    progred_graph::spine::get(holder?, spine).cloned()
+}
+
+/// Deletes the graph's selection. A cell node is fully detached —
+/// its table entry (name and value) removed, the root cleared if it
+/// is the root link, and every link to it anywhere unlinked; a cell
+/// whose whole value was such a link keeps its name and goes
+/// valueless. The root node empties the root. Unreferenced cells
+/// simply stop appearing. An edge detaches just its field or
+/// element, the way deleting it in the tree does.
 pub fn delete_selection(doc: &mut Document, selection: &GraphSelection) -> bool {
     let before = doc.cells.clone();
     let before_root = doc.root.clone();
@@ -436,6 +897,18 @@ pub fn delete_selection(doc: &mut Document, selection: &GraphSelection) -> bool
         GraphSelection::Node(GraphNode::Root) => {
             doc.root = None;
         }
+        GraphSelection::Edge(GraphNode::Root, spine) => {
+            if let Some(next) = detach(doc.root.as_ref(), spine) {
+                doc.root = next;
+            }
+        }
+        GraphSelection::Edge(GraphNode::Cell(cell), spine) => {
+            match detach(doc.cells.value(*cell), spine) {
+                Some(Some(next)) => doc.cells.set_value(*cell, next),
+                Some(None) => doc.cells.clear_value(*cell),
+                None => {}
+            }
+        }
         GraphSelection::Node(GraphNode::Cell(cell)) => {
             doc.root = doc.root.take().and_then(|root| strip(&root, *cell));
             doc.cells.remove(*cell);
@@ -468,6 +941,11 @@ pub struct Hooks<C> {
     /// Command-click: commit the pointed-at cell into the open
     /// pending; false when nothing is pending.
     pub pick: Rc<dyn Fn(&mut C, Value) -> bool>,
+    /// A click on a node's "+k" stub: show its hidden neighbors.
+    pub expand: Rc<dyn Fn(&mut C, GraphNode)>,
+    /// A click on a labeled edge: the node whose value holds the
+    /// mention, and the spine of its field or element.
+    pub press_edge: Rc<dyn Fn(&mut C, GraphNode, Vec<Step>)>,
     /// The pointer's resting claim inside the panel: the node under
     /// it, or `None` for the pane's own ground — either way the pane
     /// takes the pointer, so the tree beneath never lights.
@@ -477,11 +955,10 @@ pub struct Hooks<C> {
 const FONT_SIZE: f32 = 10.0;
 const NODE_PADDING: f64 = 7.0;
 const NODE_MIN_HEIGHT: f64 = 24.0;
+const STUB_PADDING: f64 = 4.0;
 const ARROW_LENGTH: f64 = 7.0;
 const ARROW_WIDTH: f64 = 3.5;
 
-const PANEL_BG: [f32; 4] = [0.968, 0.972, 0.984, 1.0];
-const SEPARATOR: [f32; 4] = [0.851, 0.867, 0.890, 1.0];
 const NODE_FILL: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
 /// External (library-authority) identities sit on a subtly darker
 /// ground, as in the tree.
@@ -502,6 +979,19 @@ const TEXT: [f32; 4] = [0.13, 0.14, 0.16, 1.0];
 const STRING_TEXT: [f32; 4] = [0.55, 0.33, 0.28, 1.0];
 const DIM_TEXT: [f32; 4] = [0.55, 0.58, 0.64, 1.0];
 
+struct EdgeView {
+    path: BezPath,
+    /// The path's one segment, for hit-testing by distance.
+    curve: PathSeg,
+    arrow: BezPath,
+    /// With labeled edges, the field or element's name on a pill at
+    /// the curve's midpoint.
+    label: Option<(Rect, Layout<Brush>)>,
+    /// The edge's index in the snapshot.
+    index: usize,
+    primary: bool,
+}
+
 struct NodeView {
     id: GraphNode,
     rect: Rect,
@@ -511,6 +1001,19 @@ struct NodeView {
     /// A cell holding no value: dashed border, the red-link look.
     bare: bool,
     strength: Strength,
+    /// The "+k" badge over a node whose neighbors the neighborhood
+    /// hides, and its text.
+    stub: Option<(Rect, Layout<Brush>)>,
+}
+
+/// What a press inside the panel lands on. Edges are hit by their
+/// label or near their curve, by index into the edge views.
+#[derive(Clone, Copy)]
+enum Hit {
+    Node(GraphNode),
+    Stub(GraphNode),
+    Label(usize),
+    Curve(usize),
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -564,14 +1067,7 @@ fn node_content(
             }
         }
         GraphNode::Root => {
-            let mark = match &doc.root {
-                Some(Value::Record(_)) => "{…}".to_string(),
-                Some(Value::List(elements)) if elements.is_empty() => "[ ]".to_string(),
-                Some(Value::List(_)) => "[…]".to_string(),
-                Some(Value::Atom(Atom::String(s))) => format!("\"{s}\""),
-                Some(other) => other.to_string(),
-                None => String::new(),
-            };
+            let mark = root_mark(doc);
             match &doc.root {
                 Some(Value::Atom(Atom::String(_))) => {
                     layout_text(tcx, &mark, size, STRING_TEXT, ui)
@@ -582,6 +1078,36 @@ fn node_content(
     }
 }
 
+/// The root node's text: a container as its brackets, an atom as
+/// itself.
+fn root_mark(doc: &Document) -> String {
+    match &doc.root {
+        Some(Value::Record(_)) => "{…}".to_string(),
+        Some(Value::List(elements)) if elements.is_empty() => "[ ]".to_string(),
+        Some(Value::List(_)) => "[…]".to_string(),
+        Some(Value::Atom(Atom::String(s))) => format!("\"{s}\""),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+/// A labeled edge's words: the holding field or element, from the
+/// holder's value down — "points › [0]".
+fn edge_words(
+    sources: &Sources,
+    names: &Names,
+    raw: bool,
+    from: &GraphNode,
+    spine: &[Step],
+) -> String {
+    let holder = match from {
+        GraphNode::Cell(cell) => sources.value(*cell),
+        GraphNode::Root => sources.doc.root.as_ref(),
+    };
+    let trail = crate::check::trail(sources, names, raw, holder, spine);
+    trail.trim_start_matches(" › ").to_string()
+}
+
 fn draw_content<P: Canvas>(p: &mut P, layout: &Layout<Brush>, rect: Rect) {
     let at = Point::new(
         rect.center().x - f64::from(layout.width()) / 2.0,
@@ -647,39 +1173,70 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
     let pan = view.pan;
     let center = panel.center().to_vec2();
     let to_panel = |world: Point| ((world.to_vec2() + pan) * px).to_point() + center;
-    let snapshot = snapshot(doc);
+    let snapshot = view
+        .taken(doc)
+        .unwrap_or_else(|| view.narrow(&Rc::new(snapshot(doc, view.labeled))));
 
     // The document selection projects into the graph through its
-    // VALUE: the cell it links washes as a secondary.
-    let doc_value = doc_selection.and_then(|selection| match selection {
-        Selection::Edge { path, .. } => sources.resolve(path).cloned(),
-        _ => None,
-    });
-    let secondary_cell = doc_value
-        .or_else(|| match selection {
-            Some(GraphSelection::Node(node)) => node_value(doc, node),
-            _ => None,
-        })
-        .and_then(|value| value.as_cell());
+    // VALUES: the cells a multi-selection's members link all wash as
+    // secondaries.
+    let mut values: Vec<Value> = match doc_selection {
+        Some(selection @ Selection::Edge { .. }) => selection
+            .paths()
+            .into_iter()
+            .filter_map(|path| sources.resolve(path).cloned())
+            .collect(),
+        _ => Vec::new(),
+    };
+    if values.is_empty()
+        && let Some(GraphSelection::Node(node)) = selection
+    {
+        values.extend(node_value(doc, node));
+    }
+    let secondary_cells: Vec<CellId> = values.iter().filter_map(Value::as_cell).collect();
+    // A labeled edge is primary when it is the selection, in the graph
+    // or as the tree's field or element.
+    let selected_sites: Vec<(GraphNode, &[Step])> = match (selection, doc_selection) {
+        (Some(GraphSelection::Edge(from, spine)), _) => vec![(*from, spine.as_slice())],
+        (_, Some(selection @ Selection::Edge { .. })) => selection
+            .paths()
+            .into_iter()
+            .filter_map(|path| site_of(sources, path))
+            .collect(),
+        _ => Vec::new(),
+    };
     // The document's hover projects in the same way, at half voice.
     let hover_cell = doc_hover
         .and_then(|hover| crate::raw::hover_value(sources, names, raw, doc_selection, hover))
         .and_then(|value| value.as_cell());
 
     let root_link = doc.root.as_ref().and_then(Value::as_cell);
+    // Only what the viewport shows is laid out; a node off it is a
+    // bare square its edges run to. The margin keeps a wide node whose
+    // center is just outside from popping in.
+    let shown = panel.inflate(200.0 * px, 200.0 * px);
+    let mut rects: HashMap<GraphNode, Rect> = HashMap::new();
     let node_views: Vec<NodeView> = snapshot
         .nodes
         .iter()
-        .filter_map(|id| {
+        .zip(&snapshot.hidden)
+        .filter_map(|(id, hidden)| {
             let world = *view.positions.get(id)?;
+            let at = to_panel(world);
+            if !shown.contains(at) {
+                let side = NODE_MIN_HEIGHT * px;
+                rects.insert(*id, Rect::from_center_size(at, (side, side)));
+                return None;
+            }
             let content = node_content(sources, names, raw, doc, id, tcx, zoom);
             let (w, h) = (f64::from(content.width()), f64::from(content.height()));
             let width = w + 2.0 * NODE_PADDING * px;
             let height = (h + 2.0 * NODE_PADDING * px).max(NODE_MIN_HEIGHT * px);
-            let at = to_panel(world);
+            let rect = Rect::from_center_size(at, (width, height));
+            rects.insert(*id, rect);
             let strength = if matches!(selection, Some(GraphSelection::Node(n)) if n == id) {
                 Strength::Primary
-            } else if matches!(id, GraphNode::Cell(cell) if secondary_cell == Some(*cell)) {
+            } else if matches!(id, GraphNode::Cell(cell) if secondary_cells.contains(cell)) {
                 Strength::Secondary
             } else if hover == Some(id)
                 || matches!(id, GraphNode::Cell(cell) if hover_cell == Some(*cell))
@@ -695,22 +1252,27 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
                 ),
                 GraphNode::Root => (false, false),
             };
+            // The badge straddles the node's top-right corner.
+            let stub = (*hidden > 0).then(|| {
+                let (size, ui) = (FONT_SIZE * 0.9 * zoom as f32, GenericFamily::SystemUi);
+                let text = layout_text(tcx, &format!("+{hidden}"), size, DIM_TEXT, ui);
+                let (w, h) = (f64::from(text.width()), f64::from(text.height()));
+                let size = (w + 2.0 * STUB_PADDING * px, h + STUB_PADDING * px);
+                (Rect::from_center_size(Point::new(rect.x1, rect.y0), size), text)
+            });
             Some(NodeView {
                 id: *id,
-                rect: Rect::from_center_size(at, (width, height)),
+                rect,
                 content,
                 root: matches!(id, GraphNode::Root)
                     || matches!(id, GraphNode::Cell(cell) if root_link == Some(*cell)),
                 external,
                 bare,
                 strength,
+                stub,
             })
         })
         .collect();
-    let rects: HashMap<&GraphNode, Rect> = node_views
-        .iter()
-        .map(|node| (&node.id, node.rect))
-        .collect();
 
     // The two directions between one pair arc to opposite sides;
     // offsets live in the canonical pair's frame because the normal
@@ -724,27 +1286,34 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
     }
     let mut pair_seen: HashMap<(GraphNode, GraphNode), usize> = HashMap::new();
 
-    let edge_views: Vec<(BezPath, BezPath)> = snapshot
+    let edge_views: Vec<EdgeView> = snapshot
         .edges
         .iter()
-        .filter_map(|(from, to)| {
+        .enumerate()
+        .filter_map(|(index, (from, to))| {
             let source_rect = *rects.get(from)?;
             let target = GraphNode::Cell(*to);
             let target_rect = *rects.get(&target)?;
             let key = pair(from, &target);
+            // Counted whether drawn or not, so the arcs keep their
+            // sides as the viewport moves.
             let total = pair_counts[&key];
-            let index = {
+            let nth = {
                 let seen = pair_seen.entry(key).or_default();
-                let index = *seen;
+                let nth = *seen;
                 *seen += 1;
-                index
+                nth
             };
             let aligned = (*from, target) == key;
-            let offset = (index as f64 - (total as f64 - 1.0) / 2.0)
+            let offset = (nth as f64 - (total as f64 - 1.0) / 2.0)
                 * PARALLEL_SPACING
                 * px
                 * if aligned { 1.0 } else { -1.0 };
-            let (path, tip, tip_direction) = if *from == target {
+            let reach = offset.abs() + 40.0 * px;
+            if !source_rect.union(target_rect).inflate(reach, reach).overlaps(panel) {
+                return None;
+            }
+            let (curve, tip, tip_direction) = if *from == target {
                 // Self-loop: a cubic arch above the node.
                 let top = Point::new(source_rect.center().x, source_rect.y0);
                 let rise = 40.0 * px;
@@ -753,10 +1322,7 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
                 let c2 = Point::new(top.x + spread, top.y - rise);
                 let start = Point::new(top.x - 8.0 * px, top.y);
                 let end = Point::new(top.x + 8.0 * px, top.y);
-                let mut path = BezPath::new();
-                path.move_to(start);
-                path.curve_to(c1, c2, end);
-                (path, end, end - c2)
+                (PathSeg::Cubic(CubicBez::new(start, c1, c2, end)), end, end - c2)
             } else {
                 let a = source_rect.center();
                 let b = target_rect.center();
@@ -769,12 +1335,28 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
                 let control = a.midpoint(b) + normal * offset;
                 let start = clip_to_rect(a, control, source_rect);
                 let end = clip_to_rect(b, control, target_rect);
-                let mut path = BezPath::new();
-                path.move_to(start);
-                path.quad_to(control, end);
-                (path, end, end - control)
+                (PathSeg::Quad(QuadBez::new(start, control, end)), end, end - control)
             };
-            Some((path, arrowhead(tip, tip_direction, px)))
+            let spine = snapshot.spines.get(index);
+            let label = spine.filter(|spine| !spine.is_empty()).map(|spine| {
+                let words = edge_words(sources, names, raw, from, spine);
+                let size = FONT_SIZE * 0.9 * zoom as f32;
+                let text = layout_text(tcx, &words, size, DIM_TEXT, GenericFamily::SystemUi);
+                let (w, h) = (f64::from(text.width()), f64::from(text.height()));
+                let size = (w + 2.0 * STUB_PADDING * px, h + STUB_PADDING * px);
+                (Rect::from_center_size(curve.eval(0.5), size), text)
+            });
+            let primary = spine.is_some_and(|spine| {
+                selected_sites.iter().any(|(at, selected)| at == from && *selected == spine)
+            });
+            Some(EdgeView {
+                path: BezPath::from_path_segments(std::iter::once(curve)),
+                curve,
+                arrow: arrowhead(tip, tip_direction, px),
+                label,
+                index,
+                primary,
+            })
         })
         .collect();
 
@@ -783,6 +1365,8 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
     let drag_to = hooks.drag_to.clone();
     let release = hooks.release.clone();
     let pick = hooks.pick.clone();
+    let expand = hooks.expand.clone();
+    let press_edge = hooks.press_edge.clone();
     let hover_hook = hooks.hover.clone();
     let extent = Extent {
         width: panel.width(),
@@ -794,19 +1378,30 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
         // Everything the viewport shows stays inside the panel.
         p.clip(panel, Affine::IDENTITY, |p| {
             p.fill(panel, Color::new(PANEL_BG), Affine::IDENTITY);
-            for (path, arrow) in &edge_views {
+            for edge in &edge_views {
+                let (color, width) = if edge.primary {
+                    (PRIMARY, 2.0)
+                } else {
+                    (EDGE, 1.2)
+                };
                 p.stroke(
-                    path.clone(),
-                    Stroke::new(1.2 * px),
-                    Color::new(EDGE),
+                    edge.path.clone(),
+                    Stroke::new(width * px),
+                    Color::new(color),
                     Affine::IDENTITY,
                 );
                 p.stroke(
-                    arrow.clone(),
-                    Stroke::new(1.2 * px),
-                    Color::new(EDGE),
+                    edge.arrow.clone(),
+                    Stroke::new(width * px),
+                    Color::new(color),
                     Affine::IDENTITY,
                 );
+                if let Some((rect, text)) = &edge.label {
+                    let pill = RoundedRect::from_rect(*rect, rect.height() / 2.0);
+                    p.fill(pill, Color::new(PANEL_BG), Affine::IDENTITY);
+                    p.stroke(pill, Stroke::new(width * px), Color::new(color), Affine::IDENTITY);
+                    draw_content(p, text, *rect);
+                }
             }
             for node in &node_views {
                 let shape = RoundedRect::from_rect(node.rect, 5.0 * px);
@@ -836,6 +1431,13 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
                 };
                 p.stroke(shape, stroke, Color::new(color), Affine::IDENTITY);
                 draw_content(p, &node.content, node.rect);
+                if let Some((rect, text)) = &node.stub {
+                    let badge = RoundedRect::from_rect(*rect, rect.height() / 2.0);
+                    let dashes = Stroke::new(px).with_dashes(0.0, [3.0 * px, 2.0 * px]);
+                    p.fill(badge, Color::new(PANEL_BG), Affine::IDENTITY);
+                    p.stroke(badge, dashes, Color::new(BORDER), Affine::IDENTITY);
+                    draw_content(p, text, *rect);
+                }
             }
         });
         p.stroke(
@@ -845,13 +1447,61 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
             Affine::IDENTITY,
         );
 
-        // Hit-testing mirrors draw order back-to-front: nodes over
-        // background; the pane swallows everything inside the panel
-        // so nothing lands on the document beneath.
-        let node_hits: Vec<(Rect, GraphNode)> = node_views
+        // Hit-testing mirrors draw order back-to-front: stubs over
+        // nodes over edges over background; the pane swallows
+        // everything inside the panel so nothing lands on the
+        // document beneath. Only labeled edges are there to hit.
+        let stub_hits = node_views
+            .iter()
+            .filter_map(|node| Some((node.stub.as_ref()?.0, Hit::Stub(node.id))));
+        let labeled = (edge_views.iter().enumerate())
+            .filter(|(_, edge)| snapshot.spines.get(edge.index).is_some());
+        let label_hits = labeled
+            .clone()
+            .filter_map(|(index, edge)| Some((edge.label.as_ref()?.0, Hit::Label(index))));
+        let slop = EDGE_SLOP * scale;
+        let curve_hits = labeled.map(|(index, edge)| {
+            (edge.curve.bounding_box().inflate(slop, slop), Hit::Curve(index))
+        });
+        let node_hits: Vec<(Rect, Hit)> = stub_hits
+            .chain(node_views.iter().map(|node| (node.rect, Hit::Node(node.id))))
+            .chain(label_hits)
+            .chain(curve_hits)
+            .collect();
+        let curves: Vec<PathSeg> = edge_views.iter().map(|edge| edge.curve).collect();
+        let sites: Vec<(GraphNode, Vec<Step>)> = edge_views
             .iter()
-            .map(|node| (node.rect, node.id))
+            .map(|edge| {
+                let spine = snapshot.spines.get(edge.index).cloned().unwrap_or_default();
+                (snapshot.edges[edge.index].0, spine)
+            })
             .collect();
+        // Filed clipped to the panel: a long edge's box can span far
+        // past it, and would otherwise fill buckets nothing can hit.
+        let mut grid = Grid::new(64.0 * scale);
+        for (index, (rect, _)) in node_hits.iter().enumerate() {
+            let inside = rect.intersect(panel);
+            if inside.width() > 0.0 && inside.height() > 0.0 {
+                grid.insert(index, inside);
+            }
+        }
+        // The first hit in draw order, as a scan would find it.
+        let hits = Rc::new((node_hits, grid, curves));
+        let hit = move |hits: &(Vec<(Rect, Hit)>, Grid, Vec<PathSeg>), point: Point| {
+            let (node_hits, grid, curves) = hits;
+            grid.at(point)
+                .iter()
+                .map(|index| node_hits[*index])
+                .find(|(rect, hit)| {
+                    rect.contains(point)
+                        && match hit {
+                            Hit::Curve(index) => {
+                                curves[*index].nearest(point, 0.1).distance_sq <= slop * slop
+                            }
+                            _ => true,
+                        }
+                })
+        };
         let from_panel = move |window: Point| {
             (((window - panel.center()) / px) - pan).to_point()
         };
@@ -859,41 +1509,45 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
         // or the pane's own ground — and never consume the event, so
         // the drag handler registered after still sees every move.
         let hover = hover_hook.clone();
-        let hover_hits = node_hits.clone();
+        let hover_hits = hits.clone();
         p.handler().on_pointer_move(move |ctx, update| {
             let point = Point::new(update.current.position.x, update.current.position.y);
             if panel.contains(point) {
-                hover(
-                    ctx,
-                    hover_hits
-                        .iter()
-                        .find(|(rect, _)| rect.contains(point))
-                        .map(|(_, id)| *id),
-                );
+                let node = hit(&hover_hits, point).and_then(|(_, hit)| match hit {
+                    Hit::Node(id) | Hit::Stub(id) => Some(id),
+                    Hit::Label(_) | Hit::Curve(_) => None,
+                });
+                hover(ctx, node);
             }
             false
         });
         let press_node = press_node.clone();
         let press_background = press_background.clone();
         let pick = pick.clone();
+        let expand = expand.clone();
+        let press_edge = press_edge.clone();
         p.handler().on_pointer_down(move |ctx, event| {
             let point = Point::new(event.state.position.x, event.state.position.y);
             event.button == Some(PointerButton::Primary) && panel.contains(point) && {
-                if let Some((rect, id)) =
-                    node_hits.iter().find(|(rect, _)| rect.contains(point))
-                {
-                    let picked = command(&event.state.modifiers)
-                        && match id {
-                            GraphNode::Cell(cell) => pick(ctx, Value::from(*cell)),
-                            GraphNode::Root => false,
-                        };
-                    if !picked {
-                        let world = from_panel(point);
-                        let node_world = from_panel(rect.center());
-                        press_node(ctx, *id, world - node_world, point);
+                match hit(&hits, point) {
+                    Some((_, Hit::Stub(id))) => expand(ctx, id),
+                    Some((_, Hit::Label(index) | Hit::Curve(index))) => {
+                        let (from, spine) = sites[index].clone();
+                        press_edge(ctx, from, spine);
                     }
-                } else {
-                    press_background(ctx, point);
+                    Some((rect, Hit::Node(id))) => {
+                        let picked = command(&event.state.modifiers)
+                            && match id {
+                                GraphNode::Cell(cell) => pick(ctx, Value::from(cell)),
+                                GraphNode::Root => false,
+                            };
+                        if !picked {
+                            let world = from_panel(point);
+                            let node_world = from_panel(rect.center());
+                            press_node(ctx, id, world - node_world, point);
+                        }
+                    }
+                    None => press_background(ctx, point),
                 }
                 true
             }
@@ -909,6 +1563,142 @@ pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
     })
 }
 
+/// World units around the outermost node centers in an export: room
+/// for the nodes themselves, names of usual length included.
+const EXPORT_MARGIN: f64 = 120.0;
+
+/// The canvas an export draws into: the pane's drawing kept, its
+/// handlers registered and dropped.
+struct Recording {
+    list: DrawList,
+    handler: Handler<()>,
+}
+
+impl Canvas for Recording {
+    fn fill(&mut self, shape: impl Into<draw::Shape>, brush: impl Into<Brush>, transform: Affine) {
+        self.list.fill(shape, brush, transform);
+    }
+    fn stroke(
+        &mut self,
+        shape: impl Into<draw::Shape>,
+        style: Stroke,
+        brush: impl Into<Brush>,
+        transform: Affine,
+    ) {
+        self.list.stroke(shape, style, brush, transform);
+    }
+    fn glyph_run(&mut self, run: GlyphRun) {
+        self.list.glyph_run(run);
+    }
+    fn clip(
+        &mut self,
+        shape: impl Into<draw::Shape>,
+        transform: Affine,
+        content: impl FnOnce(&mut Self),
+    ) {
+        let outer = std::mem::take(&mut self.list);
+        content(self);
+        let inner = std::mem::replace(&mut self.list, outer);
+        self.list.clip(shape, transform, |list| *list = inner);
+    }
+}
+
+impl HasHandler<()> for Recording {
+    fn handler(&mut self) -> &mut Handler<()> {
+        &mut self.handler
+    }
+}
+
+/// The graph as a standalone SVG: the pane's own drawing of what
+/// `view` shows, framed whole at unit zoom whatever the viewport, with
+/// nothing selected or hovered. Headless — the view only has to have
+/// been stepped, and a layered one draws the same file for the same
+/// document.
+pub fn export_svg(
+    sources: &Sources,
+    view: &GraphView,
+    names: &Names,
+    raw: bool,
+    tcx: &mut TextCtx,
+) -> String {
+    let doc = sources.doc;
+    let snapshot = view
+        .taken(doc)
+        .unwrap_or_else(|| view.narrow(&Rc::new(snapshot(doc, view.labeled))));
+    let world = (snapshot.nodes.iter())
+        .filter_map(|node| view.positions.get(node))
+        .fold(None, |bounds: Option<Rect>, at| {
+            Some(bounds.map_or(Rect::from_points(*at, *at), |bounds| bounds.union_pt(*at)))
+        })
+        .unwrap_or_default()
+        .inflate(EXPORT_MARGIN, EXPORT_MARGIN);
+    let framed = GraphView {
+        drag: None,
+        pan: -world.center().to_vec2(),
+        zoom: 1.0,
+        ..view.clone()
+    };
+    let panel = Rect::from_origin_size(Point::ZERO, world.size() * f64::from(tcx.scale));
+    let hooks = Hooks::<()> {
+        press_node: Rc::new(|_, _, _, _| {}),
+        press_background: Rc::new(|_, _| {}),
+        drag_to: Rc::new(|_, _, _, _| false),
+        release: Rc::new(|_| false),
+        pick: Rc::new(|_, _| false),
+        expand: Rc::new(|_, _| {}),
+        press_edge: Rc::new(|_, _, _| {}),
+        hover: Rc::new(|_, _| {}),
+    };
+    let mut recording = Recording {
+        list: DrawList::new(),
+        handler: Handler::default(),
+    };
+    let pane = pane(sources, &framed, None, None, None, None, names, raw, tcx, panel, &hooks);
+    place_top_left(pane, &mut recording, Point::ZERO);
+    crate::svg::document(&recording.list, panel)
+}
+
+/// The graph as Graphviz DOT: what `view` shows, each node by its
+/// display name — valueless cells dashed red, the root's node filled
+/// as on screen — and each edge a mention, labeled by its field or
+/// element when the view labels edges. Graphviz does its own layout.
+pub fn export_dot(sources: &Sources, view: &GraphView, names: &Names, raw: bool) -> String {
+    let doc = sources.doc;
+    let snapshot = view
+        .taken(doc)
+        .unwrap_or_else(|| view.narrow(&Rc::new(snapshot(doc, view.labeled))));
+    let quoted = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let id = |node: &GraphNode| match node {
+        GraphNode::Root => quoted("root"),
+        GraphNode::Cell(cell) => quoted(&cell.to_string()),
+    };
+    let mut out = String::from("digraph progred {\n    node [shape=box, style=rounded];\n");
+    for node in &snapshot.nodes {
+        let name = match node {
+            GraphNode::Cell(cell) => crate::conventions::display_name(sources, names, raw, *cell)
+                .unwrap_or_else(|| short_id(*cell)),
+            GraphNode::Root => root_mark(doc),
+        };
+        let mut attributes = vec![format!("label={}", quoted(&name))];
+        if snapshot.home == Some(*node) {
+            attributes.push("style=\"rounded,filled\", fillcolor=\"#EBF0FA\"".to_string());
+        } else if matches!(node, GraphNode::Cell(cell) if sources.value(*cell).is_none()) {
+            attributes.push("style=\"rounded,dashed\", color=red".to_string());
+        }
+        out.push_str(&format!("    {} [{}];\n", id(node), attributes.join(", ")));
+    }
+    for (index, (from, to)) in snapshot.edges.iter().enumerate() {
+        let label = (snapshot.spines.get(index))
+            .filter(|spine| !spine.is_empty())
+            .map(|spine| edge_words(sources, names, raw, from, spine))
+            .map(|words| format!(" [label={}]", quoted(&words)))
+            .unwrap_or_default();
+        out.push_str(&format!("    {} -> {}{label};\n", id(from), id(&GraphNode::Cell(*to))));
+    }
+    out.push_str("}\n");
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -930,6 +1720,7 @@ mod tests {
             Document {
                 root: Some(Value::from(a)),
                 cells,
+                libraries: Vec::new(),
             },
             a,
             b,
@@ -939,7 +1730,7 @@ mod tests {
     #[test]
     fn snapshot_draws_cells_and_deduped_mentions() {
         let (mut doc, a, b) = doc();
-        let snapshot = super::snapshot(&doc);
+        let snapshot = super::snapshot(&doc, false);
         // Cells only: a and b, one mention, atoms as content.
         assert_eq!(snapshot.nodes.len(), 2);
         assert!(snapshot.nodes.contains(&GraphNode::Cell(a)));
@@ -967,7 +1758,7 @@ mod tests {
             ]),
         );
         doc.root = Some(Value::record([(Label::from("shape"), Value::from(a))]));
-        let snapshot = super::snapshot(&doc);
+        let snapshot = super::snapshot(&doc, false);
         assert!(snapshot.nodes.contains(&GraphNode::Root));
         assert!(snapshot.nodes.contains(&GraphNode::Cell(bare)));
         assert_eq!(snapshot.nodes.len(), 4);
@@ -999,6 +1790,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn layered_layout_is_the_same_for_the_same_document() {
+        let (mut doc, a, b) = doc();
+        // A cycle back to the root's cell, stated in a second table
+        // built in the other order.
+        doc.cells.set_value(b, Value::record([(Label::from("back"), Value::from(a))]));
+        let mut cells = Cells::new();
+        cells.set_value(b, doc.cells.value(b).unwrap().clone());
+        cells.set_value(a, doc.cells.value(a).unwrap().clone());
+        let twin = Document {
+            root: doc.root.clone(),
+            cells,
+            libraries: Vec::new(),
+        };
+        let laid = |doc: &Document| {
+            let mut view = GraphView::default();
+            view.set_layered(true);
+            view.step(doc);
+            view
+        };
+        let mut view = laid(&doc);
+        assert_eq!(view.positions, laid(&twin).positions);
+        let at = &view.positions;
+        assert!(at[&GraphNode::Cell(a)].y < at[&GraphNode::Cell(b)].y);
+
+        // Stepping moves nothing: the view holds still, a drag aside.
+        let before = view.positions.clone();
+        view.step(&doc);
+        assert_eq!(view.positions, before);
+        assert!(!view.hot());
+    }
+
     #[test]
     fn deleting_a_cell_unlinks_it_everywhere() {
         let (mut doc, a, b) = doc();
@@ -1053,6 +1876,233 @@ mod tests {
         assert!(doc.root.is_none());
     }
 
+    #[test]
+    fn labeled_edges_are_one_per_mention_and_detach_alone() {
+        let (mut doc, a, b) = doc();
+        let points = Label::from("points");
+        doc.cells.set_value(
+            a,
+            Value::record([
+                (Label::from("to"), Value::from(b)),
+                (points.clone(), Value::list([Value::from(b), Value::from(b)])),
+            ]),
+        );
+        // Deduplicated, one arrow; labeled, one per mention, in the
+        // order the value states them.
+        assert_eq!(super::snapshot(&doc, false).edges.len(), 1);
+        let labeled = super::snapshot(&doc, true);
+        assert_eq!(labeled.edges, vec![(GraphNode::Cell(a), b); 3]);
+        let list = doc.cells.value(a).unwrap().as_record().unwrap()[&points].as_list().unwrap();
+        let first = list.keys().next().unwrap().clone();
+        let element = vec![Step::Key(points.clone()), Step::Element(first)];
+        assert_eq!(labeled.spines[0], element);
+        assert_eq!(
+            edge_value(&doc, &GraphNode::Cell(a), &element),
+            Some(Value::from(b))
+        );
+
+        // Deleting one edge drops just its element; b stays linked.
+        let edge = GraphSelection::Edge(GraphNode::Cell(a), element);
+        assert!(delete_selection(&mut doc, &edge));
+        let record = doc.cells.value(a).unwrap().as_record().unwrap();
+        assert_eq!(record[&points].as_list().unwrap().len(), 1);
+        assert_eq!(super::snapshot(&doc, true).edges.len(), 2);
+        assert!(!delete_selection(&mut doc, &edge));
+    }
+
+    #[test]
+    fn neighborhood_follows_hops_both_ways_and_stubs_the_rest() {
+        // a → b → c → d, and e → b: from c, one hop reaches b and d.
+        let ids: Vec<CellId> = (0..5).map(|_| new_cell_id()).collect();
+        let (a, b, c, d, e) = (ids[0], ids[1], ids[2], ids[3], ids[4]);
+        let mut cells = Cells::new();
+        for (from, to) in [(a, b), (b, c), (c, d), (e, b)] {
+            cells.set_value(from, Value::record([(Label::from("to"), Value::from(to))]));
+        }
+        let doc = Document {
+            root: Some(Value::from(a)),
+            cells,
+            libraries: Vec::new(),
+        };
+        let shown = |view: &mut GraphView| {
+            let snapshot = view.take(&doc);
+            let mut shown: Vec<(CellId, usize)> = (snapshot.nodes.iter().zip(&snapshot.hidden))
+                .filter_map(|(node, hidden)| match node {
+                    GraphNode::Cell(cell) => Some((*cell, *hidden)),
+                    GraphNode::Root => None,
+                })
+                .collect();
+            shown.sort();
+            shown
+        };
+        let sorted = |mut expected: Vec<(CellId, usize)>| {
+            expected.sort();
+            expected
+        };
+        let mut view = GraphView::default();
+        view.set_radius(Some(1));
+        // Nothing followed yet: the root's cell is home.
+        assert_eq!(shown(&mut view), sorted(vec![(a, 0), (b, 2)]));
+
+        view.focus(vec![GraphNode::Cell(c)]);
+        // b stubs its two hidden neighbors, a and e.
+        assert_eq!(shown(&mut view), sorted(vec![(b, 2), (c, 0), (d, 0)]));
+        let standing = view.positions[&GraphNode::Cell(b)];
+
+        view.expand(GraphNode::Cell(b));
+        assert_eq!(shown(&mut view), sorted(vec![(a, 0), (b, 0), (c, 0), (d, 0), (e, 0)]));
+        // Who stayed stays put; who entered lands beside who let them.
+        assert_eq!(view.positions[&GraphNode::Cell(b)], standing);
+        let entered = view.positions[&GraphNode::Cell(e)];
+        assert!(((entered - standing).hypot() - REST_LENGTH / 2.0).abs() < 1e-9);
+
+        // A new focus folds the expansion back; no focus keeps it.
+        view.focus(vec![GraphNode::Cell(d)]);
+        assert_eq!(shown(&mut view), sorted(vec![(c, 1), (d, 0)]));
+        view.focus(Vec::new());
+        assert_eq!(shown(&mut view).len(), 2);
+        view.set_radius(None);
+        assert_eq!(shown(&mut view).len(), 5);
+    }
+
+    #[test]
+    fn exports_draw_what_the_view_shows_headless() {
+        let (mut doc, a, b) = doc();
+        let red = new_cell_id();
+        doc.cells.set_value(b, Value::record([(Label::from("next"), Value::from(red))]));
+        let library = crate::conventions::library();
+        let sources = Sources {
+            doc: &doc,
+            library: &library,
+        };
+        let names = Names::table();
+        let mut view = GraphView::default();
+        view.set_labeled(true);
+        view.set_layered(true);
+        view.step(&doc);
+
+        // DOT: every node by name, the root's filled, the valueless
+        // one dashed red, each mention labeled by its field.
+        let dot = export_dot(&sources, &view, &names, false);
+        assert!(dot.starts_with("digraph progred {\n"), "{dot}");
+        let (a_id, b_id, red_id) = (a.to_string(), b.to_string(), red.to_string());
+        for line in [
+            format!("\"{a_id}\" [label=\"{}\", style=\"rounded,filled\"", short_id(a)),
+            format!("\"{red_id}\" [label=\"{}\", style=\"rounded,dashed\"", short_id(red)),
+            format!("\"{a_id}\" -> \"{b_id}\" [label=\"to\"];"),
+            format!("\"{b_id}\" -> \"{red_id}\" [label=\"next\"];"),
+        ] {
+            assert!(dot.contains(&line), "{line} in {dot}");
+        }
+
+        // SVG: the pane's drawing, the same file for the same layered
+        // document, framed around every node whatever the viewport.
+        let (mut fonts, mut layouts) = (parley::FontContext::new(), parley::LayoutContext::new());
+        let mut cache = puri::text::TextCache::default();
+        let mut svg = |view: &GraphView| {
+            let mut tcx = TextCtx {
+                fonts: &mut fonts,
+                layouts: &mut layouts,
+                scale: 1.0,
+                cache: &mut cache,
+            };
+            export_svg(&sources, view, &names, false, &mut tcx)
+        };
+        let drawn = svg(&view);
+        view.zoom_at(3.0, Vec2::new(40.0, 0.0), 1.0);
+        assert_eq!(drawn, svg(&view));
+        assert!(drawn.starts_with("<svg ") && drawn.ends_with("</svg>\n"));
+        // The valueless cell's dashed border survives the trip.
+        assert!(drawn.contains("<clipPath") && drawn.contains("stroke-dasharray"));
+
+        // Exporting lays out a copy only when the view has no layout
+        // of the document; the view itself never moves.
+        assert!(matches!(view.laid_out(&doc), Cow::Borrowed(_)));
+        let fresh = GraphView::default();
+        let laid = fresh.laid_out(&doc);
+        assert!(matches!(laid, Cow::Owned(_)) && laid.positions.len() == 3);
+        assert!(fresh.positions.is_empty() && fresh.taken.is_none());
+    }
+
+    /// A ring of `count` cells with a chord from each, like a document
+    /// where everything is reachable and much is shared.
+    fn ring(count: usize) -> (Document, Vec<CellId>) {
+        let ids: Vec<CellId> = (0..count).map(|_| new_cell_id()).collect();
+        let mut cells = Cells::new();
+        for (index, id) in ids.iter().enumerate() {
+            cells.set_value(
+                *id,
+                Value::record([
+                    (Label::from("next"), Value::from(ids[(index + 1) % count])),
+                    (Label::from("see"), Value::from(ids[index * 7919 % count])),
+                    (Label::from("n"), Value::from(index.to_string().as_str())),
+                ]),
+            );
+        }
+        let doc = Document {
+            root: Some(Value::from(ids[0])),
+            cells,
+            libraries: Vec::new(),
+        };
+        (doc, ids)
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "timing needs an optimized build: cargo test --release")]
+    fn layers_five_thousand_cells_in_a_few_frames() {
+        let budget = std::time::Duration::from_millis(100);
+        let (mut doc, ids) = ring(5000);
+        let mut view = GraphView::default();
+        view.set_layered(true);
+        // Best of three, as the simulation's steps are timed.
+        let mut best = std::time::Duration::MAX;
+        for _ in 0..3 {
+            view.set_layered(false);
+            view.set_layered(true);
+            let start = std::time::Instant::now();
+            view.step(&doc);
+            best = best.min(start.elapsed());
+        }
+        assert!(best < budget, "layout took {best:?}");
+        // An edit leaving the topology alone keeps the layout: only the
+        // snapshot is retaken.
+        let before = view.positions.clone();
+        let count = Label::from("n");
+        let mut fields = doc.cells.value(ids[9]).unwrap().as_record().unwrap().clone();
+        fields.insert(count, Value::from("nine"));
+        doc.cells.set_value(ids[9], Value::Record(fields));
+        let start = std::time::Instant::now();
+        view.step(&doc);
+        let edited = start.elapsed();
+        assert_eq!(view.positions, before);
+        assert!(edited < budget / 2, "an edit took {edited:?}");
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "timing needs an optimized build: cargo test --release")]
+    fn steps_over_five_thousand_cells_fit_a_frame() {
+        const STEPS: usize = 100;
+        let frame = std::time::Duration::from_micros(16_667);
+        let (doc, ids) = ring(5000);
+        // The simulation is deterministic, so each step is timed over
+        // three runs and its best kept: what the step costs, not what
+        // the scheduler cost it.
+        let mut best = vec![std::time::Duration::MAX; STEPS];
+        for _ in 0..3 {
+            let mut view = GraphView::default();
+            // Opening the view meets the document; steps then reuse
+            // its snapshot until it changes.
+            view.center_on(&doc, &GraphNode::Cell(ids[0]));
+            for best in &mut best {
+                let start = std::time::Instant::now();
+                view.step(&doc);
+                *best = (*best).min(start.elapsed());
+            }
+        }
+        let (slowest, step) = best.iter().zip(0..).max().unwrap();
+        assert!(*slowest < frame, "step {step} took {slowest:?}");
+    }
+
     #[test]
     fn release_reports_clicks_and_drags() {
         let mut view = GraphView::default();
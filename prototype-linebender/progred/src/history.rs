@@ -1,84 +1,418 @@
 //! Snapshot undo over the persistent gid: cloning a `Document` is
-//! O(1) structural sharing, so history is a dumb stack of
-//! pre-mutation snapshots. Every mutation site records its own step
-//! explicitly; text-run coalescing is not history's concern — the
+//! O(1) structural sharing, so history is a dumb TREE of snapshots —
+//! a recording after an undo starts a branch instead of discarding
+//! the redo path, and every state ever reached stays reachable. Undo
+//! walks to the parent; redo to the child last left (the branch being
+//! worked on), and jumping to any state is navigation like those two,
+//! never a rewind that cuts anything off. The tree outlives the
+//! session through a sidecar beside the document (see
+//! [`crate::sidecar`]).
+//!
+//! Every mutation site records its own step explicitly, as an
+//! [`Action`]: a KIND, a label the menus and the browser say it by,
+//! and the path it landed at. Bursts fold into one step by RULES keyed
+//! on the kind — a step coalesces into the one before it when the
+//! kinds agree, the paths are related as the rule asks, and it came
+//! within the rule's window. The rules are the defaults, as the
+//! settings override them (see [`crate::settings`]). Text runs are not history's concern — the
 //! run is the mounted editor's lifetime, and write-through reports
 //! only the run's first write (see `raw::write_through`).
 
 use crate::raw::{Document, Path};
+use crate::store::LibraryRef;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime};
 
-/// A pre-mutation snapshot: the document, and the selection to
-/// restore (an edge path; pendings restore as no selection — they
-/// were disposable).
-struct Entry {
-    doc: Document,
-    selection: Option<Path>,
+/// What sort of edit a step was — what coalescing rules key on. Its
+/// serde names ("insert", "relabel") are what the sidecar and the
+/// settings say it by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// An atom typed into: one editor run.
+    Edit,
+    /// A pending value committed: an element inserted, a field or
+    /// the root set.
+    Insert,
+    Delete,
+    Rename,
+    Cut,
+    Paste,
+    Duplicate,
+    /// An element moved along its list.
+    Move,
+    /// A cell linked in from the pool.
+    Link,
+    Merge,
+    Purge,
+    /// A library declared.
+    Library,
+    /// Find's matches rewritten, all at once.
+    Replace,
+    /// A value moved out into a new cell, a link left behind.
+    Extract,
+    /// A link replaced by a copy of its cell's value.
+    Inline,
+    /// A label renamed in every record that has it.
+    Relabel,
 }
 
-#[derive(Default)]
+/// A recorded step: its kind, what the menus call it ("rename
+/// field", "paste"), and where it landed — empty for an edit with no
+/// one place, like a merge.
+#[derive(Clone, Debug)]
+pub struct Action {
+    pub kind: Kind,
+    pub label: String,
+    pub at: Path,
+}
+
+impl Action {
+    pub fn new(kind: Kind, label: impl Into<String>, at: Path) -> Self {
+        Action {
+            kind,
+            label: label.into(),
+            at,
+        }
+    }
+}
+
+/// Which later steps of a kind fold into the step before them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Related {
+    /// Landing at the very same path: the same spot worked again.
+    Same,
+    /// Landing beside it, under the same parent: a run of inserts
+    /// into one list, or deletes out of it.
+    Siblings,
+}
+
+/// A coalescing rule: steps so related that come within `window` of
+/// the last one are one step. Written `{"related": "siblings",
+/// "window_ms": 5000}`, the window two seconds when left out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub related: Related,
+    #[serde(rename = "window_ms", with = "millis", default = "default_window")]
+    pub window: Duration,
+}
+
+fn default_window() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// A window as whole milliseconds.
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(window: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(window.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// The rules a history starts with: element runs — inserts, deletes
+/// and moves in one container — fold within two seconds, as does
+/// typing into the same atom again; everything else is its own step.
+pub fn default_rules() -> HashMap<Kind, Rule> {
+    let within = |related| Rule {
+        related,
+        window: default_window(),
+    };
+    HashMap::from([
+        (Kind::Edit, within(Related::Same)),
+        (Kind::Insert, within(Related::Siblings)),
+        (Kind::Delete, within(Related::Siblings)),
+        (Kind::Move, within(Related::Siblings)),
+    ])
+}
+
+/// The defaults, overridden per kind: a kind given a rule takes it,
+/// and one given none (`null` in the settings) never coalesces.
+pub fn rules(overrides: &BTreeMap<Kind, Option<Rule>>) -> HashMap<Kind, Rule> {
+    let mut rules = default_rules();
+    for (&kind, &rule) in overrides {
+        match rule {
+            Some(rule) => rules.insert(kind, rule),
+            None => rules.remove(&kind),
+        };
+    }
+    rules
+}
+
+/// A state of the document: its snapshot as last left, and the
+/// selection to restore on coming back (an edge path; pendings
+/// restore as no selection — they were disposable). The CURRENT
+/// state's snapshot is stale by design — the model holds the live
+/// document, and leaving stores it.
+pub struct State {
+    pub doc: Document,
+    pub selection: Option<Path>,
+    pub parent: Option<usize>,
+    /// The child redo returns to: the one most recently left or
+    /// made, so redo retraces the branch being worked on.
+    pub redo: Option<usize>,
+    /// When the state was made — the recording that led to it, or
+    /// the last step folded into it.
+    pub made: SystemTime,
+    /// The step that made it; None for the root, and for states
+    /// resumed from a sidecar that predates labels.
+    pub action: Option<Action>,
+}
+
+/// States are indexed in the order they were made, so index order is
+/// time order. Until the first recording the tree is just its root,
+/// implicit: state 0, the document as opened.
 pub struct History {
-    undo: Vec<Entry>,
-    redo: Vec<Entry>,
-    saved: usize,
+    states: Vec<State>,
+    current: usize,
+    /// The state the document was last saved at, or None when that
+    /// state is gone (a resumed history pruned past it).
+    saved: Option<usize>,
+    /// When the implicit root began: the document as opened.
+    opened: SystemTime,
+    rules: HashMap<Kind, Rule>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            states: Vec::new(),
+            current: 0,
+            saved: Some(0),
+            opened: SystemTime::now(),
+            rules: default_rules(),
+        }
+    }
 }
 
 impl History {
+    /// A history resumed from a previous session's tree, its save
+    /// mark at `current` — it was kept at the save that wrote the
+    /// document being resumed.
+    pub fn resumed(states: Vec<State>, current: usize) -> Self {
+        History {
+            states,
+            current,
+            saved: Some(current),
+            opened: SystemTime::now(),
+            rules: default_rules(),
+        }
+    }
+
+    /// Coalesces by `rules` from the next recording on.
+    pub fn set_rules(&mut self, rules: HashMap<Kind, Rule>) {
+        self.rules = rules;
+    }
+
+    /// Every state, in the order made; empty until the first
+    /// recording.
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn saved(&self) -> Option<usize> {
+        self.saved
+    }
+
+    /// Re-spells every state's library declarations through
+    /// `respell`: a document moving directories keeps each snapshot's
+    /// relative paths naming the files they named.
+    pub fn respell_libraries(&mut self, respell: impl Fn(&[LibraryRef]) -> Vec<LibraryRef>) {
+        for state in &mut self.states {
+            state.doc.libraries = respell(&state.doc.libraries);
+        }
+    }
+
+    /// Stores the live document into the state being left.
+    fn leave(&mut self, current: Document, selection: Option<Path>) {
+        let state = &mut self.states[self.current];
+        state.doc = current;
+        state.selection = selection;
+    }
+
+    /// The state at `index`, entered: its snapshot and selection.
+    fn enter(&mut self, index: usize) -> (Document, Option<Path>) {
+        self.current = index;
+        let state = &self.states[index];
+        (state.doc.clone(), state.selection.clone())
+    }
+
     /// Records a mutation the caller just made, `before` being the
-    /// pre-mutation state.
-    pub fn record(&mut self, before: Document, selection: Option<Path>) {
-        self.redo.clear();
-        self.undo.push(Entry {
-            doc: before,
-            selection,
+    /// pre-mutation state: a new child of the current state, which
+    /// becomes current. Any other children stay, as branches. A step
+    /// its kind's rule relates to the current one folds into it
+    /// instead — unless that would move the save mark's state or one
+    /// with children.
+    pub fn record(&mut self, before: Document, selection: Option<Path>, action: Action) {
+        let made = SystemTime::now();
+        if self.coalesces(&action, made) {
+            let state = &mut self.states[self.current];
+            state.made = made;
+            state.action = Some(action);
+            return;
+        }
+        if self.states.is_empty() {
+            self.states.push(State {
+                doc: before.clone(),
+                selection: None,
+                parent: None,
+                redo: None,
+                made: self.opened,
+                action: None,
+            });
+        }
+        let from = self.current;
+        let child = self.states.len();
+        self.states.push(State {
+            doc: before.clone(),
+            selection: None,
+            parent: Some(from),
+            redo: None,
+            made,
+            action: Some(action),
         });
+        self.leave(before, selection);
+        self.states[from].redo = Some(child);
+        self.current = child;
+    }
+
+    fn coalesces(&self, action: &Action, now: SystemTime) -> bool {
+        let Some(rule) = self.rules.get(&action.kind) else {
+            return false;
+        };
+        let Some(state) = self.states.get(self.current) else {
+            return false;
+        };
+        let Some(last) = &state.action else {
+            return false;
+        };
+        let parent = |path: &Path| path.split_last().map(|(_, parent)| parent.to_vec());
+        let related = match rule.related {
+            Related::Same => last.at == action.at,
+            Related::Siblings => {
+                parent(&last.at).is_some() && parent(&last.at) == parent(&action.at)
+            }
+        };
+        last.kind == action.kind
+            && related
+            && state.redo.is_none()
+            && self.saved != Some(self.current)
+            && now.duration_since(state.made).is_ok_and(|since| since <= rule.window)
+    }
+
+    /// What undo would revert: the current state's step.
+    pub fn undo_label(&self) -> Option<&str> {
+        let state = self.states.get(self.current)?;
+        state.parent?;
+        Some(state.action.as_ref().map_or("change", |action| &action.label))
     }
 
-    /// Steps back, exchanging the current state into the redo stack.
+    /// What redo would replay: the step of the state it goes to.
+    pub fn redo_label(&self) -> Option<&str> {
+        let state = &self.states[self.states.get(self.current)?.redo?];
+        Some(state.action.as_ref().map_or("change", |action| &action.label))
+    }
+
+    /// Steps back to the parent state, storing the current one.
     pub fn undo(
         &mut self,
         current: Document,
         selection: Option<Path>,
     ) -> Option<(Document, Option<Path>)> {
-        let entry = self.undo.pop()?;
-        self.redo.push(Entry {
-            doc: current,
-            selection,
-        });
-        Some((entry.doc, entry.selection))
+        let parent = self.states.get(self.current)?.parent?;
+        self.leave(current, selection);
+        self.states[parent].redo = Some(self.current);
+        Some(self.enter(parent))
     }
 
-    /// Steps forward again; a new recording clears this path.
+    /// Steps forward along the branch last left.
     pub fn redo(
         &mut self,
         current: Document,
         selection: Option<Path>,
     ) -> Option<(Document, Option<Path>)> {
-        let entry = self.redo.pop()?;
-        self.undo.push(Entry {
-            doc: current,
-            selection,
-        });
-        Some((entry.doc, entry.selection))
+        let child = self.states.get(self.current)?.redo?;
+        self.leave(current, selection);
+        Some(self.enter(child))
     }
 
-    /// Marks the current position as saved. The caller breaks any
-    /// open edit run at the selection, keeping runs off the mark.
+    /// Goes to any state, storing the current one. The redo pointers
+    /// along the target's ancestry turn toward it, so undoing from
+    /// there and redoing back retraces the way — nothing is cut off,
+    /// and jumping back is one more jump.
+    pub fn jump(
+        &mut self,
+        target: usize,
+        current: Document,
+        selection: Option<Path>,
+    ) -> Option<(Document, Option<Path>)> {
+        if target == self.current || target >= self.states.len() {
+            return None;
+        }
+        self.leave(current, selection);
+        let mut at = target;
+        while let Some(parent) = self.states[at].parent {
+            self.states[parent].redo = Some(at);
+            at = parent;
+        }
+        Some(self.enter(target))
+    }
+
+    /// Marks the current state as saved. The caller breaks any open
+    /// edit run at the selection, keeping runs off the mark.
     pub fn mark_saved(&mut self) {
-        self.saved = self.undo.len();
+        self.saved = Some(self.current);
     }
 
-    /// Modified since the save mark — a pure position comparison, so
-    /// undoing back to the mark is clean again.
+    /// Modified since the save mark — a pure state comparison, so
+    /// navigating back to the saved state, by any route, is clean
+    /// again.
     pub fn dirty(&self) -> bool {
-        self.undo.len() != self.saved
+        self.saved != Some(self.current)
     }
 
     pub fn can_undo(&self) -> bool {
-        !self.undo.is_empty()
+        self.states.get(self.current).is_some_and(|state| state.parent.is_some())
     }
 
     pub fn can_redo(&self) -> bool {
-        !self.redo.is_empty()
+        self.states.get(self.current).is_some_and(|state| state.redo.is_some())
+    }
+
+    /// Whether `index` is on the current branch: an ancestor of the
+    /// current state, or reached from it by redo.
+    pub fn on_branch(&self, index: usize) -> bool {
+        let ancestors =
+            std::iter::successors(Some(self.current), |at| self.states.get(*at)?.parent);
+        let next = |at: &usize| self.states.get(*at)?.redo;
+        let ahead = std::iter::successors(next(&self.current), next);
+        ancestors.chain(ahead).any(|at| at == index)
+    }
+
+    /// The document `offset` steps from the present: negative counts
+    /// back through undo, positive forward through redo. None at 0 —
+    /// the present is the caller's — and past either end.
+    pub fn state(&self, offset: isize) -> Option<&Document> {
+        if offset == 0 {
+            return None;
+        }
+        let mut at = self.current;
+        for _ in 0..offset.unsigned_abs() {
+            let state = self.states.get(at)?;
+            at = if offset < 0 { state.parent? } else { state.redo? };
+        }
+        Some(&self.states[at].doc)
     }
 }
 
@@ -91,6 +425,11 @@ mod tests {
         Step::Key(Label::from("x"))
     }
 
+    /// A step of a kind that never coalesces.
+    fn edit() -> Action {
+        Action::new(Kind::Paste, "paste", Vec::new())
+    }
+
     fn doc(value: &str) -> Document {
         let mut cells = Cells::new();
         let cell = new_cell_id();
@@ -98,6 +437,7 @@ mod tests {
         Document {
             root: Some(Value::from(cell)),
             cells,
+            libraries: Vec::new(),
         }
     }
 
@@ -113,7 +453,7 @@ mod tests {
     fn undo_and_redo_roundtrip_with_selection() {
         let mut history = History::default();
         let path = vec![Step::Follow, x()];
-        history.record(doc("1"), Some(path.clone()));
+        history.record(doc("1"), Some(path.clone()), edit());
 
         let (back, selection) = history.undo(doc("2"), None).unwrap();
         assert_eq!(x_of(&back), Value::from("1"));
@@ -125,13 +465,51 @@ mod tests {
     }
 
     #[test]
-    fn recording_clears_redo() {
+    fn recording_after_undo_branches_and_jumps_reach_both() {
         let mut history = History::default();
         let x = vec![Step::Follow, x()];
-        history.record(doc("1"), Some(x.clone()));
+        history.record(doc("1"), Some(x.clone()), edit());
         let (back, _) = history.undo(doc("1.5"), Some(x.clone())).unwrap();
-        history.record(back, Some(x));
-        assert!(history.redo(doc("0"), None).is_none());
+        history.record(back, Some(x), edit());
+        // The new branch has nothing ahead; the old one is kept.
+        assert!(history.redo(doc("2"), None).is_none());
+        assert_eq!(history.states().len(), 3);
+        assert!(!history.on_branch(1));
+
+        // Jumping over to the first branch restores it as it was
+        // left, and redo from the root now follows it.
+        let (other, _) = history.jump(1, doc("2"), None).unwrap();
+        assert_eq!(x_of(&other), Value::from("1.5"));
+        assert!(history.on_branch(1) && !history.on_branch(2));
+        let (root, _) = history.undo(other, None).unwrap();
+        let (again, _) = history.redo(root, None).unwrap();
+        assert_eq!(x_of(&again), Value::from("1.5"));
+        let (newer, _) = history.jump(2, again, None).unwrap();
+        assert_eq!(x_of(&newer), Value::from("2"));
+    }
+
+    #[test]
+    fn respelled_libraries_reach_every_state_undo_returns_to() {
+        let mut history = History::default();
+        let declared = |path: &str| {
+            let mut held = doc("1");
+            held.libraries = vec![LibraryRef {
+                path: path.into(),
+                hash: None,
+            }];
+            held
+        };
+        history.record(declared("lib.progred"), None, edit());
+        history.respell_libraries(|held| {
+            (held.iter())
+                .map(|library| LibraryRef {
+                    path: format!("home/{}", library.path),
+                    hash: library.hash.clone(),
+                })
+                .collect()
+        });
+        let (back, _) = history.undo(declared("home/lib.progred"), None).unwrap();
+        assert_eq!(back.libraries[0].path, "home/lib.progred");
     }
 
     #[test]
@@ -139,18 +517,83 @@ mod tests {
         let mut history = History::default();
         assert!(!history.dirty());
         let x = vec![Step::Follow, x()];
-        history.record(doc("1"), Some(x.clone()));
+        history.record(doc("1"), Some(x.clone()), edit());
         assert!(history.dirty());
 
         history.mark_saved();
         assert!(!history.dirty());
-        history.record(doc("1.2"), Some(x.clone()));
+        history.record(doc("1.2"), Some(x.clone()), edit());
         assert!(history.dirty());
 
         // Undoing back to the mark is clean; past it, dirty again.
         let (one_back, sel) = history.undo(doc("1.3"), Some(x.clone())).unwrap();
         assert!(!history.dirty());
-        let (_, _) = history.undo(one_back, sel).unwrap();
+        let (root, _) = history.undo(one_back, sel).unwrap();
         assert!(history.dirty());
+
+        // A branch from before the mark is dirty; jumping back to
+        // the saved state by any route is clean.
+        history.record(root, None, edit());
+        assert!(history.dirty());
+        history.jump(1, doc("0.5"), None).unwrap();
+        assert!(!history.dirty());
+    }
+
+    #[test]
+    fn states_count_out_from_the_present() {
+        let mut history = History::default();
+        history.record(doc("1"), None, edit());
+        history.record(doc("2"), None, edit());
+        let (back, _) = history.undo(doc("3"), None).unwrap();
+        assert_eq!(x_of(history.state(-1).unwrap()), Value::from("1"));
+        assert_eq!(x_of(history.state(1).unwrap()), Value::from("3"));
+        assert_eq!(x_of(&back), Value::from("2"));
+        assert!(history.state(0).is_none());
+        assert!(history.state(-2).is_none());
+        assert!(history.state(2).is_none());
+    }
+
+    #[test]
+    fn runs_coalesce_by_rule_and_menus_name_the_step() {
+        let mut history = History::default();
+        let positions = progred_graph::position::spread(6);
+        let items = |index: usize| vec![Step::Follow, x(), Step::Element(positions[index].clone())];
+        let insert = |index| Action::new(Kind::Insert, "insert element", items(index));
+        history.record(doc("0"), None, insert(0));
+        history.record(doc("1"), None, insert(1));
+        history.record(doc("2"), None, Action::new(Kind::Rename, "rename field", items(2)));
+        history.record(doc("3"), None, insert(3));
+        // The two inserts folded; the rename broke the run.
+        assert_eq!(history.states().len(), 4);
+        assert_eq!(history.undo_label(), Some("insert element"));
+        assert!(history.redo_label().is_none());
+
+        let (back, _) = history.undo(doc("4"), None).unwrap();
+        assert_eq!(history.undo_label(), Some("rename field"));
+        assert_eq!(history.redo_label(), Some("insert element"));
+        // A state with children takes no more of its run.
+        history.redo(back, None).unwrap();
+        history.undo(doc("4"), None).unwrap();
+        history.undo(doc("3"), None).unwrap();
+        history.record(doc("2"), None, insert(4));
+        assert_eq!(history.states().len(), 5);
+
+        // Rules as the settings spell them: kinds by their serde
+        // names, `null` for none.
+        let spelled = r#"{"insert": null, "edit": {"related": "siblings", "window_ms": 10}}"#;
+        let overrides: BTreeMap<Kind, Option<Rule>> = serde_json::from_str(spelled).unwrap();
+        history.set_rules(rules(&overrides));
+        let edit = Rule {
+            related: Related::Siblings,
+            window: Duration::from_millis(10),
+        };
+        assert_eq!(history.rules.get(&Kind::Edit), Some(&edit));
+        for wrong in [r#"{"insertion": null}"#, r#"{"insert": {"related": "sometimes"}}"#] {
+            assert!(serde_json::from_str::<BTreeMap<Kind, Option<Rule>>>(wrong).is_err());
+        }
+        history.record(doc("5"), None, insert(5));
+        assert_eq!(history.states().len(), 6);
+        // The folded run undoes to before its first insert.
+        assert_eq!(x_of(&history.states()[0].doc), Value::from("0"));
     }
 }
@@ -0,0 +1,260 @@
+//! The history browser: every state of the undo tree, newest first,
+//! each said as the step that made it and how long ago. The current
+//! state is marked and the saved one tagged; states off the current
+//! branch — reachable only by jumping — read dim. A click goes to the
+//! state (see [`crate::history::History::jump`]): navigation, so the
+//! state left behind is one more click away.
+//!
+//! Steps are said by their recorded labels (see
+//! [`crate::history::Action`]). States resumed from a sidecar written
+//! before labels are described from the structural diff with their
+//! parent instead, the way the compare overlay reads changes: a cell
+//! added, removed, renamed, or edited by name, or a count when a step
+//! touched several.
+
+use crate::conventions::{Names, display_name};
+use crate::history::History;
+use crate::panel::{Border, HOVER, ground};
+use crate::raw::{Document, RawStyles, short_id};
+use crate::sources::Sources;
+use progred_graph::diff::{self, CellDiff};
+use progred_graph::merge::Side;
+use progred_graph::{CellId, Cells};
+use puri::draw::Canvas;
+use puri::handler::HasHandler;
+use puri::layout::{HAlign, Node, col, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::rc::Rc;
+use std::time::SystemTime;
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect};
+use vello::peniko::Color;
+
+/// Dispatch-time callbacks the shell injects: a state row clicked or
+/// hovered; the shell does the jumping.
+pub struct Hooks<C> {
+    pub jump: Rc<dyn Fn(&mut C, usize)>,
+    /// The pointer's resting claim inside the panel: the state row
+    /// under it, or `None` for the pane's own ground.
+    pub hover: Rc<dyn Fn(&mut C, Option<usize>)>,
+}
+
+const CURRENT: [f32; 4] = [0.0, 0.48, 1.0, 0.14];
+
+/// The step that made state `index`: its label, else its difference
+/// from its parent; the root is the document as opened. `present` is
+/// the live document, the current state's own snapshot being stale.
+pub fn describe(
+    history: &History,
+    index: usize,
+    present: &Document,
+    library: &Cells,
+    names: &Names,
+    raw: bool,
+) -> String {
+    let states = history.states();
+    let doc = |at: usize| {
+        if at == history.current() {
+            present
+        } else {
+            &states[at].doc
+        }
+    };
+    let Some(parent) = states[index].parent else {
+        return "Opened".into();
+    };
+    if let Some(action) = &states[index].action {
+        let mut chars = action.label.chars();
+        return chars.next().map_or_else(String::new, |first| {
+            first.to_uppercase().chain(chars).collect()
+        });
+    }
+    let (before, after) = (doc(parent), doc(index));
+    fn side(doc: &Document) -> Side<'_> {
+        Side {
+            cells: &doc.cells,
+            root: doc.root.as_ref(),
+        }
+    }
+    let found = diff::diff(side(before), side(after));
+    let name = |doc: &Document, cell: CellId| {
+        let sources = Sources { doc, library };
+        display_name(&sources, names, raw, cell).unwrap_or_else(|| short_id(cell))
+    };
+    let mut cells = found.cells.iter();
+    match (cells.next(), cells.next()) {
+        (None, _) if !found.root.is_empty() => "Edit root".into(),
+        (None, _) if before.libraries != after.libraries => "Change libraries".into(),
+        (None, _) => "No change".into(),
+        (Some((cell, change)), None) if found.root.is_empty() => match change {
+            CellDiff::Added => format!("Add {}", name(after, *cell)),
+            CellDiff::Removed(_) => format!("Remove {}", name(before, *cell)),
+            CellDiff::Changed {
+                renamed: Some(_),
+                revalued,
+            } if revalued.is_empty() => {
+                format!("Rename {} → {}", name(before, *cell), name(after, *cell))
+            }
+            CellDiff::Changed { .. } => format!("Edit {}", name(after, *cell)),
+        },
+        _ => format!("Change {} cells", found.cells.len()),
+    }
+}
+
+/// How long ago, coarsely: the browser orders by time, and this is
+/// only the reading aid beside it.
+fn age(now: SystemTime, then: SystemTime) -> String {
+    let seconds = now.duration_since(then).unwrap_or_default().as_secs();
+    match seconds {
+        0..5 => "just now".into(),
+        5..60 => format!("{seconds}s ago"),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} d ago", seconds / 86400),
+    }
+}
+
+/// One pure pass over the history, like the other panes: a heading,
+/// then a row per state, newest first, as far as the strip reaches,
+/// on a [`ground`] like theirs.
+#[allow(clippy::too_many_arguments)]
+pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
+    history: &History,
+    present: &Document,
+    library: &Cells,
+    hover: Option<usize>,
+    names: &Names,
+    raw: bool,
+    now: SystemTime,
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let count = history.states().len();
+    let heading = match count {
+        0 | 1 => "No history yet".to_string(),
+        n => format!("{n} states"),
+    };
+    let mut rows: Vec<Node<P>> = vec![text(tcx, &heading, &styles.label)];
+    let mut used = rows[0].extent.height();
+    let inner = panel.width() - 16.0 * scale;
+    for index in (0..count).rev() {
+        let state = &history.states()[index];
+        let current = index == history.current();
+        let mark = if current { "●" } else { "○" };
+        let what = describe(history, index, present, library, names, raw);
+        let style = if history.on_branch(index) {
+            &styles.name
+        } else {
+            &styles.dim
+        };
+        let mut cells = vec![
+            text(tcx, mark, &styles.dim),
+            text(tcx, &what, style),
+            text(tcx, &age(now, state.made), &styles.dim),
+        ];
+        if history.saved() == Some(index) {
+            cells.push(text(tcx, "saved", &styles.label));
+        }
+        let content = min_width(
+            inner,
+            pad(
+                Insets::new(4.0 * scale, 1.0 * scale, 4.0 * scale, 1.0 * scale),
+                row(6.0 * scale, cells),
+            ),
+        );
+        used += content.extent.height() + 2.0 * scale;
+        if used > panel.height() - 16.0 * scale {
+            rows.push(text(tcx, &format!("… {} earlier", index + 1), &styles.dim));
+            break;
+        }
+        let lit = hover == Some(index);
+        let jump = hooks.jump.clone();
+        let hover = hooks.hover.clone();
+        rows.push(decorate(content, move |p: &mut P, rect| {
+            if current || lit {
+                let fill = if current { CURRENT } else { HOVER };
+                p.fill(
+                    RoundedRect::from_rect(rect, 4.0 * scale),
+                    Color::new(fill),
+                    Affine::IDENTITY,
+                );
+            }
+            p.handler().on_pointer_move(move |ctx, update| {
+                let point = Point::new(update.current.position.x, update.current.position.y);
+                rect.contains(point) && {
+                    hover(ctx, Some(index));
+                    false
+                }
+            });
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        jump(ctx, index);
+                        true
+                    }
+            });
+        }));
+    }
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(8.0 * scale),
+            col(HAlign::Start, 0, 2.0 * scale, rows),
+        ),
+    );
+
+    let hover = hooks.hover.clone();
+    ground(content, panel, Border::Left, scale, move |ctx| hover(ctx, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Action, Kind, State};
+    use progred_graph::{Label, Value, new_cell_id};
+
+    #[test]
+    fn steps_read_as_what_they_did_to_which_cell() {
+        let mut doc = crate::raw::sample_document();
+        let library = Cells::new();
+        let names = Names::default();
+        let cell = new_cell_id();
+
+        // Unlabelled, as from an old sidecar: each state's parent is
+        // the one before it.
+        let mut states = Vec::new();
+        let mut push = |doc: &Document| {
+            let parent = states.len().checked_sub(1);
+            states.push(State {
+                doc: doc.clone(),
+                selection: None,
+                parent,
+                redo: None,
+                made: SystemTime::now(),
+                action: None,
+            });
+        };
+        push(&doc);
+        doc.cells.set_name(cell, "stone");
+        push(&doc);
+        doc.cells.set_value(cell, Value::record([(Label::from("x"), Value::from("1"))]));
+        push(&doc);
+        push(&doc);
+        doc.cells.set_name(cell, "pebble");
+        let mut history = History::resumed(states, 3);
+
+        let said: Vec<String> = (0..history.states().len())
+            .map(|index| describe(&history, index, &doc, &library, &names, false))
+            .collect();
+        assert_eq!(said, ["Opened", "Add stone", "Edit stone", "Rename stone → pebble"]);
+
+        history.record(doc.clone(), None, Action::new(Kind::Paste, "paste", Vec::new()));
+        assert_eq!(describe(&history, 4, &doc, &library, &names, false), "Paste");
+        let then = SystemTime::UNIX_EPOCH;
+        assert_eq!(age(then + std::time::Duration::from_secs(90), then), "1 min ago");
+    }
+}
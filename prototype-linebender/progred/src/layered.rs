@@ -0,0 +1,347 @@
+//! The graph view's LAYERED layout: the deterministic alternative to
+//! the force simulation, for pictures that must come out the same
+//! twice — screenshots, reviews, exported diagrams. Sugiyama's four
+//! passes over the reference topology, every choice settled by node
+//! order, so identical documents give identical positions:
+//!
+//! 1. CYCLE BREAKING. A depth-first walk from the root (then from
+//!    each node nothing mentions, then from whatever is left) reverses
+//!    the edges that close a cycle. Self-mentions draw nothing here.
+//! 2. LAYERING. Longest path from the sources, so every arrow points
+//!    down; a source other than the root is then pulled down to sit
+//!    just above the highest thing it mentions, instead of every
+//!    floater crowding the top row.
+//! 3. ORDERING. An arrow spanning a few layers is threaded through
+//!    one dummy per layer crossed, and barycenter sweeps — down, then
+//!    up — reorder each layer by its neighbors' mean position,
+//!    keeping the order with the fewest crossings seen. An arrow
+//!    spanning more than `SPAN` layers is left out of the ordering:
+//!    its dummies would outnumber the nodes (a ring with chords is
+//!    thousands of layers deep) and cost seconds, for a stroke the
+//!    eye follows across the picture anyway.
+//! 4. PLACEMENT. Each layer is pulled toward its neighbors' mean x,
+//!    packed from both sides to keep the gaps and averaged, so long
+//!    chains run straight.
+//!
+//! Dummies only keep room: the pane still draws an arrow as one
+//! straight stroke, which the reserved column keeps clear of nodes —
+//! and a longer arrow is a plain stroke, with no column kept.
+
+use vello::kurbo::Point;
+
+/// Between layers, centre to centre.
+const LAYER_GAP: f64 = 90.0;
+/// Between neighboring nodes in a layer, centre to centre; a dummy
+/// takes a third of a node's room.
+const NODE_GAP: f64 = 150.0;
+const DUMMY_GAP: f64 = NODE_GAP / 3.0;
+/// The most layers an arrow spans and still takes part in ordering.
+const SPAN: usize = 4;
+/// Barycenter sweeps, each down then up.
+const SWEEPS: usize = 12;
+/// Placement passes, each down then up.
+const STRAIGHTEN: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Open,
+    Done,
+}
+
+/// Lays out `count` nodes joined by `ends` (index pairs, duplicates
+/// and self-loops welcome), `root` on top: one point per node,
+/// centred on the origin, y growing down the layers.
+pub fn layout(count: usize, ends: &[(usize, usize)], root: Option<usize>) -> Vec<Point> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let edges = acyclic(count, ends, root);
+    let mut layer = layers(count, &edges, root);
+
+    // Proper edges only: a long arrow becomes a chain through dummies,
+    // which are the vertices past `count`, and a longer one is dropped.
+    let (mut down, mut up) = (vec![Vec::new(); count], vec![Vec::new(); count]);
+    for (from, to) in edges {
+        if layer[to] - layer[from] > SPAN {
+            continue;
+        }
+        let mut at = from;
+        for between in layer[from] + 1..layer[to] {
+            let dummy = layer.len();
+            layer.push(between);
+            down.push(Vec::new());
+            up.push(Vec::new());
+            down[at].push(dummy);
+            up[dummy].push(at);
+            at = dummy;
+        }
+        down[at].push(to);
+        up[to].push(at);
+    }
+    let mut rows = vec![Vec::new(); layer.iter().max().map_or(0, |deepest| deepest + 1)];
+    for (vertex, row) in layer.iter().enumerate() {
+        rows[*row].push(vertex);
+    }
+
+    let rows = order(rows, &down, &up);
+    let mut slot = vec![0; layer.len()];
+    for row in &rows {
+        for (position, vertex) in row.iter().enumerate() {
+            slot[*vertex] = position;
+        }
+    }
+    let gap = |a: usize, b: usize| {
+        let room = |vertex: usize| if vertex < count { NODE_GAP } else { DUMMY_GAP };
+        (room(a) + room(b)) / 2.0
+    };
+    let mut x = vec![0.0; layer.len()];
+    for row in &rows {
+        let mut at = 0.0;
+        for (position, vertex) in row.iter().enumerate() {
+            if position > 0 {
+                at += gap(row[position - 1], *vertex);
+            }
+            x[*vertex] = at;
+        }
+    }
+    for _ in 0..STRAIGHTEN {
+        for row in &rows[1..] {
+            place(row, &up, &mut x, gap);
+        }
+        for row in rows[..rows.len() - 1].iter().rev() {
+            place(row, &down, &mut x, gap);
+        }
+    }
+
+    let points: Vec<Point> = (0..count)
+        .map(|node| Point::new(x[node], layer[node] as f64 * LAYER_GAP))
+        .collect();
+    let bounds = points[1..]
+        .iter()
+        .fold((points[0], points[0]), |(low, high), point| {
+            (
+                Point::new(low.x.min(point.x), low.y.min(point.y)),
+                Point::new(high.x.max(point.x), high.y.max(point.y)),
+            )
+        });
+    let centre = bounds.0.midpoint(bounds.1).to_vec2();
+    points.into_iter().map(|point| point - centre).collect()
+}
+
+/// The edges made acyclic: deduplicated, self-loops dropped, and each
+/// edge that closes a cycle on the depth-first walk reversed.
+fn acyclic(count: usize, ends: &[(usize, usize)], root: Option<usize>) -> Vec<(usize, usize)> {
+    let mut out = vec![Vec::new(); count];
+    let mut mentioned = vec![false; count];
+    for (from, to) in ends.iter().copied() {
+        if from != to {
+            out[from].push(to);
+            mentioned[to] = true;
+        }
+    }
+    for next in &mut out {
+        next.sort_unstable();
+        next.dedup();
+    }
+    let starts = (root.into_iter())
+        .chain((0..count).filter(|node| !mentioned[*node]))
+        .chain(0..count);
+    let mut visit = vec![Visit::New; count];
+    let mut edges = Vec::new();
+    for start in starts {
+        if visit[start] != Visit::New {
+            continue;
+        }
+        visit[start] = Visit::Open;
+        // Iterative, so a long chain of cells can't overflow the stack.
+        let mut stack = vec![(start, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            let Some(to) = out[node].get(*next).copied() else {
+                visit[node] = Visit::Done;
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            match visit[to] {
+                Visit::Open => edges.push((to, node)),
+                Visit::Done => edges.push((node, to)),
+                Visit::New => {
+                    edges.push((node, to));
+                    visit[to] = Visit::Open;
+                    stack.push((to, 0));
+                }
+            }
+        }
+    }
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// Each node's layer: its longest path from a source, except that a
+/// source other than the root sits one layer above the highest node
+/// it points to.
+fn layers(count: usize, edges: &[(usize, usize)], root: Option<usize>) -> Vec<usize> {
+    let mut out = vec![Vec::new(); count];
+    let mut waiting = vec![0; count];
+    for (from, to) in edges.iter().copied() {
+        out[from].push(to);
+        waiting[to] += 1;
+    }
+    let sources: Vec<usize> = (0..count).filter(|node| waiting[*node] == 0).collect();
+    let mut layer = vec![0; count];
+    let mut ready = sources.clone();
+    while let Some(node) = ready.pop() {
+        for to in out[node].iter().copied() {
+            layer[to] = layer[to].max(layer[node] + 1);
+            waiting[to] -= 1;
+            if waiting[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+    for source in sources {
+        if Some(source) != root
+            && let Some(highest) = out[source].iter().map(|to| layer[*to]).min()
+        {
+            layer[source] = highest - 1;
+        }
+    }
+    layer
+}
+
+/// Barycenter sweeps over `rows`, returning the order with the fewest
+/// crossings met. Ties keep their current order, so the result
+/// depends on nothing but the input.
+fn order(mut rows: Vec<Vec<usize>>, down: &[Vec<usize>], up: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut slot = vec![0; down.len()];
+    let renumber = |rows: &[Vec<usize>], slot: &mut [usize]| {
+        for row in rows {
+            for (position, vertex) in row.iter().enumerate() {
+                slot[*vertex] = position;
+            }
+        }
+    };
+    renumber(&rows, &mut slot);
+    let mut best = (crossings(&rows, &slot, down), rows.clone());
+    for _ in 0..SWEEPS {
+        if best.0 == 0 {
+            break;
+        }
+        for row in rows.iter_mut().skip(1) {
+            reorder(row, up, &mut slot);
+        }
+        for row in rows.iter_mut().rev().skip(1) {
+            reorder(row, down, &mut slot);
+        }
+        let crossed = crossings(&rows, &slot, down);
+        if crossed < best.0 {
+            best = (crossed, rows.clone());
+        }
+    }
+    best.1
+}
+
+/// Sorts one row by the mean slot of each vertex's neighbors in the
+/// adjacent row; a vertex with none keeps its own slot.
+fn reorder(row: &mut [usize], adjacent: &[Vec<usize>], slot: &mut [usize]) {
+    let key = |vertex: usize| match adjacent[vertex].as_slice() {
+        [] => slot[vertex] as f64,
+        neighbors => {
+            neighbors.iter().map(|next| slot[*next] as f64).sum::<f64>() / neighbors.len() as f64
+        }
+    };
+    let mut keyed: Vec<(f64, usize)> = row.iter().map(|vertex| (key(*vertex), *vertex)).collect();
+    keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (position, (_, vertex)) in keyed.into_iter().enumerate() {
+        row[position] = vertex;
+        slot[vertex] = position;
+    }
+}
+
+/// Crossings between every pair of adjacent rows: per pair, the
+/// inversions among its edges' lower slots once sorted by upper slot,
+/// counted with a Fenwick tree.
+fn crossings(rows: &[Vec<usize>], slot: &[usize], down: &[Vec<usize>]) -> usize {
+    let mut total = 0;
+    for pair in rows.windows(2) {
+        let mut edges: Vec<(usize, usize)> = pair[0]
+            .iter()
+            .flat_map(|from| down[*from].iter().map(|to| (slot[*from], slot[*to])))
+            .collect();
+        edges.sort_unstable();
+        let mut tree = vec![0; pair[1].len() + 1];
+        for (seen, (_, lower)) in edges.into_iter().enumerate() {
+            let (mut at, mut left) = (lower + 1, 0);
+            while at > 0 {
+                left += tree[at];
+                at &= at - 1;
+            }
+            total += seen - left;
+            let mut at = lower + 1;
+            while at < tree.len() {
+                tree[at] += 1;
+                at += at & at.wrapping_neg();
+            }
+        }
+    }
+    total
+}
+
+/// Pulls each of a row's vertices toward the mean x of its neighbors
+/// in the adjacent row, then restores the gaps: packed rightward from
+/// the left, leftward from the right, and the two averaged, which
+/// keeps every gap and favors neither side.
+fn place(row: &[usize], adjacent: &[Vec<usize>], x: &mut [f64], gap: impl Fn(usize, usize) -> f64) {
+    let wanted: Vec<f64> = row
+        .iter()
+        .map(|vertex| match adjacent[*vertex].as_slice() {
+            [] => x[*vertex],
+            neighbors => {
+                neighbors.iter().map(|next| x[*next]).sum::<f64>() / neighbors.len() as f64
+            }
+        })
+        .collect();
+    let mut left = wanted.clone();
+    for position in 1..row.len() {
+        let floor = left[position - 1] + gap(row[position - 1], row[position]);
+        left[position] = left[position].max(floor);
+    }
+    let mut right = wanted;
+    for position in (0..row.len().saturating_sub(1)).rev() {
+        let ceiling = right[position + 1] - gap(row[position], row[position + 1]);
+        right[position] = right[position].min(ceiling);
+    }
+    for (position, vertex) in row.iter().enumerate() {
+        x[*vertex] = (left[position] + right[position]) / 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_point_down_without_crossings_from_the_root() {
+        // 0 → 1, 2; 1 → 4; 2 → 3; 4 → 0 closes a cycle; 0 → 3 spans
+        // two layers; 5 floats, mentioning 3. In index order the
+        // second layer's arrows cross; the sweeps uncross them.
+        let ends = [(0, 1), (0, 2), (1, 4), (2, 3), (4, 0), (0, 3), (5, 3), (2, 2)];
+        let points = layout(6, &ends, Some(0));
+        assert_eq!(points, layout(6, &ends, Some(0)));
+        let y: Vec<f64> = points.iter().map(|point| point.y).collect();
+        assert!(y[0] < y[1] && y[1] == y[2] && y[2] < y[3] && y[3] == y[4]);
+        assert!(points[4].x < points[3].x);
+        // The floater sits just above what it mentions, not on top.
+        assert_eq!(y[5], y[1]);
+
+        let edges = acyclic(6, &ends, Some(0));
+        assert!(edges.contains(&(0, 4)) && !edges.contains(&(4, 0)));
+        let rows = vec![vec![0], vec![1, 2], vec![3, 4]];
+        let down = vec![vec![1, 2], vec![4], vec![3], vec![], vec![]];
+        let slot = [0, 0, 1, 0, 1];
+        assert_eq!(crossings(&rows, &slot, &down), 1);
+    }
+}
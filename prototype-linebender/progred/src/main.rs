@@ -1,22 +1,44 @@
 //! Window shell: winit + Vello plumbing around pure frame drawing.
 //! `run_frame` writes to any puri `Canvas`; here it streams into vello.
 
+mod binary;
+mod check;
+mod compare;
 mod conventions;
+mod driver;
 mod filter;
+mod find;
 mod sources;
 mod graph_view;
 mod history;
+mod history_view;
+mod layered;
+mod migrate;
+mod panel;
+mod pool;
 mod raw;
+mod references;
+mod relabel;
+mod schema;
+mod settings;
+mod sidecar;
+mod spatial;
 mod store;
+mod svg;
+mod text;
 
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 
+use history::Action;
+use panel::{Side, Sides};
+
 use muda::accelerator::{Accelerator, Code, Modifiers};
 use muda::{CheckMenuItem, Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 use parley::{FontContext, LayoutContext};
-use progred_graph::{Label, Step, Value};
+use progred_graph::mentions::Owner;
+use progred_graph::{CellId, Label, Step, Value};
 use puri::draw::{Canvas, GlyphRun, Shape};
 use puri::edit::{EditCtx, LineEditState};
 use puri::handler::{Handler, HasHandler, ImeEvent};
@@ -42,6 +64,12 @@ use winit::window::{Window, WindowId};
 enum UserEvent {
     Menu(MenuEvent),
     Discard(bool),
+    /// The merge sheet's answer: which side wins the conflicting
+    /// parts, or `None` for cancel.
+    Merge(Option<raw::Prefer>),
+    /// The inline sheet's answer: whether to inline a copy though
+    /// the cell is referenced elsewhere.
+    Inline(bool),
 }
 
 /// The action a discard confirmation gates. One at a time: requests
@@ -89,6 +117,15 @@ struct App {
     /// Where the document lives; `None` is untitled until the first
     /// save asks for a path.
     doc_path: Option<PathBuf>,
+    /// The form the document's file is in, so a save in place keeps
+    /// it — a binary file's with the journal its save appends against.
+    form: store::Form,
+    /// The older format the document was upgraded from at load, until
+    /// a save writes it current — shown in the title, and keeping Save
+    /// live though nothing was edited.
+    migrated: Option<u32>,
+    /// The editor's preferences, as last read or written.
+    settings: settings::Settings,
     /// Attached to the app once launched; commands arrive as user
     /// events.
     menu: Menu,
@@ -120,6 +157,10 @@ struct App {
     /// Routes the discard sheet's answer back into the loop.
     proxy: winit::event_loop::EventLoopProxy<UserEvent>,
     pending_discard: Option<AfterDiscard>,
+    /// The (survivor, loser) pair a merge sheet is asking about.
+    pending_merge: Option<(CellId, CellId)>,
+    /// The link an inline sheet is asking about.
+    pending_inline: Option<raw::Path>,
 }
 
 struct MenuIds {
@@ -127,19 +168,60 @@ struct MenuIds {
     open: MenuId,
     save: MenuId,
     save_as: MenuId,
+    attach_library: MenuId,
+    /// Keep History's choices, in [`HISTORY_LIMITS`] order.
+    history_limit: [MenuId; 4],
+    export_svg: MenuId,
+    export_dot: MenuId,
     quit: MenuId,
     undo: MenuId,
     redo: MenuId,
+    find: MenuId,
+    replace_all: MenuId,
+    references: MenuId,
+    relabel: MenuId,
+    extract: MenuId,
+    inline: MenuId,
+    merge: MenuId,
     graph: MenuId,
+    labeled: MenuId,
+    layered: MenuId,
+    neighborhood: MenuId,
+    widen: MenuId,
+    narrow: MenuId,
+    pool: MenuId,
+    problems: MenuId,
+    history: MenuId,
     raw: MenuId,
+    compare_file: MenuId,
+    compare_earlier: MenuId,
+    compare_later: MenuId,
+    stop_comparing: MenuId,
 }
 
 struct MenuItems {
     save: MenuItem,
+    history_limit: [CheckMenuItem; 4],
     undo: MenuItem,
     redo: MenuItem,
+    references: MenuItem,
+    relabel: MenuItem,
+    extract: MenuItem,
+    inline: MenuItem,
+    merge: MenuItem,
     graph: CheckMenuItem,
+    labeled: CheckMenuItem,
+    layered: CheckMenuItem,
+    neighborhood: CheckMenuItem,
+    widen: MenuItem,
+    narrow: MenuItem,
+    pool: CheckMenuItem,
+    problems: CheckMenuItem,
+    history: CheckMenuItem,
     raw: CheckMenuItem,
+    compare_earlier: MenuItem,
+    compare_later: MenuItem,
+    stop_comparing: MenuItem,
 }
 
 /// The View menu's frame inputs: which panes and layers this frame
@@ -147,11 +229,35 @@ struct MenuItems {
 #[derive(Clone, Copy)]
 struct ViewFlags {
     graph: bool,
+    /// The pool browser: the orphaned cells, beside the graph.
+    pool: bool,
+    /// The checker's problems list, under the document.
+    problems: bool,
+    /// The history browser, left of the other side panels.
+    history: bool,
+    /// The references pane, left of the history browser. Opened by
+    /// Find References, closed from its own heading.
+    references: bool,
+    /// The rename-label pane, left of the references pane. Opened by
+    /// Rename Label Everywhere, closed from its own heading.
+    relabel: bool,
+    /// The find bar, over the problems strip. Opened by Find rather
+    /// than toggled here, but laid out like the panes.
+    find: bool,
     /// The one Raw bit: convention layers derive from it — names
     /// answer bare identities. Lists stay lists; kind is data.
     raw: bool,
 }
 
+/// The bounds File ▸ Keep History offers on the history sidecar, and
+/// what it calls them.
+const HISTORY_LIMITS: [(usize, &str); 4] = [
+    (0, "Off"),
+    (100, "100 Steps"),
+    (1_000, "1,000 Steps"),
+    (10_000, "10,000 Steps"),
+];
+
 /// The menu bar: file commands own their key equivalents, so the
 /// platform routes Cmd+S and friends here rather than through key
 /// dispatch. Attachment is macOS-only until another platform is run.
@@ -169,6 +275,10 @@ fn build_menu() -> (Menu, MenuIds, MenuItems) {
         true,
         Some(Accelerator::new(Some(accel | Modifiers::SHIFT), Code::KeyS)),
     );
+    let attach_library = MenuItem::new("Attach Library…", true, None);
+    let history_limit = HISTORY_LIMITS.map(|(_, text)| CheckMenuItem::new(text, true, false, None));
+    let export_svg = MenuItem::new("Export Graph as SVG…", true, None);
+    let export_dot = MenuItem::new("Export Graph as DOT…", true, None);
     let quit = MenuItem::new("Quit Progred", true, Some(Accelerator::new(Some(accel), Code::KeyQ)));
     let undo = MenuItem::new("Undo", true, Some(Accelerator::new(Some(accel), Code::KeyZ)));
     let redo = MenuItem::new(
@@ -176,29 +286,83 @@ fn build_menu() -> (Menu, MenuIds, MenuItems) {
         true,
         Some(Accelerator::new(Some(accel | Modifiers::SHIFT), Code::KeyZ)),
     );
+    let find = MenuItem::new("Find…", true, Some(Accelerator::new(Some(accel), Code::KeyF)));
+    let replace_all = MenuItem::new("Replace All", true, None);
+    let references = MenuItem::new(
+        "Find References",
+        true,
+        Some(Accelerator::new(Some(accel | Modifiers::SHIFT), Code::KeyF)),
+    );
+    let relabel = MenuItem::new("Rename Label Everywhere…", true, None);
+    let extract = MenuItem::new(
+        "Extract to Cell",
+        true,
+        Some(Accelerator::new(Some(accel | Modifiers::ALT), Code::KeyV)),
+    );
+    let inline = MenuItem::new(
+        "Inline Cell",
+        true,
+        Some(Accelerator::new(Some(accel | Modifiers::ALT), Code::KeyN)),
+    );
+    let merge = MenuItem::new("Merge Cells", false, None);
     let graph = CheckMenuItem::new(
         "Graph",
         true,
         false,
         Some(Accelerator::new(Some(accel), Code::KeyG)),
     );
+    let labeled = CheckMenuItem::new("Labeled Edges", true, false, None);
+    let layered = CheckMenuItem::new("Layered Layout", true, false, None);
+    let neighborhood = CheckMenuItem::new("Neighborhood", true, false, None);
+    let widen = MenuItem::new("Widen Neighborhood", false, None);
+    let narrow = MenuItem::new("Narrow Neighborhood", false, None);
+    let pool = CheckMenuItem::new("Pool", true, false, None);
+    let problems = CheckMenuItem::new("Problems", true, false, None);
+    let history = CheckMenuItem::new("History", true, false, None);
     let raw = CheckMenuItem::new(
         "Raw",
         true,
         false,
         Some(Accelerator::new(Some(accel), Code::KeyR)),
     );
+    let compare_file = MenuItem::new("Compare With File…", true, None);
+    let compare_earlier = MenuItem::new("Compare Earlier", true, None);
+    let compare_later = MenuItem::new("Compare Later", true, None);
+    let stop_comparing = MenuItem::new("Stop Comparing", true, None);
     let menu = Menu::new();
     let ids = MenuIds {
         new: new.id().clone(),
         open: open.id().clone(),
         save: save.id().clone(),
         save_as: save_as.id().clone(),
+        attach_library: attach_library.id().clone(),
+        history_limit: history_limit.each_ref().map(|item| item.id().clone()),
+        export_svg: export_svg.id().clone(),
+        export_dot: export_dot.id().clone(),
         quit: quit.id().clone(),
         undo: undo.id().clone(),
         redo: redo.id().clone(),
+        find: find.id().clone(),
+        replace_all: replace_all.id().clone(),
+        references: references.id().clone(),
+        relabel: relabel.id().clone(),
+        extract: extract.id().clone(),
+        inline: inline.id().clone(),
+        merge: merge.id().clone(),
         graph: graph.id().clone(),
+        labeled: labeled.id().clone(),
+        layered: layered.id().clone(),
+        neighborhood: neighborhood.id().clone(),
+        widen: widen.id().clone(),
+        narrow: narrow.id().clone(),
+        pool: pool.id().clone(),
+        problems: problems.id().clone(),
+        history: history.id().clone(),
         raw: raw.id().clone(),
+        compare_file: compare_file.id().clone(),
+        compare_earlier: compare_earlier.id().clone(),
+        compare_later: compare_later.id().clone(),
+        stop_comparing: stop_comparing.id().clone(),
     };
     menu.append_items(&[
         &Submenu::with_items(
@@ -220,19 +384,85 @@ fn build_menu() -> (Menu, MenuIds, MenuItems) {
                 &PredefinedMenuItem::separator(),
                 &save,
                 &save_as,
+                &PredefinedMenuItem::separator(),
+                &export_svg,
+                &export_dot,
+                &PredefinedMenuItem::separator(),
+                &attach_library,
+                &Submenu::with_items(
+                    "Keep History",
+                    true,
+                    &history_limit.each_ref().map(|item| item as &dyn muda::IsMenuItem),
+                )
+                .expect("keep history menu"),
             ],
         )
         .expect("file menu"),
-        &Submenu::with_items("Edit", true, &[&undo, &redo]).expect("edit menu"),
-        &Submenu::with_items("View", true, &[&raw, &graph]).expect("view menu"),
+        &Submenu::with_items(
+            "Edit",
+            true,
+            &[
+                &undo,
+                &redo,
+                &PredefinedMenuItem::separator(),
+                &find,
+                &replace_all,
+                &references,
+                &PredefinedMenuItem::separator(),
+                &relabel,
+                &extract,
+                &inline,
+                &merge,
+            ],
+        )
+        .expect("edit menu"),
+        &Submenu::with_items(
+            "View",
+            true,
+            &[
+                &raw,
+                &graph,
+                &labeled,
+                &layered,
+                &neighborhood,
+                &widen,
+                &narrow,
+                &pool,
+                &problems,
+                &history,
+                &PredefinedMenuItem::separator(),
+                &compare_file,
+                &compare_earlier,
+                &compare_later,
+                &stop_comparing,
+            ],
+        )
+        .expect("view menu"),
     ])
     .expect("menu bar");
     let items = MenuItems {
         save,
+        history_limit,
         undo,
         redo,
+        references,
+        relabel,
+        extract,
+        inline,
+        merge,
         graph,
+        labeled,
+        layered,
+        neighborhood,
+        widen,
+        narrow,
+        pool,
+        problems,
+        history,
         raw,
+        compare_earlier,
+        compare_later,
+        stop_comparing,
     };
     (menu, ids, items)
 }
@@ -259,6 +489,16 @@ fn edge_path(selection: &Option<Selected>) -> Option<raw::Path> {
     }
 }
 
+/// What a path ends at, for a step's label: "delete element".
+fn place(path: &raw::Path) -> &'static str {
+    match path.last() {
+        None => "root",
+        Some(Step::Element(_)) => "element",
+        Some(Step::Key(_)) => "field",
+        Some(Step::Follow | Step::Name) => "value",
+    }
+}
+
 /// No modifiers at all — the gate for the bare editing keys.
 fn plain(event: &KeyboardEvent) -> bool {
     !(event.modifiers.ctrl()
@@ -268,7 +508,16 @@ fn plain(event: &KeyboardEvent) -> bool {
 }
 
 fn dialog() -> rfd::FileDialog {
-    rfd::FileDialog::new().add_filter("progred", &["progred"])
+    let extensions = ["progred", store::BINARY_EXTENSION, store::TEXT_EXTENSION];
+    rfd::FileDialog::new().add_filter("progred", &extensions)
+}
+
+/// The library table for `doc` living at `path`: its declared
+/// libraries composed over the built-in one. Composition problems
+/// are read past — the document opens regardless — and listed in the
+/// problems strip.
+fn compose_library(doc: &raw::Document, path: Option<&std::path::Path>) -> store::Composed {
+    store::libraries(doc, path.and_then(std::path::Path::parent))
 }
 
 impl ApplicationHandler<UserEvent> for App {
@@ -282,6 +531,20 @@ impl ApplicationHandler<UserEvent> for App {
                 }
                 return;
             }
+            UserEvent::Merge(prefer) => {
+                let pending = self.pending_merge.take();
+                if let (Some(prefer), Some((survivor, loser))) = (prefer, pending) {
+                    self.merge(survivor, loser, prefer);
+                }
+                return;
+            }
+            UserEvent::Inline(accepted) => {
+                let pending = self.pending_inline.take();
+                if accepted && let Some(path) = pending {
+                    self.inline(path, false);
+                }
+                return;
+            }
         };
         if *event.id() == self.menu_ids.new {
             self.request_discard(event_loop, AfterDiscard::New);
@@ -291,13 +554,77 @@ impl ApplicationHandler<UserEvent> for App {
             self.menu_save(false);
         } else if *event.id() == self.menu_ids.save_as {
             self.menu_save(true);
+        } else if *event.id() == self.menu_ids.export_svg {
+            self.menu_export_graph(true);
+        } else if *event.id() == self.menu_ids.export_dot {
+            self.menu_export_graph(false);
+        } else if *event.id() == self.menu_ids.attach_library {
+            self.menu_attach_library();
+        } else if let Some(at) =
+            (self.menu_ids.history_limit.iter()).position(|id| id == event.id())
+        {
+            self.set_history_limit(HISTORY_LIMITS[at].0);
         } else if *event.id() == self.menu_ids.quit {
             self.request_discard(event_loop, AfterDiscard::Quit);
         } else if *event.id() == self.menu_ids.undo {
             self.step_history(true);
         } else if *event.id() == self.menu_ids.redo {
             self.step_history(false);
-        } else if (*event.id() == self.menu_ids.graph || *event.id() == self.menu_ids.raw)
+        } else if *event.id() == self.menu_ids.find {
+            self.open_find();
+        } else if *event.id() == self.menu_ids.replace_all {
+            self.replace_all();
+        } else if *event.id() == self.menu_ids.references {
+            self.open_references();
+        } else if *event.id() == self.menu_ids.relabel {
+            self.open_relabel();
+        } else if *event.id() == self.menu_ids.extract {
+            self.extract();
+        } else if *event.id() == self.menu_ids.inline {
+            self.request_inline();
+        } else if *event.id() == self.menu_ids.merge {
+            if let Some((survivor, loser)) = self.merge_pair() {
+                self.request_merge(survivor, loser);
+            }
+        } else if *event.id() == self.menu_ids.labeled {
+            let on = self.menu_items.labeled.is_checked();
+            self.model.graph.set_labeled(on);
+            if on {
+                self.menu_items.graph.set_checked(true);
+            }
+            if let RenderState::Active { window, .. } = &self.state {
+                window.request_redraw();
+            }
+        } else if *event.id() == self.menu_ids.layered {
+            let on = self.menu_items.layered.is_checked();
+            self.model.graph.set_layered(on);
+            if on {
+                self.menu_items.graph.set_checked(true);
+            }
+            if let RenderState::Active { window, .. } = &self.state {
+                window.request_redraw();
+            }
+        } else if *event.id() == self.menu_ids.neighborhood {
+            let on = self.menu_items.neighborhood.is_checked();
+            self.set_radius(on.then_some(graph_view::RADIUS));
+        } else if *event.id() == self.menu_ids.widen {
+            self.set_radius(self.model.graph.radius().map(|radius| radius + 1));
+        } else if *event.id() == self.menu_ids.narrow {
+            self.set_radius(self.model.graph.radius().map(|radius| radius.max(2) - 1));
+        } else if *event.id() == self.menu_ids.compare_file {
+            self.menu_compare_file();
+        } else if *event.id() == self.menu_ids.compare_earlier {
+            self.step_compare(-1);
+        } else if *event.id() == self.menu_ids.compare_later {
+            self.step_compare(1);
+        } else if *event.id() == self.menu_ids.stop_comparing {
+            self.model.compare = None;
+            self.refresh_compare();
+        } else if (*event.id() == self.menu_ids.graph
+            || *event.id() == self.menu_ids.pool
+            || *event.id() == self.menu_ids.problems
+            || *event.id() == self.menu_ids.history
+            || *event.id() == self.menu_ids.raw)
             && let RenderState::Active { window, .. } = &self.state
         {
             window.request_redraw();
@@ -367,11 +694,11 @@ impl ApplicationHandler<UserEvent> for App {
 
         // Pinch zooms the graph toward the cursor; winit delivers it
         // outside the pointer stream the reducer covers.
+        let size = window.inner_size();
         if let WindowEvent::PinchGesture { delta, .. } = &event
-            && self.menu_items.graph.is_checked()
+            && let Some(panel) =
+                sides(self.view_flags(), size.width as f64, size.height as f64).get(Side::Graph)
         {
-            let size = window.inner_size();
-            let panel = graph_view::panel(size.width as f64, size.height as f64);
             let anchor = if panel.contains(self.cursor) {
                 self.cursor - panel.center()
             } else {
@@ -423,12 +750,18 @@ impl ApplicationHandler<UserEvent> for App {
                     // wins over all three.
                     (None, Some(WindowEventTranslation::Keyboard(key_event))) => {
                         dispatch.handler.dispatch_key(self, &key_event)
-                            || self.clipboard_key(&key_event)
+                            || self.clipboard_key(&dispatch.descends, &key_event)
+                            || self.reshape_key(&key_event)
                             || self.graph_key(&key_event)
                             || self.delete_key(&dispatch.descends, &key_event)
                             || self.insert_key(&dispatch.descends, &dispatch.popup, &key_event)
                             || self.rename_key(&key_event)
                             || self.collapse_key(&key_event)
+                            || raw::extend_selection(
+                                &dispatch.descends,
+                                self.model.tree_selection_mut(),
+                                &key_event,
+                            )
                             || match raw::step_selection(
                                 &dispatch.descends,
                                 self.model.tree_selection(),
@@ -504,7 +837,9 @@ impl ApplicationHandler<UserEvent> for App {
                         // life: the run's one step opens here.
                         if raw::write_through(&mut model.doc, &model.library, selection) {
                             let path = selection.path().to_vec();
-                            model.history.record(before, Some(path));
+                            let action =
+                                Action::new(history::Kind::Edit, "edit value", path.clone());
+                            model.history.record(before, Some(path), action);
                             self.refresh_title();
                         }
                     }
@@ -574,18 +909,33 @@ impl ApplicationHandler<UserEvent> for App {
 }
 
 fn main() {
-    let doc_path = std::env::args().nth(1).map(PathBuf::from);
+    let args: Vec<String> = std::env::args().collect();
+    // `progred merge BASE OURS THEIRS` is the git merge driver, run
+    // headless.
+    if args.get(1).map(String::as_str) == Some("merge") {
+        std::process::exit(driver::run(&args[2..]));
+    }
+    let doc_path = args.get(1).map(PathBuf::from);
     // A given-but-missing path is a new document there; no path is
     // untitled until the first save asks. A file that exists but does
     // not parse is refused rather than silently replaced, so a save
     // cannot clobber it with the sample.
-    let doc = match &doc_path {
-        Some(path) if path.exists() => store::load(path).unwrap_or_else(|error| {
+    let opened = match &doc_path {
+        Some(path) if path.exists() => store::open(path).unwrap_or_else(|error| {
             eprintln!("failed to load {}: {error}", path.display());
             std::process::exit(1);
         }),
-        _ => raw::sample_document(),
+        _ => store::Opened {
+            doc: raw::sample_document(),
+            form: store::Form::Json,
+            migrated: None,
+        },
     };
+    let store::Opened {
+        doc,
+        form,
+        migrated,
+    } = opened;
 
     let mut builder = EventLoop::<UserEvent>::with_user_event();
     #[cfg(target_os = "macos")]
@@ -597,6 +947,20 @@ fn main() {
     // The menu attaches to the app instance the event loop created;
     // its events arrive as user events through the proxy.
     let (menu, menu_ids, menu_items) = build_menu();
+    let (settings, settings_problem) = settings::load();
+    let settings_problems: Vec<String> = settings_problem.into_iter().collect();
+    let store::Composed {
+        cells: library,
+        diagnostics: library_problems,
+    } = compose_library(&doc, doc_path.as_deref());
+    if !library_problems.is_empty() || !settings_problems.is_empty() {
+        menu_items.problems.set_checked(true);
+    }
+    let mut history = match &doc_path {
+        Some(path) => sidecar::resume(path, &doc, &library),
+        None => history::History::default(),
+    };
+    history.set_rules(settings.rules());
     let proxy = event_loop.create_proxy();
     let menu_proxy = proxy.clone();
     MenuEvent::set_event_handler(Some(move |event| {
@@ -616,14 +980,25 @@ fn main() {
             selection: None,
             collapse: raw::Collapse::default(),
             names: conventions::Names::default(),
-            library: conventions::library(),
+            library,
+            library_problems,
+            settings_problems,
             graph: graph_view::GraphView::default(),
-            history: history::History::default(),
+            pool: pool::PoolView::default(),
+            history,
+            compare: None,
+            find: None,
+            references: None,
+            relabel: None,
+            mentions: progred_graph::mentions::Mentions::default(),
             hover: None,
             scroll: 0.0,
             scroll_x: 0.0,
         },
         doc_path,
+        form,
+        migrated,
+        settings,
         menu,
         menu_ids,
         menu_items,
@@ -636,6 +1011,8 @@ fn main() {
         reducer: WindowEventReducer::default(),
         proxy,
         pending_discard: None,
+        pending_merge: None,
+        pending_inline: None,
     };
 
     event_loop
@@ -659,6 +1036,11 @@ enum Selected {
 enum Hovered {
     Tree(raw::Hovering),
     Graph(graph_view::GraphNode),
+    Pool(CellId),
+    Problem(usize),
+    History(usize),
+    Reference(usize),
+    Relabel(usize),
 }
 
 struct Model {
@@ -668,11 +1050,39 @@ struct Model {
     /// The name policy: an editor setting, not document state, so it
     /// survives document swaps.
     names: conventions::Names,
-    /// The built-in library, read under every document; never
-    /// written, never saved.
+    /// The library table: the built-in library with the document's
+    /// declared libraries composed over it. Read under the document;
+    /// never written, never saved — only the declarations are.
     library: progred_graph::Cells,
+    /// What went wrong composing it, for the problems strip.
+    library_problems: Vec<String>,
+    /// What went wrong reading or writing the settings, for the
+    /// problems strip. The editor's, not the document's: it carries
+    /// over to the next document opened.
+    settings_problems: Vec<String>,
     graph: graph_view::GraphView,
+    /// The pool browser's marks; like the graph's layout, editor
+    /// state bound to the document.
+    pool: pool::PoolView,
     history: history::History,
+    /// The state the document is being compared with, when it is:
+    /// the raw projection overlays the difference. Bound to the
+    /// document like its history.
+    compare: Option<compare::Against>,
+    /// The find bar while it is open: editor state like the name
+    /// policy, so it survives document swaps.
+    find: Option<find::FindBar>,
+    /// The cell the references pane lists the mentions of, while it
+    /// is up.
+    references: Option<CellId>,
+    /// The rename-label pane while it is up: bound to the document,
+    /// whose label it renames.
+    relabel: Option<relabel::Relabel>,
+    /// The reverse-mention index the references and rename-label
+    /// panes read: synced before each pass while either is up, which
+    /// costs the edits since the last sync, however long ago that
+    /// was.
+    mentions: progred_graph::mentions::Mentions,
     /// What the pointer rests on — the claim a click would fire —
     /// previewed by the frame as the hover highlight. Written by move
     /// dispatch like the selection is written by clicks; goes stale
@@ -690,6 +1100,14 @@ struct Model {
 }
 
 impl Model {
+    /// Recomposes the library table from the document's declarations,
+    /// read against `path`'s directory.
+    fn compose(&mut self, path: Option<&std::path::Path>) {
+        let composed = compose_library(&self.doc, path);
+        self.library = composed.cells;
+        self.library_problems = composed.diagnostics;
+    }
+
     /// The reading context: this document over the editor's library.
     fn sources(&self) -> sources::Sources<'_> {
         sources::Sources {
@@ -705,6 +1123,17 @@ impl Model {
         }
     }
 
+    /// The selected edges' paths, the primary first: the set batch
+    /// edits act on. Empty unless the selection is an edge.
+    fn edge_paths(&self) -> Vec<raw::Path> {
+        match self.tree_selection() {
+            Some(selection @ raw::Selection::Edge { .. }) => {
+                selection.paths().into_iter().cloned().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
     fn tree_selection_mut(&mut self) -> Option<&mut raw::Selection> {
         match &mut self.selection {
             Some(Selected::Tree(selection)) => Some(selection),
@@ -726,6 +1155,61 @@ impl Model {
         }
     }
 
+    fn pool_hover(&self) -> Option<CellId> {
+        match &self.hover {
+            Some(Hovered::Pool(cell)) => Some(*cell),
+            _ => None,
+        }
+    }
+
+    fn problem_hover(&self) -> Option<usize> {
+        match &self.hover {
+            Some(Hovered::Problem(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn history_hover(&self) -> Option<usize> {
+        match &self.hover {
+            Some(Hovered::History(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn reference_hover(&self) -> Option<usize> {
+        match &self.hover {
+            Some(Hovered::Reference(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn relabel_hover(&self) -> Option<usize> {
+        match &self.hover {
+            Some(Hovered::Relabel(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    /// Brings the mention index up to the document, while a pane
+    /// reads it.
+    /// Brings the find bar's hits up to the document and query, for
+    /// a pass to show.
+    fn sync_find(&mut self) {
+        let sources = sources::Sources {
+            doc: &self.doc,
+            library: &self.library,
+        };
+        if let Some(bar) = &mut self.find {
+            bar.hits(&sources);
+        }
+    }
+
+    fn sync_mentions(&mut self) {
+        if self.references.is_some() || self.relabel.is_some() {
+            self.mentions.sync(&self.doc.cells, self.doc.root.as_ref());
+        }
+    }
+
     fn graph_hover(&self) -> Option<&graph_view::GraphNode> {
         match &self.hover {
             Some(Hovered::Graph(node)) => Some(node),
@@ -755,6 +1239,36 @@ impl Model {
             _ => None,
         }
     }
+
+    /// What the graph's neighborhood centers on: the graph-selected
+    /// node, or the nodes the tree selection's members sit at. Empty
+    /// for no selection or a pending one, which leave it in place.
+    fn graph_focus(&self) -> Vec<graph_view::GraphNode> {
+        match &self.selection {
+            Some(Selected::Graph(
+                graph_view::GraphSelection::Node(node) | graph_view::GraphSelection::Edge(node, _),
+            )) => vec![*node],
+            Some(Selected::Tree(selection @ raw::Selection::Edge { .. })) => {
+                let sources = self.sources();
+                let mut focus: Vec<_> = selection
+                    .paths()
+                    .into_iter()
+                    .filter_map(|path| graph_view::focus_of(&sources, path))
+                    .collect();
+                focus.sort();
+                focus.dedup();
+                focus
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The compare overlay: the document diffed against the state
+    /// it is compared with, when there is one still to reach.
+    fn overlay(&self) -> Option<compare::Overlay> {
+        let base = self.compare.as_ref()?.base(&self.history)?;
+        Some(compare::Overlay::new(base, &self.doc))
+    }
 }
 
 /// One pass over the UI: read-only in the model, producing draw calls
@@ -868,9 +1382,19 @@ impl App {
 
     fn title(&self) -> String {
         let dirty = if self.model.history.dirty() { " •" } else { "" };
+        let migrated = self
+            .migrated
+            .map(|format| format!(" (upgraded from format {format})"))
+            .unwrap_or_default();
+        let compared = match (&self.model.compare, self.model.overlay()) {
+            (Some(against), Some(overlay)) => {
+                format!(" — comparing with {}: {}", against.describe(), overlay.summary())
+            }
+            _ => String::new(),
+        };
         match &self.doc_path {
-            Some(path) => format!("Progred — {}{dirty}", path.display()),
-            None => format!("Progred — untitled{dirty}"),
+            Some(path) => format!("Progred — {}{migrated}{dirty}{compared}", path.display()),
+            None => format!("Progred — untitled{migrated}{dirty}{compared}"),
         }
     }
 
@@ -882,17 +1406,108 @@ impl App {
 
     /// Menu enablement follows the model: gray what can't act. Save
     /// stays live for untitled documents — it defers to the save
-    /// panel, per platform convention.
+    /// panel, per platform convention — and for upgraded ones, which
+    /// have a current format to write.
     fn sync_menus(&self) {
         self.menu_items
             .save
-            .set_enabled(self.model.history.dirty() || self.doc_path.is_none());
+            .set_enabled(
+                self.model.history.dirty() || self.doc_path.is_none() || self.migrated.is_some(),
+            );
+        // The items say the step they revert: "Undo rename field".
+        let history = &self.model.history;
+        let said = |verb: &str, label: Option<&str>| match label {
+            Some(label) => format!("{verb} {label}"),
+            None => verb.to_string(),
+        };
+        let kept = self.settings.history_limit;
+        for (item, (limit, _)) in self.menu_items.history_limit.iter().zip(HISTORY_LIMITS) {
+            item.set_checked(kept == limit);
+        }
+        self.menu_items.undo.set_text(said("Undo", history.undo_label()));
+        self.menu_items.undo.set_enabled(history.can_undo());
+        self.menu_items.redo.set_text(said("Redo", history.redo_label()));
+        self.menu_items.redo.set_enabled(history.can_redo());
+        self.menu_items
+            .references
+            .set_enabled(self.reference_target().is_some());
+        self.menu_items
+            .relabel
+            .set_enabled(self.relabel_source().is_some());
+        self.menu_items
+            .extract
+            .set_enabled(self.extract_target().is_some());
         self.menu_items
-            .undo
-            .set_enabled(self.model.history.can_undo());
+            .inline
+            .set_enabled(self.inline_target().is_some());
+        self.menu_items.merge.set_enabled(self.merge_pair().is_some());
+        self.menu_items.labeled.set_checked(self.model.graph.labeled());
+        self.menu_items.layered.set_checked(self.model.graph.layered());
+        let radius = self.model.graph.radius();
+        self.menu_items.neighborhood.set_checked(radius.is_some());
+        self.menu_items.widen.set_enabled(radius.is_some());
+        self.menu_items.narrow.set_enabled(radius.is_some_and(|radius| radius > 1));
+        let offset = match self.model.compare {
+            Some(compare::Against::History(offset)) => offset,
+            _ => 0,
+        };
+        let reaches = |offset: isize| self.model.history.state(offset).is_some();
+        self.menu_items.compare_earlier.set_enabled(reaches(offset - 1));
+        self.menu_items.compare_later.set_enabled(reaches(offset + 1));
         self.menu_items
-            .redo
-            .set_enabled(self.model.history.can_redo());
+            .stop_comparing
+            .set_enabled(self.model.compare.is_some());
+    }
+
+    /// Compares with a document file picked from disk; one that
+    /// doesn't open leaves the comparison as it was.
+    fn menu_compare_file(&mut self) {
+        let Some(path) = dialog().pick_file() else {
+            return;
+        };
+        match store::open(&path) {
+            Ok(opened) => {
+                self.model.compare = Some(compare::Against::File {
+                    path,
+                    doc: opened.doc,
+                });
+                self.refresh_compare();
+            }
+            Err(error) => eprintln!("failed to open {}: {error}", path.display()),
+        }
+    }
+
+    /// Moves the compared history state a step earlier or later,
+    /// starting from the present; past either end of the history it
+    /// stays where it was, and reaching the present stops comparing.
+    fn step_compare(&mut self, by: isize) {
+        let offset = match self.model.compare {
+            Some(compare::Against::History(offset)) => offset + by,
+            _ => by,
+        };
+        if offset == 0 {
+            self.model.compare = None;
+        } else if self.model.history.state(offset).is_some() {
+            self.model.compare = Some(compare::Against::History(offset));
+        } else {
+            return;
+        }
+        self.refresh_compare();
+    }
+
+    /// The overlay is the pass's to draw; the title follows the
+    /// comparison here.
+    fn refresh_compare(&mut self) {
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            let window = window.clone();
+            let size = window.inner_size();
+            self.retain_dispatch(
+                window.scale_factor(),
+                Size::new(size.width as f64, size.height as f64),
+            );
+            window.request_redraw();
+        }
     }
 
     /// Undo or redo one step, restoring the snapshot's document and
@@ -905,8 +1520,30 @@ impl App {
         } else {
             self.model.history.redo(current, selection)
         };
+        self.restore(restored);
+    }
+
+    /// Goes to a state picked in the history browser — navigation
+    /// like undo and redo, so the state left stays a click away.
+    fn jump_history(&mut self, index: usize) {
+        let current = self.model.doc.clone();
+        let selection = edge_path(&self.model.selection);
+        let restored = self.model.history.jump(index, current, selection);
+        self.restore(restored);
+    }
+
+    /// Adopts a state the history handed back: its document and the
+    /// selection it restores.
+    fn restore(&mut self, restored: Option<(raw::Document, Option<raw::Path>)>) {
         if let Some((doc, restore)) = restored {
+            // Declarations are document state like any other; the
+            // table they compose follows them across the step.
+            let recompose = doc.libraries != self.model.doc.libraries;
             self.model.doc = doc;
+            if recompose {
+                self.model.compose(self.doc_path.as_deref());
+                self.reveal_library_problems();
+            }
             // One slot: restoring (or clearing) the tree selection
             // also drops any graph selection, which may reference
             // content the restored document no longer has.
@@ -974,73 +1611,852 @@ impl App {
         });
     }
 
-    /// The action a confirmed (or unneeded) discard proceeds to.
-    fn proceed(&mut self, event_loop: &ActiveEventLoop, then: AfterDiscard) {
-        match then {
-            AfterDiscard::New => self.adopt_model(
-                raw::Document {
-                    root: None,
-                    cells: progred_graph::Cells::new(),
-                },
-                None,
-            ),
-            AfterDiscard::Open => {
-                if let Some(path) = dialog().pick_file() {
-                    match store::load(&path) {
-                        Ok(doc) => self.adopt_model(doc, Some(path)),
-                        Err(error) => {
-                            eprintln!("failed to open {}: {error}", path.display());
-                        }
+    /// The action a confirmed (or unneeded) discard proceeds to.
+    fn proceed(&mut self, event_loop: &ActiveEventLoop, then: AfterDiscard) {
+        match then {
+            AfterDiscard::New => self.adopt_model(
+                store::Opened {
+                    doc: raw::Document {
+                        root: None,
+                        cells: progred_graph::Cells::new(),
+                        libraries: Vec::new(),
+                    },
+                    form: store::Form::Json,
+                    migrated: None,
+                },
+                None,
+            ),
+            AfterDiscard::Open => {
+                if let Some(path) = dialog().pick_file() {
+                    match store::open(&path) {
+                        Ok(opened) => self.adopt_model(opened, Some(path)),
+                        Err(error) => {
+                            eprintln!("failed to open {}: {error}", path.display());
+                        }
+                    }
+                }
+            }
+            AfterDiscard::Quit => event_loop.exit(),
+        }
+    }
+
+    /// Save saves in place, or asks for a path when untitled; save-as
+    /// always asks. Write-through editing means the graph is always
+    /// current, so there is nothing to flush first. A cancelled dialog
+    /// saves nothing. A binary document saved in place appends; one
+    /// saved elsewhere starts over in whatever form the new name says.
+    fn menu_save(&mut self, save_as: bool) {
+        let in_place = (!save_as).then(|| self.doc_path.clone()).flatten();
+        let target = in_place.or_else(|| dialog().set_file_name("untitled.progred").save_file());
+        if let Some(path) = target {
+            // A failed write keeps the held form: a binary journal
+            // no longer matching its file rewrites it whole.
+            let form = match &self.doc_path {
+                Some(held) if *held == path => self.form.clone(),
+                _ => store::Form::for_path(&path),
+            };
+            // Relative library paths are read against the document's
+            // directory, so a save elsewhere re-spells them to keep
+            // naming the same files — in every state of the history
+            // too, or undo would bring back spellings for the old one.
+            let from = self.doc_path.as_deref().and_then(std::path::Path::parent);
+            let to = path.parent();
+            let libraries = if from == to {
+                self.model.doc.libraries.clone()
+            } else {
+                store::rebase(&self.model.doc.libraries, from, to)
+            };
+            let written = if libraries == self.model.doc.libraries {
+                store::write(&path, &self.model.doc, &form)
+            } else {
+                let doc = raw::Document {
+                    libraries: libraries.clone(),
+                    ..self.model.doc.clone()
+                };
+                store::write(&path, &doc, &form)
+            };
+            match written {
+                Ok(form) => {
+                    if from != to {
+                        (self.model.history)
+                            .respell_libraries(|held| store::rebase(held, from, to));
+                    }
+                    self.model.doc.libraries = libraries;
+                    self.form = form;
+                    self.migrated = None;
+                    self.model.history.mark_saved();
+                    let model = &self.model;
+                    let limit = sidecar::limit(self.settings.history_limit);
+                    if let Err(error) =
+                        sidecar::write(&path, &model.history, &model.doc, &model.library, limit)
+                    {
+                        eprintln!("failed to save the history beside {}: {error}", path.display());
+                    }
+                    // A run must not straddle the save mark, or edits
+                    // after it would coalesce into a pre-save step.
+                    raw::break_edit_run(self.model.tree_selection_mut());
+                    self.adopt_doc_path(path);
+                }
+                Err(error) => {
+                    eprintln!("failed to save {}: {error}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Writes the graph as SVG — the pane's picture, framed whole — or
+    /// as Graphviz DOT, to a file the user picks beside the document.
+    /// Either shows what the view would: its neighborhood, its labels,
+    /// its layout as it stands — or, with none of this document yet,
+    /// a copy's stepped once, so even a graph never opened has one
+    /// and the open one doesn't jump. A cancelled dialog writes
+    /// nothing.
+    fn menu_export_graph(&mut self, svg: bool) {
+        let extension = if svg { "svg" } else { "dot" };
+        let stem = (self.doc_path.as_deref())
+            .and_then(|path| path.file_stem())
+            .map_or("graph".into(), |stem| stem.to_string_lossy());
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(extension, &[extension])
+            .set_file_name(format!("{stem}.{extension}"))
+            .save_file()
+        else {
+            return;
+        };
+        let graph = self.model.graph.laid_out(&self.model.doc);
+        let (sources, raw) = (self.model.sources(), self.menu_items.raw.is_checked());
+        let text = if svg {
+            let mut tcx = TextCtx {
+                fonts: &mut self.font_cx,
+                layouts: &mut self.layout_cx,
+                scale: 1.0,
+                cache: &mut puri::text::TextCache::default(),
+            };
+            graph_view::export_svg(&sources, &graph, &self.model.names, raw, &mut tcx)
+        } else {
+            graph_view::export_dot(&sources, &graph, &self.model.names, raw)
+        };
+        if let Err(error) = std::fs::write(&path, text) {
+            eprintln!("failed to export the graph to {}: {error}", path.display());
+        }
+    }
+
+    /// Declares a library file under the document — one undo step —
+    /// and recomposes the table so its cells read immediately, as
+    /// external. The declaration is pinned to the file's content and
+    /// spelled relative to the document when it lies beneath it. A
+    /// file already declared, or one that isn't a document, attaches
+    /// nothing.
+    fn menu_attach_library(&mut self) {
+        let Some(path) = dialog().pick_file() else {
+            return;
+        };
+        let dir = self.doc_path.as_deref().and_then(std::path::Path::parent);
+        let library = match store::attach(&path, dir) {
+            Ok(library) => library,
+            Err(error) => {
+                eprintln!("failed to attach {}: {error}", path.display());
+                return;
+            }
+        };
+        if self.model.doc.libraries.iter().any(|held| held.path == library.path) {
+            return;
+        }
+        let before = self.model.doc.clone();
+        self.model.doc.libraries.push(library);
+        self.model.history.record(
+            before,
+            edge_path(&self.model.selection),
+            Action::new(history::Kind::Library, "attach library", Vec::new()),
+        );
+        self.model.compose(self.doc_path.as_deref());
+        self.reveal_library_problems();
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            let window = window.clone();
+            let size = window.inner_size();
+            self.retain_dispatch(
+                window.scale_factor(),
+                Size::new(size.width as f64, size.height as f64),
+            );
+            window.request_redraw();
+        }
+    }
+
+    /// Carries out a pool action on the marked cells. Jump opens the
+    /// graph on the cell; Attach links it at the tree selection; Merge
+    /// folds the second mark into the first and Purge drops what
+    /// nothing surviving mentions — each edit one undo step.
+    fn pool_action(&mut self, action: pool::Action) {
+        let marked = self.model.pool.marked.clone();
+        match (action, marked.as_slice()) {
+            (pool::Action::Jump, &[cell]) => self.show_in_graph(cell),
+            (pool::Action::Attach, &[cell]) => match &self.model.selection {
+                Some(Selected::Tree(raw::Selection::Pending { .. })) => {
+                    self.pick_identity(Value::from(cell));
+                }
+                Some(Selected::Tree(raw::Selection::Edge { path, .. })) => {
+                    let path = path.clone();
+                    let before = self.model.doc.clone();
+                    if raw::set_value(
+                        &mut self.model.doc,
+                        &self.model.library,
+                        &path,
+                        Value::from(cell),
+                    ) {
+                        let action = Action::new(history::Kind::Link, "link cell", path.clone());
+                        self.model.history.record(before, Some(path), action);
+                    }
+                }
+                _ => return,
+            },
+            (pool::Action::Merge, &[survivor, loser]) => {
+                self.request_merge(survivor, loser);
+                return;
+            }
+            (pool::Action::Purge, chosen) if !chosen.is_empty() => {
+                let before = self.model.doc.clone();
+                let purged = pool::purge(&mut self.model.doc, &self.model.library, chosen);
+                if !purged.is_empty() {
+                    let label = match purged.len() {
+                        1 => "purge cell".to_string(),
+                        n => format!("purge {n} cells"),
+                    };
+                    self.model.history.record(
+                        before,
+                        edge_path(&self.model.selection),
+                        Action::new(history::Kind::Purge, label, Vec::new()),
+                    );
+                    self.model.pool.marked.retain(|cell| !purged.contains(cell));
+                }
+            }
+            _ => return,
+        }
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            let window = window.clone();
+            let size = window.inner_size();
+            self.retain_dispatch(
+                window.scale_factor(),
+                Size::new(size.width as f64, size.height as f64),
+            );
+            window.request_redraw();
+        }
+    }
+
+    /// Opens the graph on `cell`, centered and selected.
+    fn show_in_graph(&mut self, cell: CellId) {
+        let node = graph_view::GraphNode::Cell(cell);
+        self.menu_items.graph.set_checked(true);
+        self.model.graph.center_on(&self.model.doc, &node);
+        self.model.selection = Some(Selected::Graph(graph_view::GraphSelection::Node(node)));
+    }
+
+    /// Selects a site a problem or a find hit names: in the tree for
+    /// a rooted one — every container on the way opened, so the row
+    /// is there to reveal — and in the graph for one from `origin`, a
+    /// cell the root never reaches.
+    fn jump_to(&mut self, origin: Option<CellId>, path: raw::Path) {
+        match origin {
+            Some(cell) => self.show_in_graph(cell),
+            None => {
+                let sources = sources::Sources {
+                    doc: &self.model.doc,
+                    library: &self.model.library,
+                };
+                for end in 0..path.len() {
+                    raw::set_collapse(&sources, &mut self.model.collapse, &path[..end], false);
+                }
+                self.model.selection =
+                    Some(Selected::Tree(raw::Selection::edge(&sources, path)));
+            }
+        }
+        if let RenderState::Active { window, .. } = &self.state {
+            let window = window.clone();
+            let size = window.inner_size();
+            let scale = window.scale_factor();
+            let viewport = Size::new(size.width as f64, size.height as f64);
+            self.retain_dispatch(scale, viewport);
+            self.reveal_selection(scale, viewport);
+            window.request_redraw();
+        }
+    }
+
+    /// Opens the find bar, or hands the keys back to it when it is
+    /// already open.
+    fn open_find(&mut self) {
+        let bar = self.model.find.get_or_insert_with(find::FindBar::new);
+        bar.focused = true;
+        bar.input = find::Input::Query;
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The find bar query's hits, against the document as it is now.
+    fn find_hits(&mut self) -> Rc<[find::Hit]> {
+        let sources = sources::Sources {
+            doc: &self.model.doc,
+            library: &self.model.library,
+        };
+        (self.model.find.as_mut()).map_or_else(Rc::default, |bar| bar.hits(&sources))
+    }
+
+    /// Carries out a find bar command: stepping selects the hit it
+    /// lands on and reveals it, like jumping to a problem.
+    fn find_act(&mut self, command: find::Command) {
+        let hits = self.find_hits();
+        let Some(bar) = &mut self.model.find else {
+            return;
+        };
+        match command {
+            find::Command::Next | find::Command::Previous => {
+                let forward = command == find::Command::Next;
+                if let Some(index) = bar.step(hits.len(), forward) {
+                    let hit = hits[index].clone();
+                    self.jump_to(hit.origin, hit.path);
+                }
+            }
+            find::Command::Switch => {
+                bar.input = match bar.input {
+                    find::Input::Query => find::Input::Replace,
+                    find::Input::Replace => find::Input::Query,
+                };
+            }
+            find::Command::ReplaceAll => self.replace_all(),
+            find::Command::Close => self.model.find = None,
+        }
+    }
+
+    /// The keys a focused find bar's fields decline: its commands,
+    /// and anything without the command modifier swallowed, so typing
+    /// meant for the bar never edits the document. Chords fall
+    /// through to the document's.
+    fn find_key(&mut self, event: &KeyboardEvent) -> bool {
+        if !self.model.find.as_ref().is_some_and(|bar| bar.focused) {
+            return false;
+        }
+        match find::command(event) {
+            Some(command) => {
+                self.find_act(command);
+                true
+            }
+            None if cfg!(target_os = "macos") => !event.modifiers.meta(),
+            None => !event.modifiers.ctrl(),
+        }
+    }
+
+    /// Rewrites every hit of the bar's query to its replacement, as
+    /// one undo step.
+    fn replace_all(&mut self) {
+        let Some(bar) = &mut self.model.find else {
+            return;
+        };
+        bar.current = None;
+        let needle = bar.query.text().to_string();
+        let with = bar.replace.text().to_string();
+        let hits = self.find_hits();
+        let before = self.model.doc.clone();
+        let count = find::replace(&mut self.model.doc, &self.model.library, &hits, &needle, &with);
+        if count == 0 {
+            return;
+        }
+        let label = match count {
+            1 => "replace 1 match".to_string(),
+            n => format!("replace {n} matches"),
+        };
+        let selection = edge_path(&self.model.selection);
+        let action = Action::new(history::Kind::Replace, label, Vec::new());
+        self.model.history.record(before, selection.clone(), action);
+        // A mounted editor holds the text it was seeded with, and the
+        // write-through would put it back: remount on the new text.
+        if let Some(path) = selection {
+            self.model.selection =
+                Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), path)));
+        }
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The cell Find References asks about: the selected link's
+    /// target, the cell whose head is selected, or the graph's cell.
+    fn reference_target(&self) -> Option<CellId> {
+        match &self.model.selection {
+            Some(Selected::Tree(raw::Selection::Edge { path, .. })) => {
+                references::target(&self.model.sources(), path)
+            }
+            Some(Selected::Graph(graph_view::GraphSelection::Node(
+                graph_view::GraphNode::Cell(cell),
+            ))) => Some(*cell),
+            _ => None,
+        }
+    }
+
+    /// Selects a labeled edge's mention: in the tree, where some chain
+    /// of links from the root reaches its holder, else as the graph's
+    /// own edge selection.
+    fn select_edge(&mut self, from: graph_view::GraphNode, spine: raw::Path) {
+        let owner = match from {
+            graph_view::GraphNode::Root => Owner::Root,
+            graph_view::GraphNode::Cell(cell) => Owner::Cell(cell),
+        };
+        let root = self.model.doc.root.as_ref();
+        self.model.mentions.sync(&self.model.doc.cells, root);
+        match references::locate(&self.model.mentions, owner, spine.clone()) {
+            (None, path) => self.jump_to(None, path),
+            (Some(_), _) => {
+                let edge = graph_view::GraphSelection::Edge(from, spine);
+                self.model.selection = Some(Selected::Graph(edge));
+            }
+        }
+    }
+
+    /// Narrows the graph to a neighborhood of `radius` hops, or widens
+    /// it back to everything; narrowing opens the graph.
+    fn set_radius(&mut self, radius: Option<usize>) {
+        self.model.graph.set_radius(radius);
+        if radius.is_some() {
+            self.menu_items.graph.set_checked(true);
+        }
+        self.sync_menus();
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// Lists the target's references in the pane, replacing whatever
+    /// it listed.
+    fn open_references(&mut self) {
+        let Some(cell) = self.reference_target() else {
+            return;
+        };
+        self.model.references = Some(cell);
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The label Rename Label Everywhere renames: the selected
+    /// field's.
+    fn relabel_source(&self) -> Option<Label> {
+        match edge_path(&self.model.selection)?.pop()? {
+            Step::Key(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    /// Opens the rename-label pane on the selected field's label,
+    /// replacing whatever it was renaming.
+    fn open_relabel(&mut self) {
+        let Some(from) = self.relabel_source() else {
+            return;
+        };
+        self.model.relabel = Some(relabel::Relabel::new(from));
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    fn relabel_act(&mut self, command: relabel::Command) {
+        let Some(pane) = &mut self.model.relabel else {
+            return;
+        };
+        match command {
+            relabel::Command::Apply => self.relabel(),
+            relabel::Command::Toggle => pane.cell = !pane.cell,
+            relabel::Command::Close => self.model.relabel = None,
+        }
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The keys a focused rename-label field declines, as with the
+    /// find bar: its commands, and anything unchorded swallowed.
+    fn relabel_key(&mut self, event: &KeyboardEvent) -> bool {
+        if !self.model.relabel.as_ref().is_some_and(|pane| pane.focused) {
+            return false;
+        }
+        match relabel::command(event) {
+            Some(command) => {
+                self.relabel_act(command);
+                true
+            }
+            None if cfg!(target_os = "macos") => !event.modifiers.meta(),
+            None => !event.modifiers.ctrl(),
+        }
+    }
+
+    /// Rewrites the pane's label everywhere it is free to, as one
+    /// undo step, and closes the pane. The selection follows its
+    /// field to the new label.
+    fn relabel(&mut self) {
+        let Some(pane) = &self.model.relabel else {
+            return;
+        };
+        let from = pane.from.clone();
+        let sources = self.model.sources();
+        let Ok(target) = relabel::target(&sources, &from, pane.to.text(), pane.cell) else {
+            return;
+        };
+        let before = self.model.doc.clone();
+        let Some((to, count)) = relabel::apply(&mut self.model.doc, &from, target) else {
+            return;
+        };
+        let label = match count {
+            1 => "rename label in 1 record".to_string(),
+            n => format!("rename label in {n} records"),
+        };
+        let selection = edge_path(&self.model.selection);
+        let action = Action::new(history::Kind::Relabel, label, Vec::new());
+        self.model.history.record(before, selection.clone(), action);
+        if let Some(path) = selection {
+            let sources = self.model.sources();
+            let path = relabel::relabel_path(&sources, &path, &from, &to);
+            self.model.selection = Some(Selected::Tree(raw::Selection::edge(&sources, path)));
+        }
+        self.model.relabel = None;
+        self.refresh_title();
+    }
+
+    /// The value Extract to Cell moves out: the selected edge's,
+    /// unless it is a link or a head — already an identity.
+    fn extract_target(&self) -> Option<raw::Path> {
+        let path = edge_path(&self.model.selection)?;
+        let link = self.model.sources().resolve(&path)?.as_cell().is_some();
+        (!link && path.last() != Some(&Step::Name)).then_some(path)
+    }
+
+    /// Extracts the selected value into a new cell, a link left in its
+    /// place — one undo step. The selection lands on the new cell's
+    /// head, whose empty name editor names it on the spot; moving on
+    /// leaves it unnamed.
+    fn extract(&mut self) {
+        let Some(path) = self.extract_target() else {
+            return;
+        };
+        let before = self.model.doc.clone();
+        if raw::extract_cell(&mut self.model.doc, &self.model.library, &path).is_none() {
+            return;
+        }
+        let label = format!("extract {}", place(&path));
+        let action = Action::new(history::Kind::Extract, label, path.clone());
+        self.model.history.record(before, Some(path.clone()), action);
+        let mut head = path;
+        head.push(Step::Name);
+        self.model.selection =
+            Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), head)));
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The link Inline Cell replaces — the selected one, or the one
+    /// whose head is selected — and its cell, when it has a value to
+    /// copy in.
+    fn inline_target(&self) -> Option<(raw::Path, CellId)> {
+        let mut path = edge_path(&self.model.selection)?;
+        if path.last() == Some(&Step::Name) {
+            path.pop();
+        }
+        let sources = self.model.sources();
+        let cell = sources.resolve(&path).and_then(Value::as_cell)?;
+        sources.value(cell).is_some().then_some((path, cell))
+    }
+
+    /// Inlines the selected link, first asking when its cell is
+    /// referenced anywhere else — the copy would fork from the cell
+    /// the others still share. The same sheet and proxy round trip
+    /// as [`Self::request_merge`]; the sole link inlines at once.
+    fn request_inline(&mut self) {
+        let Some((path, cell)) = self.inline_target() else {
+            return;
+        };
+        let model = &mut self.model;
+        model.mentions.sync(&model.doc.cells, model.doc.root.as_ref());
+        let others = model.mentions.of(cell).len().saturating_sub(1);
+        if others == 0 {
+            self.inline(path, true);
+            return;
+        }
+        if self.pending_inline.is_some()
+            || self.pending_merge.is_some()
+            || self.pending_discard.is_some()
+        {
+            return;
+        }
+        let RenderState::Active { window, .. } = &self.state else {
+            return;
+        };
+        let label = match self.model.sources().name(cell) {
+            Some(name) => format!("“{name}”"),
+            None => raw::short_id(cell),
+        };
+        let places = match others {
+            1 => "1 other place".to_string(),
+            n => format!("{n} other places"),
+        };
+        let description = format!(
+            "{label} is referenced in {places}. Inline a copy here anyway? The cell stays for \
+             the others."
+        );
+        // rfd reports a custom button by its label; one spelling.
+        const INLINE: &str = "Inline Anyway";
+        let sheet = rfd::AsyncMessageDialog::new()
+            .set_title("Inline cell?")
+            .set_description(description)
+            .set_buttons(rfd::MessageButtons::OkCancelCustom(
+                INLINE.to_string(),
+                "Cancel".to_string(),
+            ))
+            .set_parent(window.as_ref())
+            .show();
+        self.pending_inline = Some(path);
+        let proxy = self.proxy.clone();
+        std::thread::spawn(move || {
+            let accepted = matches!(
+                pollster::block_on(sheet),
+                rfd::MessageDialogResult::Custom(choice) if choice == INLINE
+            );
+            let _ = proxy.send_event(UserEvent::Inline(accepted));
+        });
+    }
+
+    /// The inline itself — one undo step, the selection on the copy.
+    /// `sole` removes the cell along with its only link.
+    fn inline(&mut self, path: raw::Path, sole: bool) {
+        let before = self.model.doc.clone();
+        if raw::inline_cell(&mut self.model.doc, &self.model.library, &path, sole).is_none() {
+            return;
+        }
+        let action = Action::new(history::Kind::Inline, "inline cell", path.clone());
+        self.model.history.record(before, edge_path(&self.model.selection), action);
+        self.model.selection =
+            Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), path)));
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
+        }
+    }
+
+    /// The two cells Merge Cells acts on: the pool's two marks, the
+    /// first surviving. The one selection slot holds one cell, so the
+    /// pool is where a pair is chosen; with it closed the menu item
+    /// grays rather than doing nothing.
+    fn merge_pair(&self) -> Option<(CellId, CellId)> {
+        match self.model.pool.marked.as_slice() {
+            &[survivor, loser] if self.menu_items.pool.is_checked() => Some((survivor, loser)),
+            _ => None,
+        }
+    }
+
+    /// Merges `loser` into `survivor`, first asking which side wins
+    /// when both state a name or a value and they differ — the same
+    /// window sheet and proxy round trip as [`Self::request_discard`].
+    /// Cells that agree, or where one side fills the other's gaps,
+    /// merge at once.
+    fn request_merge(&mut self, survivor: CellId, loser: CellId) {
+        let conflicts = raw::merge_conflicts(&self.model.doc, survivor, loser);
+        if !conflicts.any() {
+            self.merge(survivor, loser, raw::Prefer::Survivor);
+            return;
+        }
+        if self.pending_merge.is_some()
+            || self.pending_inline.is_some()
+            || self.pending_discard.is_some()
+        {
+            return;
+        }
+        let RenderState::Active { window, .. } = &self.state else {
+            return;
+        };
+        let sources = self.model.sources();
+        let label = |cell: CellId| match sources.name(cell) {
+            Some(name) => format!("“{name}”"),
+            None => raw::short_id(cell),
+        };
+        let parts: Vec<&str> = [
+            (conflicts.name, "names"),
+            (conflicts.value, "values"),
+            (conflicts.fields, "fields under both"),
+        ]
+        .into_iter()
+        .filter_map(|(differ, part)| differ.then_some(part))
+        .collect();
+        let parts = match parts.split_last() {
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+            None => return,
+        };
+        let description = format!(
+            "{} and {} have different {parts}. Which should the merged cell keep?",
+            label(survivor),
+            label(loser),
+        );
+        // rfd reports a custom button by its label; one spelling each.
+        const FIRST: &str = "Keep First";
+        const SECOND: &str = "Keep Second";
+        let sheet = rfd::AsyncMessageDialog::new()
+            .set_title("Merge cells?")
+            .set_description(description)
+            .set_buttons(rfd::MessageButtons::YesNoCancelCustom(
+                FIRST.to_string(),
+                SECOND.to_string(),
+                "Cancel".to_string(),
+            ))
+            .set_parent(window.as_ref())
+            .show();
+        self.pending_merge = Some((survivor, loser));
+        let proxy = self.proxy.clone();
+        std::thread::spawn(move || {
+            let prefer = match pollster::block_on(sheet) {
+                rfd::MessageDialogResult::Custom(choice) if choice == FIRST => {
+                    Some(raw::Prefer::Survivor)
+                }
+                rfd::MessageDialogResult::Custom(choice) if choice == SECOND => {
+                    Some(raw::Prefer::Loser)
+                }
+                _ => None,
+            };
+            let _ = proxy.send_event(UserEvent::Merge(prefer));
+        });
+    }
+
+    /// The merge itself — one undo step. Whatever pointed at the
+    /// loser follows it: the selection's path and node, the pool's
+    /// marks, and the graph, which folds the two nodes together where
+    /// the eye can follow.
+    fn merge(&mut self, survivor: CellId, loser: CellId, prefer: raw::Prefer) {
+        let before = self.model.doc.clone();
+        if !raw::merge_cells(&mut self.model.doc, &self.model.library, survivor, loser, prefer) {
+            return;
+        }
+        self.model.history.record(
+            before,
+            edge_path(&self.model.selection),
+            Action::new(history::Kind::Merge, "merge cells", Vec::new()),
+        );
+        let selection = self.model.selection.take();
+        let sources = self.model.sources();
+        let selection = match selection {
+            Some(Selected::Graph(graph_view::GraphSelection::Node(node))) => {
+                let node = match node {
+                    graph_view::GraphNode::Cell(cell) if cell == loser => {
+                        graph_view::GraphNode::Cell(survivor)
                     }
-                }
+                    node => node,
+                };
+                Some(Selected::Graph(graph_view::GraphSelection::Node(node)))
             }
-            AfterDiscard::Quit => event_loop.exit(),
+            selection => edge_path(&selection).map(|path| {
+                let path = raw::fold_path(&path, survivor, loser);
+                Selected::Tree(raw::Selection::edge(&sources, path))
+            }),
+        };
+        self.model.selection = selection;
+        let mut marked: Vec<CellId> = Vec::new();
+        for cell in &self.model.pool.marked {
+            let cell = if *cell == loser { survivor } else { *cell };
+            if !marked.contains(&cell) {
+                marked.push(cell);
+            }
+        }
+        self.model.pool.marked = marked;
+        self.model.hover = None;
+        self.model.graph.collapse(survivor, loser);
+        self.refresh_title();
+        if let RenderState::Active { window, .. } = &self.state {
+            let window = window.clone();
+            let size = window.inner_size();
+            self.retain_dispatch(
+                window.scale_factor(),
+                Size::new(size.width as f64, size.height as f64),
+            );
+            window.request_redraw();
         }
     }
 
-    /// Save saves in place, or asks for a path when untitled; save-as
-    /// always asks. Write-through editing means the graph is always
-    /// current, so there is nothing to flush first. A cancelled dialog
-    /// saves nothing.
-    fn menu_save(&mut self, save_as: bool) {
-        let in_place = (!save_as).then(|| self.doc_path.clone()).flatten();
-        let target = in_place.or_else(|| dialog().set_file_name("untitled.progred").save_file());
-        if let Some(path) = target {
-            match store::save(&path, &self.model.doc) {
-                Ok(()) => {
-                    self.model.history.mark_saved();
-                    // A run must not straddle the save mark, or edits
-                    // after it would coalesce into a pre-save step.
-                    raw::break_edit_run(self.model.tree_selection_mut());
-                    self.adopt_doc_path(path);
-                }
-                Err(error) => {
-                    eprintln!("failed to save {}: {error}", path.display());
-                }
-            }
+    /// Puts the problems strip up when the library composed with
+    /// problems: the document opened regardless, and the user should
+    /// see what it opened without.
+    fn reveal_library_problems(&self) {
+        if !self.model.library_problems.is_empty() {
+            self.menu_items.problems.set_checked(true);
+        }
+    }
+
+    /// Keeps at most `limit` states in the history sidecar from the
+    /// next save on, and remembers it in the settings. A settings file
+    /// that won't write goes to the problems strip; the bound holds
+    /// for the session regardless.
+    fn set_history_limit(&mut self, limit: usize) {
+        self.settings.history_limit = limit;
+        self.model.settings_problems.clear();
+        if let Some(path) = settings::path()
+            && let Err(error) = settings::write(&path, &self.settings)
+        {
+            (self.model.settings_problems).push(format!("settings {}: {error}", path.display()));
+            self.menu_items.problems.set_checked(true);
+        }
+        if let RenderState::Active { window, .. } = &self.state {
+            window.request_redraw();
         }
     }
 
     /// Replaces the model wholesale for New and Open. Selection,
     /// collapse overrides, scroll, and history are bound to the old
-    /// document and reset with it. Mints the successor dispatch
+    /// document and reset with it — the history to whatever the new
+    /// file's sidecar resumes. Mints the successor dispatch
     /// immediately, as every mutation site does: the retained handler
     /// was built from the old document, and its dispatches must not
     /// run against the new model.
-    fn adopt_model(&mut self, doc: raw::Document, path: Option<PathBuf>) {
+    fn adopt_model(&mut self, opened: store::Opened, path: Option<PathBuf>) {
+        let store::Opened {
+            doc,
+            form,
+            migrated,
+        } = opened;
+        let store::Composed {
+            cells: library,
+            diagnostics: library_problems,
+        } = compose_library(&doc, path.as_deref());
+        // The history a previous session saved beside the file, when
+        // it still leads from what the file holds.
+        let mut history = match &path {
+            Some(path) => sidecar::resume(path, &doc, &library),
+            None => history::History::default(),
+        };
+        history.set_rules(self.settings.rules());
         self.model = Model {
             doc,
             selection: None,
             collapse: raw::Collapse::default(),
             names: self.model.names.clone(),
-            library: conventions::library(),
+            library,
+            library_problems,
+            settings_problems: std::mem::take(&mut self.model.settings_problems),
             graph: graph_view::GraphView::default(),
-            history: history::History::default(),
+            pool: pool::PoolView::default(),
+            history,
+            compare: None,
+            find: self.model.find.take().map(|mut bar| {
+                bar.current = None;
+                bar
+            }),
+            references: None,
+            relabel: None,
+            mentions: progred_graph::mentions::Mentions::default(),
             hover: None,
             scroll: 0.0,
             scroll_x: 0.0,
         };
         self.doc_path = path;
+        self.form = form;
+        self.migrated = migrated;
+        self.reveal_library_problems();
         self.revealed = None;
         if let RenderState::Active { window, .. } = &self.state {
             let window = window.clone();
@@ -1096,8 +2512,9 @@ impl App {
             // The pad is the landing margin, not the trigger: fully
             // visible rects are left alone, so a click near an edge
             // doesn't nudge.
-            if rect.y1 > viewport.height {
-                scroll += (rect.y1 + pad - viewport.height) / scale;
+            let bottom = body_bottom(self.view_flags(), scale, viewport.width, viewport.height);
+            if rect.y1 > bottom {
+                scroll += (rect.y1 + pad - bottom) / scale;
             }
             // Checked against the adjusted position, so when the rect
             // is taller than the viewport the top wins.
@@ -1107,12 +2524,8 @@ impl App {
             }
             self.model.scroll = scroll.clamp(0.0, dispatch.max_scroll);
             // The same chase horizontally, against the width the
-            // graph panel leaves visible.
-            let visible = if self.menu_items.graph.is_checked() {
-                graph_view::panel(viewport.width, viewport.height).x0
-            } else {
-                viewport.width
-            };
+            // side panels leave visible.
+            let visible = body_right(self.view_flags(), viewport.width, viewport.height);
             let mut scroll_x = self.model.scroll_x;
             if rect.x1 > visible {
                 scroll_x += (rect.x1 + pad - visible) / scale;
@@ -1128,6 +2541,12 @@ impl App {
     fn view_flags(&self) -> ViewFlags {
         ViewFlags {
             graph: self.menu_items.graph.is_checked(),
+            pool: self.menu_items.pool.is_checked(),
+            problems: self.menu_items.problems.is_checked(),
+            history: self.menu_items.history.is_checked(),
+            references: self.model.references.is_some(),
+            relabel: self.model.relabel.is_some(),
+            find: self.model.find.is_some(),
             raw: self.menu_items.raw.is_checked(),
         }
     }
@@ -1135,6 +2554,8 @@ impl App {
     /// Runs the pure pass for the current state and retains its
     /// dispatch outputs; no scene — pixels are the redraw's job.
     fn retain_dispatch(&mut self, scale: f64, viewport: Size) {
+        self.model.sync_mentions();
+        self.model.sync_find();
         let mut frame = Frame {
             scene: None,
             handler: Handler::new(),
@@ -1256,10 +2677,9 @@ impl App {
         width: f64,
         height: f64,
     ) -> bool {
-        if !self.menu_items.graph.is_checked() {
+        let Some(panel) = sides(self.view_flags(), width, height).get(Side::Graph) else {
             return false;
-        }
-        let panel = graph_view::panel(width, height);
+        };
         let position = Point::new(update.state.position.x, update.state.position.y);
         panel.contains(position) && {
             self.model
@@ -1271,7 +2691,8 @@ impl App {
 
     /// Graph-view keys: Delete detaches the selected node — the
     /// cell's whole entry removed and every link to it unlinked, or
-    /// the root emptied. The one selection slot means this and the
+    /// the root emptied — or the selected edge's one field or
+    /// element. The one selection slot means this and the
     /// document delete below can never both match; Escape falls
     /// through to the universal clear in `insert_key`.
     fn graph_key(&mut self, event: &KeyboardEvent) -> bool {
@@ -1283,10 +2704,15 @@ impl App {
             )
             && match self.model.graph_selection() {
                 Some(selection) => {
-                    let selection = *selection;
+                    let selection = selection.clone();
                     let before = self.model.doc.clone();
                     if graph_view::delete_selection(&mut self.model.doc, &selection) {
-                        self.model.history.record(before, None);
+                        let label = match selection {
+                            graph_view::GraphSelection::Node(_) => "delete node",
+                            graph_view::GraphSelection::Edge(..) => "delete edge",
+                        };
+                        let action = Action::new(history::Kind::Delete, label, Vec::new());
+                        self.model.history.record(before, None, action);
                         self.refresh_title();
                     }
                     self.model.selection = None;
@@ -1299,8 +2725,9 @@ impl App {
     /// Backspace or Delete removes the selected edge — a focused atom
     /// editor claims the keys while it has text and declines on an
     /// empty buffer, so emptying a string then backspacing again
-    /// deletes the element. Selection lands on the next sibling, else
-    /// the previous, else the parent.
+    /// deletes the element. A multi-selection deletes every member as
+    /// one step. Selection lands on the next sibling, else the
+    /// previous, else the parent.
     fn delete_key(&mut self, descends: &[raw::Descend], event: &KeyboardEvent) -> bool {
         event.state.is_down()
             && plain(event)
@@ -1311,20 +2738,28 @@ impl App {
             && match &self.model.selection {
                 // Only a real edge deletes; a pending's Backspace is
                 // its cancel, handled by insert_key.
-                Some(Selected::Tree(raw::Selection::Edge { path, recorded, .. })) => {
+                Some(Selected::Tree(raw::Selection::Edge {
+                    path, recorded, also, ..
+                })) => {
                     let path = path.clone();
                     // Backspacing through the value and once more to
                     // delete the edge is one gesture: when this edge
                     // has the open run, its frame (pre-run document,
                     // edge intact) already covers the deletion.
-                    let covered = *recorded;
+                    let covered = *recorded && also.is_empty();
+                    let paths = self.model.edge_paths();
                     let before = self.model.doc.clone();
-                    raw::delete_edge(&mut self.model.doc, &self.model.library, &path) && {
+                    self.delete_paths(&paths) && {
                         if !covered {
-                            self.model.history.record(before, Some(path.clone()));
+                            let label = match paths.len() {
+                                1 => format!("delete {}", place(&path)),
+                                n => format!("delete {n} values"),
+                            };
+                            let action = Action::new(history::Kind::Delete, label, path.clone());
+                            self.model.history.record(before, Some(path), action);
                             self.refresh_title();
                         }
-                        let next = raw::selection_after_delete(descends, &path);
+                        let next = raw::selection_after_deletes(descends, &paths);
                         self.model.selection =
                             Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), next)));
                         true
@@ -1334,6 +2769,16 @@ impl App {
             }
     }
 
+    /// Deletes every path of a batch; whether any was deleted. One
+    /// inside another already gone declines quietly.
+    fn delete_paths(&mut self, paths: &[raw::Path]) -> bool {
+        let mut any = false;
+        for path in paths {
+            any |= raw::delete_edge(&mut self.model.doc, &self.model.library, path);
+        }
+        any
+    }
+
     /// The chosen entry's action — from the frame's popup, else the
     /// query's inferred atom.
     fn chosen_action(
@@ -1387,7 +2832,13 @@ impl App {
     fn commit_value(&mut self, path: raw::Path, action: &raw::EntryAction) {
         let before = self.model.doc.clone();
         if raw::commit_pending(&mut self.model.doc, &self.model.library, &path, action) {
-            self.model.history.record(before, None);
+            let label = match path.last() {
+                Some(Step::Element(_)) => "insert element".to_string(),
+                Some(Step::Key(_)) => "add field".to_string(),
+                _ => format!("set {}", place(&path)),
+            };
+            let step = Action::new(history::Kind::Insert, label, path.clone());
+            self.model.history.record(before, None, step);
             self.refresh_title();
         }
         self.model.selection = Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), path)));
@@ -1435,7 +2886,8 @@ impl App {
                     label,
                 );
                 if renamed {
-                    self.model.history.record(before, None);
+                    let action = Action::new(history::Kind::Rename, "rename field", path.clone());
+                    self.model.history.record(before, None, action);
                     self.refresh_title();
                 } else {
                     // The rename could not land; back to the field.
@@ -1457,8 +2909,8 @@ impl App {
     /// editor's own clipboard handling wins by dispatch order, so
     /// these fire on cell, list, and graph selections. Deliberately
     /// NOT menu items — muda accelerators intercept ahead of key
-    /// dispatch, which would take Cmd+C/V away from text editing.
-    fn clipboard_key(&mut self, event: &KeyboardEvent) -> bool {
+    /// dispatch, which would take Cmd+C/X/V away from text editing.
+    fn clipboard_key(&mut self, descends: &[raw::Descend], event: &KeyboardEvent) -> bool {
         if !event.state.is_down() || !raw::command(&event.modifiers) {
             return false;
         }
@@ -1466,38 +2918,138 @@ impl App {
             return false;
         };
         match c.to_lowercase().as_str() {
-            "c" => self.copy_selection(),
+            "c" => self.copy_selection(event.modifiers.shift()),
+            "x" => self.cut_selection(descends, event.modifiers.shift()),
             "v" => self.paste_clipboard(),
             _ => false,
         }
     }
 
-    /// Copies the selected value — SHALLOW: a link is its identity
-    /// alone, no cell values travel. Tree selections copy what the
-    /// path resolves to; graph selections their node's value.
-    fn copy_selection(&self) -> bool {
+    /// Copies the selected value — SHALLOW by default: a link is its
+    /// identity alone, no cell values travel. With Shift, DEEP: the
+    /// value and its whole cell closure, for a paste that duplicates
+    /// or forks. Tree selections copy what the path resolves to — a
+    /// multi-selection the LIST of its values, primary first; graph
+    /// selections their node's value.
+    fn copy_selection(&self, deep: bool) -> bool {
         use clipboard_rs::{Clipboard, ClipboardContext};
         let value = match &self.model.selection {
-            Some(Selected::Tree(selection)) => {
-                self.model.sources().resolve(selection.path()).cloned()
-            }
+            Some(Selected::Tree(selection)) => match self.model.edge_paths().as_slice() {
+                [_, _, ..] => {
+                    let sources = self.model.sources();
+                    let paths = self.model.edge_paths();
+                    let values: Option<Vec<Value>> =
+                        paths.iter().map(|path| sources.resolve(path).cloned()).collect();
+                    values.map(Value::list)
+                }
+                _ => self.model.sources().resolve(selection.path()).cloned(),
+            },
             Some(Selected::Graph(graph_view::GraphSelection::Node(node))) => {
                 graph_view::node_value(&self.model.doc, node)
             }
+            Some(Selected::Graph(graph_view::GraphSelection::Edge(from, spine))) => {
+                graph_view::edge_value(&self.model.doc, from, spine)
+            }
             None => None,
         };
         let Some(value) = value else {
             return false;
         };
-        ClipboardContext::new()
-            .and_then(|cb| cb.set_text(raw::to_clipboard(&value)))
-            .is_ok()
+        let text = if deep {
+            raw::closure_to_clipboard(&raw::closure(&self.model.sources(), &value))
+        } else {
+            raw::to_clipboard(&value)
+        };
+        ClipboardContext::new().and_then(|cb| cb.set_text(text)).is_ok()
+    }
+
+    /// Copies the selected edges as Cmd+C would, then deletes them —
+    /// one undo step of its own, even over an open edit run: the cut
+    /// is a gesture apart from the typing. Selection lands as after a
+    /// delete.
+    fn cut_selection(&mut self, descends: &[raw::Descend], deep: bool) -> bool {
+        let paths = self.model.edge_paths();
+        let Some(path) = paths.first().cloned() else {
+            return false;
+        };
+        if !self.copy_selection(deep) {
+            return false;
+        }
+        let before = self.model.doc.clone();
+        if !self.delete_paths(&paths) {
+            return false;
+        }
+        let label = match paths.len() {
+            1 => format!("cut {}", place(&path)),
+            n => format!("cut {n} values"),
+        };
+        let action = Action::new(history::Kind::Cut, label, path.clone());
+        self.model.history.record(before, Some(path), action);
+        self.refresh_title();
+        let next = raw::selection_after_deletes(descends, &paths);
+        self.model.selection =
+            Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), next)));
+        true
+    }
+
+    /// Reshaping in place: Cmd+D duplicates the selected element or
+    /// field beside itself, Alt+Up and Alt+Down move the selected
+    /// element along its list — each one undo step, the selection
+    /// following the value to where it landed. Menu items would
+    /// steal these from text editing, as with the clipboard keys.
+    fn reshape_key(&mut self, event: &KeyboardEvent) -> bool {
+        if !event.state.is_down() {
+            return false;
+        }
+        let Some(Selected::Tree(raw::Selection::Edge { path, .. })) = &self.model.selection
+        else {
+            return false;
+        };
+        let path = path.clone();
+        let alone = !event.modifiers.ctrl() && !event.modifiers.meta() && !event.modifiers.shift();
+        let (kind, landed) = match &event.key {
+            Key::Character(c)
+                if raw::command(&event.modifiers)
+                    && !event.modifiers.alt()
+                    && c.eq_ignore_ascii_case("d") =>
+            {
+                let before = self.model.doc.clone();
+                let landed = raw::duplicate_edge(&mut self.model.doc, &self.model.library, &path);
+                (history::Kind::Duplicate, landed.map(|landed| (before, landed)))
+            }
+            Key::Named(named @ (NamedKey::ArrowUp | NamedKey::ArrowDown))
+                if event.modifiers.alt() && alone =>
+            {
+                let up = *named == NamedKey::ArrowUp;
+                let before = self.model.doc.clone();
+                let landed =
+                    raw::move_element(&mut self.model.doc, &self.model.library, &path, up);
+                (history::Kind::Move, landed.map(|landed| (before, landed)))
+            }
+            _ => return false,
+        };
+        // A gesture that can't apply here is still the shell's key:
+        // nothing beneath should take it.
+        let Some((before, landed)) = landed else {
+            return true;
+        };
+        let label = match kind {
+            history::Kind::Duplicate => format!("duplicate {}", place(&path)),
+            _ => "move element".to_string(),
+        };
+        let action = Action::new(kind, label, landed.clone());
+        self.model.history.record(before, Some(path), action);
+        self.refresh_title();
+        self.model.selection =
+            Some(Selected::Tree(raw::Selection::edge(&self.model.sources(), landed)));
+        true
     }
 
     /// Pastes the clipboard's value: into an open pending first (the
     /// label stage narrows to atoms through the pick), else over the
-    /// selected edge — one undo step, the selection remounted so a
-    /// pasted atom gets its editor.
+    /// selected edge — over every member of a multi-selection — one
+    /// undo step, the selection remounted so a pasted atom gets its
+    /// editor.
     fn paste_clipboard(&mut self) -> bool {
         use clipboard_rs::{Clipboard, ClipboardContext};
         let Some(text) = ClipboardContext::new().ok().and_then(|cb| cb.get_text().ok())
@@ -1507,32 +3059,70 @@ impl App {
         if text.is_empty() {
             return false;
         }
+        if let Some(closure) = raw::closure_from_clipboard(&text) {
+            return self.paste_closure(&closure);
+        }
         let value = raw::from_clipboard(&text);
         if self.pick_identity(value.clone()) {
             return true;
         }
-        let Some(Selected::Tree(raw::Selection::Edge { path, .. })) = &self.model.selection
-        else {
+        let paths = self.model.edge_paths();
+        if paths.is_empty() {
             return false;
-        };
-        let path = path.clone();
+        }
         // Idempotent pastes stay off the undo stack, as write_through
         // keeps no-op rewrites off it.
-        if self.model.sources().resolve(&path) == Some(&value) {
+        let sources = self.model.sources();
+        if paths.iter().all(|path| sources.resolve(path) == Some(&value)) {
             return true;
         }
         let before = self.model.doc.clone();
-        if raw::set_value(&mut self.model.doc, &self.model.library, &path, value) {
-            self.model.history.record(before, Some(path.clone()));
-            self.refresh_title();
-            self.model.selection = Some(Selected::Tree(raw::Selection::edge(
-                &self.model.sources(),
-                path,
-            )));
-            true
-        } else {
-            false
+        let mut wrote = false;
+        for path in &paths {
+            wrote |= raw::set_value(&mut self.model.doc, &self.model.library, path, value.clone());
+        }
+        if wrote {
+            self.record_paste(before, paths);
         }
+        wrote
+    }
+
+    /// Records a paste over `paths` and remounts the selection on
+    /// them, the set kept.
+    fn record_paste(&mut self, before: raw::Document, mut paths: Vec<raw::Path>) {
+        let path = paths.remove(0);
+        let label = match paths.len() {
+            0 => "paste".to_string(),
+            n => format!("paste into {} values", n + 1),
+        };
+        let action = Action::new(history::Kind::Paste, label, path.clone());
+        self.model.history.record(before, Some(path.clone()), action);
+        self.refresh_title();
+        let selection = raw::Selection::edge(&self.model.sources(), path).with_also(paths);
+        self.model.selection = Some(Selected::Tree(selection));
+    }
+
+    /// Lands a deep copy at the open value pending or over the
+    /// selected edges — a copy of its own at each, cells and values
+    /// together as one undo step, the selection remounted on what
+    /// landed. A label stage declines: a closure is a value, not a
+    /// label.
+    fn paste_closure(&mut self, closure: &raw::Closure) -> bool {
+        let paths = match self.model.tree_selection() {
+            Some(selection @ (raw::Selection::Edge { .. } | raw::Selection::Pending { .. })) => {
+                selection.paths().into_iter().cloned().collect::<Vec<_>>()
+            }
+            _ => return false,
+        };
+        let before = self.model.doc.clone();
+        let mut landed = false;
+        for path in &paths {
+            landed |= raw::paste_closure(&mut self.model.doc, &self.model.library, path, closure);
+        }
+        if landed {
+            self.record_paste(before, paths);
+        }
+        landed
     }
 
     /// Enter advances a pending stage or begins one (the chains live
@@ -1684,8 +3274,10 @@ impl App {
 
     /// Space toggles the selection's collapse override, and Cmd+Up /
     /// Cmd+Down close and open it — the fold axis of the keyboard's
-    /// third dimension, under the same keys that walk the rows. A
-    /// focused string editor claims Space first and types instead.
+    /// third dimension, under the same keys that walk the rows —
+    /// over every member of a multi-selection, each toggled on its
+    /// own. A focused string editor claims Space first and types
+    /// instead.
     fn collapse_key(&mut self, event: &KeyboardEvent) -> bool {
         if !event.state.is_down() {
             return false;
@@ -1696,19 +3288,20 @@ impl App {
             Key::Named(NamedKey::ArrowDown) if raw::command(&event.modifiers) => Some(false),
             _ => return false,
         };
-        let Some(Selected::Tree(raw::Selection::Edge { path, .. })) = &self.model.selection
-        else {
-            return false;
-        };
-        let path = path.clone();
+        let paths = self.model.edge_paths();
         let sources = sources::Sources {
             doc: &self.model.doc,
             library: &self.model.library,
         };
-        match set {
-            None => raw::toggle_collapse(&sources, &mut self.model.collapse, &path),
-            Some(closed) => raw::set_collapse(&sources, &mut self.model.collapse, &path, closed),
+        let collapse = &mut self.model.collapse;
+        let mut any = false;
+        for path in &paths {
+            any |= match set {
+                None => raw::toggle_collapse(&sources, collapse, path),
+                Some(closed) => raw::set_collapse(&sources, collapse, path, closed),
+            };
         }
+        any
     }
 
     /// Renders the current model to the surface, from `RedrawRequested`.
@@ -1729,8 +3322,12 @@ impl App {
         // Advance the force simulation while the graph is open; the
         // continuous redraw request below keeps it animating.
         self.sync_menus();
+        self.model.sync_mentions();
+        self.model.sync_find();
         let view = self.view_flags();
         if view.graph {
+            let focus = self.model.graph_focus();
+            self.model.graph.focus(focus);
             self.model.graph.step(&self.model.doc);
         }
         self.scene.reset();
@@ -1840,6 +3437,46 @@ impl App {
     }
 }
 
+/// Where the document's body stops: the window's right edge, less
+/// whichever side panels are up — they overlay the right side, and
+/// content should break rather than run beneath them.
+fn body_right(view: ViewFlags, width: f64, height: f64) -> f64 {
+    sides(view, width, height).left()
+}
+
+/// The window's right edge split between the side panes that are up.
+fn sides(view: ViewFlags, width: f64, height: f64) -> Sides {
+    let open = [
+        (view.graph, Side::Graph),
+        (view.pool, Side::Pool),
+        (view.history, Side::History),
+        (view.references, Side::References),
+        (view.relabel, Side::Relabel),
+    ];
+    Sides::split(width, height, open.into_iter().filter_map(|(up, side)| up.then_some(side)))
+}
+
+/// Where the document's body stops below: the window's bottom, or
+/// the top of the problems strip and the find bar over it when they
+/// are up.
+fn body_bottom(view: ViewFlags, scale: f64, width: f64, height: f64) -> f64 {
+    let bottom = problems_top(view, width, height);
+    if view.find {
+        find::panel(scale, bottom, body_right(view, width, height)).y0
+    } else {
+        bottom
+    }
+}
+
+/// Where the problems strip begins — what the find bar stands on.
+fn problems_top(view: ViewFlags, width: f64, height: f64) -> f64 {
+    if view.problems {
+        check::panel(height, body_right(view, width, height)).y0
+    } else {
+        height
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_frame(
     frame: &mut Frame<'_>,
@@ -1885,15 +3522,17 @@ fn run_frame(
     let sources = model.sources();
     let graph_node = model.graph_node();
     let margin = 12.0 * scale;
-    // The width layout answers to: the window, less the graph panel
-    // when it is up — the panel overlays the right side, and content
-    // should break rather than run beneath it.
-    let body_width = if view.graph {
-        graph_view::panel(viewport_width, viewport_height).x0 - 2.0 * margin
-    } else {
-        viewport_width - 2.0 * margin
-    };
+    // The width layout answers to: the window, less the side panels.
+    let body_width = body_right(view, viewport_width, viewport_height) - 2.0 * margin;
+    let body_height = body_bottom(view, scale, viewport_width, viewport_height);
     let hover_node = model.hover_node();
+    // The checker runs every pass like everything else: underlines
+    // follow the document with no invalidation to get wrong.
+    let mut diagnostics =
+        check::reported(model.settings_problems.iter().chain(&model.library_problems));
+    diagnostics.extend(check::check(&sources));
+    let flagged = check::flagged(&diagnostics);
+    let overlay = model.overlay();
     let body = raw::project(
         &sources,
         model.tree_selection(),
@@ -1901,6 +3540,8 @@ fn run_frame(
         model.tree_hover(),
         hover_node.as_ref(),
         &model.collapse,
+        &flagged,
+        overlay.as_ref(),
         &model.names,
         view.raw,
         &mut tcx,
@@ -1982,6 +3623,17 @@ fn run_frame(
                     app.model.selection = Some(Selected::Tree(pending));
                 }
             }),
+            add: Rc::new(|app: &mut App, path| {
+                // With no edge selection to grow, the click starts one.
+                let grown = app
+                    .model
+                    .tree_selection_mut()
+                    .is_some_and(|selection| selection.toggle_also(path.clone()));
+                if !grown {
+                    app.model.selection =
+                        Some(Selected::Tree(raw::Selection::edge(&app.model.sources(), path)));
+                }
+            }),
         },
     );
     // The body rides puri's scroll viewport: margins pad into the
@@ -1991,7 +3643,7 @@ fn run_frame(
     // scroll where even the block forms overflowed it — not the
     // window edge the viewport clips at.
     let content = puri::layout::pad(vello::kurbo::Insets::uniform(margin), body);
-    frame.max_scroll = ((content.extent.height() - viewport_height) / scale).max(0.0);
+    frame.max_scroll = ((content.extent.height() - body_height) / scale).max(0.0);
     frame.max_scroll_x =
         ((content.extent.width - (body_width + 2.0 * margin)) / scale).max(0.0);
     let offset = Vec2::new(
@@ -2002,13 +3654,73 @@ fn run_frame(
         content,
         frame,
         Point::ZERO,
-        Size::new(viewport_width, viewport_height),
+        Size::new(viewport_width, body_height),
         offset,
     );
+    // The problems strip sits under the body, over nothing else.
+    if view.problems {
+        let right = body_right(view, viewport_width, viewport_height);
+        let panel = check::panel(viewport_height, right);
+        let pane = check::pane(
+            &sources,
+            &diagnostics,
+            model.problem_hover(),
+            &model.names,
+            view.raw,
+            &mut tcx,
+            &styles,
+            panel,
+            &check::Hooks {
+                jump: Rc::new(|app: &mut App, diagnostic: check::Diagnostic| {
+                    if !matches!(diagnostic.problem, check::Problem::Reported(_)) {
+                        app.jump_to(diagnostic.origin, diagnostic.path);
+                    }
+                }),
+                hover: Rc::new(|app: &mut App, index| {
+                    app.claim_hover(index.map(Hovered::Problem));
+                }),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+    }
+    // The find bar stands on the problems strip, under the body.
+    let find_panel = model.find.as_ref().map(|bar| {
+        let right = body_right(view, viewport_width, viewport_height);
+        let bottom = problems_top(view, viewport_width, viewport_height);
+        let panel = find::panel(scale, bottom, right);
+        let pane = find::bar(
+            bar,
+            bar.found(),
+            &mut tcx,
+            &styles,
+            panel,
+            &find::Hooks {
+                press: Rc::new(move |app: &mut App, input, point, count| {
+                    if let Some(bar) = &mut app.model.find {
+                        bar.focused = true;
+                        bar.input = input;
+                        bar.field_mut(input).pointer_down(
+                            &mut app.font_cx,
+                            &mut app.layout_cx,
+                            scale as f32,
+                            point,
+                            false,
+                            count,
+                        );
+                    }
+                }),
+                edit: Rc::new(find_edit),
+                key: Rc::new(|app: &mut App, event| app.find_key(event)),
+                act: Rc::new(|app: &mut App, command| app.find_act(command)),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+        panel
+    });
     // The graph pane draws over the document's right side; placed
     // after the body so its handlers win inside the panel.
-    if view.graph {
-        let panel = graph_view::panel(viewport_width, viewport_height);
+    let sides = sides(view, viewport_width, viewport_height);
+    if let Some(panel) = sides.get(Side::Graph) {
         let pane = graph_view::pane(
             &sources,
             &model.graph,
@@ -2051,6 +3763,8 @@ fn run_frame(
                     None => false,
                 }),
                 pick: Rc::new(|app: &mut App, id| app.pick_identity(id)),
+                expand: Rc::new(|app: &mut App, node| app.model.graph.expand(node)),
+                press_edge: Rc::new(|app: &mut App, from, spine| app.select_edge(from, spine)),
                 hover: Rc::new(|app: &mut App, node| {
                     app.claim_hover(node.map(Hovered::Graph));
                 }),
@@ -2058,6 +3772,119 @@ fn run_frame(
         );
         place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
     }
+    // The pool sits beside the graph, over the document likewise.
+    if let Some(panel) = sides.get(Side::Pool) {
+        let attachable = matches!(
+            model.tree_selection(),
+            Some(raw::Selection::Edge { .. } | raw::Selection::Pending { .. })
+        );
+        let pane = pool::pane(
+            &sources,
+            &model.pool,
+            model.pool_hover(),
+            &model.names,
+            view.raw,
+            attachable,
+            &mut tcx,
+            &styles,
+            panel,
+            &pool::Hooks {
+                mark: Rc::new(|app: &mut App, cell, toggle| app.model.pool.mark(cell, toggle)),
+                act: Rc::new(|app: &mut App, action| app.pool_action(action)),
+                hover: Rc::new(|app: &mut App, cell| {
+                    app.claim_hover(cell.map(Hovered::Pool));
+                }),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+    }
+    // The history browser sits left of them both.
+    if let Some(panel) = sides.get(Side::History) {
+        let pane = history_view::pane(
+            &model.history,
+            &model.doc,
+            &model.library,
+            model.history_hover(),
+            &model.names,
+            view.raw,
+            std::time::SystemTime::now(),
+            &mut tcx,
+            &styles,
+            panel,
+            &history_view::Hooks {
+                jump: Rc::new(|app: &mut App, index| app.jump_history(index)),
+                hover: Rc::new(|app: &mut App, index| {
+                    app.claim_hover(index.map(Hovered::History));
+                }),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+    }
+    // The references pane sits left of the history browser.
+    if let (Some(cell), Some(panel)) = (model.references, sides.get(Side::References)) {
+        let sites = references::sites(&model.mentions, cell);
+        let pane = references::pane(
+            &sources,
+            cell,
+            &sites,
+            model.reference_hover(),
+            &model.names,
+            view.raw,
+            &mut tcx,
+            &styles,
+            panel,
+            &references::Hooks {
+                jump: Rc::new(|app: &mut App, site: references::Site| {
+                    app.jump_to(site.origin, site.path);
+                }),
+                close: Rc::new(|app: &mut App| app.model.references = None),
+                hover: Rc::new(|app: &mut App, index| {
+                    app.claim_hover(index.map(Hovered::Reference));
+                }),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+    }
+
+    // The rename-label pane sits left of the references pane.
+    let relabeling = (model.relabel.as_ref()).zip(sides.get(Side::Relabel));
+    let relabel_panel = relabeling.map(|(state, panel)| {
+        let pane = relabel::pane(
+            &sources,
+            state,
+            &model.mentions,
+            model.relabel_hover(),
+            &model.names,
+            view.raw,
+            &mut tcx,
+            &styles,
+            panel,
+            &relabel::Hooks {
+                press: Rc::new(move |app: &mut App, point, count| {
+                    if let Some(pane) = &mut app.model.relabel {
+                        pane.focused = true;
+                        pane.to.pointer_down(
+                            &mut app.font_cx,
+                            &mut app.layout_cx,
+                            scale as f32,
+                            point,
+                            false,
+                            count,
+                        );
+                    }
+                }),
+                edit: Rc::new(relabel_edit),
+                key: Rc::new(|app: &mut App, event| app.relabel_key(event)),
+                act: Rc::new(|app: &mut App, command| app.relabel_act(command)),
+                jump: Rc::new(|app: &mut App, origin, path| app.jump_to(origin, path)),
+                hover: Rc::new(|app: &mut App, index| {
+                    app.claim_hover(index.map(Hovered::Relabel));
+                }),
+            },
+        );
+        place_top_left(pane, frame, Point::new(panel.x0, panel.y0));
+        panel
+    });
 
     // The pending row's popup draws after the body, so it overlays
     // and its click targets win.
@@ -2100,6 +3927,53 @@ fn run_frame(
         place_top_left(card, frame, Point::new(popup.anchor.x0, y));
         frame.popup = Some(popup);
     }
+    // Registered last, so it sees every press first and passes it
+    // on: one outside the find bar or the rename-label pane hands the
+    // keys back to the document.
+    if find_panel.is_some() || relabel_panel.is_some() {
+        frame.handler().on_pointer_down(move |app: &mut App, event| {
+            let point = Point::new(event.state.position.x, event.state.position.y);
+            if let Some(panel) = find_panel
+                && !panel.contains(point)
+                && let Some(bar) = &mut app.model.find
+            {
+                bar.focused = false;
+            }
+            if let Some(panel) = relabel_panel
+                && !panel.contains(point)
+                && let Some(pane) = &mut app.model.relabel
+            {
+                pane.focused = false;
+            }
+            false
+        });
+    }
+}
+
+/// Dispatch-time access to the find bar's focused field, the bar's
+/// counterpart of [`edit_ctx`].
+fn find_edit(app: &mut App, input: find::Input) -> Option<EditCtx<'_>> {
+    let state = app
+        .model
+        .find
+        .as_mut()
+        .filter(|bar| bar.focused && bar.input == input)?
+        .field_mut(input);
+    Some(EditCtx {
+        state,
+        fonts: &mut app.font_cx,
+        layouts: &mut app.layout_cx,
+    })
+}
+
+/// Dispatch-time access to the rename-label field, while focused.
+fn relabel_edit(app: &mut App) -> Option<EditCtx<'_>> {
+    let pane = app.model.relabel.as_mut().filter(|pane| pane.focused)?;
+    Some(EditCtx {
+        state: &mut pane.to,
+        fonts: &mut app.font_cx,
+        layouts: &mut app.layout_cx,
+    })
 }
 
 /// Dispatch-time access to the selection's editor. Retained-frame
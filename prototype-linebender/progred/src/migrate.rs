@@ -0,0 +1,151 @@
+//! Format migrations: the hook the store's refusal promised. Each
+//! entry takes a JSON document of one format to the next, as JSON, so
+//! a file of any older format reaches the current one by running the
+//! chain from its own number on. A migration only has to know its two
+//! neighbours; the store reads what comes out the end exactly as it
+//! reads a current file, strictness included.
+//!
+//! Format 0 is not an earlier file of this editor but the egui
+//! prototype's gid-triple file (`MutGid` JSON, `semantics.progred`):
+//! untagged, recognizable by its `graph` table. Its entities become
+//! cells and their label/value edges records, so its work opens here
+//! as a starting point rather than a dead end.
+//!
+//! The binary store has its own version line (see
+//! [`crate::binary`]) and, having only one version, no migrations.
+
+use progred_graph::{Cells, Label, Uuid, Value};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+type Migration = fn(serde_json::Value) -> Result<serde_json::Value, String>;
+
+/// Indexed by the format each migration reads: `MIGRATIONS[n]` takes
+/// format `n` to `n + 1`, so the chain's length is the current format.
+pub const MIGRATIONS: [Migration; crate::store::FORMAT as usize] = [from_gid];
+
+/// The format a JSON document says it is — or 0 for an untagged gid
+/// file. An untagged file of any other shape refuses, as it always
+/// did: a guess is not a migration.
+pub fn format_of(json: &serde_json::Value) -> Result<u32, String> {
+    match json.get("format") {
+        Some(format) => format
+            .as_u64()
+            .and_then(|format| u32::try_from(format).ok())
+            .ok_or_else(|| format!("format {format} is not a version")),
+        None if json.get("graph").is_some() => Ok(0),
+        None => Err("untagged file (no format)".into()),
+    }
+}
+
+/// Runs the chain from `format` to the current format.
+pub fn upgrade(mut json: serde_json::Value, format: u32) -> Result<serde_json::Value, String> {
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(format as usize) {
+        json = migration(json).map_err(|error| format!("migrating format {from}: {error}"))?;
+    }
+    Ok(json)
+}
+
+/// The egui prototype's name edge. Names there were data; here they
+/// are the cell's own, so the edge becomes the name.
+const GID_NAME: Uuid = Uuid::from_u128(0x21ab3439_ef04_4783_9022_3d0400de4bbb);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GidId {
+    Uuid(Uuid),
+    String(String),
+    Number(f64),
+}
+
+#[derive(Deserialize)]
+struct GidFile {
+    graph: BTreeMap<Uuid, Vec<(GidId, GidId)>>,
+    root: Option<GidId>,
+}
+
+/// Entities become cells holding a record of their edges; uuids read
+/// as links (or cell labels), strings as strings. Numbers have no
+/// atom here, so they carry over as their spelling. Cons lists stay
+/// the records they were — the importer converts edges, not
+/// conventions.
+fn from_gid(json: serde_json::Value) -> Result<serde_json::Value, String> {
+    let file: GidFile = serde_json::from_value(json).map_err(|error| error.to_string())?;
+    let mut cells = Cells::new();
+    for (entity, edges) in file.graph {
+        let mut fields = Vec::new();
+        for (label, value) in edges {
+            match (label, value) {
+                (GidId::Uuid(GID_NAME), GidId::String(name)) => cells.set_name(entity, &name),
+                (label, value) => fields.push((gid_label(label), gid_value(value))),
+            }
+        }
+        if !fields.is_empty() {
+            cells.set_value(entity, Value::record(fields));
+        }
+    }
+    Ok(serde_json::json!({
+        "format": 1,
+        "root": file.root.map(gid_value),
+        "cells": cells,
+    }))
+}
+
+fn gid_label(id: GidId) -> Label {
+    match id {
+        GidId::Uuid(uuid) => Label::Cell(uuid),
+        GidId::String(s) => Label::String(s),
+        GidId::Number(n) => Label::String(n.to_string()),
+    }
+}
+
+fn gid_value(id: GidId) -> Value {
+    match id {
+        GidId::Uuid(uuid) => Value::from(uuid),
+        GidId::String(s) => Value::from(s),
+        GidId::Number(n) => Value::from(n.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gid_files_import_as_records_of_cells() {
+        let entity = Uuid::from_u128(1);
+        let field = Uuid::from_u128(2);
+        let target = Uuid::from_u128(3);
+        let json = serde_json::json!({
+            "graph": {
+                entity.to_string(): [
+                    [{"uuid": GID_NAME}, {"string": "point"}],
+                    [{"uuid": field}, {"uuid": target}],
+                    [{"string": "x"}, {"number": 1.5}],
+                ],
+                target.to_string(): [[{"uuid": GID_NAME}, {"string": "origin"}]],
+            },
+            "root": {"uuid": entity},
+        });
+        assert_eq!(format_of(&json), Ok(0));
+        let upgraded = upgrade(json, 0).unwrap();
+        assert_eq!(format_of(&upgraded), Ok(crate::store::FORMAT));
+        let cells: Cells = serde_json::from_value(upgraded["cells"].clone()).unwrap();
+        assert_eq!(cells.name(entity), Some("point"));
+        assert_eq!(
+            cells.value(entity),
+            Some(&Value::record([
+                (Label::Cell(field), Value::from(target)),
+                (Label::from("x"), Value::from("1.5")),
+            ]))
+        );
+        // A name was all the target said, so it reads as a named,
+        // valueless cell.
+        assert_eq!(cells.name(target), Some("origin"));
+        assert_eq!(cells.value(target), None);
+        let root: Value = serde_json::from_value(upgraded["root"].clone()).unwrap();
+        assert_eq!(root, Value::from(entity));
+
+        assert!(format_of(&serde_json::json!({"cells": {}})).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! What the panes share: their colors, the ground each stands on, and
+//! how the side panes split the window's right edge between them.
+//! Every pane draws over the document, so every one swallows the
+//! presses its own content declines — nothing lands on the document
+//! beneath — and the side panes, however many are up, leave the body
+//! a share of the window to break into.
+
+use puri::draw::Canvas;
+use puri::handler::HasHandler;
+use puri::layout::{Node, decorate};
+use vello::kurbo::{Affine, Line, Point, Rect, Stroke};
+use vello::peniko::Color;
+
+pub const PANEL_BG: [f32; 4] = [0.968, 0.972, 0.984, 1.0];
+pub const SEPARATOR: [f32; 4] = [0.851, 0.867, 0.890, 1.0];
+/// A row under the pointer: the hover wash the lists share.
+pub const HOVER: [f32; 4] = [0.0, 0.48, 1.0, 0.06];
+
+/// The panes standing along the window's right edge, in the order
+/// they stack leftward from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Side {
+    Graph,
+    Pool,
+    History,
+    References,
+    Relabel,
+}
+
+impl Side {
+    /// The share of the window's width the pane asks for.
+    fn share(self) -> f64 {
+        match self {
+            Side::Graph => 0.4,
+            Side::Pool => 0.25,
+            Side::History => 0.22,
+            Side::References => 0.24,
+            Side::Relabel => 0.24,
+        }
+    }
+}
+
+/// The most of the window's width the side panes take together; past
+/// it they narrow in proportion, and the document keeps the rest.
+const SIDES_MAX: f64 = 0.75;
+
+/// The window's right edge split between the side panes that are up.
+pub struct Sides {
+    rects: Vec<(Side, Rect)>,
+    right: f64,
+}
+
+impl Sides {
+    /// Each of the `open` panes at its share of `width`, stacked
+    /// leftward in [`Side`] order — all narrowed in proportion when
+    /// together they would take more than [`SIDES_MAX`] of it.
+    pub fn split(width: f64, height: f64, open: impl IntoIterator<Item = Side>) -> Sides {
+        let mut open: Vec<Side> = open.into_iter().collect();
+        open.sort();
+        open.dedup();
+        let asked: f64 = open.iter().map(|side| side.share()).sum();
+        let fit = (SIDES_MAX / asked).min(1.0);
+        let (mut right, mut taken) = (width, 0.0);
+        let rects = open
+            .into_iter()
+            .map(|side| {
+                taken += side.share() * fit;
+                let left = (width * (1.0 - taken)).round();
+                let rect = Rect::new(left, 0.0, right, height);
+                right = left;
+                (side, rect)
+            })
+            .collect();
+        Sides {
+            rects,
+            right: width,
+        }
+    }
+
+    /// The pane's window rectangle, when it is up.
+    pub fn get(&self, side: Side) -> Option<Rect> {
+        (self.rects.iter()).find_map(|(held, rect)| (*held == side).then_some(*rect))
+    }
+
+    /// Where the side panes begin: the leftmost one's left edge, or the
+    /// window's right with none up.
+    pub fn left(&self) -> f64 {
+        self.rects.last().map_or(self.right, |(_, rect)| rect.x0)
+    }
+}
+
+/// Which edge of a pane meets the document and carries the separator:
+/// a side pane's left, a strip's top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Border {
+    Left,
+    Top,
+}
+
+/// `content` on the pane's ground: the background over the whole
+/// `panel`, whatever the content measures, with the separator along
+/// `border`. The ground registers first, so the content's handlers
+/// win; it swallows every press they decline, and a move over it
+/// reports to `hover` — the pointer resting on the pane itself.
+pub fn ground<C: 'static, P: Canvas + HasHandler<C>>(
+    content: Node<P>,
+    panel: Rect,
+    border: Border,
+    scale: f64,
+    hover: impl Fn(&mut C) + 'static,
+) -> Node<P> {
+    decorate(content, move |p: &mut P, rect: Rect| {
+        let panel = Rect::from_origin_size(rect.origin(), panel.size());
+        p.handler().on_pointer_move(move |ctx, update| {
+            let point = Point::new(update.current.position.x, update.current.position.y);
+            if panel.contains(point) {
+                hover(ctx);
+            }
+            false
+        });
+        p.handler().on_pointer_down(move |_, event| {
+            panel.contains(Point::new(event.state.position.x, event.state.position.y))
+        });
+        p.fill(panel, Color::new(PANEL_BG), Affine::IDENTITY);
+        let separator = match border {
+            Border::Left => Line::new((panel.x0, panel.y0), (panel.x0, panel.y1)),
+            Border::Top => Line::new((panel.x0, panel.y0), (panel.x1, panel.y0)),
+        };
+        p.stroke(
+            separator,
+            Stroke::new(1.0 * scale),
+            Color::new(SEPARATOR),
+            Affine::IDENTITY,
+        );
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_panes_stack_leftward_and_leave_the_body_its_share() {
+        let sides = Sides::split(1000.0, 600.0, []);
+        assert_eq!(sides.left(), 1000.0);
+        assert_eq!(sides.get(Side::Graph), None);
+
+        // Each asks its share, stacked in order whatever order they
+        // are named in.
+        let sides = Sides::split(1000.0, 600.0, [Side::Pool, Side::Graph]);
+        assert_eq!(sides.get(Side::Graph), Some(Rect::new(600.0, 0.0, 1000.0, 600.0)));
+        assert_eq!(sides.get(Side::Pool), Some(Rect::new(350.0, 0.0, 600.0, 600.0)));
+        assert_eq!(sides.left(), 350.0);
+        let alone = Sides::split(1000.0, 600.0, [Side::Pool]);
+        assert_eq!(alone.get(Side::Pool), Some(Rect::new(750.0, 0.0, 1000.0, 600.0)));
+
+        // Past the cap they narrow together, and the body keeps the
+        // rest of the window.
+        let all = Sides::split(1000.0, 600.0, [Side::Graph, Side::Pool, Side::History]);
+        assert_eq!(all.left(), 250.0);
+        let graph = all.get(Side::Graph).unwrap();
+        let history = all.get(Side::History).unwrap();
+        assert!(graph.width() < 400.0 && graph.width() > history.width());
+    }
+}
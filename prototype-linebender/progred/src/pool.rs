@@ -0,0 +1,439 @@
+//! The pool browser: the cells the document states that its root no
+//! longer reaches — detached subtrees, dead drafts, and the floating
+//! definitions kept on purpose. Floating is doctrine (nothing needs
+//! to live under anything; see docs/model.md, Floating Definitions),
+//! so the pool is never swept on save; this pane is its visibility
+//! and its EXPLICIT cleanup. Reachability and reference counts treat
+//! record keys as references like links, or the floating field
+//! definitions the pool exists to protect would read as garbage.
+
+use crate::conventions::{Names, display_name};
+use crate::panel::{Border, HOVER, SEPARATOR, ground};
+use crate::raw::{Document, RawStyles, command, short_id, value_cells};
+use crate::sources::Sources;
+use progred_graph::{CellId, Cells};
+use puri::draw::Canvas;
+use puri::handler::HasHandler;
+use puri::layout::{HAlign, Node, col, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect, Stroke};
+use vello::peniko::Color;
+
+/// How an orphan reads: named with a value, a value alone, or a name
+/// alone — the red link, said but never defined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group {
+    Named,
+    Unnamed,
+    RedLink,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orphan {
+    pub cell: CellId,
+    pub group: Group,
+    /// Mentions anywhere in the document, links and keys alike — an
+    /// orphan can still be referenced, by other orphans.
+    pub refs: usize,
+}
+
+/// Every cell the root reaches, links and record keys followed alike,
+/// through both sides — a library value linking back into the
+/// document reaches too.
+pub fn reachable(sources: &Sources) -> HashSet<CellId> {
+    let mut seen = HashSet::new();
+    let mut frontier = Vec::new();
+    if let Some(root) = sources.root() {
+        value_cells(root, &mut frontier);
+    }
+    while let Some(cell) = frontier.pop() {
+        if seen.insert(cell)
+            && let Some(value) = sources.value(cell)
+        {
+            value_cells(value, &mut frontier);
+        }
+    }
+    seen
+}
+
+/// How often each cell is mentioned in the document — the root and
+/// every cell's value, counting each link and each cell label.
+pub fn mentions(doc: &Document) -> HashMap<CellId, usize> {
+    let mut found = Vec::new();
+    if let Some(root) = &doc.root {
+        value_cells(root, &mut found);
+    }
+    for (_, entry) in doc.cells.iter() {
+        if let Some(value) = entry.value() {
+            value_cells(value, &mut found);
+        }
+    }
+    let mut counts = HashMap::new();
+    for cell in found {
+        *counts.entry(cell).or_default() += 1;
+    }
+    counts
+}
+
+/// The pool: document cells the root doesn't reach, grouped, then
+/// by name and id for a stable listing.
+pub fn orphans(sources: &Sources) -> Vec<Orphan> {
+    let reached = reachable(sources);
+    let counts = mentions(sources.doc);
+    let mut pool: Vec<Orphan> = sources
+        .doc
+        .cells
+        .iter()
+        .filter(|(cell, _)| !reached.contains(*cell))
+        .map(|(cell, entry)| Orphan {
+            cell: *cell,
+            group: match (entry.name(), entry.value()) {
+                (Some(_), Some(_)) => Group::Named,
+                (None, _) => Group::Unnamed,
+                (Some(_), None) => Group::RedLink,
+            },
+            refs: counts.get(cell).copied().unwrap_or(0),
+        })
+        .collect();
+    pool.sort_by(|a, b| {
+        (a.group, sources.doc.cells.name(a.cell), a.cell).cmp(&(
+            b.group,
+            sources.doc.cells.name(b.cell),
+            b.cell,
+        ))
+    });
+    pool
+}
+
+/// Removes the chosen orphans' entries, returning what went. A chosen
+/// cell is KEPT while anything surviving still mentions it — the
+/// root, a reachable cell, an unchosen orphan, or the library — and
+/// record keys count, so a floating field definition survives as long
+/// as one record uses it. Chosen cells mentioning only each other go
+/// together. Reachable cells are never purged.
+pub fn purge(doc: &mut Document, library: &Cells, chosen: &[CellId]) -> Vec<CellId> {
+    let reached = reachable(&Sources { doc: &*doc, library });
+    let mut doomed: HashSet<CellId> = chosen
+        .iter()
+        .copied()
+        .filter(|cell| !reached.contains(cell) && doc.cells.entry(*cell).is_some())
+        .collect();
+    // A kept cell's own mentions keep others, so settle to a fixed
+    // point.
+    loop {
+        let mut kept = Vec::new();
+        if let Some(root) = &doc.root {
+            value_cells(root, &mut kept);
+        }
+        let survivors = doc
+            .cells
+            .iter()
+            .filter(|(cell, _)| !doomed.contains(*cell))
+            .chain(library.iter());
+        for (_, entry) in survivors {
+            if let Some(value) = entry.value() {
+                value_cells(value, &mut kept);
+            }
+        }
+        let before = doomed.len();
+        for cell in kept {
+            doomed.remove(&cell);
+        }
+        if doomed.len() == before {
+            break;
+        }
+    }
+    let mut purged: Vec<CellId> = doomed.into_iter().collect();
+    purged.sort();
+    for cell in &purged {
+        doc.cells.remove(*cell);
+    }
+    purged
+}
+
+/// The pane's editor state: the marked rows, in marking order — a
+/// merge keeps the FIRST marked and folds the second into it.
+#[derive(Default)]
+pub struct PoolView {
+    pub marked: Vec<CellId>,
+}
+
+impl PoolView {
+    /// A plain click marks the row alone; with the command modifier
+    /// it toggles the row in or out of the marks.
+    pub fn mark(&mut self, cell: CellId, toggle: bool) {
+        if !toggle {
+            self.marked = vec![cell];
+        } else if let Some(index) = self.marked.iter().position(|marked| *marked == cell) {
+            self.marked.remove(index);
+        } else {
+            self.marked.push(cell);
+        }
+    }
+}
+
+/// What the action bar asks of the shell, over the marked orphans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Reveal the one marked cell in the graph.
+    Jump,
+    /// Link the one marked cell at the tree selection.
+    Attach,
+    /// Fold the second marked cell into the first.
+    Merge,
+    /// Remove the marked cells that nothing surviving mentions.
+    Purge,
+}
+
+/// Dispatch-time callbacks the shell injects, mirroring the other
+/// panes: the pane reports, the shell transitions.
+pub struct Hooks<C> {
+    pub mark: Rc<dyn Fn(&mut C, CellId, bool)>,
+    pub act: Rc<dyn Fn(&mut C, Action)>,
+    /// The pointer's resting claim inside the panel: the row under
+    /// it, or `None` for the pane's own ground — the tree beneath
+    /// never lights through it.
+    pub hover: Rc<dyn Fn(&mut C, Option<CellId>)>,
+}
+
+const MARK: [f32; 4] = [0.0, 0.48, 1.0, 0.14];
+
+/// One pure pass over the pool, like the graph pane: rows from the
+/// orphans, an action bar whose buttons gray when they can't act,
+/// all on a [`ground`] bordering the body to the left. `attachable`
+/// is whether the tree selection can take a link.
+#[allow(clippy::too_many_arguments)]
+pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
+    sources: &Sources,
+    view: &PoolView,
+    hover: Option<CellId>,
+    names: &Names,
+    raw: bool,
+    attachable: bool,
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let pool = orphans(sources);
+    let marked: Vec<CellId> = view
+        .marked
+        .iter()
+        .copied()
+        .filter(|cell| pool.iter().any(|orphan| orphan.cell == *cell))
+        .collect();
+    let inner = panel.width() - 16.0 * scale;
+
+    let button = |tcx: &mut TextCtx, label: &str, action: Action, live: bool| -> Node<P> {
+        let style = if live { &styles.name } else { &styles.dim };
+        let content = pad(
+            Insets::new(6.0 * scale, 2.0 * scale, 6.0 * scale, 2.0 * scale),
+            text(tcx, label, style),
+        );
+        let act = hooks.act.clone();
+        decorate(content, move |p: &mut P, rect| {
+            let shape = RoundedRect::from_rect(rect, 4.0 * scale);
+            p.stroke(
+                shape,
+                Stroke::new(1.0 * scale),
+                Color::new(SEPARATOR),
+                Affine::IDENTITY,
+            );
+            if live {
+                p.handler().on_pointer_down(move |ctx, event| {
+                    event.button == Some(PointerButton::Primary)
+                        && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                        && {
+                            act(ctx, action);
+                            true
+                        }
+                });
+            }
+        })
+    };
+    let mut rows: Vec<Node<P>> = vec![row(
+        6.0 * scale,
+        vec![
+            button(tcx, "Jump", Action::Jump, marked.len() == 1),
+            button(tcx, "Attach", Action::Attach, marked.len() == 1 && attachable),
+            button(tcx, "Merge", Action::Merge, marked.len() == 2),
+            button(tcx, "Purge", Action::Purge, !marked.is_empty()),
+        ],
+    )];
+    if pool.is_empty() {
+        rows.push(text(tcx, "No orphans", &styles.dim));
+    }
+    let mut group = None;
+    for orphan in &pool {
+        if group != Some(orphan.group) {
+            group = Some(orphan.group);
+            let count = pool.iter().filter(|other| other.group == orphan.group).count();
+            let heading = match orphan.group {
+                Group::Named => "Named",
+                Group::Unnamed => "Unnamed",
+                Group::RedLink => "Red links",
+            };
+            rows.push(pad(
+                Insets::new(0.0, 6.0 * scale, 0.0, 0.0),
+                text(tcx, &format!("{heading} · {count}"), &styles.label),
+            ));
+        }
+        let title = match display_name(sources, names, raw, orphan.cell) {
+            Some(name) => text(tcx, &name, &styles.name),
+            None => text(tcx, &short_id(orphan.cell), &styles.id),
+        };
+        let refs = match orphan.refs {
+            0 => "unreferenced".to_string(),
+            1 => "1 ref".to_string(),
+            n => format!("{n} refs"),
+        };
+        let content = min_width(
+            inner,
+            pad(
+                Insets::new(4.0 * scale, 1.0 * scale, 4.0 * scale, 1.0 * scale),
+                row(8.0 * scale, vec![title, text(tcx, &refs, &styles.dim)]),
+            ),
+        );
+        let cell = orphan.cell;
+        let lit = if marked.contains(&cell) {
+            Some(MARK)
+        } else if hover == Some(cell) {
+            Some(HOVER)
+        } else {
+            None
+        };
+        let mark = hooks.mark.clone();
+        let hover = hooks.hover.clone();
+        rows.push(decorate(content, move |p: &mut P, rect| {
+            if let Some(fill) = lit {
+                p.fill(
+                    RoundedRect::from_rect(rect, 4.0 * scale),
+                    Color::new(fill),
+                    Affine::IDENTITY,
+                );
+            }
+            p.handler().on_pointer_move(move |ctx, update| {
+                let point = Point::new(update.current.position.x, update.current.position.y);
+                rect.contains(point) && {
+                    hover(ctx, Some(cell));
+                    false
+                }
+            });
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        mark(ctx, cell, command(&event.state.modifiers));
+                        true
+                    }
+            });
+        }));
+    }
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(8.0 * scale),
+            col(HAlign::Start, 0, 2.0 * scale, rows),
+        ),
+    );
+
+    let hover = hooks.hover.clone();
+    ground(content, panel, Border::Left, scale, move |ctx| hover(ctx, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::{Label, Value, new_cell_id};
+
+    /// A root reaching `kept`, whose record is keyed by the floating
+    /// definition `width`; beside them a detached `draft` linking the
+    /// unnamed `scrap`, and a red link nothing mentions.
+    fn pool_doc() -> (Document, [CellId; 5]) {
+        let [kept, width, draft, scrap, red] = [(); 5].map(|_| new_cell_id());
+        let mut cells = Cells::new();
+        cells.set_value(kept, Value::record([(Label::from(width), Value::from("2"))]));
+        cells.set_name(width, "width");
+        cells.set_name(draft, "draft");
+        cells.set_value(draft, Value::list([Value::from(scrap)]));
+        cells.set_value(scrap, Value::from("x"));
+        cells.set_name(red, "someday");
+        let doc = Document {
+            root: Some(Value::from(kept)),
+            cells,
+            libraries: Vec::new(),
+        };
+        (doc, [kept, width, draft, scrap, red])
+    }
+
+    #[test]
+    fn orphans_group_and_count_keys_as_reaching() {
+        let lib = Cells::new();
+        let (doc, [_, _, draft, scrap, red]) = pool_doc();
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        // The width definition is reached through a KEY, so it is no
+        // orphan; the draft's own link keeps scrap counted.
+        assert_eq!(
+            orphans(&sources),
+            vec![
+                Orphan {
+                    cell: draft,
+                    group: Group::Named,
+                    refs: 0,
+                },
+                Orphan {
+                    cell: scrap,
+                    group: Group::Unnamed,
+                    refs: 1,
+                },
+                Orphan {
+                    cell: red,
+                    group: Group::RedLink,
+                    refs: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn purge_keeps_what_survivors_mention() {
+        let lib = Cells::new();
+        let (mut doc, [kept, width, draft, scrap, red]) = pool_doc();
+        // Scrap alone stays: the unchosen draft still links it.
+        assert!(purge(&mut doc, &lib, &[scrap]).is_empty());
+        // Chosen together, the draft and what only it mentions go;
+        // reachable cells never do, keys included.
+        let mut expected = vec![draft, scrap];
+        expected.sort();
+        assert_eq!(purge(&mut doc, &lib, &[draft, scrap, kept, width]), expected);
+        assert!(doc.cells.entry(kept).is_some());
+        assert_eq!(doc.cells.name(width), Some("width"));
+        // Detached records still keyed by a floating definition keep
+        // it alive until they go too.
+        doc.cells
+            .set_value(red, Value::record([(Label::from(width), Value::from("1"))]));
+        doc.root = None;
+        assert_eq!(purge(&mut doc, &lib, &[width]), Vec::<CellId>::new());
+        assert_eq!(purge(&mut doc, &lib, &[width, red, kept]).len(), 3);
+    }
+
+    #[test]
+    fn marks_replace_or_toggle() {
+        let [a, b] = [new_cell_id(), new_cell_id()];
+        let mut view = PoolView::default();
+        view.mark(a, false);
+        view.mark(b, true);
+        assert_eq!(view.marked, vec![a, b]);
+        view.mark(a, true);
+        assert_eq!(view.marked, vec![b]);
+        view.mark(a, false);
+        assert_eq!(view.marked, vec![a]);
+    }
+}
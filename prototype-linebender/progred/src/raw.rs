@@ -5,10 +5,13 @@
 //! literals or bare element rows; atoms render as their values;
 //! positions are session bookkeeping and never render at all.
 
+use crate::compare::{self, Overlay, Owner};
 use crate::conventions::Names;
 use crate::filter;
+use crate::schema;
 use crate::sources::Sources;
 use im::OrdMap;
+use progred_graph::diff::Change;
 use progred_graph::{
     Atom, CellId, Cells, Label, Position, Step, Value, hex_string, new_cell_id, position, spine,
 };
@@ -84,6 +87,12 @@ impl RawStyles {
 pub struct Document {
     pub root: Option<Value>,
     pub cells: Cells,
+    /// The library files this document reads over — declarations
+    /// only; their cells compose into the editor's library table at
+    /// load (see [`crate::store::libraries`]) and are never saved
+    /// here.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libraries: Vec<crate::store::LibraryRef>,
 }
 
 /// A small document shaped like a real one. The root is an inline
@@ -181,6 +190,7 @@ pub fn sample_document() -> Document {
             (Label::from("favorite"), Value::from(favorite)),
         ])),
         cells,
+        libraries: Vec::new(),
     }
 }
 
@@ -216,11 +226,17 @@ struct Cx<'a> {
     selection: Option<&'a Selection>,
     /// The pointer's current claim, previewed by the target it names.
     hover: Option<&'a Hover>,
-    /// The value whose other projections carry the secondary mark.
-    secondary: Option<Value>,
+    /// The values whose other projections carry the secondary mark —
+    /// one per member of a multi-selection.
+    secondary: Vec<Value>,
     /// The value the hover refers to; its projections carry the faint
     /// hover variant of the secondary mark.
     secondary_hover: Option<Value>,
+    /// The paths the checker found problems at, underlined.
+    flagged: &'a HashSet<Path>,
+    /// The compare overlay, when the document is being read against
+    /// another state of itself.
+    overlay: Option<&'a Overlay>,
 }
 
 /// A reported click on a string's text, in text-local coordinates.
@@ -262,6 +278,9 @@ pub struct Hooks<C> {
     /// Open a pending sibling after the element at `path` — the flat
     /// list separator's click.
     pub insert: Rc<dyn Fn(&mut C, Path)>,
+    /// Toggle `path` in the multi-selection — a command-click with no
+    /// pending open to pick into.
+    pub add: Rc<dyn Fn(&mut C, Path)>,
 }
 
 /// The hover hook's shape, shared by every claim site: the report,
@@ -290,13 +309,25 @@ impl Cx<'_> {
     /// selected there, something is being authored inside; the
     /// pending row carries the highlight itself.
     fn selected(&self, path: &[Step]) -> bool {
-        match self.selection {
-            Some(Selection::Edge { path: selected, .. })
-            | Some(Selection::Pending {
-                path: selected, ..
-            }) => selected.as_slice() == path,
-            _ => false,
-        }
+        self.selection
+            .is_some_and(|selection| selection.paths().iter().any(|selected| *selected == path))
+    }
+
+    /// The compare overlay's changes at `path`, read by its owner —
+    /// the cell its last Follow crosses into, or the root — and the
+    /// spine below, so every projection of a cell shows its changes.
+    fn changes(&self, path: &[Step]) -> &[Change] {
+        let Some(overlay) = self.overlay else {
+            return &[];
+        };
+        let (owner, spine) = match last_follow(path) {
+            Some(index) => match self.sources.resolve(&path[..index]).and_then(Value::as_cell) {
+                Some(cell) => (Owner::Cell(cell), &path[index + 1..]),
+                None => return &[],
+            },
+            None => (Owner::Root, path),
+        };
+        overlay.at(owner, spine)
     }
 
     fn hovered_value(&self, path: &[Step]) -> bool {
@@ -363,6 +394,13 @@ pub type Path = Vec<Step>;
 /// completion query instead; the query resolves to the value that
 /// commits, and until then the graph is untouched — deselecting
 /// discards the pending entirely.
+///
+/// An edge selection can be MULTIPLE: the primary path, which holds
+/// the editor and anchors range extension, plus `also` — the rest of
+/// the set. Batch gestures (delete, copy, paste, collapse) act on
+/// the whole set; typing and stepping act on the primary. Any fresh
+/// selection starts single, so the set never outlives the gesture
+/// that built it.
 pub enum Selection {
     Edge {
         path: Path,
@@ -371,6 +409,10 @@ pub enum Selection {
         /// undo step: the run is the editor's lifetime, so the first
         /// write records and the rest coalesce by staying silent.
         recorded: bool,
+        /// The multi-selection's other members: added by command-
+        /// click in the order clicked, or a shift-arrow range ordered
+        /// out from the primary, so the last is the range's head.
+        also: Vec<Path>,
     },
     /// A nonexistent location's value being authored (the root and a
     /// bare cell's value included).
@@ -439,6 +481,7 @@ impl Selection {
             path,
             edit,
             recorded: false,
+            also: Vec::new(),
         }
     }
 
@@ -449,6 +492,43 @@ impl Selection {
         }
     }
 
+    /// Every selected value's path, the primary first; a label-stage
+    /// pending selects no value and answers none.
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            Selection::Edge { path, also, .. } => std::iter::once(path).chain(also).collect(),
+            Selection::Pending { path, .. } => vec![path],
+            Selection::PendingEdge { .. } => Vec::new(),
+        }
+    }
+
+    /// The same selection with `paths` as the rest of its set — how a
+    /// batch edit keeps the set across its remount.
+    pub fn with_also(mut self, paths: Vec<Path>) -> Self {
+        if let Selection::Edge { also, .. } = &mut self {
+            *also = paths;
+        }
+        self
+    }
+
+    /// Adds `path` to an edge selection, or drops it if already in —
+    /// the command-click toggle. The primary stays: it holds the
+    /// editor. False when there is no edge selection to add to.
+    pub fn toggle_also(&mut self, path: Path) -> bool {
+        let Selection::Edge { path: primary, also, .. } = self else {
+            return false;
+        };
+        if *primary != path {
+            match also.iter().position(|held| *held == path) {
+                Some(index) => {
+                    also.remove(index);
+                }
+                None => also.push(path),
+            }
+        }
+        true
+    }
+
     pub fn edit(&self) -> Option<&LineEditState> {
         match self {
             Selection::Edge { edit, .. } => edit.as_ref(),
@@ -582,17 +662,93 @@ pub fn delete_edge(doc: &mut Document, library: &Cells, path: &[Step]) -> bool {
     }
 }
 
+/// Copies the element or field at `path` in place — an element just
+/// after its source, at a fresh position; a field under the first
+/// free `label 2`, `label 3`… — one write through [`set_value`].
+/// Returns where the copy landed. Fields labelled by a cell decline:
+/// there is no fresh spelling of an identity.
+pub fn duplicate_edge(doc: &mut Document, library: &Cells, path: &[Step]) -> Option<Path> {
+    let (last, parent) = path.split_last()?;
+    let (step, value) = {
+        let sources = Sources { doc: &*doc, library };
+        let value = sources.resolve(path)?.clone();
+        let step = match (last, sources.resolve(parent)?) {
+            (Step::Element(at), Value::List(items)) => {
+                let next = items.range(at..).map(|(position, _)| position).nth(1);
+                Step::Element(position::between(Some(at), next)?)
+            }
+            (Step::Key(Label::String(label)), Value::Record(fields)) => Step::Key(
+                (2..)
+                    .map(|n| Label::String(format!("{label} {n}")))
+                    .find(|fresh| !fields.contains_key(fresh))?,
+            ),
+            _ => return None,
+        };
+        (step, value)
+    };
+    let mut landed = parent.to_vec();
+    landed.push(step);
+    set_value(doc, library, &landed, value).then_some(landed)
+}
+
+/// Moves the element at `path` one place up or down its list,
+/// re-keyed between its new neighbours — the rest keep their
+/// positions. Returns where it landed; the ends decline.
+pub fn move_element(doc: &mut Document, library: &Cells, path: &[Step], up: bool) -> Option<Path> {
+    let (Step::Element(at), parent) = path.split_last()? else {
+        return None;
+    };
+    let (moved, list) = {
+        let sources = Sources { doc: &*doc, library };
+        let items = sources.resolve(parent)?.as_list()?;
+        let value = items.get(at)?.clone();
+        let (low, high) = if up {
+            let mut before = items.range(..at).rev().map(|(position, _)| position);
+            let previous = before.next()?;
+            (before.next(), Some(previous))
+        } else {
+            let mut after = items.range(at..).skip(1).map(|(position, _)| position);
+            let next = after.next()?;
+            (Some(next), after.next())
+        };
+        let moved = position::between(low, high)?;
+        let list = Value::List(items.without(at).update(moved.clone(), value));
+        (moved, list)
+    };
+    let mut landed = parent.to_vec();
+    landed.push(Step::Element(moved));
+    set_value(doc, library, parent, list).then_some(landed)
+}
+
 /// Where the selection lands after deleting `path`: the next sibling,
 /// else the previous, else the parent. Also where a discarded pending
 /// edge returns to.
 pub fn selection_after_delete(descends: &[Descend], path: &[Step]) -> Path {
-    sibling(descends, path, true)
-        .or_else(|| sibling(descends, path, false))
-        .unwrap_or_else(|| {
-            path.split_last()
-                .map(|(_, parent)| parent.to_vec())
-                .unwrap_or_default()
-        })
+    selection_after_deletes(descends, &[path.to_vec()])
+}
+
+/// Where the selection lands after deleting every path in `paths`:
+/// the first sibling onward from the first of them that survives,
+/// else backward, else the first's parent — never a path inside
+/// anything deleted.
+pub fn selection_after_deletes(descends: &[Descend], paths: &[Path]) -> Path {
+    let Some(first) = paths.first() else {
+        return Vec::new();
+    };
+    let gone = |path: &Path| paths.iter().any(|deleted| path.starts_with(deleted));
+    for next in [true, false] {
+        let mut at = first.clone();
+        while let Some(found) = sibling(descends, &at, next) {
+            if !gone(&found) {
+                return found;
+            }
+            at = found;
+        }
+    }
+    first
+        .split_last()
+        .map(|(_, parent)| parent.to_vec())
+        .unwrap_or_default()
 }
 
 /// A value-stage pending: the location named by `path` does not
@@ -815,8 +971,8 @@ pub fn resolve_query(text: &str) -> Value {
 }
 
 /// The clipboard spelling of a value — SHALLOW by design: one value,
-/// a link being its identity alone, no cell values traveling (deep
-/// copy waits on the projection-boundary design; see docs/model.md).
+/// a link being its identity alone, no cell values traveling (the
+/// deep copy is [`Closure`], its own chord).
 /// Strings and blobs spell as the query language — "quoted" strings,
 /// `0x` hex — so they read in other apps and [`from_clipboard`] reads
 /// them back; links, lists, and records spell as Value JSON.
@@ -834,6 +990,110 @@ pub fn from_clipboard(text: &str) -> Value {
     serde_json::from_str(text).unwrap_or_else(|_| resolve_query(text))
 }
 
+/// A DEEP copy's payload: one value plus every cell it transitively
+/// links, each carried with its whole entry — self-contained, so it
+/// pastes into another document as well as this one. Cells linked
+/// but bare travel as ids alone; nothing is said about them to carry.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Closure {
+    pub value: Value,
+    pub cells: Cells,
+}
+
+/// The closure of `value` as `sources` reads it: links and cell
+/// labels followed through both sides, so a library entity copies
+/// with the document's view of it. Cycles stop at the first visit.
+pub fn closure(sources: &Sources, value: &Value) -> Closure {
+    let mut cells = Cells::new();
+    let mut seen = HashSet::new();
+    let mut frontier = Vec::new();
+    value_cells(value, &mut frontier);
+    while let Some(cell) = frontier.pop() {
+        if !seen.insert(cell) {
+            continue;
+        }
+        if let Some(name) = sources.name(cell) {
+            cells.set_name(cell, name);
+        }
+        if let Some(linked) = sources.value(cell) {
+            value_cells(linked, &mut frontier);
+            cells.set_value(cell, linked.clone());
+        }
+    }
+    Closure {
+        value: value.clone(),
+        cells,
+    }
+}
+
+/// The deep clipboard spelling: the closure as JSON — an object no
+/// Value spelling shares, so [`closure_from_clipboard`] tells the two
+/// apart and a shallow paste never misreads it.
+pub fn closure_to_clipboard(closure: &Closure) -> String {
+    serde_json::to_string(closure).expect("closures serialize")
+}
+
+pub fn closure_from_clipboard(text: &str) -> Option<Closure> {
+    serde_json::from_str(text).ok()
+}
+
+/// Lands a closure at `path` — ONE write, declining whole like
+/// [`set_value`]. Identity is remapped on the way in: every cell the
+/// payload mentions gets a fresh id, rewritten through its links and
+/// cell labels alike, so the paste is a duplicate that shares nothing
+/// with its source — EXCEPT a cell the library states and the
+/// document doesn't, which keeps its id. The pasted cell itself, if
+/// it is one, also takes the copied entry: that is the fork, the
+/// document becoming the authority for the same identity. Library
+/// cells it reaches stay plain links, their entries left to the
+/// library — a typed instance must not fork `isa`, its type, and
+/// every field cell into the document. Once forked, later pastes
+/// duplicate the cell like any document cell.
+pub fn paste_closure(
+    doc: &mut Document,
+    library: &Cells,
+    path: &[Step],
+    closure: &Closure,
+) -> bool {
+    let sources = Sources { doc: &*doc, library };
+    let external = |cell: CellId| sources.external(cell);
+    let fork = closure.value.as_cell().filter(|cell| external(*cell));
+    let mut remap: HashMap<CellId, CellId> = HashMap::new();
+    let mut rename = |cell: CellId| {
+        *remap
+            .entry(cell)
+            .or_insert_with(|| if external(cell) { cell } else { new_cell_id() })
+    };
+    let value = closure.value.map_cells(&mut rename);
+    let entries: Vec<(CellId, Option<String>, Option<Value>)> = closure
+        .cells
+        .iter()
+        .filter(|(cell, _)| Some(**cell) == fork || !external(**cell))
+        .map(|(cell, entry)| {
+            (
+                rename(*cell),
+                entry.name().map(str::to_owned),
+                entry.value().map(|value| value.map_cells(&mut rename)),
+            )
+        })
+        .collect();
+    let mut next = doc.clone();
+    for (cell, name, value) in entries {
+        if let Some(name) = name {
+            next.cells.set_name(cell, &name);
+        }
+        if let Some(value) = value {
+            next.cells.set_value(cell, value);
+        }
+    }
+    if set_value(&mut next, library, path, value) {
+        *doc = next;
+        true
+    } else {
+        false
+    }
+}
+
 /// A completion offer on a pending. The display styles itself by the
 /// action's kind at draw time.
 #[derive(Clone)]
@@ -884,11 +1144,17 @@ pub trait HasPopup {
 /// design. The label stage (`labels`) offers only what can label:
 /// strings and cell references — no blobs, and "new list"/"new
 /// record" stay value offers.
+///
+/// Over it, the schema layer leads where the slot is typed: `at` is
+/// the pending value's path, or — at the label stage — the record's,
+/// and what the schema says fits there ranks first (see
+/// [`schema_entries`]).
 fn completion_entries(
     sources: &Sources,
     names: &Names,
     raw: bool,
     labels: bool,
+    at: &[Step],
     query: &str,
 ) -> Vec<Entry> {
     let trimmed = query.trim();
@@ -937,15 +1203,17 @@ fn completion_entries(
     // query. "new list" and "new record" rank among them under their
     // own display text: type toward one and it surfaces, type away
     // and it leaves.
-    let mut references_pool: Vec<(String, bool, EntryAction)> = document_cells(sources)
-        .into_iter()
-        .map(
-            |cell| match crate::conventions::display_name(sources, names, raw, cell) {
-                Some(name) => (name, true, EntryAction::Value(Value::from(cell))),
-                None => (short_id(cell), false, EntryAction::Value(Value::from(cell))),
-            },
-        )
-        .collect();
+    let (vocabulary, cells): (Vec<CellId>, Vec<CellId>) =
+        document_cells(sources).into_iter().partition(|cell| schema::vocabulary_cell(*cell));
+    let reference = |cell: CellId| {
+        let action = EntryAction::Value(Value::from(cell));
+        match crate::conventions::display_name(sources, names, raw, cell) {
+            Some(name) => (name, true, action),
+            None => (short_id(cell), false, action),
+        }
+    };
+    let mut references_pool: Vec<(String, bool, EntryAction)> =
+        cells.into_iter().map(reference).collect();
     references_pool.sort_by(|a, b| a.0.cmp(&b.0));
     // "new cell" is one of them — a plain constructor like list and
     // record (the mint is bare; naming happens on the head after).
@@ -955,6 +1223,11 @@ fn completion_entries(
         references_pool.push(("new list".to_string(), true, EntryAction::NewList));
         references_pool.push(("new record".to_string(), true, EntryAction::NewRecord));
     }
+    // The schema vocabulary is under every document, so it trails:
+    // reached by typing its words, never crowding an empty query.
+    let mut vocabulary: Vec<_> = vocabulary.into_iter().map(reference).collect();
+    vocabulary.sort_by(|a, b| a.0.cmp(&b.0));
+    references_pool.extend(vocabulary);
     let references: Vec<(Entry, bool)> = filter::rank(references_pool, |(key, _, _)| key, query)
         .into_iter()
         .take(8)
@@ -983,7 +1256,26 @@ fn completion_entries(
             (entry, demoted)
         })
         .collect();
-    let mut entries = Vec::new();
+    let schema = schema_entries(sources, names, raw, labels, at, query);
+    // A typed string slot wants the typed text: the atom leads there
+    // as if quoted.
+    let atom_leads = atom_leads
+        || (!labels
+            && matches!(
+                schema::expected(sources, at),
+                Some(schema::Expected::Value(schema::Type::String))
+            )
+            && matches!(&atom_entry.action, EntryAction::Value(Value::Atom(Atom::String(_)))));
+    let offered = |entry: &Entry| {
+        schema.iter().any(|led| match (&led.action, &entry.action) {
+            (EntryAction::Value(a), EntryAction::Value(b)) => a == b,
+            (EntryAction::NewList, EntryAction::NewList) => true,
+            _ => false,
+        })
+    };
+    let references: Vec<(Entry, bool)> =
+        references.into_iter().filter(|(entry, _)| !offered(entry)).collect();
+    let mut entries = schema.clone();
     if atom_leads {
         entries.push(atom_entry);
         entries.extend(string_entry);
@@ -998,10 +1290,115 @@ fn completion_entries(
     entries
 }
 
+/// The schema layer's offers at `at`, in the order the schema gives
+/// them and narrowed by `query`: at the label stage, a typed record's
+/// fields it doesn't have yet (its `isa` when it has none); at the
+/// value stage, by what the slot expects — a record type's or sum's
+/// constructors as fresh tagged records (`new circle`) followed by
+/// the cells already conforming, the record types an `isa` slot
+/// admits, a fresh list for a list slot. Untyped slots offer nothing
+/// here and the universal layer stands alone.
+fn schema_entries(
+    sources: &Sources,
+    names: &Names,
+    raw: bool,
+    labels: bool,
+    at: &[Step],
+    query: &str,
+) -> Vec<Entry> {
+    let name_of = |cell: CellId| match crate::conventions::display_name(sources, names, raw, cell) {
+        Some(name) => (name, true),
+        None => (short_id(cell), false),
+    };
+    let reference = |cell: CellId| {
+        let (display, named) = name_of(cell);
+        (display, named, EntryAction::Value(Value::from(cell)))
+    };
+    let mut offers: Vec<(String, bool, EntryAction)> = Vec::new();
+    if labels {
+        let record = sources.resolve(at).and_then(Value::as_record);
+        let has = |cell: CellId| {
+            record.is_some_and(|record| record.contains_key(&Label::Cell(cell)))
+        };
+        match schema::record_type_at(sources, at) {
+            Some(record_type) => offers.extend(
+                schema::fields(sources, record_type)
+                    .into_iter()
+                    .filter(|field| !has(field.label))
+                    .map(|field| reference(field.label)),
+            ),
+            None if record.is_some() && !has(schema::ISA) => {
+                offers.push(reference(schema::ISA));
+            }
+            None => {}
+        }
+    } else {
+        use schema::{Expected, Type};
+        match schema::expected(sources, at) {
+            Some(Expected::Value(ty @ (Type::Record(_) | Type::Sum(_)))) => {
+                let constructors = schema::constructors(sources, &ty);
+                offers.extend(constructors.iter().map(|ctor| {
+                    let (display, named) = name_of(*ctor);
+                    let fresh = Value::record([(Label::from(schema::ISA), Value::from(*ctor))]);
+                    (format!("new {display}"), named, EntryAction::Value(fresh))
+                }));
+                offers.extend(
+                    document_cells(sources)
+                        .into_iter()
+                        .filter(|cell| {
+                            !sources.external(*cell)
+                                && schema::conforms(sources, &Value::from(*cell), &ty)
+                        })
+                        .map(reference),
+                );
+            }
+            Some(Expected::Tag(ty)) => {
+                let admitted = match ty {
+                    Some(ty) => schema::constructors(sources, &ty),
+                    None => schema::record_types(sources),
+                };
+                offers.extend(admitted.into_iter().map(reference));
+            }
+            Some(Expected::Value(Type::List(_))) => {
+                offers.push(("new list".to_string(), true, EntryAction::NewList));
+            }
+            _ => {}
+        }
+    }
+    // The schema's order stands on an empty query; typing narrows it
+    // through the same tiers as everything else.
+    let ranked: Vec<_> = if query.trim().is_empty() {
+        offers.into_iter().map(|offer| (offer, Vec::new())).collect()
+    } else {
+        filter::rank(offers, |(key, _, _)| key, query)
+            .into_iter()
+            .filter(|ranked| !ranked.fuzzy())
+            .map(|ranked| (ranked.item, ranked.matches))
+            .collect()
+    };
+    ranked
+        .into_iter()
+        .take(8)
+        .map(|((display, named, action), matches)| Entry {
+            detail: match &action {
+                EntryAction::Value(value) => value
+                    .as_cell()
+                    .map(short_id)
+                    .filter(|detail| *detail != display),
+                _ => None,
+            },
+            display,
+            matches,
+            id: !named,
+            action,
+        })
+        .collect()
+}
+
 /// The cells a value links, walked structurally — lists and records
 /// are values, so their contents are right here; record labels
 /// reference too.
-fn value_cells(value: &Value, cells: &mut Vec<CellId>) {
+pub(crate) fn value_cells(value: &Value, cells: &mut Vec<CellId>) {
     match value {
         Value::Atom(atom) => cells.extend(atom.as_cell()),
         Value::List(elements) => {
@@ -1152,6 +1549,217 @@ pub fn set_name(doc: &mut Document, library: &Cells, path: &[Step], name: &str)
     }
 }
 
+/// Which side's statement a merge keeps where both cells state one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prefer {
+    Survivor,
+    Loser,
+}
+
+/// What two cells state differently — the parts a merge has to ask
+/// about. A part only one side states is no conflict: it fills in.
+/// `fields` is a record somewhere keyed by both cells, its two fields
+/// reading differently: folding the keys together keeps only one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Conflicts {
+    pub name: bool,
+    pub value: bool,
+    pub fields: bool,
+}
+
+impl Conflicts {
+    pub fn any(self) -> bool {
+        self.name || self.value || self.fields
+    }
+}
+
+/// Where merging `loser` into `survivor` would have to choose. Values
+/// compare as they will read after the merge — each side's mentions
+/// of the loser already the survivor — so two cells differing only
+/// in which of the pair they link agree.
+pub fn merge_conflicts(doc: &Document, survivor: CellId, loser: CellId) -> Conflicts {
+    let name = match (doc.cells.name(survivor), doc.cells.name(loser)) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    };
+    let fold = |value: &Value| fold_mentions(value, survivor, loser, Prefer::Survivor);
+    let value = match (doc.cells.value(survivor), doc.cells.value(loser)) {
+        (Some(a), Some(b)) => fold(a) != fold(b),
+        _ => false,
+    };
+    let fields = (doc.root.iter())
+        .chain(doc.cells.iter().filter_map(|(_, entry)| entry.value()))
+        .any(|value| clashes(value, survivor, loser));
+    Conflicts {
+        name,
+        value,
+        fields,
+    }
+}
+
+/// Whether some record in `value` is keyed by both cells with fields
+/// that differ once the pair is one.
+fn clashes(value: &Value, survivor: CellId, loser: CellId) -> bool {
+    match value {
+        Value::Atom(_) => false,
+        Value::List(elements) => elements.values().any(|element| clashes(element, survivor, loser)),
+        Value::Record(fields) => {
+            let fold = |value: &Value| fold_mentions(value, survivor, loser, Prefer::Survivor);
+            let both = (fields.get(&Label::Cell(survivor)))
+                .zip(fields.get(&Label::Cell(loser)));
+            both.is_some_and(|(kept, gone)| fold(kept) != fold(gone))
+                || fields.values().any(|field| clashes(field, survivor, loser))
+        }
+    }
+}
+
+/// `value` with every mention of `loser` reading `survivor`. A record
+/// keyed by both keeps the field `prefer` picks: two keys becoming
+/// one is the one place the rewrite could lose data, so the other
+/// field is dropped first rather than left to the rewrite's order.
+fn fold_mentions(value: &Value, survivor: CellId, loser: CellId, prefer: Prefer) -> Value {
+    let (keep, drop) = match prefer {
+        Prefer::Survivor => (survivor, loser),
+        Prefer::Loser => (loser, survivor),
+    };
+    keep_one(value, keep, drop).map_cells(&mut |cell| if cell == loser { survivor } else { cell })
+}
+
+/// `value` with `drop`'s field gone from every record also keyed by
+/// `keep`.
+fn keep_one(value: &Value, keep: CellId, drop: CellId) -> Value {
+    match value {
+        Value::Atom(_) => value.clone(),
+        Value::List(elements) => Value::List(
+            elements
+                .iter()
+                .map(|(position, element)| (position.clone(), keep_one(element, keep, drop)))
+                .collect(),
+        ),
+        Value::Record(fields) => {
+            let both = fields.contains_key(&Label::Cell(keep));
+            Value::Record(
+                fields
+                    .iter()
+                    .filter(|(label, _)| !(both && **label == Label::Cell(drop)))
+                    .map(|(label, field)| (label.clone(), keep_one(field, keep, drop)))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Merges `loser` into `survivor`: one identity where there were two.
+/// Every mention of the loser — links and cell labels, in the root
+/// and in every document cell — rewrites to the survivor, and the
+/// loser's entry goes. The two entries reconcile part by part: what
+/// only one side states fills in, so two red links merge to one and
+/// a stub merges into its definition losing nothing; where both
+/// state a part, `prefer` picks (see [`merge_conflicts`] for asking
+/// first). Library identities are not the document's to merge:
+/// either side external declines, as does a cell with itself.
+pub fn merge_cells(
+    doc: &mut Document,
+    library: &Cells,
+    survivor: CellId,
+    loser: CellId,
+    prefer: Prefer,
+) -> bool {
+    let sources = Sources { doc: &*doc, library };
+    if survivor == loser || sources.external(survivor) || sources.external(loser) {
+        return false;
+    }
+    let (first, second) = match prefer {
+        Prefer::Survivor => (survivor, loser),
+        Prefer::Loser => (loser, survivor),
+    };
+    let name = doc.cells.name(first).or(doc.cells.name(second)).map(str::to_owned);
+    let value = doc.cells.value(first).or(doc.cells.value(second)).cloned();
+    let mut next = doc.clone();
+    next.root = next.root.map(|root| fold_mentions(&root, survivor, loser, prefer));
+    next.cells.remove(loser);
+    for (cell, held) in doc.cells.iter() {
+        let mut mentioned = Vec::new();
+        if let Some(held) = held.value() {
+            value_cells(held, &mut mentioned);
+            if mentioned.contains(&loser) && *cell != loser && *cell != survivor {
+                next.cells.set_value(*cell, fold_mentions(held, survivor, loser, prefer));
+            }
+        }
+    }
+    if let Some(value) = value {
+        next.cells.set_value(survivor, fold_mentions(&value, survivor, loser, prefer));
+    }
+    if let Some(name) = name {
+        next.cells.set_name(survivor, &name);
+    }
+    *doc = next;
+    true
+}
+
+/// A path as it reads after merging `loser` into `survivor`: keys
+/// naming the loser name the survivor. Only meaningful for a path
+/// the merge left standing.
+pub fn fold_path(path: &[Step], survivor: CellId, loser: CellId) -> Path {
+    path.iter()
+        .map(|step| match step {
+            Step::Key(Label::Cell(cell)) if *cell == loser => Step::Key(Label::Cell(survivor)),
+            _ => step.clone(),
+        })
+        .collect()
+}
+
+/// Moves the value at `path` into a fresh cell, leaving a link to it
+/// in its place: inline structure made a shared identity. Returns the
+/// minted cell, unnamed — the caller offers its head for naming on
+/// the spot. A link is already an identity and a Name step addresses
+/// none, so both decline, as do missing and unwritable spots.
+pub fn extract_cell(doc: &mut Document, library: &Cells, path: &[Step]) -> Option<CellId> {
+    if matches!(path.last(), Some(Step::Name)) {
+        return None;
+    }
+    let value = {
+        let sources = Sources { doc: &*doc, library };
+        sources.resolve(path).filter(|value| value.as_cell().is_none())?.clone()
+    };
+    let cell = new_cell_id();
+    set_value(doc, library, path, Value::from(cell)).then(|| {
+        doc.cells.set_value(cell, value);
+        cell
+    })
+}
+
+/// Replaces the link at `path` with a copy of its cell's value — the
+/// reverse of [`extract_cell`], one write through [`set_value`].
+/// `sole` says the caller knows this link is the cell's only mention
+/// (see [`progred_graph::mentions`]), and the entry goes too; a cell
+/// mentioned elsewhere keeps its statement for them, and a library
+/// cell is never the document's to remove. Returns the inlined cell.
+/// A bare cell has nothing to copy and declines, as do non-links and
+/// unwritable spots.
+pub fn inline_cell(
+    doc: &mut Document,
+    library: &Cells,
+    path: &[Step],
+    sole: bool,
+) -> Option<CellId> {
+    if matches!(path.last(), Some(Step::Name)) {
+        return None;
+    }
+    let (cell, value) = {
+        let sources = Sources { doc: &*doc, library };
+        let cell = sources.resolve(path).and_then(Value::as_cell)?;
+        (cell, sources.value(cell)?.clone())
+    };
+    if !set_value(doc, library, path, value) {
+        return None;
+    }
+    if sole && !(Sources { doc: &*doc, library }).external(cell) {
+        doc.cells.remove(cell);
+    }
+    Some(cell)
+}
+
 /// Toggle the collapse override for the value at `path`. Declines
 /// unless there is something to collapse — a cell with a value, or a
 /// nonempty list or record.
@@ -1229,6 +1837,7 @@ pub fn write_through(doc: &mut Document, library: &Cells, selection: &mut Select
         path,
         edit,
         recorded,
+        ..
     } = selection
     else {
         return false;
@@ -1427,6 +2036,64 @@ fn same_line(a: Rect, b: Rect, line: f64) -> bool {
     a.y1.min(b.y1) - a.y0.max(b.y0) > line * 0.5
 }
 
+/// Shift+arrow range extension: grows or shrinks the multi-selection
+/// along the primary's list, in descend order — Down and Right
+/// toward the end, Up and Left toward the start — from the primary
+/// to a head one element further each press. Only an element's
+/// selection extends, and only among its siblings; at an end the
+/// press is spent without effect. Replaces any command-clicked
+/// members: a range is its own set.
+pub fn extend_selection(
+    descends: &[Descend],
+    selection: Option<&mut Selection>,
+    event: &KeyboardEvent,
+) -> bool {
+    let modifiers = &event.modifiers;
+    let alone = !modifiers.ctrl() && !modifiers.meta() && !modifiers.alt();
+    if !event.state.is_down() || !modifiers.shift() || !alone {
+        return false;
+    }
+    let forward = match &event.key {
+        Key::Named(NamedKey::ArrowDown | NamedKey::ArrowRight) => true,
+        Key::Named(NamedKey::ArrowUp | NamedKey::ArrowLeft) => false,
+        _ => return false,
+    };
+    let Some(Selection::Edge { path, also, .. }) = selection else {
+        return false;
+    };
+    let Some((Step::Element(_), parent)) = path.split_last() else {
+        return false;
+    };
+    let mut siblings: Vec<&Path> = Vec::new();
+    for descend in descends {
+        let element = matches!(
+            descend.path.split_last(),
+            Some((Step::Element(_), prefix)) if prefix == parent
+        );
+        if element && !siblings.contains(&&descend.path) {
+            siblings.push(&descend.path);
+        }
+    }
+    let Some(anchor) = siblings.iter().position(|sibling| *sibling == path) else {
+        return false;
+    };
+    let head = also
+        .last()
+        .and_then(|head| siblings.iter().position(|sibling| *sibling == head))
+        .unwrap_or(anchor);
+    let head = match forward {
+        true if head + 1 < siblings.len() => head + 1,
+        false if head > 0 => head - 1,
+        _ => return true,
+    };
+    *also = if head >= anchor {
+        siblings[anchor + 1..=head].iter().map(|path| (*path).clone()).collect()
+    } else {
+        siblings[head..anchor].iter().rev().map(|path| (*path).clone()).collect()
+    };
+    true
+}
+
 /// The neighboring sibling in placement order, continuing through
 /// ancestors at the ends — where the selection lands after a delete,
 /// via [`selection_after_delete`].
@@ -1555,12 +2222,14 @@ pub fn hover_value(
             }
         }
         Hover::Entry(index) => {
-            let (query, labels) = match selection? {
+            let selection = selection?;
+            let (query, labels) = match selection {
                 Selection::Pending { query, .. } => (query, false),
                 Selection::PendingEdge { query, .. } => (query, true),
                 Selection::Edge { .. } => return None,
             };
-            let entries = completion_entries(sources, names, raw, labels, query.text());
+            let at = selection.path();
+            let entries = completion_entries(sources, names, raw, labels, at, query.text());
             match &entries.get(*index)?.action {
                 EntryAction::Value(value) => Some(value.clone()),
                 _ => None,
@@ -1842,7 +2511,8 @@ fn primary_highlight<P: Canvas>(scale: f64, p: &mut P, rect: Rect) {
 /// is an honest click target. On placement it draws the highlight
 /// when this is the selected path, registers a click that selects it
 /// (innermost wins by handler precedence) — or, with the command
-/// modifier and a pending open, picks `value` into it — and records
+/// modifier, picks `value` into an open pending, else adds the path
+/// to the multi-selection — and records
 /// itself for keyboard navigation. Views whose boxes span structural
 /// whitespace use [`descend_landmark`] plus explicit content claims
 /// instead.
@@ -1858,6 +2528,7 @@ fn descend<C: 'static, P: Canvas + HasHandler<C> + HasDescends>(
     let hovered = cx.hovered_value(&path);
     let select = hooks.select.clone();
     let pick = hooks.pick.clone();
+    let add = hooks.add.clone();
     let hover = hooks.hover.clone();
     decorate(child, move |p, rect| {
         if selected {
@@ -1868,18 +2539,22 @@ fn descend<C: 'static, P: Canvas + HasHandler<C> + HasDescends>(
         hover_claim(p, rect, hover.clone(), Hover::Value(path.clone()));
         let select = select.clone();
         let pick = pick.clone();
+        let add = add.clone();
         let target = path.clone();
         let value = value.clone();
         p.handler().on_pointer_down(move |ctx, event| {
             event.button == Some(PointerButton::Primary)
                 && rect.contains(Point::new(event.state.position.x, event.state.position.y))
                 && {
-                    let picked = command(&event.state.modifiers)
+                    let command = command(&event.state.modifiers);
+                    let picked = command
                         && value
                             .as_ref()
                             .is_some_and(|value| pick(ctx, value.clone()));
-                    if !picked {
-                        select(ctx, target.clone(), None);
+                    match (picked, command) {
+                        (true, _) => {}
+                        (false, true) => add(ctx, target.clone()),
+                        (false, false) => select(ctx, target.clone(), None),
                     }
                     true
                 }
@@ -1888,14 +2563,19 @@ fn descend<C: 'static, P: Canvas + HasHandler<C> + HasDescends>(
     })
 }
 
-/// The value marked as the secondary selection: the one at the
-/// selected path. A value can project in many places — links, but
+/// The values marked as the secondary selection: the ones at the
+/// selected paths. A value can project in many places — links, but
 /// equally strings, blobs, and equal lists — and the marks make that
 /// sameness visible. Inline records are structure, not identity: no
 /// marks. A selected name marks as its string.
-fn secondary_of(sources: &Sources, selection: Option<&Selection>) -> Option<Value> {
-    match selection? {
-        Selection::Edge { path, .. } => match path.split_last() {
+fn secondary_of(sources: &Sources, selection: Option<&Selection>) -> Vec<Value> {
+    let Some(selection @ Selection::Edge { .. }) = selection else {
+        return Vec::new();
+    };
+    selection
+        .paths()
+        .into_iter()
+        .filter_map(|path| match path.split_last() {
             Some((Step::Name, parent)) => sources
                 .resolve(parent)
                 .and_then(Value::as_cell)
@@ -1905,9 +2585,8 @@ fn secondary_of(sources: &Sources, selection: Option<&Selection>) -> Option<Valu
                 .resolve(path)
                 .filter(|value| !matches!(value, Value::Record(_)))
                 .cloned(),
-        },
-        _ => None,
-    }
+        })
+        .collect()
 }
 
 // The explicit-state boundary: everything a pass reads arrives here.
@@ -1924,6 +2603,8 @@ pub fn project<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     hover: Option<&Hover>,
     hover_node: Option<&Value>,
     collapse: &Collapse,
+    flagged: &HashSet<Path>,
+    overlay: Option<&Overlay>,
     names: &Names,
     raw: bool,
     tcx: &mut TextCtx,
@@ -1942,10 +2623,15 @@ pub fn project<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
         // The graph view's selected cell is a secondary here too:
         // its projections are the same value — and the graph view's
         // HOVERED cell is a hover secondary the same way.
-        secondary: secondary_of(sources, selection).or_else(|| graph_node.cloned()),
+        secondary: match secondary_of(sources, selection) {
+            values if values.is_empty() => graph_node.cloned().into_iter().collect(),
+            values => values,
+        },
         secondary_hover: hover
             .and_then(|hover| hover_value(sources, names, raw, selection, hover))
             .or_else(|| hover_node.cloned()),
+        flagged,
+        overlay,
     };
     // The Raw view derives from the one bit: names answer None and
     // nothing else changes — lists and records render as themselves
@@ -2327,6 +3013,16 @@ fn list_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
         items.sort_by(|a, b| a.0.cmp(&b.0));
     }
     let target = Value::List(elements.clone());
+    // Elements the compared base had here, each after the surviving
+    // element it followed.
+    let gone: Vec<(Option<&Position>, &Value)> = cx
+        .changes(path)
+        .iter()
+        .filter_map(|change| match change {
+            Change::ElementRemoved { after, value } => Some((after.as_ref(), value)),
+            _ => None,
+        })
+        .collect();
 
     // A pending child forces the list open; the collapse override
     // outranks the layout the content would pick. Collapsed is pure
@@ -2362,10 +3058,11 @@ fn list_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     // candidate keeps zero-budget probe builds closed and cheap. An
     // EMPTY list is the exception both ways: `[]` is its one form —
     // a block of zero rows is not a representation — so it takes the
-    // literal whatever the width says.
+    // literal whatever the width says. Struck-through elements from
+    // a compared base are rows, and take the block form.
     let bare = items.is_empty();
     let writable = writable_at(&cx.sources, path);
-    let mut flat = (avail > 0.0 || bare).then(|| {
+    let mut flat = (gone.is_empty() && (avail > 0.0 || bare)).then(|| {
         let mut cells: Vec<Node<P>> = vec![hover_target(
             path.to_vec(),
             hooks,
@@ -2414,17 +3111,22 @@ fn list_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     // say "list", every multi-line element carries its own
     // delimiter, and each value's ink selects its element — a
     // leading dash would restate all three.
-    let rows: Vec<Node<P>> = items
-        .into_iter()
-        .map(|(position, value)| {
-            let mut child = path.to_vec();
-            child.push(Step::Element(position));
-            match value {
-                Some(value) => value_view(cx, tcx, &child, ancestors, &value, inside, hooks),
-                None => pending_view(cx, tcx, child, hooks),
-            }
-        })
-        .collect();
+    let ghosts_after = |tcx: &mut TextCtx, rows: &mut Vec<Node<P>>, at: Option<&Position>| {
+        for (_, value) in gone.iter().filter(|(after, _)| *after == at) {
+            rows.push(ghost(cx, tcx, &ghost_spelling(cx, value)));
+        }
+    };
+    let mut rows: Vec<Node<P>> = Vec::new();
+    ghosts_after(tcx, &mut rows, None);
+    for (position, value) in items {
+        let mut child = path.to_vec();
+        child.push(Step::Element(position.clone()));
+        rows.push(match value {
+            Some(value) => value_view(cx, tcx, &child, ancestors, &value, inside, hooks),
+            None => pending_view(cx, tcx, child, hooks),
+        });
+        ghosts_after(tcx, &mut rows, Some(&position));
+    }
     // The block form: the brackets span the element column and are
     // the list's click claims; everything between the rows falls
     // through. Collapsing is Space on the selection — no button.
@@ -2478,6 +3180,15 @@ fn record_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     let pending_edge = cx.pending_edge_under(path).is_some();
     let renaming = cx.pending_rename_under(path);
     let target = Value::Record(fields.clone());
+    // Fields the compared base had here, in label order.
+    let gone: Vec<(&Label, &Value)> = cx
+        .changes(path)
+        .iter()
+        .filter_map(|change| match change {
+            Change::FieldRemoved(label, value) => Some((label, value)),
+            _ => None,
+        })
+        .collect();
 
     // A pending inside forces the record open; the collapse override
     // outranks the layout the content would pick. Collapsed is pure
@@ -2517,9 +3228,10 @@ fn record_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     // EMPTY record is the exception both ways: `{}` is its one form —
     // a block of zero rows is not a representation — so it takes the
     // literal whatever the width says. An active label query counts
-    // as content and layouts normally.
+    // as content and layouts normally; struck-through fields from a
+    // compared base are rows, and take the block form.
     let bare = items.is_empty() && !pending_edge;
-    let mut flat = (avail > 0.0 || bare).then(|| {
+    let mut flat = (gone.is_empty() && (avail > 0.0 || bare)).then(|| {
         let mut cells: Vec<Node<P>> = vec![hover_target(
             path.to_vec(),
             hooks,
@@ -2568,10 +3280,21 @@ fn record_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
 
     let inside =
         (avail - 2.0 * (delim_advance(cx.styles, Delim::Brace) + 2.0 * scale)).max(0.0);
-    let mut rows: Vec<Node<P>> = items
-        .into_iter()
-        .map(|(key, value)| field_row(cx, tcx, path, ancestors, key, value, inside, hooks))
-        .collect();
+    let ghost_row = |tcx: &mut TextCtx, (label, value): (&Label, &Value)| {
+        let spelling = format!("{}: {}", label_spelling(cx, label).0, ghost_spelling(cx, value));
+        ghost(cx, tcx, &spelling)
+    };
+    let mut gone = gone.into_iter().peekable();
+    let mut rows: Vec<Node<P>> = Vec::new();
+    for (key, value) in items {
+        while let Some(field) = gone.next_if(|(label, _)| **label < key) {
+            rows.push(ghost_row(tcx, field));
+        }
+        rows.push(field_row(cx, tcx, path, ancestors, key, value, inside, hooks));
+    }
+    for field in gone {
+        rows.push(ghost_row(tcx, field));
+    }
     // A new field being authored: the label query, unsorted until it
     // has a label to sort by.
     if let Some((query, choice)) = cx.pending_edge_under(path) {
@@ -2702,7 +3425,7 @@ fn ground<P: Canvas>(cx: &Cx, path: &[Step], value: &Value, content: Node<P>) ->
 /// handle, or a label. The primary selection's geometry at lower
 /// strength, so the two read as one family.
 fn secondary_mark<P: Canvas>(cx: &Cx, value: &Value, content: Node<P>) -> Node<P> {
-    let strong = cx.secondary.as_ref() == Some(value);
+    let strong = cx.secondary.contains(value);
     let faint = !strong && cx.secondary_hover.as_ref() == Some(value);
     if !strong && !faint {
         return content;
@@ -2772,7 +3495,119 @@ fn value_view<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>(
     // the full bounds, while clicks belong to the content each arm
     // claimed above — structural whitespace deselects.
     let placed = descend_landmark(cx, path.to_vec(), inner);
-    ground(cx, path, value, placed)
+    flag(cx, path, compared(cx, tcx, path, ground(cx, path, value, placed)))
+}
+
+/// The checker's mark: a red underline along the bottom of a value
+/// with a problem at its path — the row reads as wrong without
+/// anything moving.
+fn flag<P: Canvas>(cx: &Cx, path: &[Step], content: Node<P>) -> Node<P> {
+    if !cx.flagged.contains(path) {
+        return content;
+    }
+    let scale = cx.styles.scale;
+    decorate(content, move |p: &mut P, rect| {
+        let y = rect.y1 - 1.0 * scale;
+        p.stroke(
+            vello::kurbo::Line::new((rect.x0 + 2.0 * scale, y), (rect.x1 - 2.0 * scale, y)),
+            Stroke::new(1.5 * scale).with_dashes(0.0, [3.0 * scale, 2.0 * scale]),
+            Color::new(crate::check::FLAG),
+            Affine::IDENTITY,
+        );
+    })
+}
+
+/// The compare overlay's mark on a value: a green wash where the
+/// base had nothing here, and where it had something else, the wash
+/// with the old value struck through after it.
+fn compared<P: Canvas>(cx: &Cx, tcx: &mut TextCtx, path: &[Step], content: Node<P>) -> Node<P> {
+    let mut replaced = None;
+    let mut added = false;
+    for change in cx.changes(path) {
+        match change {
+            Change::Added(_) => added = true,
+            Change::Replaced { from, .. } => replaced = Some(from),
+            _ => {}
+        }
+    }
+    if !added && replaced.is_none() {
+        return content;
+    }
+    let scale = cx.styles.scale;
+    let tinted = decorate(content, move |p: &mut P, rect| {
+        let bg = RoundedRect::from_rect(rect.inset(1.0 * scale), 4.0 * scale);
+        p.fill(bg, Color::new(compare::ADDED), Affine::IDENTITY);
+    });
+    match replaced {
+        Some(from) => {
+            let old = ghost(cx, tcx, &ghost_spelling(cx, from));
+            row(6.0 * scale, vec![tinted, old])
+        }
+        None => tinted,
+    }
+}
+
+/// What the compared base had and the document doesn't, as one
+/// struck-through line in the gone ink — read, never selected, so it
+/// claims nothing and the rows around it keep their targets.
+fn ghost<P: Canvas>(cx: &Cx, tcx: &mut TextCtx, spelling: &str) -> Node<P> {
+    let style = TextStyle {
+        brush: Brush::from(Color::new(compare::GONE)),
+        ..cx.styles.dim.clone()
+    };
+    let scale = cx.styles.scale;
+    decorate(text(tcx, spelling, &style), move |p: &mut P, rect| {
+        let y = (rect.y0 + rect.y1) / 2.0;
+        p.stroke(
+            vello::kurbo::Line::new((rect.x0, y), (rect.x1, y)),
+            Stroke::new(1.0 * scale),
+            Color::new(compare::GONE),
+            Affine::IDENTITY,
+        );
+    })
+}
+
+/// A gone value's one-line spelling: the literal forms, links by
+/// name or short id, cut off past a line's worth.
+fn ghost_spelling(cx: &Cx, value: &Value) -> String {
+    fn spell(cx: &Cx, value: &Value, out: &mut String) {
+        match value {
+            Value::Atom(Atom::Cell(cell)) => {
+                out.push_str(&cx.name(*cell).unwrap_or_else(|| short_id(*cell)))
+            }
+            Value::Atom(Atom::String(s)) => out.push_str(&format!("\"{s}\"")),
+            Value::Atom(Atom::Blob(bytes)) => out.push_str(&blob_text(bytes)),
+            Value::List(elements) => {
+                out.push('[');
+                for (index, element) in elements.values().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    spell(cx, element, out);
+                }
+                out.push(']');
+            }
+            Value::Record(fields) => {
+                out.push('{');
+                for (index, (key, field)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(&label_spelling(cx, key).0);
+                    out.push_str(": ");
+                    spell(cx, field, out);
+                }
+                out.push('}');
+            }
+        }
+    }
+    const LONGEST: usize = 60;
+    let mut out = String::new();
+    spell(cx, value, &mut out);
+    match out.char_indices().nth(LONGEST) {
+        Some((cut, _)) => format!("{}…", &out[..cut]),
+        None => out,
+    }
 }
 
 /// An EMPTY SLOT at `path`: the [`placeholder`] widget wired to this
@@ -2834,7 +3669,10 @@ fn query_content<C: 'static, P: Canvas + HasHandler<C> + HasDescends + HasPopup>
     labels: bool,
     hooks: &Hooks<C>,
 ) -> Node<P> {
-    let entries = completion_entries(&cx.sources, cx.names, cx.raw, labels, query.text());
+    // The engaged query is the selection's, so its path is where the
+    // offers land.
+    let at = cx.selection.map(Selection::path).unwrap_or_default();
+    let entries = completion_entries(&cx.sources, cx.names, cx.raw, labels, at, query.text());
     let fallback = text(tcx, "…", &cx.styles.dim);
     let content = atom_content(Some(query), fallback, None, tcx, cx.styles, hooks);
     // The FRAME holds the slot's width as a minimum — the text field
@@ -3234,19 +4072,22 @@ fn quiet_select_target<C: 'static, P: Canvas + HasHandler<C>>(
 ) -> Node<P> {
     let select = hooks.select.clone();
     let pick = hooks.pick.clone();
+    let add = hooks.add.clone();
     decorate(content, move |p, rect| {
         let select = select.clone();
         let pick = pick.clone();
+        let add = add.clone();
         let target = path.clone();
         let value = value.clone();
         p.handler().on_pointer_down(move |ctx, event| {
             event.button == Some(PointerButton::Primary)
                 && rect.contains(Point::new(event.state.position.x, event.state.position.y))
                 && {
-                    let picked = command(&event.state.modifiers)
-                        && pick(ctx, value.clone());
-                    if !picked {
-                        select(ctx, target.clone(), None);
+                    let command = command(&event.state.modifiers);
+                    match (command && pick(ctx, value.clone()), command) {
+                        (true, _) => {}
+                        (false, true) => add(ctx, target.clone()),
+                        (false, false) => select(ctx, target.clone(), None),
                     }
                     true
                 }
@@ -3257,8 +4098,9 @@ fn quiet_select_target<C: 'static, P: Canvas + HasHandler<C>>(
 /// A click on a string's text reports what happened — this path, this
 /// text-local position — and nothing more; the shell's selection
 /// transition decides what it means. One report serves the first
-/// click and every one after. With the command modifier and a pending
-/// open, picks the atom's value into it instead.
+/// click and every one after. With the command modifier, picks the
+/// atom's value into an open pending instead, else adds the path to
+/// the multi-selection.
 fn cursor_target<C: 'static, P: Canvas + HasHandler<C> + HasDescends>(
     path: Path,
     value: Value,
@@ -3267,16 +4109,21 @@ fn cursor_target<C: 'static, P: Canvas + HasHandler<C> + HasDescends>(
 ) -> Node<P> {
     let select = hooks.select.clone();
     let pick = hooks.pick.clone();
+    let add = hooks.add.clone();
     let hover = hooks.hover.clone();
     decorate(content, move |p, rect| {
         hover_claim(p, rect, hover.clone(), Hover::Value(path.clone()));
         let pick = pick.clone();
+        let add = add.clone();
         let value = value.clone();
         p.handler().on_pointer_down(move |ctx, event| {
             event.button == Some(PointerButton::Primary)
                 && rect.contains(Point::new(event.state.position.x, event.state.position.y))
                 && {
-                    if command(&event.state.modifiers) && pick(ctx, value.clone()) {
+                    if command(&event.state.modifiers) {
+                        if !pick(ctx, value.clone()) {
+                            add(ctx, path.clone());
+                        }
                         return true;
                     }
                     let click = TextClick {
@@ -3316,6 +4163,7 @@ mod tests {
             Document {
                 root: Some(Value::from(cell)),
                 cells,
+                libraries: Vec::new(),
             },
             cell,
         )
@@ -3349,6 +4197,7 @@ mod tests {
             path,
             edit: None,
             recorded: false,
+            also: Vec::new(),
         });
         step_selection(ds, selection.as_ref(), LINE, &arrow(named))
     }
@@ -3479,6 +4328,62 @@ mod tests {
         assert!(step_selection(&ds, None, LINE, &arrow(NamedKey::Escape)).is_none());
     }
 
+    #[test]
+    fn shift_arrows_range_along_the_list_and_batches_land_past_the_set() {
+        let items = position::spread(4);
+        let item = |index: usize| vec![key("xs"), Step::Element(items[index].clone())];
+        let mut ds: Vec<Descend> = (0..4)
+            .map(|index| {
+                let y = 20.0 * index as f64;
+                stop(item(index), 20.0, y, 80.0, y + 16.0)
+            })
+            .collect();
+        ds.push(stop(vec![key("xs")], 0.0, 0.0, 100.0, 80.0));
+        ds.push(stop(vec![], 0.0, 0.0, 120.0, 84.0));
+        let shifted = |named| KeyboardEvent {
+            modifiers: Modifiers::SHIFT,
+            ..arrow(named)
+        };
+        let mut selection = Selection::Edge {
+            path: item(1),
+            edit: None,
+            recorded: false,
+            also: Vec::new(),
+        };
+        let also = |selection: &Selection| -> Vec<Path> {
+            selection.paths()[1..].iter().map(|path| (*path).clone()).collect()
+        };
+
+        // Down grows the range from the primary; up shrinks it back
+        // and grows it the other way; the end spends the press.
+        let extend = |selection: &mut Selection, named| {
+            extend_selection(&ds, Some(selection), &shifted(named))
+        };
+        assert!(extend(&mut selection, NamedKey::ArrowDown));
+        assert!(extend(&mut selection, NamedKey::ArrowDown));
+        assert_eq!(also(&selection), [item(2), item(3)]);
+        assert!(extend(&mut selection, NamedKey::ArrowDown));
+        assert_eq!(also(&selection), [item(2), item(3)]);
+        for _ in 0..3 {
+            assert!(extend(&mut selection, NamedKey::ArrowUp));
+        }
+        assert_eq!(also(&selection), [item(0)]);
+        assert!(!extend_selection(&ds, Some(&mut selection), &arrow(NamedKey::ArrowDown)));
+
+        // Command-click toggles members; the primary stays.
+        assert!(selection.toggle_also(item(3)));
+        assert!(selection.toggle_also(item(1)));
+        assert!(selection.toggle_also(item(0)));
+        assert_eq!(also(&selection), [item(3)]);
+
+        // A batch delete lands on the first survivor past the set.
+        let gone = [item(1), item(2)];
+        assert_eq!(selection_after_deletes(&ds, &gone), item(3));
+        assert_eq!(selection_after_deletes(&ds, &[item(2), item(3)]), item(1));
+        let all: Vec<Path> = (0..4).map(item).collect();
+        assert_eq!(selection_after_deletes(&ds, &all), [key("xs")]);
+    }
+
     #[test]
     fn set_collapse_is_directional_and_stays_sparse() {
         let lib = Cells::new();
@@ -3656,6 +4561,7 @@ mod tests {
         let mut doc = Document {
             root: Some(Value::from(lib_cell)),
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         // The library's cell declines writes wholesale — fields,
         // value, and name alike.
@@ -3753,6 +4659,51 @@ mod tests {
         assert!(!delete_edge(&mut doc, &lib, &[]));
     }
 
+    #[test]
+    fn duplicates_land_beside_and_moves_rekey_between_neighbours() {
+        let lib = Cells::new();
+        let (mut doc, _) = doc_of(vec![
+            (Label::from("size"), Value::from("1")),
+            (Label::from("size 2"), Value::from("2")),
+            (
+                Label::from("dash"),
+                Value::list([Value::from("a"), Value::from("b"), Value::from("c")]),
+            ),
+        ]);
+        let dash = vec![Step::Follow, key("dash")];
+        let at = |doc: &Document, index: usize| {
+            let mut path = dash.clone();
+            let ps = positions(src(doc, &lib).resolve(&dash).unwrap());
+            path.push(Step::Element(ps[index].clone()));
+            path
+        };
+        let strings = |items: &[&str]| Value::list(items.iter().map(|item| Value::from(*item)));
+
+        // The copy follows its source; the field takes the first
+        // free spelling.
+        let at0 = at(&doc, 0);
+        let copy = duplicate_edge(&mut doc, &lib, &at0).unwrap();
+        assert_eq!(copy, at(&doc, 1));
+        assert_eq!(src(&doc, &lib).resolve(&dash), Some(&strings(&["a", "a", "b", "c"])));
+        let field = duplicate_edge(&mut doc, &lib, &[Step::Follow, key("size")]).unwrap();
+        assert_eq!(field, [Step::Follow, key("size 3")]);
+        assert_eq!(src(&doc, &lib).resolve(&field), Some(&Value::from("1")));
+
+        // Moving re-keys only the moved element; the ends decline.
+        let before = positions(src(&doc, &lib).resolve(&dash).unwrap());
+        let at3 = at(&doc, 3);
+        let moved = move_element(&mut doc, &lib, &at3, true).unwrap();
+        assert_eq!(moved, at(&doc, 2));
+        assert_eq!(src(&doc, &lib).resolve(&dash), Some(&strings(&["a", "a", "c", "b"])));
+        let after = positions(src(&doc, &lib).resolve(&dash).unwrap());
+        assert_eq!((&after[..2], &after[3]), (&before[..2], &before[2]));
+        let (first, last) = (at(&doc, 0), at(&doc, 3));
+        assert!(move_element(&mut doc, &lib, &first, true).is_none());
+        assert!(move_element(&mut doc, &lib, &last, false).is_none());
+        let down = move_element(&mut doc, &lib, &first, false).unwrap();
+        assert_eq!(down, at(&doc, 1));
+    }
+
     #[test]
     fn pendings_normalize_through_links_and_gate_on_authority() {
         let mut lib = Cells::new();
@@ -3844,6 +4795,205 @@ mod tests {
         assert_eq!(from_clipboard("loose text"), Value::from("loose text"));
     }
 
+    #[test]
+    fn deep_paste_duplicates_the_closure_under_fresh_ids() {
+        let lib = Cells::new();
+        // A cycle through a cell label: `roof` holds a record keyed by
+        // `kind`, whose value links back to `roof`.
+        let (mut doc, roof) = doc_of(vec![]);
+        let kind = new_cell_id();
+        doc.cells.set_name(roof, "roof");
+        doc.cells.set_name(kind, "kind");
+        doc.cells
+            .set_value(roof, Value::record([(Label::from(kind), Value::from(roof))]));
+        let copied = closure(&src(&doc, &lib), &Value::from(roof));
+        assert_eq!(copied.cells.iter().count(), 2);
+        // The payload is its own spelling; shallow reads don't take it.
+        let text = closure_to_clipboard(&copied);
+        assert!(closure_from_clipboard(&text).is_some());
+        assert!(closure_from_clipboard(&to_clipboard(&Value::from(roof))).is_none());
+
+        doc.cells.set_value(
+            roof,
+            Value::record([
+                (Label::from(kind), Value::from(roof)),
+                (Label::from("copy"), Value::record([])),
+            ]),
+        );
+        let at = vec![Step::Follow, key("copy")];
+        assert!(paste_closure(&mut doc, &lib, &at, &copied));
+        let pasted = src(&doc, &lib).resolve(&at).unwrap().as_cell().unwrap();
+        assert_ne!(pasted, roof);
+        assert_eq!(doc.cells.name(pasted), Some("roof"));
+        // Links and labels inside the copy point at the copies, so the
+        // duplicate's cycle closes on itself.
+        let fields = doc.cells.value(pasted).unwrap().as_record().unwrap();
+        let (label, link) = fields.iter().next().unwrap();
+        let new_kind = label.as_cell().unwrap();
+        assert_ne!(new_kind, kind);
+        assert_eq!(doc.cells.name(new_kind), Some("kind"));
+        assert_eq!(link, &Value::from(pasted));
+    }
+
+    #[test]
+    fn merging_rewrites_every_mention_and_fills_gaps() {
+        let lib = Cells::new();
+        let (mut doc, holder) = doc_of(vec![]);
+        let (stub, definition) = (new_cell_id(), new_cell_id());
+        doc.cells.set_name(stub, "width");
+        doc.cells.set_value(definition, Value::from("a length"));
+        doc.cells.set_value(
+            holder,
+            Value::record([
+                (Label::from(stub), Value::from("2")),
+                (Label::from("see"), Value::list([Value::from(stub)])),
+            ]),
+        );
+        doc.root = Some(Value::list([Value::from(holder), Value::from(stub)]));
+        assert!(merge_cells(&mut doc, &lib, definition, stub, Prefer::Survivor));
+        // One identity: the stub's entry is gone, its name filled in
+        // beside the definition's value, and every link and label
+        // reads the survivor.
+        assert!(doc.cells.entry(stub).is_none());
+        assert_eq!(doc.cells.name(definition), Some("width"));
+        assert_eq!(doc.cells.value(definition), Some(&Value::from("a length")));
+        assert_eq!(
+            doc.cells.value(holder),
+            Some(&Value::record([
+                (Label::from(definition), Value::from("2")),
+                (Label::from("see"), Value::list([Value::from(definition)])),
+            ]))
+        );
+        assert_eq!(
+            doc.root,
+            Some(Value::list([Value::from(holder), Value::from(definition)]))
+        );
+        // A cell with itself, or a library identity, declines.
+        assert!(!merge_cells(&mut doc, &lib, holder, holder, Prefer::Survivor));
+        let mut lib = Cells::new();
+        let external = new_cell_id();
+        lib.set_name(external, "convention");
+        assert!(!merge_cells(&mut doc, &lib, definition, external, Prefer::Survivor));
+    }
+
+    #[test]
+    fn merging_asks_only_where_both_sides_state_a_part() {
+        let lib = Cells::new();
+        let (mut doc, holder) = doc_of(vec![]);
+        let (kept, gone) = (new_cell_id(), new_cell_id());
+        doc.cells.set_name(kept, "width");
+        doc.cells.set_name(gone, "breadth");
+        doc.cells.set_value(kept, Value::list([Value::from(kept)]));
+        doc.cells.set_value(gone, Value::list([Value::from(gone)]));
+        // Each side linking itself reads the same once merged: only
+        // the names differ.
+        assert_eq!(
+            merge_conflicts(&doc, kept, gone),
+            Conflicts {
+                name: true,
+                value: false,
+                fields: false
+            }
+        );
+        // A record keyed by both asks too, unless its two fields
+        // agree; the field kept is the preferred side's.
+        let keyed = |a: Value, b: Value| {
+            Value::record([(Label::from(kept), a), (Label::from(gone), b)])
+        };
+        doc.cells.set_value(holder, keyed(Value::from(kept), Value::from(gone)));
+        assert!(!merge_conflicts(&doc, kept, gone).fields);
+        doc.cells.set_value(holder, keyed(Value::from("1"), Value::from("2")));
+        assert!(merge_conflicts(&doc, kept, gone).fields);
+        assert!(merge_cells(&mut doc, &lib, kept, gone, Prefer::Loser));
+        assert_eq!(doc.cells.name(kept), Some("breadth"));
+        assert_eq!(doc.cells.value(kept), Some(&Value::list([Value::from(kept)])));
+        assert_eq!(
+            doc.cells.value(holder),
+            Some(&Value::record([(Label::from(kept), Value::from("2"))]))
+        );
+        assert_eq!(
+            fold_path(&[Step::Key(Label::from(gone)), Step::Follow], kept, gone),
+            vec![Step::Key(Label::from(kept)), Step::Follow]
+        );
+    }
+
+    #[test]
+    fn extracting_and_inlining_trade_structure_for_identity() {
+        let lib = Cells::new();
+        let point = Value::record([(Label::from("x"), Value::from("1"))]);
+        let (mut doc, holder) = doc_of(vec![(Label::from("at"), point.clone())]);
+        let at = vec![Step::Follow, key("at")];
+
+        // The value moves into an unnamed cell; a link stands in.
+        let cell = extract_cell(&mut doc, &lib, &at).unwrap();
+        assert_eq!(src(&doc, &lib).resolve(&at), Some(&Value::from(cell)));
+        assert_eq!(doc.cells.value(cell), Some(&point));
+        assert_eq!(doc.cells.name(cell), None);
+        // A link is already an identity; a head addresses none.
+        assert!(extract_cell(&mut doc, &lib, &at).is_none());
+        assert!(extract_cell(&mut doc, &lib, &[Step::Name]).is_none());
+
+        // Inlining keeps the entry unless told this link was its only
+        // mention.
+        let before = doc.clone();
+        assert_eq!(inline_cell(&mut doc, &lib, &at, false), Some(cell));
+        assert_eq!(src(&doc, &lib).resolve(&at), Some(&point));
+        assert_eq!(doc.cells.value(cell), Some(&point));
+        let mut doc = before;
+        assert_eq!(inline_cell(&mut doc, &lib, &at, true), Some(cell));
+        assert!(doc.cells.entry(cell).is_none());
+        assert!(doc.cells.value(holder).is_some());
+        // Nothing links there now; a bare cell has nothing to copy.
+        assert!(inline_cell(&mut doc, &lib, &at, true).is_none());
+        let bare = new_cell_id();
+        assert!(set_value(&mut doc, &lib, &at, Value::from(bare)));
+        assert!(inline_cell(&mut doc, &lib, &at, true).is_none());
+    }
+
+    #[test]
+    fn deep_paste_forks_library_cells_keeping_their_ids() {
+        let mut lib = Cells::new();
+        let (entity, kind) = (new_cell_id(), new_cell_id());
+        lib.set_name(kind, "kind");
+        lib.set_name(entity, "convention");
+        lib.set_value(
+            entity,
+            Value::record([
+                (Label::from("a"), Value::from("1")),
+                (Label::Cell(kind), Value::from(kind)),
+            ]),
+        );
+        let mut doc = Document {
+            root: Some(Value::record([])),
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        let copied = closure(&src(&doc, &lib), &Value::from(entity));
+        assert!(paste_closure(&mut doc, &lib, &[key("x")], &copied));
+        // The document now states the same identity: a fork, writable.
+        assert_eq!(
+            src(&doc, &lib).resolve(&[key("x")]),
+            Some(&Value::from(entity))
+        );
+        assert!(!src(&doc, &lib).external(entity));
+        assert_eq!(doc.cells.name(entity), Some("convention"));
+        // What it reaches in the library stays the library's: linked
+        // under its own id, its entry not copied in.
+        assert!(doc.cells.entry(kind).is_none());
+        let fields = doc.cells.value(entity).unwrap().as_record().unwrap();
+        assert_eq!(fields.get(&Label::Cell(kind)), Some(&Value::from(kind)));
+        // Forked once, a second paste duplicates like any document cell.
+        assert!(paste_closure(&mut doc, &lib, &[key("y")], &copied));
+        assert_ne!(
+            src(&doc, &lib).resolve(&[key("y")]),
+            Some(&Value::from(entity))
+        );
+        // A declined write lands nothing.
+        let before = doc.cells.iter().count();
+        assert!(!paste_closure(&mut doc, &lib, &[key("x"), key("z")], &copied));
+        assert_eq!(doc.cells.iter().count(), before);
+    }
+
     #[test]
     fn completion_offers_follow_the_stage() {
         let lib = crate::conventions::library();
@@ -3852,7 +5002,7 @@ mod tests {
         let sources = src(&doc, &lib);
         let names = Names::table();
         let displays = |labels: bool, query: &str| -> Vec<String> {
-            completion_entries(&sources, &names, false, labels, query)
+            completion_entries(&sources, &names, false, labels, &[], query)
                 .into_iter()
                 .map(|entry| entry.display)
                 .collect()
@@ -3872,7 +5022,7 @@ mod tests {
         assert!(label_stage.iter().all(|d| d != "new list"));
         assert!(label_stage.iter().all(|d| d != "new record"));
         assert!(label_stage.iter().any(|d| d == "new cell"));
-        let label_blob = completion_entries(&sources, &names, false, true, "0xff");
+        let label_blob = completion_entries(&sources, &names, false, true, &[], "0xff");
         assert!(matches!(
             &label_blob[0].action,
             EntryAction::Value(value) if value.as_str() == Some("0xff")
@@ -3884,7 +5034,7 @@ mod tests {
         assert_eq!(value_blob[1], "\"0xff\"");
 
         // Reference commits are links.
-        let roof = completion_entries(&sources, &names, false, false, "roof");
+        let roof = completion_entries(&sources, &names, false, false, &[], "roof");
         assert!(matches!(
             &roof[0].action,
             EntryAction::Value(value) if value.as_cell() == Some(cell)
@@ -3897,7 +5047,7 @@ mod tests {
         let unnamed = new_cell_id();
         doc.cells.set_value(unnamed, Value::from("x"));
         let sources = src(&doc, &lib);
-        let entries = completion_entries(&sources, &names, false, false, &short_id(unnamed));
+        let entries = completion_entries(&sources, &names, false, false, &[], &short_id(unnamed));
         let atom = entries
             .iter()
             .position(|e| matches!(&e.action, EntryAction::Value(v) if v.as_str().is_some()))
@@ -3911,6 +5061,91 @@ mod tests {
         assert!(atom < reference);
     }
 
+    #[test]
+    fn schema_offers_lead_where_the_slot_is_typed() {
+        use crate::schema::{ALTERNATIVES, FIELDS, ISA, OPTIONAL, STRING, TYPE};
+        let mut lib = crate::conventions::library();
+        let [shape, circle, square, radius, label] = [(); 5].map(|_| new_cell_id());
+        lib.set_name(radius, "radius");
+        lib.set_value(radius, Value::record([(Label::from(TYPE), Value::from(STRING))]));
+        lib.set_name(label, "label");
+        lib.set_value(label, Value::record([(Label::from(TYPE), Value::from(STRING))]));
+        lib.set_name(circle, "circle");
+        lib.set_value(
+            circle,
+            Value::record([
+                (Label::from(FIELDS), Value::list([Value::from(radius)])),
+                (Label::from(OPTIONAL), Value::list([Value::from(label)])),
+            ]),
+        );
+        lib.set_name(square, "square");
+        lib.set_value(square, Value::record([(Label::from(FIELDS), Value::list([]))]));
+        lib.set_name(shape, "shape");
+        lib.set_value(
+            shape,
+            Value::record([(
+                Label::from(ALTERNATIVES),
+                Value::list([Value::from(circle), Value::from(square)]),
+            )]),
+        );
+        let (mut doc, cell) = doc_of(vec![]);
+        // A cell whose slot is typed `shape`: its record type's field
+        // says so.
+        let holder = new_cell_id();
+        lib.set_name(holder, "holder");
+        lib.set_value(holder, Value::record([(Label::from(FIELDS), Value::list([]))]));
+        let slot = new_cell_id();
+        lib.set_value(slot, Value::record([(Label::from(TYPE), Value::from(shape))]));
+        lib.set_value(
+            holder,
+            Value::record([(Label::from(FIELDS), Value::list([Value::from(slot)]))]),
+        );
+        doc.cells.set_value(
+            cell,
+            Value::record([
+                (Label::from(ISA), Value::from(holder)),
+                (
+                    Label::from(slot),
+                    Value::record([
+                        (Label::from(ISA), Value::from(circle)),
+                        (Label::from(radius), Value::from("1")),
+                    ]),
+                ),
+            ]),
+        );
+        let sources = src(&doc, &lib);
+        let names = Names::table();
+        let at_slot = [Step::Follow, Step::Key(Label::from(slot))];
+        let displays = |labels: bool, at: &[Step], query: &str| -> Vec<String> {
+            completion_entries(&sources, &names, false, labels, at, query)
+                .into_iter()
+                .map(|entry| entry.display)
+                .collect()
+        };
+        // The sum's constructors lead as fresh tagged records.
+        let values = displays(false, &at_slot, "");
+        assert_eq!(values[..2], ["new circle", "new square"]);
+        let fresh = completion_entries(&sources, &names, false, false, &at_slot, "sq");
+        assert!(matches!(
+            &fresh[0].action,
+            EntryAction::Value(value) if value.as_record().is_some_and(
+                |record| record.get(&Label::from(ISA)) == Some(&Value::from(square))
+            )
+        ));
+        // A typed record's missing fields lead the label stage.
+        assert_eq!(displays(true, &at_slot, "")[0], "label");
+        // Its isa slot offers the sum's record types.
+        let mut at_tag = at_slot.to_vec();
+        at_tag.push(Step::Key(Label::from(ISA)));
+        assert_eq!(displays(false, &at_tag, "")[..2], ["circle", "square"]);
+        // A string slot leads with the typed text.
+        let mut at_radius = at_slot.to_vec();
+        at_radius.push(Step::Key(Label::from(radius)));
+        assert_eq!(displays(false, &at_radius, "circle")[0], "\"circle\"");
+        // Untyped slots are the universal layer alone.
+        assert_eq!(displays(false, &[], "")[..], displays(false, &[Step::Name], "")[..]);
+    }
+
     #[test]
     fn cycles_collapse_by_default_and_expand_turn_by_turn() {
         // A: { next: A } — the re-entry at [Follow, next] repeats the
@@ -3921,6 +5156,7 @@ mod tests {
         let doc = Document {
             root: Some(Value::from(a)),
             cells,
+            libraries: Vec::new(),
         };
         let lib = Cells::new();
         let sources = src(&doc, &lib);
@@ -3959,6 +5195,7 @@ mod tests {
         let empty = Document {
             root: None,
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         assert!(!toggle_collapse(
             &src(&empty, &lib),
@@ -4225,6 +5462,7 @@ mod tests {
         let mut doc = Document {
             root: Some(Value::from(bare)),
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         // A writable valueless cell's Follow slot is already
         // authoring: selecting it (the rendered placeholder) pends.
@@ -4251,6 +5489,7 @@ mod tests {
         let empty = Document {
             root: None,
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         assert!(matches!(
             Selection::edge(&src(&empty, &lib), vec![]),
@@ -4309,13 +5548,8 @@ mod tests {
 #[cfg(test)]
 mod svg_bench {
     use super::*;
-    use puri::draw::{DrawCmd, DrawList, GlyphRun, Shape};
+    use puri::draw::{DrawList, GlyphRun, Shape};
     use puri::handler::Handler;
-    use skrifa::instance::{LocationRef, NormalizedCoord, Size};
-    use skrifa::outline::{DrawSettings, OutlinePen};
-    use skrifa::{FontRef, GlyphId, MetadataProvider};
-    use std::fmt::Write as _;
-    use vello::kurbo::{BezPath, Shape as KurboShape};
 
     /// The dispatch context for bench frames: every hover report the
     /// move crossed, in claim order — the first is the winner the
@@ -4374,130 +5608,6 @@ mod svg_bench {
         }
     }
 
-    fn css(brush: &Brush) -> String {
-        match brush {
-            Brush::Solid(color) => {
-                let [r, g, b, a] = color.components;
-                format!(
-                    "rgba({},{},{},{:.3})",
-                    (r * 255.0).round(),
-                    (g * 255.0).round(),
-                    (b * 255.0).round(),
-                    a
-                )
-            }
-            _ => "magenta".to_string(),
-        }
-    }
-
-    struct BezPen {
-        path: BezPath,
-        offset: Point,
-    }
-
-    impl OutlinePen for BezPen {
-        fn move_to(&mut self, x: f32, y: f32) {
-            self.path
-                .move_to((self.offset.x + x as f64, self.offset.y - y as f64));
-        }
-        fn line_to(&mut self, x: f32, y: f32) {
-            self.path
-                .line_to((self.offset.x + x as f64, self.offset.y - y as f64));
-        }
-        fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
-            self.path.quad_to(
-                (self.offset.x + cx0 as f64, self.offset.y - cy0 as f64),
-                (self.offset.x + x as f64, self.offset.y - y as f64),
-            );
-        }
-        fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
-            self.path.curve_to(
-                (self.offset.x + cx0 as f64, self.offset.y - cy0 as f64),
-                (self.offset.x + cx1 as f64, self.offset.y - cy1 as f64),
-                (self.offset.x + x as f64, self.offset.y - y as f64),
-            );
-        }
-        fn close(&mut self) {
-            self.path.close_path();
-        }
-    }
-
-    fn svg_shape(shape: &Shape, transform: Affine) -> String {
-        let mut path = match shape {
-            Shape::Rect(rect) => rect.to_path(0.05),
-            Shape::RoundedRect(rect) => rect.to_path(0.05),
-            Shape::Circle(circle) => circle.to_path(0.05),
-            Shape::Line(line) => {
-                let mut p = BezPath::new();
-                p.move_to(line.p0);
-                p.line_to(line.p1);
-                p
-            }
-            Shape::Path(path) => path.clone(),
-        };
-        path.apply_affine(transform);
-        path.to_svg()
-    }
-
-    fn write_cmds(out: &mut String, cmds: &[DrawCmd]) {
-        for cmd in cmds {
-            match cmd {
-                DrawCmd::Fill {
-                    shape,
-                    brush,
-                    transform,
-                } => writeln!(
-                    out,
-                    r#"<path d="{}" fill="{}"/>"#,
-                    svg_shape(shape, *transform),
-                    css(brush)
-                )
-                .unwrap(),
-                DrawCmd::Stroke {
-                    shape,
-                    style,
-                    brush,
-                    transform,
-                } => writeln!(
-                    out,
-                    r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
-                    svg_shape(shape, *transform),
-                    css(brush),
-                    style.width
-                )
-                .unwrap(),
-                DrawCmd::GlyphRun(run) => {
-                    let Ok(font_ref) = FontRef::from_index(run.font.data.as_ref(), run.font.index)
-                    else {
-                        continue;
-                    };
-                    let outlines = font_ref.outline_glyphs();
-                    let coords: Vec<NormalizedCoord> = run
-                        .normalized_coords
-                        .iter()
-                        .map(|bits| NormalizedCoord::from_bits(*bits))
-                        .collect();
-                    let size = Size::new(run.size);
-                    let mut path = BezPath::new();
-                    for glyph in &run.glyphs {
-                        let mut pen = BezPen {
-                            path: std::mem::take(&mut path),
-                            offset: run.transform * Point::new(glyph.x as f64, glyph.y as f64),
-                        };
-                        if let Some(outline) = outlines.get(GlyphId::new(glyph.id)) {
-                            let settings = DrawSettings::unhinted(size, LocationRef::new(&coords));
-                            let _ = outline.draw(settings, &mut pen);
-                        }
-                        path = pen.path;
-                    }
-                    writeln!(out, r#"<path d="{}" fill="{}"/>"#, path.to_svg(), css(&run.brush))
-                        .unwrap();
-                }
-                DrawCmd::Clip { children, .. } => write_cmds(out, children),
-            }
-        }
-    }
-
     fn place(doc: &Document, selection: Option<&Selection>, width: f64) -> (Bench, Extent) {
         let library = crate::conventions::library();
         let sources = Sources {
@@ -4524,6 +5634,7 @@ mod svg_bench {
             pick: Rc::new(|_, _| false),
             hover: Rc::new(|claims: &mut Claims, claim, _| claims.push(claim)),
             insert: Rc::new(|_, _| {}),
+            add: Rc::new(|_, _| {}),
         };
         // Timed as the layout perf canary: a projection is a
         // per-keystroke cost, and the fallback-heavy narrow widths
@@ -4538,6 +5649,8 @@ mod svg_bench {
             None,
             None,
             &collapse,
+            &HashSet::new(),
+            None,
             &names,
             false,
             &mut tcx,
@@ -4561,16 +5674,8 @@ mod svg_bench {
     fn render(doc: &Document, selection: Option<&Selection>, width: f64, out_path: &str) {
         let (bench, extent) = place(doc, selection, width);
         let (width, height) = (width.max(extent.width + 48.0), extent.height() + 48.0);
-        let mut out = String::new();
-        writeln!(
-            out,
-            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}">"#
-        )
-        .unwrap();
-        writeln!(out, r##"<rect width="{width:.0}" height="{height:.0}" fill="#FFFFFF"/>"##).unwrap();
-        write_cmds(&mut out, &bench.list.0);
-        writeln!(out, "</svg>").unwrap();
-        std::fs::write(out_path, out).unwrap();
+        let view = Rect::new(0.0, 0.0, width, height);
+        std::fs::write(out_path, crate::svg::document(&bench.list, view)).unwrap();
     }
 
     #[test]
@@ -4611,6 +5716,7 @@ mod svg_bench {
             path: path.clone(),
             edit: None,
             recorded: false,
+            also: Vec::new(),
         };
         let mut selection: Option<Selection> = None;
         let mut walk: Vec<Path> = Vec::new();
@@ -4799,6 +5905,7 @@ mod svg_bench {
                 ),
             ])),
             cells: Cells::new(),
+            libraries: Vec::new(),
         }
     }
 
@@ -4967,6 +6074,7 @@ mod svg_bench {
         let empty = Document {
             root: None,
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         render(&empty, None, 320.0, "../target/raw_placeholder_root.svg");
         // The engaged twin: same slot, same rect, selection blue.
@@ -4983,6 +6091,7 @@ mod svg_bench {
             &Document {
                 root: Some(Value::from(bare)),
                 cells,
+                libraries: Vec::new(),
             },
             None,
             320.0,
@@ -5004,11 +6113,13 @@ mod svg_bench {
             &Document {
                 root: Some(Value::from("asdf")),
                 cells: Cells::new(),
+                libraries: Vec::new(),
             },
             Some(&Selection::Edge {
                 path: Vec::new(),
                 edit: None,
                 recorded: false,
+                also: Vec::new(),
             }),
             320.0,
             "../target/raw_placeholder_committed.svg",
@@ -5018,6 +6129,7 @@ mod svg_bench {
         let empty_string = Document {
             root: Some(Value::from("")),
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         let library = crate::conventions::library();
         let sel = Selection::edge(
@@ -0,0 +1,294 @@
+//! Find references: every place a cell's id is stated, read off the
+//! reverse-mention index ([`progred_graph::mentions`]) rather than a
+//! scan of the document. A mention is an owner and a spine; the pane
+//! turns each into a SITE the selection can go to — a tree path from
+//! the root when some chain of links reaches the owner, or a path
+//! from the owner itself when only the graph can show it (the find
+//! bar's convention, [`crate::find`]).
+//!
+//! The chain is found on the index too, walking mentions backwards
+//! from the owner until one sits in the root: breadth first, so the
+//! path is the fewest links deep, and never through a cell twice, so
+//! it is one the projection unfolds (cycle re-entry stops at a
+//! repeat).
+
+use crate::check;
+use crate::conventions::{Names, display_name};
+use crate::panel::{Border, HOVER, ground};
+use crate::raw::{Path, RawStyles, short_id};
+use crate::sources::Sources;
+use progred_graph::mentions::{Mention, Mentions, Owner, Role};
+use progred_graph::{CellId, Label, Step, Value};
+use puri::draw::Canvas;
+use puri::handler::HasHandler;
+use puri::layout::{HAlign, Node, col, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect};
+use vello::peniko::Color;
+
+/// A mention, located for navigation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Site {
+    pub mention: Mention,
+    /// `None` when `path` is from the root; otherwise the owner no
+    /// link chain from the root reaches, which `path` is from.
+    pub origin: Option<CellId>,
+    pub path: Path,
+}
+
+/// The cell a selected edge names: a link's target, or the cell whose
+/// head (its Name step) is selected.
+pub fn target(sources: &Sources, path: &[Step]) -> Option<CellId> {
+    match path.split_last() {
+        Some((Step::Name, parent)) => sources.resolve(parent).and_then(Value::as_cell),
+        _ => sources.resolve(path).and_then(Value::as_cell),
+    }
+}
+
+/// A path from the root to a link to `cell` — the fewest links deep —
+/// or None when no chain of links from the root reaches it.
+pub fn route(mentions: &Mentions, cell: CellId) -> Option<Path> {
+    // Each entry is a cell and the path from a link to it on down to
+    // the link the search began from.
+    let mut queue = VecDeque::from([(cell, Vec::new())]);
+    let mut seen = HashSet::from([cell]);
+    while let Some((cell, below)) = queue.pop_front() {
+        let links = mentions.of(cell).into_iter().filter(|mention| mention.role == Role::Value);
+        for Mention { owner, spine, .. } in links {
+            let path: Path = spine.into_iter().chain(below.iter().cloned()).collect();
+            match owner {
+                Owner::Root => return Some(path),
+                Owner::Cell(holder) if seen.insert(holder) => {
+                    queue.push_back((holder, [Step::Follow].into_iter().chain(path).collect()));
+                }
+                Owner::Cell(_) => {}
+            }
+        }
+    }
+    None
+}
+
+/// A spine inside `owner`'s value as a path the selection can go to:
+/// from the root through the fewest links, or — when no chain reaches
+/// the owner — from the owner itself, which is then the origin.
+pub fn locate(mentions: &Mentions, owner: Owner, inner: Path) -> (Option<CellId>, Path) {
+    match owner {
+        Owner::Root => (None, inner),
+        Owner::Cell(holder) => {
+            let inner = [Step::Follow].into_iter().chain(inner);
+            match route(mentions, holder) {
+                Some(route) => (None, route.into_iter().chain(inner).collect()),
+                None => (Some(holder), inner.collect()),
+            }
+        }
+    }
+}
+
+/// Every mention of `cell`, located: in the index's order, the root's
+/// first. A label mention's path is its field's.
+pub fn sites(mentions: &Mentions, cell: CellId) -> Vec<Site> {
+    mentions
+        .of(cell)
+        .into_iter()
+        .map(|mention| {
+            let mut inner = mention.spine.clone();
+            if mention.role == Role::Label {
+                inner.push(Step::Key(Label::Cell(cell)));
+            }
+            let (origin, path) = locate(mentions, mention.owner, inner);
+            Site {
+                mention,
+                origin,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// A site in words: the containing cell's name — or the root — and
+/// the way down inside it.
+pub fn describe(sources: &Sources, names: &Names, raw: bool, site: &Site) -> String {
+    let Mention { owner, spine, role } = &site.mention;
+    match role {
+        Role::Value if *owner == Owner::Root && spine.is_empty() => "the root".into(),
+        Role::Value => place(sources, names, raw, *owner, spine),
+        Role::Label => format!("{} (label)", place(sources, names, raw, *owner, spine)),
+    }
+}
+
+/// A spine in words: the owner's name — or "root" — and the way down.
+pub fn place(sources: &Sources, names: &Names, raw: bool, owner: Owner, spine: &[Step]) -> String {
+    let (holder, value) = match owner {
+        Owner::Root => ("root".to_string(), sources.root()),
+        Owner::Cell(cell) => (
+            display_name(sources, names, raw, cell).unwrap_or_else(|| short_id(cell)),
+            sources.value(cell),
+        ),
+    };
+    format!("{holder}{}", check::trail(sources, names, raw, value, spine))
+}
+
+/// Dispatch-time callbacks the shell injects: a site clicked or
+/// hovered, or the pane closed from its heading.
+pub struct Hooks<C> {
+    pub jump: Rc<dyn Fn(&mut C, Site)>,
+    pub close: Rc<dyn Fn(&mut C)>,
+    /// The pointer's resting claim inside the panel: the site row
+    /// under it, or `None` for the pane's own ground.
+    pub hover: Rc<dyn Fn(&mut C, Option<usize>)>,
+}
+
+
+/// One pure pass over `cell`'s sites: a heading naming the cell with
+/// the pane's close button, then a row per site as far as the strip
+/// reaches — clicking one goes to it — on a [`ground`].
+#[allow(clippy::too_many_arguments)]
+pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
+    sources: &Sources,
+    cell: CellId,
+    sites: &[Site],
+    hover: Option<usize>,
+    names: &Names,
+    raw: bool,
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let name = display_name(sources, names, raw, cell).unwrap_or_else(|| short_id(cell));
+    let heading = match sites.len() {
+        0 => format!("No references to {name}"),
+        1 => format!("1 reference to {name}"),
+        n => format!("{n} references to {name}"),
+    };
+    let close = hooks.close.clone();
+    let close = decorate(text(tcx, "✕", &styles.dim), move |p: &mut P, rect: Rect| {
+        let close = close.clone();
+        p.handler().on_pointer_down(move |ctx, event| {
+            event.button == Some(PointerButton::Primary)
+                && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                && {
+                    close(ctx);
+                    true
+                }
+        });
+    });
+    let heading = row(8.0 * scale, vec![text(tcx, &heading, &styles.label), close]);
+    let mut used = heading.extent.height();
+    let mut rows: Vec<Node<P>> = vec![heading];
+    let inner = panel.width() - 16.0 * scale;
+    for (index, site) in sites.iter().enumerate() {
+        let style = if site.origin.is_none() {
+            &styles.name
+        } else {
+            &styles.dim
+        };
+        let content = min_width(
+            inner,
+            pad(
+                Insets::new(4.0 * scale, 1.0 * scale, 4.0 * scale, 1.0 * scale),
+                row(
+                    6.0 * scale,
+                    vec![
+                        text(tcx, "●", &styles.dim),
+                        text(tcx, &describe(sources, names, raw, site), style),
+                    ],
+                ),
+            ),
+        );
+        used += content.extent.height() + 2.0 * scale;
+        if used > panel.height() - 16.0 * scale {
+            let rest = sites.len() - index;
+            rows.push(text(tcx, &format!("… {rest} more"), &styles.dim));
+            break;
+        }
+        let lit = hover == Some(index);
+        let site = site.clone();
+        let jump = hooks.jump.clone();
+        let hover = hooks.hover.clone();
+        rows.push(decorate(content, move |p: &mut P, rect| {
+            if lit {
+                p.fill(
+                    RoundedRect::from_rect(rect, 4.0 * scale),
+                    Color::new(HOVER),
+                    Affine::IDENTITY,
+                );
+            }
+            p.handler().on_pointer_move(move |ctx, update| {
+                let point = Point::new(update.current.position.x, update.current.position.y);
+                rect.contains(point) && {
+                    hover(ctx, Some(index));
+                    false
+                }
+            });
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        jump(ctx, site.clone());
+                        true
+                    }
+            });
+        }));
+    }
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(8.0 * scale),
+            col(HAlign::Start, 0, 2.0 * scale, rows),
+        ),
+    );
+
+    let hover = hooks.hover.clone();
+    ground(content, panel, Border::Left, scale, move |ctx| hover(ctx, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::Document;
+    use progred_graph::{Cells, new_cell_id};
+
+    #[test]
+    fn sites_route_through_the_fewest_links_from_the_root() {
+        let (shape, color, stray) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let mut cells = Cells::new();
+        cells.set_name(shape, "shape");
+        cells.set_value(shape, Value::record([(Label::from("fill"), Value::from(color))]));
+        cells.set_name(stray, "stray");
+        cells.set_value(stray, Value::record([(Label::from(color), Value::from("x"))]));
+        let doc = Document {
+            root: Some(Value::record([(Label::from("main"), Value::from(shape))])),
+            cells,
+            libraries: Vec::new(),
+        };
+        let library = Cells::new();
+        let sources = Sources {
+            doc: &doc,
+            library: &library,
+        };
+        let mentions = Mentions::new(&doc.cells, doc.root.as_ref());
+        let key = |label: &str| Step::Key(Label::from(label));
+
+        assert_eq!(route(&mentions, shape), Some(vec![key("main")]));
+        assert_eq!(route(&mentions, stray), None);
+        let found = sites(&mentions, color);
+        assert_eq!(found.len(), 2);
+        let from = |origin| found.iter().find(|site| site.origin == origin).unwrap();
+        // Reached from the root through `shape`'s link.
+        assert_eq!(from(None).path, [key("main"), Step::Follow, key("fill")]);
+        // Only the graph shows `stray`: its label site is from itself.
+        assert_eq!(from(Some(stray)).path, [Step::Follow, Step::Key(Label::Cell(color))]);
+        let names = Names::default();
+        assert_eq!(describe(&sources, &names, false, from(None)), "shape › fill");
+        assert_eq!(describe(&sources, &names, false, from(Some(stray))), "stray (label)");
+
+        assert_eq!(target(&sources, &[key("main"), Step::Name]), Some(shape));
+        assert_eq!(target(&sources, &[key("main"), Step::Follow, key("fill")]), Some(color));
+        assert_eq!(target(&sources, &[]), None);
+    }
+}
@@ -0,0 +1,574 @@
+//! Rename a label everywhere: one label rewritten in every record the
+//! document states — the root and every document cell — where
+//! [`crate::raw::rename_field`] re-keys one record's field. Library records
+//! are the library's business and are left as they are.
+//!
+//! The new label is typed as text. As a string it is that string; as
+//! a CELL it is the identity whose name the text is — an existing
+//! cell when exactly one is so named, else a new cell minted under
+//! that name. Turning `"colour"` into a cell-keyed field is the
+//! migration from spelling toward identity, run across the document
+//! in one step.
+//!
+//! A record that already has the new label keeps both fields as they
+//! are: like a single rename, a bulk one never destroys a sibling.
+//! The pane previews every record the rewrite would touch and every
+//! one it would leave for that reason before anything is written; the
+//! shell records the rewrite as one undo step.
+
+use crate::conventions::Names;
+use crate::panel::{Border, HOVER, SEPARATOR, ground};
+use crate::raw::{Document, Path, RawStyles};
+use crate::references;
+use crate::sources::Sources;
+use im::OrdMap;
+use progred_graph::mentions::{Mentions, Owner};
+use progred_graph::{CellId, Label, Step, Value, new_cell_id};
+use puri::draw::Canvas;
+use puri::edit::{EditCtx, LineEditState, text_edit};
+use puri::handler::HasHandler;
+use puri::layout::{HAlign, Node, col, decorate, min_width, pad, row};
+use puri::text::{TextCtx, text};
+use std::rc::Rc;
+use ui_events::keyboard::{Key, KeyboardEvent, NamedKey};
+use ui_events::pointer::PointerButton;
+use vello::kurbo::{Affine, Insets, Point, Rect, RoundedRect, Stroke};
+use vello::peniko::{Brush, Color};
+
+/// A record holding the label: its owner and the spine to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spot {
+    pub owner: Owner,
+    pub spine: Path,
+}
+
+/// What a rewrite would do, record by record: the records it
+/// re-keys, and the ones it leaves because the new label is taken
+/// there — root first, then the cells in id order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preview {
+    pub renamed: Vec<Spot>,
+    pub kept: Vec<Spot>,
+}
+
+/// The label a rewrite writes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Label(Label),
+    /// A cell not minted yet, to be named this: no record can have
+    /// its label, so nothing clashes.
+    Mint(String),
+}
+
+/// The typed text read as a target, or why it can't be one.
+pub fn target(sources: &Sources, from: &Label, text: &str, cell: bool) -> Result<Target, String> {
+    if text.is_empty() {
+        return Err("Type the new label".into());
+    }
+    let target = if cell {
+        let mut named: Vec<CellId> = sources.cells().copied().collect();
+        named.sort();
+        named.dedup();
+        named.retain(|id| sources.name(*id) == Some(text));
+        match named.as_slice() {
+            [] => Target::Mint(text.to_string()),
+            [id] => Target::Label(Label::Cell(*id)),
+            several => return Err(format!("{} cells are named “{text}”", several.len())),
+        }
+    } else {
+        Target::Label(Label::from(text))
+    };
+    match &target {
+        Target::Label(label) if label == from => Err("That is the label already".into()),
+        _ => Ok(target),
+    }
+}
+
+/// Every record holding `from`, sorted by whether `to` — `None` for a
+/// fresh cell — is free there.
+pub fn preview(doc: &Document, from: &Label, to: Option<&Label>) -> Preview {
+    let mut preview = Preview::default();
+    let owners = doc.root.iter().map(|root| (Owner::Root, root));
+    let cells = doc
+        .cells
+        .iter()
+        .filter_map(|(cell, held)| Some((Owner::Cell(*cell), held.value()?)));
+    for (owner, value) in owners.chain(cells) {
+        records(value, &mut Vec::new(), &mut |spine, fields| {
+            if !fields.contains_key(from) {
+                return;
+            }
+            let spot = Spot {
+                owner,
+                spine: spine.to_vec(),
+            };
+            match to {
+                Some(to) if fields.contains_key(to) => preview.kept.push(spot),
+                _ => preview.renamed.push(spot),
+            }
+        });
+    }
+    preview
+}
+
+fn records(
+    value: &Value,
+    spine: &mut Vec<Step>,
+    found: &mut impl FnMut(&[Step], &OrdMap<Label, Value>),
+) {
+    match value {
+        Value::Atom(_) => {}
+        Value::List(elements) => {
+            for (position, item) in elements {
+                spine.push(Step::Element(position.clone()));
+                records(item, spine, found);
+                spine.pop();
+            }
+        }
+        Value::Record(fields) => {
+            found(spine, fields);
+            for (label, item) in fields {
+                spine.push(Step::Key(label.clone()));
+                records(item, spine, found);
+                spine.pop();
+            }
+        }
+    }
+}
+
+/// `value` with `from` re-keyed to `to` wherever `to` is free,
+/// counting the records rewritten. Only what changed is restated: a
+/// subtree with nothing to re-key comes back as the original's clone,
+/// sharing its structure.
+fn relabeled(value: &Value, from: &Label, to: &Label, count: &mut usize) -> Value {
+    match value {
+        Value::Atom(_) => value.clone(),
+        Value::List(elements) => {
+            let mut rewritten = elements.clone();
+            for (position, item) in elements {
+                let before = *count;
+                let item = relabeled(item, from, to, count);
+                if *count > before {
+                    rewritten.insert(position.clone(), item);
+                }
+            }
+            Value::List(rewritten)
+        }
+        Value::Record(fields) => {
+            let mut rewritten = fields.clone();
+            for (label, item) in fields {
+                let before = *count;
+                let item = relabeled(item, from, to, count);
+                if *count > before {
+                    rewritten.insert(label.clone(), item);
+                }
+            }
+            if !fields.contains_key(to)
+                && let Some(item) = rewritten.remove(from)
+            {
+                *count += 1;
+                rewritten.insert(to.clone(), item);
+            }
+            Value::Record(rewritten)
+        }
+    }
+}
+
+/// Rewrites `from` to `to` across the root and every document cell,
+/// returning how many records changed. Cells with nothing to rewrite
+/// are not restated.
+pub fn relabel(doc: &mut Document, from: &Label, to: &Label) -> usize {
+    let mut total = 0;
+    if let Some(root) = &doc.root {
+        let mut count = 0;
+        let rewritten = relabeled(root, from, to, &mut count);
+        if count > 0 {
+            doc.root = Some(rewritten);
+            total += count;
+        }
+    }
+    let mut restated = Vec::new();
+    for (cell, held) in doc.cells.iter() {
+        let mut count = 0;
+        if let Some(value) = held.value() {
+            let rewritten = relabeled(value, from, to, &mut count);
+            if count > 0 {
+                restated.push((*cell, rewritten));
+                total += count;
+            }
+        }
+    }
+    for (cell, value) in restated {
+        doc.cells.set_value(cell, value);
+    }
+    total
+}
+
+/// The rewrite a target asks for, minting its cell only when a record
+/// will use it. Returns the label written and the records changed.
+pub fn apply(doc: &mut Document, from: &Label, target: Target) -> Option<(Label, usize)> {
+    let to = match target {
+        Target::Label(label) => label,
+        Target::Mint(name) => {
+            if preview(doc, from, None).renamed.is_empty() {
+                return None;
+            }
+            let cell = new_cell_id();
+            doc.cells.set_name(cell, &name);
+            Label::Cell(cell)
+        }
+    };
+    let count = relabel(doc, from, &to);
+    (count > 0).then_some((to, count))
+}
+
+/// A path as it reads after the rewrite: keys spelled `from` read
+/// `to`, unless the path no longer resolves that way — a kept record
+/// still holds `from`. Only meaningful for a path the rewrite left
+/// standing, the way [`crate::raw::fold_path`] is for a merge.
+pub fn relabel_path(sources: &Sources, path: &[Step], from: &Label, to: &Label) -> Path {
+    let moved: Path = path
+        .iter()
+        .map(|step| match step {
+            Step::Key(label) if label == from => Step::Key(to.clone()),
+            _ => step.clone(),
+        })
+        .collect();
+    if sources.resolve(&moved).is_some() {
+        moved
+    } else {
+        path.to_vec()
+    }
+}
+
+/// The pane's state: the label being renamed and the new one as typed.
+pub struct Relabel {
+    pub from: Label,
+    pub to: LineEditState,
+    /// Whether the typed text names a cell rather than spells a
+    /// string.
+    pub cell: bool,
+    /// Whether keys go to the field, as with the find bar.
+    pub focused: bool,
+}
+
+impl Relabel {
+    /// Seeded with a string label's spelling, ready to be edited.
+    pub fn new(from: Label) -> Self {
+        let seed = match &from {
+            Label::String(spelling) => spelling.clone(),
+            Label::Cell(_) => String::new(),
+        };
+        Self {
+            from,
+            to: LineEditState::new(&seed, 14.0, Brush::from(Color::new(INK))).with_cursor_at_end(),
+            cell: false,
+            focused: true,
+        }
+    }
+}
+
+/// What a key or button asks of the pane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Apply,
+    /// Read the text as a cell's name, or back as a string.
+    Toggle,
+    Close,
+}
+
+/// The pane's own keys, over whatever its field declines: Return
+/// renames, Escape closes.
+pub fn command(event: &KeyboardEvent) -> Option<Command> {
+    if !event.state.is_down() {
+        return None;
+    }
+    match &event.key {
+        Key::Named(NamedKey::Enter) => Some(Command::Apply),
+        Key::Named(NamedKey::Escape) => Some(Command::Close),
+        _ => None,
+    }
+}
+
+/// Dispatch-time callbacks the shell injects, mirroring the find bar
+/// for the field and the references pane for the rows.
+pub struct Hooks<C> {
+    /// A press in the field, at a point relative to its text origin.
+    pub press: Rc<dyn Fn(&mut C, Point, u8)>,
+    pub edit: Rc<dyn for<'a> Fn(&'a mut C) -> Option<EditCtx<'a>>>,
+    /// Keys the field declines, while the pane is focused.
+    pub key: Rc<dyn Fn(&mut C, &KeyboardEvent) -> bool>,
+    pub act: Rc<dyn Fn(&mut C, Command)>,
+    /// A previewed record clicked: its origin and path, as
+    /// [`references::locate`] gives them.
+    pub jump: Rc<dyn Fn(&mut C, Option<CellId>, Path)>,
+    pub hover: Rc<dyn Fn(&mut C, Option<usize>)>,
+}
+
+const FIELD_BG: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const FOCUS: [f32; 4] = [0.0, 0.48, 1.0, 0.55];
+const INK: [f32; 4] = [0.13, 0.14, 0.16, 1.0];
+
+/// One pure pass over the pane: a heading with the close button, the
+/// field, the string-or-cell switch and the rename button, then the
+/// preview — a row per record the rewrite touches, clicking one goes
+/// to the field, and the records it leaves, dimmed — on a [`ground`].
+/// The pane takes the keys the field declines while focused, as the
+/// find bar does.
+#[allow(clippy::too_many_arguments)]
+pub fn pane<C: 'static, P: Canvas + HasHandler<C>>(
+    sources: &Sources,
+    state: &Relabel,
+    mentions: &Mentions,
+    hover: Option<usize>,
+    names: &Names,
+    raw: bool,
+    tcx: &mut TextCtx,
+    styles: &RawStyles,
+    panel: Rect,
+    hooks: &Hooks<C>,
+) -> Node<P> {
+    let scale = styles.scale;
+    let target = target(sources, &state.from, state.to.text(), state.cell);
+    let preview = match &target {
+        Ok(Target::Label(to)) => preview(sources.doc, &state.from, Some(to)),
+        _ => preview(sources.doc, &state.from, None),
+    };
+    let spelling = match &state.from {
+        Label::String(spelling) => format!("“{spelling}”"),
+        Label::Cell(cell) => crate::conventions::display_name(sources, names, raw, *cell)
+            .unwrap_or_else(|| crate::raw::short_id(*cell)),
+    };
+
+    let button = |tcx: &mut TextCtx, said: &str, command: Command| {
+        let act = hooks.act.clone();
+        decorate(
+            pad(
+                Insets::new(6.0 * scale, 3.0 * scale, 6.0 * scale, 3.0 * scale),
+                text(tcx, said, &styles.name),
+            ),
+            move |p: &mut P, rect: Rect| {
+                let shape = RoundedRect::from_rect(rect, 4.0 * scale);
+                p.fill(shape, Color::new(FIELD_BG), Affine::IDENTITY);
+                p.stroke(shape, Stroke::new(1.0 * scale), Color::new(SEPARATOR), Affine::IDENTITY);
+                let act = act.clone();
+                p.handler().on_pointer_down(move |ctx, event| {
+                    event.button == Some(PointerButton::Primary)
+                        && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                        && {
+                            act(ctx, command);
+                            true
+                        }
+                });
+            },
+        )
+    };
+    let act = hooks.act.clone();
+    let close = decorate(text(tcx, "✕", &styles.dim), move |p: &mut P, rect: Rect| {
+        let act = act.clone();
+        p.handler().on_pointer_down(move |ctx, event| {
+            event.button == Some(PointerButton::Primary)
+                && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                && {
+                    act(ctx, Command::Close);
+                    true
+                }
+        });
+    });
+    let heading = text(tcx, &format!("Rename {spelling} everywhere"), &styles.label);
+    let heading = row(8.0 * scale, vec![heading, close]);
+
+    let focused = state.focused;
+    let edit = hooks.edit.clone();
+    let content = text_edit(
+        &state.to,
+        focused,
+        &styles.edit,
+        Some(("New label", &styles.dim)),
+        tcx,
+        move |c| edit(c),
+    );
+    let press = hooks.press.clone();
+    let inner = panel.width() - 16.0 * scale;
+    let field = decorate(
+        min_width(
+            inner,
+            pad(Insets::new(6.0 * scale, 3.0 * scale, 6.0 * scale, 3.0 * scale), content),
+        ),
+        move |p: &mut P, rect: Rect| {
+            let shape = RoundedRect::from_rect(rect, 4.0 * scale);
+            p.fill(shape, Color::new(FIELD_BG), Affine::IDENTITY);
+            let edge = if focused { FOCUS } else { SEPARATOR };
+            p.stroke(shape, Stroke::new(1.0 * scale), Color::new(edge), Affine::IDENTITY);
+            let origin = Point::new(rect.x0 + 6.0 * scale, rect.y0 + 3.0 * scale);
+            p.handler().on_pointer_down(move |ctx, event| {
+                let point = Point::new(event.state.position.x, event.state.position.y);
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(point)
+                    && {
+                        press(ctx, point - origin.to_vec2(), event.state.count.max(1));
+                        true
+                    }
+            });
+        },
+    );
+    let switch = if state.cell { "☑ As a cell" } else { "☐ As a cell" };
+    let count = preview.renamed.len();
+    let rename = match count {
+        1 => "Rename 1 record".to_string(),
+        n => format!("Rename {n} records"),
+    };
+    let status = match &target {
+        Err(why) => why.clone(),
+        Ok(Target::Mint(name)) => format!("A new cell “{name}”"),
+        Ok(Target::Label(Label::Cell(cell))) => {
+            format!("The cell {}", crate::raw::short_id(*cell))
+        }
+        Ok(Target::Label(_)) => String::new(),
+    };
+    let mut controls = vec![button(tcx, switch, Command::Toggle)];
+    if target.is_ok() && count > 0 {
+        controls.push(button(tcx, &rename, Command::Apply));
+    }
+    let mut rows: Vec<Node<P>> = vec![
+        heading,
+        field,
+        row(8.0 * scale, controls),
+        text(tcx, &status, &styles.dim),
+    ];
+    let mut used: f64 = rows.iter().map(|node| node.extent.height() + 4.0 * scale).sum();
+    let spots = preview.renamed.iter().map(|spot| (spot, true));
+    let spots: Vec<_> = spots.chain(preview.kept.iter().map(|spot| (spot, false))).collect();
+    for (index, (spot, renamed)) in spots.iter().enumerate() {
+        let mut said = references::place(sources, names, raw, spot.owner, &spot.spine);
+        if !renamed {
+            said.push_str(" (label taken)");
+        }
+        let style = if *renamed { &styles.name } else { &styles.dim };
+        let content = min_width(
+            inner,
+            pad(
+                Insets::new(4.0 * scale, 1.0 * scale, 4.0 * scale, 1.0 * scale),
+                row(6.0 * scale, vec![text(tcx, "●", &styles.dim), text(tcx, &said, style)]),
+            ),
+        );
+        used += content.extent.height() + 4.0 * scale;
+        if used > panel.height() - 16.0 * scale {
+            let rest = spots.len() - index;
+            rows.push(text(tcx, &format!("… {rest} more"), &styles.dim));
+            break;
+        }
+        let lit = hover == Some(index);
+        let mut inner = spot.spine.clone();
+        inner.push(Step::Key(state.from.clone()));
+        let (origin, path) = references::locate(mentions, spot.owner, inner);
+        let jump = hooks.jump.clone();
+        let hover = hooks.hover.clone();
+        rows.push(decorate(content, move |p: &mut P, rect| {
+            if lit {
+                p.fill(
+                    RoundedRect::from_rect(rect, 4.0 * scale),
+                    Color::new(HOVER),
+                    Affine::IDENTITY,
+                );
+            }
+            p.handler().on_pointer_move(move |ctx, update| {
+                let point = Point::new(update.current.position.x, update.current.position.y);
+                rect.contains(point) && {
+                    hover(ctx, Some(index));
+                    false
+                }
+            });
+            p.handler().on_pointer_down(move |ctx, event| {
+                event.button == Some(PointerButton::Primary)
+                    && rect.contains(Point::new(event.state.position.x, event.state.position.y))
+                    && {
+                        jump(ctx, origin, path.clone());
+                        true
+                    }
+            });
+        }));
+    }
+    let content = min_width(
+        panel.width(),
+        pad(
+            Insets::uniform(8.0 * scale),
+            col(HAlign::Start, 0, 4.0 * scale, rows),
+        ),
+    );
+
+    let key = hooks.key.clone();
+    let hover = hooks.hover.clone();
+    let content = ground(content, panel, Border::Left, scale, move |ctx| hover(ctx, None));
+    // Outside the ground, the key handler registers before the
+    // field's too, so it wins.
+    decorate(content, move |p: &mut P, _| {
+        if focused {
+            p.handler().on_key(move |ctx, event| key(ctx, event));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::Cells;
+
+    #[test]
+    fn previews_then_rewrites_every_record_but_the_ones_it_would_clobber() {
+        let library = Cells::new();
+        let (shape, pen, paint) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let mut doc = Document {
+            root: Some(Value::record([
+                (Label::from("colour"), Value::from("red")),
+                (Label::from("main"), Value::from(shape)),
+            ])),
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        let inner = Value::record([(Label::from("colour"), Value::from("blue"))]);
+        doc.cells.set_value(shape, Value::list([inner.clone(), Value::from("plain")]));
+        doc.cells.set_value(
+            pen,
+            Value::record([
+                (Label::from("colour"), Value::from("black")),
+                (Label::from("color"), Value::from("also black")),
+            ]),
+        );
+        doc.cells.set_name(paint, "colour");
+        let (from, to) = (Label::from("colour"), Label::from("color"));
+
+        let found = preview(&doc, &from, Some(&to));
+        assert_eq!(found.renamed.len(), 2);
+        assert_eq!(found.renamed[0], Spot { owner: Owner::Root, spine: Vec::new() });
+        assert_eq!(found.renamed[1].owner, Owner::Cell(shape));
+        assert!(matches!(found.renamed[1].spine[..], [Step::Element(_)]));
+        // The record that has both keeps both.
+        assert_eq!(found.kept, [Spot { owner: Owner::Cell(pen), spine: Vec::new() }]);
+
+        let sources = Sources { doc: &doc, library: &library };
+        assert_eq!(target(&sources, &from, "color", false), Ok(Target::Label(to.clone())));
+        assert!(target(&sources, &from, "colour", false).is_err());
+        assert_eq!(
+            target(&sources, &from, "colour", true),
+            Ok(Target::Label(Label::Cell(paint)))
+        );
+        assert_eq!(target(&sources, &from, "hue", true), Ok(Target::Mint("hue".into())));
+
+        let mut strings = doc.clone();
+        assert_eq!(relabel(&mut strings, &from, &to), 2);
+        assert_eq!(preview(&strings, &from, Some(&to)).renamed, []);
+        assert_eq!(strings.cells.value(pen), doc.cells.value(pen));
+
+        // Toward identity: a new cell keys every record, named as typed.
+        let (label, count) = apply(&mut doc, &from, Target::Mint("hue".into())).unwrap();
+        assert_eq!(count, 3);
+        let Label::Cell(hue) = label else {
+            panic!("a cell label");
+        };
+        assert_eq!(doc.cells.name(hue), Some("hue"));
+        let root = doc.root.as_ref().unwrap().as_record().unwrap();
+        assert_eq!(root.get(&Label::Cell(hue)), Some(&Value::from("red")));
+        assert!(apply(&mut doc, &from, Target::Mint("hue".into())).is_none());
+    }
+}
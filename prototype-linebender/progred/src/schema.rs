@@ -0,0 +1,477 @@
+//! The minimal schema the TypeScript prototype proved sufficient —
+//! record types (its ctors) with fields, sums over them, an expected
+//! element type for lists, and the atom kinds — expressed as ORDINARY
+//! CELLS: a schema is data in a library like any other, read through
+//! [`Sources`], authored in the raw projection, never a side format.
+//! What the editor knows is only the vocabulary: a handful of
+//! well-known cells, minted once and fixed here, living in the
+//! built-in library ([`crate::conventions::library`]).
+//!
+//! The shapes, in that vocabulary:
+//!
+//! - A record type is a cell whose value is a record keyed `fields`
+//!   (and/or `optional`), each a list of links to field cells.
+//! - A field cell's name is the label's name; its value is a record
+//!   `{type: T}`. The field's identity IS the label — instances key
+//!   by the field cell, the separated-identity gradient of the
+//!   naming doctrine.
+//! - A sum is a cell whose value is a record keyed `alternatives`,
+//!   a list of types — record types, other sums, atom kinds.
+//! - A list type is a record `{element: T}`, inline or in a cell.
+//! - The atom kinds are the well-known `string` and `blob` cells.
+//! - An instance says what it is with an `isa` key linking its
+//!   record type. Everything is tagged, so structural description
+//!   gets strong identity for free.
+//!
+//! Kinds are told apart by the keys present, not by a meta-`isa`: the
+//! schema stays the smallest thing that works. No generics; the list
+//! case is the one special container (see `docs/model.md`, Types And
+//! Autocomplete). Unreadable schema data reads as no type — the
+//! schema informs completion and checking, it never blocks an edit.
+
+use crate::sources::Sources;
+use progred_graph::{Atom, CellId, Cells, Label, Step, Value};
+
+/// The record key naming an instance's record type.
+pub const ISA: CellId = CellId::from_u128(0x6f1c_2a4e_93d7_4b0a_8e15_c7f2_0d9b_3a61);
+/// A record type's required fields: a list of field cells.
+pub const FIELDS: CellId = CellId::from_u128(0x2b8e_71d4_05a3_4c9f_b6e2_914d_7a0c_58f3);
+/// A record type's optional fields: a list of field cells.
+pub const OPTIONAL: CellId = CellId::from_u128(0x9d47_e0b2_6c18_4f53_a2d9_3e7b_c156_08a4);
+/// A field cell's type.
+pub const TYPE: CellId = CellId::from_u128(0x41a6_c93f_d875_4e20_9b1c_6f8e_24d7_b590);
+/// A sum's alternatives: a list of types.
+pub const ALTERNATIVES: CellId = CellId::from_u128(0xc3f0_5b87_1e2d_4a96_87c4_d0a1_6b39_e72f);
+/// A list type's element type.
+pub const ELEMENT: CellId = CellId::from_u128(0x7e29_d1c6_4b0f_4385_a93e_5c27_f8b4_1d06);
+/// The string atom kind.
+pub const STRING: CellId = CellId::from_u128(0x15d8_b3a0_f76e_4c12_9e4b_a28f_63c1_d7e9);
+/// The blob atom kind.
+pub const BLOB: CellId = CellId::from_u128(0xe85b_4f12_a3c9_4d7e_b061_7d3a_9e2f_c458);
+
+/// The vocabulary and the words it reads as.
+const VOCABULARY: [(CellId, &str); 8] = [
+    (ISA, "isa"),
+    (FIELDS, "fields"),
+    (OPTIONAL, "optional"),
+    (TYPE, "type"),
+    (ALTERNATIVES, "alternatives"),
+    (ELEMENT, "element"),
+    (STRING, "string"),
+    (BLOB, "blob"),
+];
+
+/// The vocabulary's cells, named so they read (and complete) as
+/// words; valueless — what they mean is this module.
+pub fn vocabulary() -> Cells {
+    let mut cells = Cells::new();
+    for (cell, name) in VOCABULARY {
+        cells.set_name(cell, name);
+    }
+    cells
+}
+
+/// Whether `cell` is one of the vocabulary's own.
+pub fn vocabulary_cell(cell: CellId) -> bool {
+    VOCABULARY.iter().any(|(word, _)| *word == cell)
+}
+
+/// A type, read out of schema cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    String,
+    Blob,
+    /// A record type: instances are records whose `isa` links it.
+    Record(CellId),
+    /// A sum: values of any of its alternatives.
+    Sum(CellId),
+    /// A list whose elements are expected to be of the boxed type;
+    /// `None` when the element type doesn't read.
+    List(Option<Box<Type>>),
+}
+
+/// What a slot expects: a value of a type, or — in a record's `isa`
+/// slot — a record type the record's context admits (`None` when
+/// nothing constrains the record, so any record type fits).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expected {
+    Value(Type),
+    Tag(Option<Type>),
+}
+
+/// A record type's field: the label instances key by, its type, and
+/// whether it must be present.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub label: CellId,
+    pub ty: Option<Type>,
+    pub required: bool,
+}
+
+/// Reads a type written as `spec`: a link to an atom kind, record
+/// type, sum, or list-type cell, or an inline list type. Self-nesting
+/// list types (a cell `{element: itself}`) stop at the cycle with the
+/// element unread.
+pub fn read(sources: &Sources, spec: &Value) -> Option<Type> {
+    read_within(sources, spec, &mut Vec::new())
+}
+
+fn read_within(sources: &Sources, spec: &Value, seen: &mut Vec<CellId>) -> Option<Type> {
+    let record = match spec {
+        Value::Atom(Atom::Cell(STRING)) => return Some(Type::String),
+        Value::Atom(Atom::Cell(BLOB)) => return Some(Type::Blob),
+        Value::Atom(Atom::Cell(cell)) => {
+            if seen.contains(cell) {
+                return None;
+            }
+            let record = sources.value(*cell)?.as_record()?;
+            if record.contains_key(&Label::Cell(FIELDS))
+                || record.contains_key(&Label::Cell(OPTIONAL))
+            {
+                return Some(Type::Record(*cell));
+            }
+            if record.contains_key(&Label::Cell(ALTERNATIVES)) {
+                return Some(Type::Sum(*cell));
+            }
+            seen.push(*cell);
+            record
+        }
+        Value::Record(record) => record,
+        _ => return None,
+    };
+    let element = record.get(&Label::Cell(ELEMENT))?;
+    Some(Type::List(read_within(sources, element, seen).map(Box::new)))
+}
+
+/// A record type's fields, required first, in declaration order.
+pub fn fields(sources: &Sources, record_type: CellId) -> Vec<Field> {
+    let Some(record) = sources.value(record_type).and_then(Value::as_record) else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    for (key, required) in [(FIELDS, true), (OPTIONAL, false)] {
+        let Some(list) = record.get(&Label::Cell(key)).and_then(Value::as_list) else {
+            continue;
+        };
+        for label in list.values().filter_map(Value::as_cell) {
+            let ty = sources
+                .value(label)
+                .and_then(Value::as_record)
+                .and_then(|field| field.get(&Label::Cell(TYPE)))
+                .and_then(|spec| read(sources, spec));
+            fields.push(Field {
+                label,
+                ty,
+                required,
+            });
+        }
+    }
+    fields
+}
+
+/// The record types a type admits, sums flattened in order — the
+/// constructors completion offers. Sums that include each other
+/// contribute once.
+pub fn constructors(sources: &Sources, ty: &Type) -> Vec<CellId> {
+    let mut out = Vec::new();
+    collect_constructors(sources, ty, &mut Vec::new(), &mut out);
+    out
+}
+
+fn collect_constructors(
+    sources: &Sources,
+    ty: &Type,
+    seen: &mut Vec<CellId>,
+    out: &mut Vec<CellId>,
+) {
+    match ty {
+        Type::Record(cell) if !out.contains(cell) => out.push(*cell),
+        Type::Sum(sum) if !seen.contains(sum) => {
+            seen.push(*sum);
+            for alternative in alternatives(sources, *sum) {
+                collect_constructors(sources, &alternative, seen, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A sum's alternatives as written; unreadable entries drop.
+pub fn alternatives(sources: &Sources, sum: CellId) -> Vec<Type> {
+    sources
+        .value(sum)
+        .and_then(Value::as_record)
+        .and_then(|record| record.get(&Label::Cell(ALTERNATIVES)))
+        .and_then(Value::as_list)
+        .map(|list| list.values().filter_map(|spec| read(sources, spec)).collect())
+        .unwrap_or_default()
+}
+
+/// Every record type the sources define, for an unconstrained `isa`.
+pub fn record_types(sources: &Sources) -> Vec<CellId> {
+    let mut types: Vec<CellId> = sources
+        .cells()
+        .copied()
+        .filter(|cell| matches!(read(sources, &Value::from(*cell)), Some(Type::Record(_))))
+        .collect();
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// The record type a record says it is. A link reads through to its
+/// cell's value, as the projection shows it.
+pub fn isa(sources: &Sources, value: &Value) -> Option<CellId> {
+    let record = match value {
+        Value::Atom(Atom::Cell(cell)) => sources.value(*cell)?,
+        value => value,
+    };
+    record.as_record()?.get(&Label::Cell(ISA))?.as_cell()
+}
+
+/// Whether `value` is of `ty` at the surface: the right atom kind, a
+/// record tagged with an admitted record type, a list. A link stands
+/// for its cell's value, as the projection shows it; a valueless
+/// cell is of no type yet. Elements and fields are the checker's
+/// business, not this one's.
+pub fn conforms(sources: &Sources, value: &Value, ty: &Type) -> bool {
+    let value = match value {
+        Value::Atom(Atom::Cell(cell)) => match sources.value(*cell) {
+            Some(value) => value,
+            None => return false,
+        },
+        value => value,
+    };
+    match ty {
+        Type::String => matches!(value, Value::Atom(Atom::String(_))),
+        Type::Blob => matches!(value, Value::Atom(Atom::Blob(_))),
+        Type::List(_) => value.as_list().is_some(),
+        Type::Record(_) | Type::Sum(_) => {
+            let admitted = constructors(sources, ty);
+            isa(sources, value).is_some_and(|tag| admitted.contains(&tag))
+                || match ty {
+                    Type::Sum(sum) => alternatives(sources, *sum).iter().any(|alternative| {
+                        matches!(alternative, Type::String | Type::Blob | Type::List(_))
+                            && conforms(sources, value, alternative)
+                    }),
+                    _ => false,
+                }
+        }
+    }
+}
+
+/// How a type reads in a message: the atom kinds and lists by kind,
+/// record types and sums by name.
+pub fn describe(sources: &Sources, ty: &Type) -> String {
+    match ty {
+        Type::String => "a string".to_string(),
+        Type::Blob => "a blob".to_string(),
+        Type::List(_) => "a list".to_string(),
+        Type::Record(cell) | Type::Sum(cell) => match sources.name(*cell) {
+            Some(name) => format!("a {name}"),
+            None => format!("a {}", crate::raw::short_id(*cell)),
+        },
+    }
+}
+
+/// The type of the field `label` in the record at `record`: the
+/// record's own `isa` decides, else the type its context expects.
+fn field_type(
+    sources: &Sources,
+    record: Option<&Value>,
+    context: Option<&Type>,
+    label: &Label,
+) -> Option<Type> {
+    let record_type = record.and_then(|record| isa(sources, record)).or(match context {
+        Some(Type::Record(cell)) => Some(*cell),
+        _ => None,
+    })?;
+    let Label::Cell(label) = label else {
+        return None;
+    };
+    fields(sources, record_type)
+        .into_iter()
+        .find(|field| field.label == *label)?
+        .ty
+}
+
+/// What the slot at `path` expects, walked from the root: a key step
+/// asks the record's type for the field, an element step takes the
+/// list type's element, a follow keeps what the link's slot expected
+/// (the cell IS the value there). The root expects nothing — a
+/// document's kind is a projection's business — so types enter at the
+/// first tagged record.
+pub fn expected(sources: &Sources, path: &[Step]) -> Option<Expected> {
+    let mut current: Option<Expected> = None;
+    for (index, step) in path.iter().enumerate() {
+        let ty = match current {
+            Some(Expected::Value(ty)) => Some(ty),
+            _ => None,
+        };
+        current = match step {
+            Step::Follow => ty.map(Expected::Value),
+            Step::Name => None,
+            Step::Element(_) => match ty {
+                Some(Type::List(Some(element))) => Some(Expected::Value(*element)),
+                _ => None,
+            },
+            Step::Key(Label::Cell(ISA)) => Some(Expected::Tag(ty)),
+            Step::Key(label) => {
+                let record = sources.resolve(&path[..index]);
+                field_type(sources, record, ty.as_ref(), label).map(Expected::Value)
+            }
+        };
+    }
+    current
+}
+
+/// The record type governing the record at `path`, for the label
+/// stage: its `isa`, else what its slot expects.
+pub fn record_type_at(sources: &Sources, path: &[Step]) -> Option<CellId> {
+    let record = sources.resolve(path);
+    record.and_then(|record| isa(sources, record)).or(match expected(sources, path) {
+        Some(Expected::Value(Type::Record(cell))) => Some(cell),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::Document;
+    use progred_graph::{new_cell_id, position};
+
+    /// `shape = circle | square`; circle has a required `radius:
+    /// string` and an optional `tags: [string]`; the root is a
+    /// record `{ shapes: [circle instance] }` under a `drawing` type.
+    fn schema_doc() -> (Document, Cells, [CellId; 6]) {
+        let mut lib = vocabulary();
+        let [shape, circle, square, radius, tags, drawing] = [(); 6].map(|_| new_cell_id());
+        let shapes = new_cell_id();
+        lib.set_name(radius, "radius");
+        lib.set_value(radius, Value::record([(Label::from(TYPE), Value::from(STRING))]));
+        lib.set_name(tags, "tags");
+        lib.set_value(
+            tags,
+            Value::record([(
+                Label::from(TYPE),
+                Value::record([(Label::from(ELEMENT), Value::from(STRING))]),
+            )]),
+        );
+        lib.set_name(circle, "circle");
+        lib.set_value(
+            circle,
+            Value::record([
+                (Label::from(FIELDS), Value::list([Value::from(radius)])),
+                (Label::from(OPTIONAL), Value::list([Value::from(tags)])),
+            ]),
+        );
+        lib.set_name(square, "square");
+        lib.set_value(square, Value::record([(Label::from(FIELDS), Value::list([]))]));
+        lib.set_name(shape, "shape");
+        lib.set_value(
+            shape,
+            Value::record([(
+                Label::from(ALTERNATIVES),
+                Value::list([Value::from(circle), Value::from(square)]),
+            )]),
+        );
+        lib.set_name(shapes, "shapes");
+        lib.set_value(
+            shapes,
+            Value::record([(
+                Label::from(TYPE),
+                Value::record([(Label::from(ELEMENT), Value::from(shape))]),
+            )]),
+        );
+        lib.set_name(drawing, "drawing");
+        lib.set_value(
+            drawing,
+            Value::record([(Label::from(FIELDS), Value::list([Value::from(shapes)]))]),
+        );
+        let instance = Value::record([
+            (Label::from(ISA), Value::from(circle)),
+            (Label::from(radius), Value::from("2")),
+        ]);
+        let doc = Document {
+            root: Some(Value::record([
+                (Label::from(ISA), Value::from(drawing)),
+                (Label::from(shapes), Value::list([instance])),
+            ])),
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        (doc, lib, [shape, circle, square, radius, tags, shapes])
+    }
+
+    #[test]
+    fn expected_types_walk_keys_and_elements() {
+        let (doc, lib, [shape, circle, square, radius, _, shapes]) = schema_doc();
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        let first = sources
+            .resolve(&[Step::Key(Label::from(shapes))])
+            .and_then(Value::as_list)
+            .and_then(|list| list.keys().next().cloned())
+            .unwrap();
+        let element = [Step::Key(Label::from(shapes)), Step::Element(first.clone())];
+        assert_eq!(
+            expected(&sources, &element[..1]),
+            Some(Expected::Value(Type::List(Some(Box::new(Type::Sum(shape))))))
+        );
+        assert_eq!(
+            expected(&sources, &element),
+            Some(Expected::Value(Type::Sum(shape)))
+        );
+        let mut at_radius = element.to_vec();
+        at_radius.push(Step::Key(Label::from(radius)));
+        assert_eq!(expected(&sources, &at_radius), Some(Expected::Value(Type::String)));
+        let mut at_tag = element.to_vec();
+        at_tag.push(Step::Key(Label::from(ISA)));
+        assert_eq!(
+            expected(&sources, &at_tag),
+            Some(Expected::Tag(Some(Type::Sum(shape))))
+        );
+        assert_eq!(constructors(&sources, &Type::Sum(shape)), vec![circle, square]);
+        assert_eq!(record_type_at(&sources, &element), Some(circle));
+        // A fresh element slot still expects the sum.
+        let fresh = position::between(Some(&first), None).unwrap();
+        assert_eq!(
+            expected(&sources, &[Step::Key(Label::from(shapes)), Step::Element(fresh)]),
+            Some(Expected::Value(Type::Sum(shape)))
+        );
+    }
+
+    #[test]
+    fn fields_read_required_first_and_self_nesting_lists_stop() {
+        let (doc, mut lib, [_, circle, _, radius, tags, _]) = schema_doc();
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        let read_fields = fields(&sources, circle);
+        assert_eq!(
+            read_fields
+                .iter()
+                .map(|field| (field.label, field.required))
+                .collect::<Vec<_>>(),
+            vec![(radius, true), (tags, false)]
+        );
+        assert_eq!(
+            read_fields[1].ty,
+            Some(Type::List(Some(Box::new(Type::String))))
+        );
+        let nested = new_cell_id();
+        lib.set_value(nested, Value::record([(Label::from(ELEMENT), Value::from(nested))]));
+        let sources = Sources {
+            doc: &doc,
+            library: &lib,
+        };
+        assert_eq!(
+            read(&sources, &Value::from(nested)),
+            Some(Type::List(None))
+        );
+    }
+}
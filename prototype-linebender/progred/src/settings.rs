@@ -0,0 +1,116 @@
+//! The editor's own preferences, bound to no document: one JSON file
+//! under the user's configuration directory (`progred/settings.json`),
+//! read at launch. A menu that changes a setting rewrites it; those no
+//! menu offers, like the coalescing rules, are edited there. A field
+//! the file leaves out takes its default, so a file an older build
+//! wrote still reads; one that doesn't read at all is reported, and
+//! the defaults stand until a menu writes it afresh.
+
+use crate::history::{self, Kind, Rule};
+use crate::sidecar;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The most states the history sidecar keeps; zero keeps none.
+    pub history_limit: usize,
+    /// How each kind of step coalesces, by the kind's name: a rule
+    /// (see [`Rule`]), or `null` to keep every step its own. Kinds
+    /// left out coalesce as they always have.
+    pub coalesce: BTreeMap<Kind, Option<Rule>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            history_limit: sidecar::DEFAULT_LIMIT,
+            coalesce: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// The coalescing rules in force: the defaults, as overridden.
+    pub fn rules(&self) -> HashMap<Kind, Rule> {
+        history::rules(&self.coalesce)
+    }
+}
+
+/// Where the settings live: the platform's configuration directory,
+/// or None on a system that names none.
+pub fn path() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let config = if cfg!(target_os = "windows") {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    };
+    Some(config?.join("progred").join("settings.json"))
+}
+
+/// The settings at `path`: the defaults when there is no file yet.
+pub fn read(path: &Path) -> Result<Settings, String> {
+    match std::fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json).map_err(|error| error.to_string()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Writes `settings` to `path`, making its directory if need be.
+pub fn write(path: &Path, settings: &Settings) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(settings).map_err(|error| error.to_string())?;
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json).map_err(|error| error.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|error| error.to_string())
+}
+
+/// The settings the editor launches with, and what went wrong reading
+/// them — said with the file's path, for the problems strip.
+pub fn load() -> (Settings, Option<String>) {
+    let Some(path) = path() else {
+        return (Settings::default(), None);
+    };
+    match read(&path) {
+        Ok(settings) => (settings, None),
+        Err(error) => (Settings::default(), Some(format!("settings {}: {error}", path.display()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_and_absent_fields_take_their_defaults() {
+        let dir = std::env::temp_dir().join(format!("progred-settings-{}", std::process::id()));
+        let path = dir.join("progred").join("settings.json");
+        assert_eq!(read(&path), Ok(Settings::default()));
+
+        let mut settings = Settings {
+            history_limit: 100,
+            ..Settings::default()
+        };
+        settings.coalesce.insert(Kind::Insert, None);
+        write(&path, &settings).unwrap();
+        assert_eq!(read(&path), Ok(settings));
+
+        std::fs::write(&path, "{}").unwrap();
+        assert_eq!(read(&path), Ok(Settings::default()));
+        std::fs::write(&path, "{\"history_limit\": \"many\"}").unwrap();
+        assert!(read(&path).is_err());
+        // A kind is said by its name; one that names none is refused.
+        std::fs::write(&path, "{\"coalesce\": {\"insertion\": null}}").unwrap();
+        assert!(read(&path).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
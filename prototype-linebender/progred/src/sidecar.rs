@@ -0,0 +1,532 @@
+//! The history sidecar: the undo tree kept beside the document
+//! (`name.progred` → `name.progred.history`), so reopening a file can
+//! step back past the session that last saved it, branches and all.
+//! Written at every save, read at every open; a document without one
+//! opens with an empty history, as it always did.
+//!
+//! Snapshots share structure in memory but not on disk, so the file
+//! holds DELTAS instead: each state says only what differs from its
+//! neighbour in the tree nearer the present — the root, the library
+//! declarations, the cell entries that changed — and reading replays
+//! them outward from the document as saved. The sidecar pins the document's content
+//! hash; a file changed since (another editor, a merge, a checkout)
+//! leaves a history that no longer leads anywhere, and it is ignored.
+//!
+//! Selections are paths, and a path's element steps name positions —
+//! session bookkeeping, minted afresh at every read. They are written
+//! as indices and turned back into positions against the state they
+//! restore into; one that no longer resolves restores as nothing.
+//!
+//! The sidecar keeps at most [`limit`] states; past it the oldest
+//! are pruned at the next save, by when they were made — but only
+//! from the tree's edges, never one another kept state's delta is
+//! from, so what is kept stays one connected tree around the present.
+
+use crate::history::{Action, History, Kind, State};
+use crate::raw::{Document, Path};
+use crate::sources::Sources;
+use crate::store::{self, LibraryRef};
+use progred_graph::{CellId, Cells, Label, Step, Value};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+pub const FORMAT: u32 = 2;
+
+/// The default bound on the persisted states.
+pub const DEFAULT_LIMIT: usize = 1000;
+
+/// The bound on the persisted states: the `setting` (see
+/// [`crate::settings`]), unless `PROGRED_HISTORY_LIMIT` overrides it
+/// with a number. Zero keeps no sidecar at all.
+pub fn limit(setting: usize) -> usize {
+    std::env::var("PROGRED_HISTORY_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(setting)
+}
+
+fn path_for(document: &std::path::Path) -> std::path::PathBuf {
+    let mut name = document.file_name().unwrap_or_default().to_os_string();
+    name.push(".history");
+    document.with_file_name(name)
+}
+
+#[derive(Serialize, Deserialize)]
+struct SidecarFile {
+    format: u32,
+    /// The content hash of the document file the tree leads from.
+    document: String,
+    /// The kept states in the order made; indices below are into
+    /// this list.
+    states: Vec<Saved>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    /// The neighbour nearer the present this state's delta is from;
+    /// None for the present itself, which is the document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    redo: Option<usize>,
+    /// Milliseconds since the Unix epoch.
+    made: u64,
+    /// The step that made the state, by kind and label; where it
+    /// landed isn't kept — it only serves coalescing, which doesn't
+    /// reach across sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    step: Option<(Kind, String)>,
+    delta: Delta,
+}
+
+/// One state, said as its differences from its neighbour nearer the
+/// present.
+#[derive(Serialize, Deserialize)]
+struct Delta {
+    #[serde(default, skip_serializing_if = "Root::is_kept")]
+    root: Root,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    libraries: Option<Vec<LibraryRef>>,
+    /// The entries this state holds that its neighbour doesn't.
+    #[serde(default)]
+    cells: Cells,
+    /// The cells its neighbour has entries for and it doesn't.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gone: Vec<CellId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selection: Option<Vec<Spelled>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Root {
+    #[default]
+    Kept,
+    Set(Value),
+    Cleared,
+}
+
+impl Root {
+    fn is_kept(&self) -> bool {
+        matches!(self, Root::Kept)
+    }
+}
+
+/// A path step as written: an element by its index.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Spelled {
+    Key(Label),
+    Element(usize),
+    Follow,
+    Name,
+}
+
+fn delta(near: &Document, far: &Document, selection: Option<&Path>, library: &Cells) -> Delta {
+    let root = match (&near.root, &far.root) {
+        (near, far) if near == far => Root::Kept,
+        (_, Some(root)) => Root::Set(root.clone()),
+        (_, None) => Root::Cleared,
+    };
+    let libraries = (near.libraries != far.libraries).then(|| far.libraries.clone());
+    let mut cells = Cells::new();
+    let mut gone = Vec::new();
+    for cell in far.cells.changed(&near.cells) {
+        match far.cells.entry(cell) {
+            Some(entry) => {
+                if let Some(name) = entry.name() {
+                    cells.set_name(cell, name);
+                }
+                if let Some(value) = entry.value() {
+                    cells.set_value(cell, value.clone());
+                }
+            }
+            None => gone.push(cell),
+        }
+    }
+    let sources = Sources { doc: far, library };
+    Delta {
+        root,
+        libraries,
+        cells,
+        gone,
+        selection: selection.and_then(|path| spell(&sources, path)),
+    }
+}
+
+fn apply(near: &Document, delta: Delta, library: &Cells) -> (Document, Option<Path>) {
+    let mut doc = near.clone();
+    match delta.root {
+        Root::Kept => {}
+        Root::Set(root) => doc.root = Some(root),
+        Root::Cleared => doc.root = None,
+    }
+    if let Some(libraries) = delta.libraries {
+        doc.libraries = libraries;
+    }
+    for cell in delta.gone {
+        doc.cells.remove(cell);
+    }
+    for (cell, entry) in delta.cells.iter() {
+        doc.cells.remove(*cell);
+        if let Some(name) = entry.name() {
+            doc.cells.set_name(*cell, name);
+        }
+        if let Some(value) = entry.value() {
+            doc.cells.set_value(*cell, value.clone());
+        }
+    }
+    let selection = delta.selection.and_then(|spelled| {
+        let sources = Sources {
+            doc: &doc,
+            library,
+        };
+        unspell(&sources, &spelled)
+    });
+    (doc, selection)
+}
+
+/// The path with its positions as indices, or None when it no
+/// longer resolves far enough to say them.
+fn spell(sources: &Sources, path: &[Step]) -> Option<Vec<Spelled>> {
+    let mut spelled = Vec::with_capacity(path.len());
+    for (index, step) in path.iter().enumerate() {
+        spelled.push(match step {
+            Step::Key(label) => Spelled::Key(label.clone()),
+            Step::Element(position) => {
+                let list = sources.resolve(&path[..index])?.as_list()?;
+                Spelled::Element(list.keys().position(|at| at == position)?)
+            }
+            Step::Follow => Spelled::Follow,
+            Step::Name => Spelled::Name,
+        });
+    }
+    Some(spelled)
+}
+
+fn unspell(sources: &Sources, spelled: &[Spelled]) -> Option<Path> {
+    let mut path = Vec::with_capacity(spelled.len());
+    for step in spelled {
+        let step = match step {
+            Spelled::Key(label) => Step::Key(label.clone()),
+            Spelled::Element(index) => {
+                let list = sources.resolve(&path)?.as_list()?;
+                Step::Element(list.keys().nth(*index)?.clone())
+            }
+            Spelled::Follow => Step::Follow,
+            Spelled::Name => Step::Name,
+        };
+        path.push(step);
+    }
+    Some(path)
+}
+
+/// Which states to keep under `limit`: all of them, pruned oldest
+/// `made` first down to the bound. Only a state at the tree's edge —
+/// one kept neighbour at most — is pruned, and never the present, so
+/// every kept state still reaches the present through kept ones.
+fn kept(states: &[State], current: usize, limit: usize) -> Vec<bool> {
+    let mut children = vec![Vec::new(); states.len()];
+    for (at, state) in states.iter().enumerate() {
+        if let Some(parent) = state.parent {
+            children[parent].push(at);
+        }
+    }
+    let mut neighbours: Vec<usize> = (states.iter().zip(&children))
+        .map(|(state, children)| usize::from(state.parent.is_some()) + children.len())
+        .collect();
+    let mut kept = vec![true; states.len()];
+    let mut edge: BinaryHeap<Reverse<(SystemTime, usize)>> = (0..states.len())
+        .filter(|&at| at != current && neighbours[at] <= 1)
+        .map(|at| Reverse((states[at].made, at)))
+        .collect();
+    let mut count = states.len();
+    while count > limit {
+        let Some(Reverse((_, at))) = edge.pop() else {
+            break;
+        };
+        kept[at] = false;
+        count -= 1;
+        // Its one kept neighbour, if any, may be at the edge now.
+        let parent = states[at].parent.filter(|&parent| kept[parent]);
+        let beside = parent.or_else(|| children[at].iter().copied().find(|&child| kept[child]));
+        if let Some(beside) = beside {
+            neighbours[beside] -= 1;
+            if beside != current && neighbours[beside] == 1 {
+                edge.push(Reverse((states[beside].made, beside)));
+            }
+        }
+    }
+    kept
+}
+
+/// Writes the sidecar for the document just saved at `path` — or,
+/// with a zero limit, removes any there was.
+pub fn write(
+    path: &std::path::Path,
+    history: &History,
+    doc: &Document,
+    library: &Cells,
+    limit: usize,
+) -> Result<(), String> {
+    let target = path_for(path);
+    if limit == 0 {
+        return match std::fs::remove_file(&target) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.to_string()),
+            _ => Ok(()),
+        };
+    }
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    let states = history.states();
+    let keep = kept(states, history.current(), limit);
+    let mut children = vec![Vec::new(); states.len()];
+    for (at, state) in states.iter().enumerate() {
+        if let Some(parent) = state.parent.filter(|&parent| keep[at] && keep[parent]) {
+            children[parent].push(at);
+        }
+    }
+    // Outward from the present: each kept state reached from the
+    // neighbour its delta will be from.
+    let mut from: Vec<Option<Option<usize>>> = vec![None; states.len()];
+    let mut frontier = VecDeque::new();
+    if !states.is_empty() {
+        from[history.current()] = Some(None);
+        frontier.push_back(history.current());
+    }
+    while let Some(at) = frontier.pop_front() {
+        let parent = states[at].parent.filter(|&parent| keep[parent]);
+        for &next in parent.iter().chain(&children[at]) {
+            if from[next].is_none() {
+                from[next] = Some(Some(at));
+                frontier.push_back(next);
+            }
+        }
+    }
+    let order: Vec<usize> = (0..states.len()).filter(|&at| keep[at]).collect();
+    let index = |at: usize| order.binary_search(&at).ok();
+    // The present's own snapshot is stale; the document is its state.
+    let snapshot = |at: usize| if at == history.current() { doc } else { &states[at].doc };
+    let saved = order
+        .iter()
+        .map(|&at| {
+            let state = &states[at];
+            let near = from[at].flatten();
+            let delta = match near {
+                Some(near) => delta(snapshot(near), &state.doc, state.selection.as_ref(), library),
+                None => delta(doc, doc, state.selection.as_ref(), library),
+            };
+            let made = state.made.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+            Saved {
+                from: near.and_then(index),
+                parent: state.parent.and_then(index),
+                redo: state.redo.and_then(index),
+                made: made.as_millis() as u64,
+                step: state.action.as_ref().map(|action| (action.kind, action.label.clone())),
+                delta,
+            }
+        })
+        .collect();
+    let file = SidecarFile {
+        format: FORMAT,
+        document: store::content_hash(&bytes),
+        states: saved,
+    };
+    let json = serde_json::to_string(&file).map_err(|error| error.to_string())?;
+    let mut tmp = target.clone().into_os_string();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json).map_err(|error| error.to_string())?;
+    std::fs::rename(&tmp, &target).map_err(|error| error.to_string())
+}
+
+/// The history the sidecar beside `path` leads back through from
+/// `doc`, as read from that file: None when there is no sidecar, or
+/// one whose document has changed since; an unreadable one is an
+/// error.
+pub fn read(
+    path: &std::path::Path,
+    doc: &Document,
+    library: &Cells,
+) -> Result<Option<History>, String> {
+    let json = match std::fs::read(path_for(path)) {
+        Ok(json) => json,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.to_string()),
+    };
+    let file: SidecarFile = serde_json::from_slice(&json).map_err(|error| error.to_string())?;
+    if file.format != FORMAT {
+        return Err(format!("history format {} (this build reads {FORMAT})", file.format));
+    }
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    if file.document != store::content_hash(&bytes) {
+        return Ok(None);
+    }
+    let count = file.states.len();
+    let out_of_range = |index: Option<usize>| index.is_some_and(|index| index >= count);
+    if file.states.iter().any(|saved| {
+        out_of_range(saved.from) || out_of_range(saved.parent) || out_of_range(saved.redo)
+    }) {
+        return Err("a state refers past the end".into());
+    }
+    let Some(current) = file.states.iter().position(|saved| saved.from.is_none()) else {
+        return Ok(None);
+    };
+    // Replayed outward from the present, each state after the
+    // neighbour its delta is from.
+    let mut outward: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (at, saved) in file.states.iter().enumerate() {
+        if let Some(near) = saved.from {
+            outward[near].push(at);
+        }
+    }
+    let mut restored: Vec<Option<(Document, Option<Path>)>> = vec![None; count];
+    let mut pending: Vec<Option<Saved>> = file.states.into_iter().map(Some).collect();
+    let mut info = vec![None; count];
+    let mut frontier = VecDeque::from([current]);
+    while let Some(at) = frontier.pop_front() {
+        let saved = pending[at].take().ok_or("a state is reached twice")?;
+        let near = match saved.from {
+            Some(near) => &restored[near].as_ref().ok_or("a state's neighbour is missing")?.0,
+            None => doc,
+        };
+        restored[at] = Some(apply(near, saved.delta, library));
+        let action = saved.step.map(|(kind, label)| Action::new(kind, label, Vec::new()));
+        info[at] = Some((saved.parent, saved.redo, saved.made, action));
+        frontier.extend(&outward[at]);
+    }
+    let states = restored
+        .into_iter()
+        .zip(info)
+        .map(|(restored, info)| {
+            let ((doc, selection), (parent, redo, made, action)) =
+                restored.zip(info).ok_or("a state is unreachable")?;
+            Ok(State {
+                doc,
+                selection,
+                parent,
+                redo,
+                made: SystemTime::UNIX_EPOCH + Duration::from_millis(made),
+                action,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Some(History::resumed(states, current)))
+}
+
+/// The history to open `doc` with: the sidecar's, or an empty one
+/// when there is none to resume — reported when it didn't read.
+pub fn resume(path: &std::path::Path, doc: &Document, library: &Cells) -> History {
+    read(path, doc, library).unwrap_or_else(|error| {
+        eprintln!("ignoring the history beside {}: {error}", path.display());
+        None
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_tree_survives_reopening_and_a_changed_file_drops_it() {
+        let dir = std::env::temp_dir().join(format!("progred-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.progred");
+        let library = Cells::new();
+        let cell = progred_graph::new_cell_id();
+        let state = |name: &str, value: Value| {
+            let mut cells = Cells::new();
+            cells.set_name(cell, name);
+            cells.set_value(cell, value);
+            Document {
+                root: Some(Value::from(cell)),
+                cells,
+                libraries: Vec::new(),
+            }
+        };
+        let first = state("corner", Value::from("x"));
+        let second = state("vertex", Value::from("x"));
+        let third = state("vertex", Value::list([Value::from("a"), Value::from("b")]));
+        let fourth = state("vertex", Value::from("y"));
+        let position = third.cells.value(cell).unwrap().as_list().unwrap().keys().next();
+        let element = vec![Step::Follow, Step::Element(position.unwrap().clone())];
+
+        // first → second → third, then back to second and on to a
+        // fourth: third is a branch left with its selection on an
+        // element.
+        let mut history = History::default();
+        let rename = || Action::new(Kind::Rename, "rename cell", Vec::new());
+        history.record(first.clone(), None, rename());
+        history.record(second.clone(), Some(vec![Step::Follow]), rename());
+        let (back, _) = history.undo(third.clone(), Some(element)).unwrap();
+        history.record(back, None, Action::new(Kind::Paste, "paste", Vec::new()));
+        store::save(&path, &fourth).unwrap();
+        write(&path, &history, &fourth, &library, 10).unwrap();
+
+        let opened = store::open(&path).unwrap().doc;
+        let resumed = resume(&path, &opened, &library);
+        assert!(!resumed.dirty());
+        assert_eq!(resumed.current(), 3);
+        let states = resumed.states();
+        assert_eq!(states.len(), 4);
+        assert_eq!(states[0].doc.cells.name(cell), Some("corner"));
+        assert_eq!(states[1].doc.cells.name(cell), Some("vertex"));
+        assert_eq!(states[1].selection, None);
+        assert_eq!(resumed.undo_label(), Some("paste"));
+        assert_eq!((states[2].parent, states[3].parent), (Some(1), Some(1)));
+        assert_eq!(states[2].doc.cells.value(cell), third.cells.value(cell));
+        // The element step comes back under the positions read back.
+        let list = states[2].doc.cells.value(cell).unwrap().as_list().unwrap();
+        let position = list.keys().next().unwrap().clone();
+        assert_eq!(states[2].selection, Some(vec![Step::Follow, Step::Element(position)]));
+
+        // The bound prunes the oldest from the edges: the first, then
+        // the branch, leaving the fourth and its parent as the root.
+        write(&path, &history, &fourth, &library, 2).unwrap();
+        let resumed = resume(&path, &opened, &library);
+        let states = resumed.states();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].parent, None);
+        assert_eq!(states[0].doc.cells.value(cell), Some(&Value::from("x")));
+        assert_eq!(resumed.current(), 1);
+
+        // The document changed behind the sidecar: nothing to resume.
+        store::save(&path, &first).unwrap();
+        assert!(read(&path, &first, &library).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn pruning_takes_the_oldest_edge_first_and_keeps_the_tree_whole() {
+        // 0 → 1 → 2, then back to 1 and on to 3, the present: 0 is
+        // as near the present as 2, but older.
+        let doc = Document {
+            root: None,
+            cells: Cells::new(),
+            libraries: Vec::new(),
+        };
+        let states: Vec<State> = [None, Some(0), Some(1), Some(1)]
+            .into_iter()
+            .enumerate()
+            .map(|(at, parent)| State {
+                doc: doc.clone(),
+                selection: None,
+                parent,
+                redo: None,
+                made: SystemTime::UNIX_EPOCH + Duration::from_secs(at as u64),
+                action: None,
+            })
+            .collect();
+        assert_eq!(kept(&states, 3, 4), [true; 4]);
+        assert_eq!(kept(&states, 3, 3), [false, true, true, true]);
+        // 1 is older than 2, but 2 and the present are kept through
+        // it.
+        assert_eq!(kept(&states, 3, 2), [false, true, false, true]);
+        assert_eq!(kept(&states, 3, 1), [false, false, false, true]);
+        // With the present at the root, the old root stays.
+        assert_eq!(kept(&states, 0, 2), [true, true, false, false]);
+    }
+}
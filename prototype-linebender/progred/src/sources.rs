@@ -85,7 +85,11 @@ mod tests {
     use progred_graph::{Label, new_cell_id};
 
     fn doc_of(cells: Cells) -> Document {
-        Document { root: None, cells }
+        Document {
+            root: None,
+            cells,
+            libraries: Vec::new(),
+        }
     }
 
     #[test]
@@ -175,6 +179,7 @@ mod tests {
         let doc = Document {
             root: Some(Value::from(root)),
             cells,
+            libraries: Vec::new(),
         };
         let library = Cells::new();
         let sources = Sources {
@@ -211,6 +216,7 @@ mod tests {
         let bare_doc = Document {
             root: Some(Value::from(new_cell_id())),
             cells: Cells::new(),
+            libraries: Vec::new(),
         };
         let bare_sources = Sources {
             doc: &bare_doc,
@@ -0,0 +1,237 @@
+//! The graph view's spatial indexes, so a question about one place —
+//! what pushes on this node, what is under the pointer — reads what
+//! is near it instead of every item. Both are rebuilt per frame from
+//! the caller's own list, items being indices into it.
+//!
+//! [`Quads`] is a Barnes-Hut quadtree over points: each quad knows how
+//! many points it holds and their centroid, so a distant quad stands
+//! in for all of them at once and a visit costs about the log of the
+//! count, however densely the points pack. [`Grid`] buckets
+//! rectangles by the squares they touch: the hit-test index, where
+//! items are few on screen and similar in size, and a flat grid is
+//! all the adaptivity needed. Its buckets answer in insertion order,
+//! so a caller that inserts in draw order can pick the topmost hit.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use vello::kurbo::{Point, Rect, Vec2};
+
+/// Points per leaf: at or below this a quad stops splitting, and
+/// visiting it is exact.
+const LEAF: usize = 8;
+/// Splitting stops here too, so coincident points end in one leaf.
+const DEPTH: usize = 24;
+
+struct Quad {
+    bounds: Rect,
+    centroid: Point,
+    weight: f64,
+    /// The first of four consecutive children; `None` for a leaf.
+    children: Option<usize>,
+    /// The quad's points, as a run of [`Quads::items`].
+    run: Range<usize>,
+}
+
+pub struct Quads {
+    quads: Vec<Quad>,
+    /// Item indices, ordered so every quad's are one contiguous run.
+    items: Vec<usize>,
+    points: Vec<Point>,
+}
+
+/// What a visit meets: one point, or a far quad's points summed at
+/// their centroid.
+pub enum Mass {
+    Item(usize, Point),
+    Cluster(Point, f64),
+}
+
+impl Quads {
+    pub fn new(points: &[Point]) -> Self {
+        let bounds = match points.first() {
+            Some(first) => points
+                .iter()
+                .fold(Rect::from_points(*first, *first), |bounds, point| {
+                    bounds.union_pt(*point)
+                }),
+            None => Rect::ZERO,
+        };
+        // Square, so a quad's width is its size.
+        let side = bounds.width().max(bounds.height()).max(1.0);
+        let mut tree = Self {
+            quads: Vec::new(),
+            items: (0..points.len()).collect(),
+            points: points.to_vec(),
+        };
+        let square = Rect::from_origin_size(bounds.origin(), (side, side));
+        tree.quads.push(tree.quad(square, 0..points.len()));
+        tree.split(0, 0);
+        tree
+    }
+
+    fn quad(&self, bounds: Rect, run: Range<usize>) -> Quad {
+        let weight = run.len() as f64;
+        let sum = self.items[run.clone()]
+            .iter()
+            .fold(Vec2::ZERO, |sum, item| sum + self.points[*item].to_vec2());
+        Quad {
+            bounds,
+            centroid: if run.is_empty() {
+                bounds.center()
+            } else {
+                (sum / weight).to_point()
+            },
+            weight,
+            children: None,
+            run,
+        }
+    }
+
+    fn split(&mut self, index: usize, depth: usize) {
+        let (bounds, run) = (self.quads[index].bounds, self.quads[index].run.clone());
+        if run.len() <= LEAF || depth >= DEPTH {
+            return;
+        }
+        let middle = bounds.center();
+        let quadrant =
+            |point: Point| usize::from(point.x >= middle.x) + 2 * usize::from(point.y >= middle.y);
+        let points = &self.points;
+        self.items[run.clone()].sort_by_key(|item| quadrant(points[*item]));
+        let first = self.quads.len();
+        let mut start = run.start;
+        for which in 0..4 {
+            let end = start
+                + self.items[start..run.end]
+                    .iter()
+                    .take_while(|item| quadrant(self.points[**item]) == which)
+                    .count();
+            let (x0, x1) = if which % 2 == 0 {
+                (bounds.x0, middle.x)
+            } else {
+                (middle.x, bounds.x1)
+            };
+            let (y0, y1) = if which < 2 {
+                (bounds.y0, middle.y)
+            } else {
+                (middle.y, bounds.y1)
+            };
+            let quad = self.quad(Rect::new(x0, y0, x1, y1), start..end);
+            self.quads.push(quad);
+            start = end;
+        }
+        self.quads[index].children = Some(first);
+        for child in first..first + 4 {
+            self.split(child, depth + 1);
+        }
+    }
+
+    /// Meets what `at` feels within `cutoff`: quads wholly farther are
+    /// skipped, quads that look narrower than `theta` from `at` are
+    /// met as one [`Mass::Cluster`], and the rest open down to their
+    /// points — `at`'s own item among them, for the caller to skip.
+    pub fn visit(&self, at: Point, theta: f64, cutoff: f64, meet: &mut impl FnMut(Mass)) {
+        // Squared throughout: no roots on the hot path.
+        let (theta2, cutoff2) = (theta * theta, cutoff * cutoff);
+        let mut stack = Vec::with_capacity(4 * DEPTH);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let quad = &self.quads[index];
+            if quad.run.is_empty() {
+                continue;
+            }
+            let nearest = Point::new(
+                at.x.clamp(quad.bounds.x0, quad.bounds.x1),
+                at.y.clamp(quad.bounds.y0, quad.bounds.y1),
+            );
+            if (nearest - at).hypot2() > cutoff2 {
+                continue;
+            }
+            let width2 = quad.bounds.width() * quad.bounds.width();
+            match quad.children {
+                Some(_) if width2 < theta2 * (quad.centroid - at).hypot2() => {
+                    meet(Mass::Cluster(quad.centroid, quad.weight));
+                }
+                Some(first) => stack.extend(first..first + 4),
+                None => {
+                    for item in &self.items[quad.run.clone()] {
+                        meet(Mass::Item(*item, self.points[*item]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct Grid {
+    size: f64,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    /// An empty grid of `size`-wide square buckets.
+    pub fn new(size: f64) -> Self {
+        Self {
+            size,
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn key(&self, point: Point) -> (i64, i64) {
+        (
+            (point.x / self.size).floor() as i64,
+            (point.y / self.size).floor() as i64,
+        )
+    }
+
+    /// Files a rectangle item under every bucket it overlaps.
+    pub fn insert(&mut self, index: usize, rect: Rect) {
+        let (x0, y0) = self.key(Point::new(rect.x0, rect.y0));
+        let (x1, y1) = self.key(Point::new(rect.x1, rect.y1));
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.buckets.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    /// The items in `point`'s bucket — every rectangle that could
+    /// contain it, and some that don't.
+    pub fn at(&self, point: Point) -> &[usize] {
+        self.buckets.get(&self.key(point)).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_points_meet_as_one_near_ones_one_by_one() {
+        // A tight knot of forty far off, and two points close by.
+        let mut points: Vec<Point> = (0..40)
+            .map(|i| Point::new(1000.0 + f64::from(i % 5), f64::from(i / 5)))
+            .collect();
+        points.extend([Point::new(0.0, 0.0), Point::new(3.0, 4.0)]);
+        let tree = Quads::new(&points);
+        let (mut items, mut clustered) = (Vec::new(), 0.0);
+        tree.visit(Point::ZERO, 0.5, f64::INFINITY, &mut |mass| match mass {
+            Mass::Item(item, _) => items.push(item),
+            Mass::Cluster(_, weight) => clustered += weight,
+        });
+        items.sort();
+        assert_eq!(items, [40, 41]);
+        assert_eq!(clustered, 40.0);
+        // Within a cutoff, the knot is not met at all.
+        let mut met = 0;
+        tree.visit(Point::ZERO, 0.5, 10.0, &mut |_| met += 1);
+        assert_eq!(met, 2);
+
+        let mut grid = Grid::new(10.0);
+        grid.insert(0, Rect::new(-4.0, -4.0, 12.0, 4.0));
+        grid.insert(1, Rect::new(0.0, 0.0, 4.0, 4.0));
+        assert_eq!(grid.at(Point::new(11.0, 1.0)), [0]);
+        assert_eq!(grid.at(Point::new(-1.0, -1.0)), [0]);
+        assert_eq!(grid.at(Point::new(1.0, 1.0)), [0, 1]);
+        assert!(grid.at(Point::new(100.0, 100.0)).is_empty());
+    }
+}
@@ -1,33 +1,242 @@
 //! Document files: the graph as pretty JSON on disk, format-tagged.
 //! Write-through editing means the graph is always current, so saving
-//! is a plain serialization of the model. Loaders refuse unknown
-//! versions (and untagged pre-version files) rather than guess — a
-//! precise refusal today, a migration hook if a file ever matters.
+//! is a plain serialization of the model. Loaders refuse future
+//! versions (and untagged pre-version files) rather than guess; an
+//! OLDER version runs the migration chain (see [`crate::migrate`])
+//! and opens flagged as upgraded, so the editor can say so and the
+//! next save writes it current.
+//!
+//! Beside it sit the BINARY store (see [`crate::binary`]), for
+//! documents heavy with blobs — raw bytes, appended saves — and the
+//! canonical TEXT store (see [`crate::text`]), for documents reviewed
+//! as diffs. Loading sniffs which a file is, so any opens anywhere a
+//! document is read, libraries included; a file stays in the form it
+//! was found in, and a new one takes the form its name says.
+//!
+//! A document may declare LIBRARIES: other document files whose cells
+//! read under it, read-only. Only the declarations are saved; the
+//! cells compose into the editor's library table at load, and a
+//! library that is missing, drifted, or contradicting another is a
+//! diagnostic — the document still opens, its references to the
+//! absent cells simply reading bare.
 
+use crate::binary::{self, Journal};
+use crate::migrate;
 use crate::raw::Document;
+use crate::text;
 use progred_graph::Cells;
 use progred_graph::Value;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-const FORMAT: u32 = 1;
+pub const FORMAT: u32 = 1;
 
 #[derive(Serialize, Deserialize)]
 struct FileDoc {
     format: u32,
     root: Option<Value>,
     cells: Cells,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    libraries: Vec<LibraryRef>,
 }
 
-pub fn load(path: &Path) -> Result<Document, String> {
-    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
-    let file: FileDoc = serde_json::from_str(&text).map_err(|error| error.to_string())?;
-    if file.format != FORMAT {
-        return Err(format!("format {} (this build reads {FORMAT})", file.format));
+/// A declared library: a document file whose cells read under the
+/// declaring one. The path is as written — relative paths resolve
+/// against the declaring document's directory — and the hash, when
+/// present, pins the content the declaration was made against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryRef {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// The library table a document reads over, and what went wrong
+/// composing it. Never a failure: whatever could be read composes.
+pub struct Composed {
+    pub cells: Cells,
+    pub diagnostics: Vec<String>,
+}
+
+/// The extensions that make a new document binary, or text.
+pub const BINARY_EXTENSION: &str = "progredb";
+pub const TEXT_EXTENSION: &str = "progredt";
+
+/// Which of the forms a document's file is in — the one it was found
+/// in, or the one a new file's name asks for.
+#[derive(Clone, Debug)]
+pub enum Form {
+    Json,
+    /// With the journal of what the file last said, once there is one
+    /// to append to.
+    Binary(Option<Journal>),
+    Text,
+}
+
+impl Form {
+    pub fn for_path(path: &Path) -> Form {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(BINARY_EXTENSION) => Form::Binary(None),
+            Some(TEXT_EXTENSION) => Form::Text,
+            _ => Form::Json,
+        }
+    }
+}
+
+/// A document as read, and how: its form (a binary file's with the
+/// journal its next save appends against), and for an older file the
+/// format it was upgraded from.
+pub struct Opened {
+    pub doc: Document,
+    pub form: Form,
+    pub migrated: Option<u32>,
+}
+
+pub fn open(path: &Path) -> Result<Opened, String> {
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    parse(&bytes)
+}
+
+fn parse(bytes: &[u8]) -> Result<Opened, String> {
+    if bytes.starts_with(binary::MAGIC) {
+        let (doc, journal) = binary::read(bytes)?;
+        return Ok(Opened {
+            doc,
+            form: Form::Binary(Some(journal)),
+            migrated: None,
+        });
+    }
+    if bytes.starts_with(text::HEADER.as_bytes()) {
+        let text = std::str::from_utf8(bytes).map_err(|error| error.to_string())?;
+        return Ok(Opened {
+            doc: text::read(text)?,
+            form: Form::Text,
+            migrated: None,
+        });
     }
-    Ok(Document {
-        root: file.root,
-        cells: file.cells,
+    let json: serde_json::Value =
+        serde_json::from_slice(bytes).map_err(|error| error.to_string())?;
+    let format = migrate::format_of(&json)?;
+    if format > FORMAT {
+        return Err(format!("format {format} (this build reads {FORMAT})"));
+    }
+    let json = migrate::upgrade(json, format)?;
+    let file: FileDoc = serde_json::from_value(json).map_err(|error| error.to_string())?;
+    Ok(Opened {
+        doc: Document {
+            root: file.root,
+            cells: file.cells,
+            libraries: file.libraries,
+        },
+        form: Form::Json,
+        migrated: (format < FORMAT).then_some(format),
+    })
+}
+
+/// Composes `doc`'s declared libraries over the built-in one, in
+/// declaration order — left-biased, as `Cells::merge` is, so the
+/// first statement of a cell wins and a later library restating it
+/// differently is reported, not applied. `dir` is the declaring
+/// document's directory (None for an untitled document, whose
+/// declarations must then be absolute). A library's own declarations
+/// are not followed: a document names everything it reads.
+pub fn libraries(doc: &Document, dir: Option<&Path>) -> Composed {
+    let mut cells = crate::conventions::library();
+    let mut diagnostics = Vec::new();
+    for library in &doc.libraries {
+        let path = resolve_library(&library.path, dir);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                diagnostics.push(format!("library {}: {error}", library.path));
+                continue;
+            }
+        };
+        if let Some(pinned) = &library.hash {
+            let actual = content_hash(&bytes);
+            if *pinned != actual {
+                diagnostics.push(format!(
+                    "library {} changed since it was attached ({pinned}, now {actual})",
+                    library.path
+                ));
+            }
+        }
+        let read = match parse(&bytes) {
+            Ok(read) => read,
+            Err(error) => {
+                diagnostics.push(format!("library {}: {error}", library.path));
+                continue;
+            }
+        };
+        let conflicts = read
+            .doc
+            .cells
+            .iter()
+            .filter(|(cell, entry)| cells.entry(**cell).is_some_and(|held| held != *entry))
+            .count();
+        if conflicts > 0 {
+            diagnostics.push(format!(
+                "library {} restates {conflicts} cell(s) differently; the earlier statement wins",
+                library.path
+            ));
+        }
+        cells.merge(read.doc.cells);
+    }
+    Composed { cells, diagnostics }
+}
+
+/// A declaration for the library file at `path`, pinned to its
+/// current content and spelled relative to `dir` when it lies
+/// beneath it. Refuses a file that doesn't read as a document.
+pub fn attach(path: &Path, dir: Option<&Path>) -> Result<LibraryRef, String> {
+    let bytes = std::fs::read(path).map_err(|error| error.to_string())?;
+    parse(&bytes)?;
+    let spelled = dir
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path);
+    Ok(LibraryRef {
+        path: spelled.to_string_lossy().into_owned(),
+        hash: Some(content_hash(&bytes)),
+    })
+}
+
+fn resolve_library(path: &str, dir: Option<&Path>) -> PathBuf {
+    match dir {
+        Some(dir) => dir.join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+/// The declarations re-spelled for a document moving from directory
+/// `from` to `to`, so each still names the same file: resolved where
+/// they were written, then relative to `to` when beneath it (as
+/// [`attach`] spells them) and absolute otherwise. Pins are kept.
+pub fn rebase(libraries: &[LibraryRef], from: Option<&Path>, to: Option<&Path>) -> Vec<LibraryRef> {
+    libraries
+        .iter()
+        .map(|library| {
+            let path = resolve_library(&library.path, from);
+            let spelled = to
+                .and_then(|dir| path.strip_prefix(dir).ok())
+                .unwrap_or(&path);
+            LibraryRef {
+                path: spelled.to_string_lossy().into_owned(),
+                hash: library.hash.clone(),
+            }
+        })
+        .collect()
+}
+
+/// A drift pin, not a security boundary: 64-bit FNV-1a over the
+/// file's bytes, tagged with its algorithm so a stronger one can
+/// arrive beside it.
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("fnv1a64:{:016x}", fnv1a64(bytes))
+}
+
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
     })
 }
 
@@ -36,6 +245,7 @@ pub fn save(path: &Path, doc: &Document) -> Result<(), String> {
         format: FORMAT,
         root: doc.root.clone(),
         cells: doc.cells.clone(),
+        libraries: doc.libraries.clone(),
     };
     let json = serde_json::to_string_pretty(&file).map_err(|error| error.to_string())?;
     // Write-then-rename, so a crash mid-write cannot truncate the
@@ -45,10 +255,31 @@ pub fn save(path: &Path, doc: &Document) -> Result<(), String> {
     std::fs::rename(&tmp, path).map_err(|error| error.to_string())
 }
 
+/// Saves `doc` at `path` in `form`, returning the form for the next
+/// save — a binary file's with its fresh journal.
+pub fn write(path: &Path, doc: &Document, form: &Form) -> Result<Form, String> {
+    match form {
+        Form::Json => save(path, doc).map(|()| Form::Json),
+        Form::Binary(journal) => {
+            binary::write(path, doc, journal.as_ref()).map(|journal| Form::Binary(Some(journal)))
+        }
+        Form::Text => {
+            let tmp = path.with_extension("progredt.tmp");
+            std::fs::write(&tmp, text::write(doc)).map_err(|error| error.to_string())?;
+            std::fs::rename(&tmp, path).map_err(|error| error.to_string())?;
+            Ok(Form::Text)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn load(path: &Path) -> Result<Document, String> {
+        open(path).map(|opened| opened.doc)
+    }
+
     #[test]
     fn documents_round_trip_through_disk_json() {
         let doc = crate::raw::sample_document();
@@ -64,6 +295,23 @@ mod tests {
             serde_json::to_string(&loaded).unwrap(),
             serde_json::to_string(&doc).unwrap()
         );
+
+        // Named for them, the other forms are written and sniffed back,
+        // each reading as the form it was found in.
+        for extension in [BINARY_EXTENSION, TEXT_EXTENSION] {
+            let other = path.with_extension(extension);
+            write(&other, &doc, &Form::for_path(&other)).unwrap();
+            let opened = open(&other).unwrap();
+            std::fs::remove_file(&other).ok();
+            assert!(matches!(
+                (Form::for_path(&other), opened.form),
+                (Form::Binary(None), Form::Binary(Some(_))) | (Form::Text, Form::Text)
+            ));
+            assert_eq!(
+                serde_json::to_string(&opened.doc).unwrap(),
+                serde_json::to_string(&doc).unwrap()
+            );
+        }
     }
 
     #[test]
@@ -77,6 +325,139 @@ mod tests {
         assert!(load(&future).unwrap_err().contains("99"));
         std::fs::remove_file(&old).ok();
         std::fs::remove_file(&future).ok();
+
+        // The binary header is sniffed ahead of its format, which
+        // refuses as precisely.
+        let binary = dir.join(format!("progred-store-bin-{}.progredb", std::process::id()));
+        let mut bytes = binary::MAGIC.to_vec();
+        bytes.extend_from_slice(&99_u32.to_le_bytes());
+        std::fs::write(&binary, bytes).unwrap();
+        assert!(load(&binary).unwrap_err().contains("binary format 99"));
+        std::fs::remove_file(&binary).ok();
+        // As is the text header's.
+        let text = dir.join(format!("progred-store-text-{}.progredt", std::process::id()));
+        std::fs::write(&text, format!("{}99\n", text::HEADER)).unwrap();
+        assert!(load(&text).unwrap_err().contains("text format 99"));
+        std::fs::remove_file(&text).ok();
+    }
+
+    #[test]
+    fn older_files_open_upgraded_and_save_current() {
+        let path = std::env::temp_dir().join(format!(
+            "progred-store-gid-{}.progred",
+            std::process::id()
+        ));
+        let entity = progred_graph::Uuid::from_u128(1);
+        let gid = serde_json::json!({
+            "graph": {entity.to_string(): [[{"string": "x"}, {"string": "1"}]]},
+            "root": {"uuid": entity},
+        });
+        std::fs::write(&path, gid.to_string()).unwrap();
+        let opened = open(&path).unwrap();
+        assert_eq!(opened.migrated, Some(0));
+        assert_eq!(opened.doc.root, Some(Value::from(entity)));
+        save(&path, &opened.doc).unwrap();
+        let reopened = open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reopened.migrated, None);
+        assert_eq!(reopened.doc.cells.value(entity), opened.doc.cells.value(entity));
+    }
+
+    #[test]
+    fn libraries_compose_and_diagnose_without_failing() {
+        use progred_graph::new_cell_id;
+        let dir = std::env::temp_dir().join(format!("progred-store-lib-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let shared = new_cell_id();
+        let library = |name: &str| {
+            let mut cells = Cells::new();
+            cells.set_name(shared, name);
+            Document {
+                root: None,
+                cells,
+                libraries: Vec::new(),
+            }
+        };
+        save(&dir.join("a.progred"), &library("shape")).unwrap();
+        save(&dir.join("b.progred"), &library("form")).unwrap();
+
+        // Attaching pins the content and spells the path relative to
+        // the declaring document's directory.
+        let a = attach(&dir.join("a.progred"), Some(&dir)).unwrap();
+        assert_eq!(a.path, "a.progred");
+        let mut doc = crate::raw::sample_document();
+        doc.libraries = vec![
+            a,
+            LibraryRef {
+                path: "b.progred".into(),
+                hash: None,
+            },
+            LibraryRef {
+                path: "missing.progred".into(),
+                hash: None,
+            },
+        ];
+        let composed = libraries(&doc, Some(&dir));
+        // The first statement wins; the contradiction and the missing
+        // file are diagnostics, not failures.
+        assert_eq!(composed.cells.name(shared), Some("shape"));
+        assert_eq!(composed.diagnostics.len(), 2);
+        assert!(composed.diagnostics[0].contains("b.progred"));
+        assert!(composed.diagnostics[1].contains("missing.progred"));
+
+        // The declarations round-trip; drift under a pin is reported.
+        let path = dir.join("doc.progred");
+        save(&path, &doc).unwrap();
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.libraries, doc.libraries);
+        save(&dir.join("a.progred"), &library("renamed")).unwrap();
+        let drifted = libraries(&loaded, Some(&dir));
+        assert!(drifted.diagnostics[0].contains("changed since it was attached"));
+        assert_eq!(drifted.cells.name(shared), Some("renamed"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn save_as_elsewhere_keeps_naming_the_same_libraries() {
+        use progred_graph::new_cell_id;
+        let dir = std::env::temp_dir().join(format!("progred-store-move-{}", std::process::id()));
+        let (home, away) = (dir.join("home"), dir.join("away"));
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::create_dir_all(&away).unwrap();
+        let shared = new_cell_id();
+        let mut cells = Cells::new();
+        cells.set_name(shared, "shape");
+        let library = Document {
+            root: None,
+            cells,
+            libraries: Vec::new(),
+        };
+        save(&home.join("lib.progred"), &library).unwrap();
+        let mut doc = crate::raw::sample_document();
+        doc.libraries = vec![attach(&home.join("lib.progred"), Some(&home)).unwrap()];
+
+        // Moving aside spells the path absolute; moving up to a
+        // directory above the library keeps it relative, one level
+        // deeper. Either way the saved file reads the library it read
+        // before, pin intact.
+        for to in [&away, &dir, &home] {
+            let rebased = rebase(&doc.libraries, Some(&home), Some(to));
+            assert_eq!(rebased[0].hash, doc.libraries[0].hash);
+            assert_eq!(Path::new(&rebased[0].path).is_absolute(), to == &away);
+            let moved = Document {
+                libraries: rebased,
+                ..doc.clone()
+            };
+            let path = to.join("doc.progred");
+            save(&path, &moved).unwrap();
+            let loaded = load(&path).unwrap();
+            let composed = libraries(&loaded, Some(to));
+            assert!(composed.diagnostics.is_empty(), "{:?}", composed.diagnostics);
+            assert_eq!(composed.cells.name(shared), Some("shape"));
+        }
+        let home_again = rebase(&doc.libraries, Some(&home), Some(&home));
+        assert_eq!(home_again, doc.libraries);
+        std::fs::remove_dir_all(&dir).ok();
     }
 }
 
@@ -0,0 +1,197 @@
+//! A recorded frame as SVG: the qlmanage trick, no window needed.
+//! Panes draw into any [`Canvas`](puri::draw::Canvas), so a
+//! [`DrawList`] captures one exactly and this writes it out — shapes
+//! as paths (dashes kept), glyph runs as their outlines (the file
+//! needs no fonts, and reads the same wherever it is opened), clips
+//! as clip paths. The headless benches write their pictures through
+//! it, and so does the graph view's export.
+
+use puri::draw::{DrawCmd, DrawList, GlyphRun, Shape};
+use skrifa::instance::{LocationRef, NormalizedCoord, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::{FontRef, GlyphId, MetadataProvider};
+use std::fmt::Write as _;
+use vello::kurbo::{Affine, BezPath, Point, Rect, Shape as _};
+use vello::peniko::Brush;
+
+/// A standalone SVG document of `list`, showing the `view` rectangle
+/// of its coordinates over white.
+pub fn document(list: &DrawList, view: Rect) -> String {
+    let (x, y, width, height) = (view.x0, view.y0, view.width(), view.height());
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="{x:.0} {y:.0} {width:.0} {height:.0}">"#
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"<rect x="{x:.0}" y="{y:.0}" width="{width:.0}" height="{height:.0}" fill="#FFFFFF"/>"##
+    )
+    .unwrap();
+    write_cmds(&mut out, &list.0, &mut 0);
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+/// Solid brushes as CSS colors; anything fancier shows as magenta, so
+/// it is noticed rather than quietly flattened.
+fn css(brush: &Brush) -> String {
+    match brush {
+        Brush::Solid(color) => {
+            let [r, g, b, a] = color.components;
+            format!(
+                "rgba({},{},{},{:.3})",
+                (r * 255.0).round(),
+                (g * 255.0).round(),
+                (b * 255.0).round(),
+                a
+            )
+        }
+        _ => "magenta".to_string(),
+    }
+}
+
+/// Collects a glyph's outline into a path, flipping font units'
+/// y-up into the page's y-down around the glyph's origin.
+struct BezPen {
+    path: BezPath,
+    offset: Point,
+}
+
+impl BezPen {
+    fn at(&self, x: f32, y: f32) -> Point {
+        Point::new(self.offset.x + f64::from(x), self.offset.y - f64::from(y))
+    }
+}
+
+impl OutlinePen for BezPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.path.move_to(self.at(x, y));
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.path.line_to(self.at(x, y));
+    }
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.path.quad_to(self.at(cx0, cy0), self.at(x, y));
+    }
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.path
+            .curve_to(self.at(cx0, cy0), self.at(cx1, cy1), self.at(x, y));
+    }
+    fn close(&mut self) {
+        self.path.close_path();
+    }
+}
+
+fn svg_shape(shape: &Shape, transform: Affine) -> String {
+    let mut path = match shape {
+        Shape::Rect(rect) => rect.to_path(0.05),
+        Shape::RoundedRect(rect) => rect.to_path(0.05),
+        Shape::Circle(circle) => circle.to_path(0.05),
+        Shape::Line(line) => {
+            let mut p = BezPath::new();
+            p.move_to(line.p0);
+            p.line_to(line.p1);
+            p
+        }
+        Shape::Path(path) => path.clone(),
+    };
+    path.apply_affine(transform);
+    path.to_svg()
+}
+
+/// A run's glyphs as one outline path, unhinted at the run's size and
+/// variation; `None` when the run's font doesn't parse.
+fn glyph_outlines(run: &GlyphRun) -> Option<BezPath> {
+    let font = FontRef::from_index(run.font.data.as_ref(), run.font.index).ok()?;
+    let outlines = font.outline_glyphs();
+    let coords: Vec<NormalizedCoord> = run
+        .normalized_coords
+        .iter()
+        .map(|bits| NormalizedCoord::from_bits(*bits))
+        .collect();
+    let size = Size::new(run.size);
+    let mut path = BezPath::new();
+    for glyph in &run.glyphs {
+        let mut pen = BezPen {
+            path: std::mem::take(&mut path),
+            offset: run.transform * Point::new(f64::from(glyph.x), f64::from(glyph.y)),
+        };
+        if let Some(outline) = outlines.get(GlyphId::new(glyph.id)) {
+            let settings = DrawSettings::unhinted(size, LocationRef::new(&coords));
+            let _ = outline.draw(settings, &mut pen);
+        }
+        path = pen.path;
+    }
+    Some(path)
+}
+
+/// `clips` numbers the clip paths, so every one's id is unique in the
+/// document.
+fn write_cmds(out: &mut String, cmds: &[DrawCmd], clips: &mut usize) {
+    for cmd in cmds {
+        match cmd {
+            DrawCmd::Fill {
+                shape,
+                brush,
+                transform,
+            } => writeln!(
+                out,
+                r#"<path d="{}" fill="{}"/>"#,
+                svg_shape(shape, *transform),
+                css(brush)
+            )
+            .unwrap(),
+            DrawCmd::Stroke {
+                shape,
+                style,
+                brush,
+                transform,
+            } => {
+                let dashes = if style.dash_pattern.is_empty() {
+                    String::new()
+                } else {
+                    let pattern: Vec<String> =
+                        style.dash_pattern.iter().map(|dash| dash.to_string()).collect();
+                    format!(
+                        r#" stroke-dasharray="{}" stroke-dashoffset="{}""#,
+                        pattern.join(" "),
+                        style.dash_offset
+                    )
+                };
+                writeln!(
+                    out,
+                    r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}"{dashes} stroke-linecap="round" stroke-linejoin="round"/>"#,
+                    svg_shape(shape, *transform),
+                    css(brush),
+                    style.width
+                )
+                .unwrap();
+            }
+            DrawCmd::GlyphRun(run) => {
+                if let Some(path) = glyph_outlines(run) {
+                    writeln!(out, r#"<path d="{}" fill="{}"/>"#, path.to_svg(), css(&run.brush))
+                        .unwrap();
+                }
+            }
+            DrawCmd::Clip {
+                shape,
+                transform,
+                children,
+            } => {
+                *clips += 1;
+                let id = *clips;
+                writeln!(
+                    out,
+                    r#"<clipPath id="clip{id}"><path d="{}"/></clipPath>"#,
+                    svg_shape(shape, *transform)
+                )
+                .unwrap();
+                writeln!(out, r#"<g clip-path="url(#clip{id})">"#).unwrap();
+                write_cmds(out, children, clips);
+                writeln!(out, "</g>").unwrap();
+            }
+        }
+    }
+}
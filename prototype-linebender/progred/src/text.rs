@@ -0,0 +1,327 @@
+//! The canonical text store: a third on-disk form, for documents
+//! reviewed in git. Every part of the document has exactly one
+//! spelling and one place, so a file changes only where the document
+//! did — a one-field edit is a one-line diff:
+//!
+//! ```text
+//! progred text format 3
+//! library "shapes.progred" "fnv1a64:…"
+//! root @<id>
+//!
+//! cell <id>
+//!   name "point"
+//!   value {
+//!     "x": "1"
+//!     @<id>: [
+//!       0x00ff
+//!     ]
+//!   }
+//! ```
+//!
+//! Cells come in id order, a blank line before each; a name and a
+//! value each have their own line; compound values open a block with
+//! one field or element per line, records in label order. Links and
+//! cell labels are `@` and the id, strings are JSON string literals
+//! (escapes keep them on their line), blobs are `0x` and lowercase
+//! hex.
+//!
+//! Reads are strict in the repo's sense — parsable means canonical:
+//! a file is re-spelled after reading and refused at the first line
+//! that differs, so two files holding the same document are the same
+//! bytes, and hand edits that drift from the form are caught rather
+//! than silently normalized by the next save.
+
+use crate::raw::Document;
+use crate::store::LibraryRef;
+use progred_graph::{Atom, Cells, Label, Uuid, Value, hex_bytes, hex_string};
+
+pub const FORMAT: u32 = 3;
+
+/// The first line, before the format number; sniffed by the store.
+pub const HEADER: &str = "progred text format ";
+
+pub fn write(doc: &Document) -> String {
+    let mut out = format!("{HEADER}{FORMAT}\n");
+    for library in &doc.libraries {
+        out.push_str("library ");
+        string(&mut out, &library.path);
+        if let Some(hash) = &library.hash {
+            out.push(' ');
+            string(&mut out, hash);
+        }
+        out.push('\n');
+    }
+    if let Some(root) = &doc.root {
+        out.push_str("root ");
+        value(&mut out, root, 0);
+    }
+    let mut cells: Vec<_> = doc.cells.iter().collect();
+    cells.sort_by_key(|(cell, _)| **cell);
+    for (cell, entry) in cells {
+        out.push_str(&format!("\ncell {cell}\n"));
+        if let Some(name) = entry.name() {
+            out.push_str("  name ");
+            string(&mut out, name);
+            out.push('\n');
+        }
+        if let Some(held) = entry.value() {
+            out.push_str("  value ");
+            value(&mut out, held, 1);
+        }
+    }
+    out
+}
+
+fn string(out: &mut String, s: &str) {
+    out.push_str(&serde_json::to_string(s).expect("strings always serialize"));
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.extend(std::iter::repeat_n("  ", depth));
+}
+
+/// Writes `v` from the current column to the end of its last line.
+/// `depth` is the indentation of the line it starts on.
+fn value(out: &mut String, v: &Value, depth: usize) {
+    match v {
+        Value::Atom(Atom::Cell(cell)) => out.push_str(&format!("@{cell}")),
+        Value::Atom(Atom::String(s)) => string(out, s),
+        Value::Atom(Atom::Blob(bytes)) => out.push_str(&format!("0x{}", hex_string(bytes))),
+        Value::List(elements) if elements.is_empty() => out.push_str("[]"),
+        Value::Record(fields) if fields.is_empty() => out.push_str("{}"),
+        Value::List(elements) => {
+            out.push_str("[\n");
+            for element in elements.values() {
+                indent(out, depth + 1);
+                value(out, element, depth + 1);
+            }
+            indent(out, depth);
+            out.push(']');
+        }
+        Value::Record(fields) => {
+            out.push_str("{\n");
+            for (key, field) in fields {
+                indent(out, depth + 1);
+                match key {
+                    Label::Cell(cell) => out.push_str(&format!("@{cell}")),
+                    Label::String(s) => string(out, s),
+                }
+                out.push_str(": ");
+                value(out, field, depth + 1);
+            }
+            indent(out, depth);
+            out.push('}');
+        }
+    }
+    out.push('\n');
+}
+
+pub fn read(text: &str) -> Result<Document, String> {
+    let mut lines = Lines {
+        lines: text.lines().collect(),
+        at: 0,
+    };
+    let format = lines.next()?;
+    let format = format
+        .strip_prefix(HEADER)
+        .and_then(|format| format.parse::<u32>().ok())
+        .ok_or("not a text document")?;
+    if format != FORMAT {
+        return Err(format!("text format {format} (this build reads {FORMAT})"));
+    }
+    let mut doc = Document {
+        root: None,
+        cells: Cells::new(),
+        libraries: Vec::new(),
+    };
+    let mut cell = None;
+    while lines.at < lines.lines.len() {
+        let line = lines.next()?;
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            "" => {}
+            "library" => {
+                let (path, rest) = string_prefix(rest).map_err(|error| lines.error(&error))?;
+                let hash = match rest.strip_prefix(' ') {
+                    Some(rest) => Some(lines.string(rest)?),
+                    None => None,
+                };
+                doc.libraries.push(LibraryRef { path, hash });
+            }
+            "root" => doc.root = Some(lines.value(rest)?),
+            "cell" => cell = Some(lines.cell(rest)?),
+            "name" | "value" => {
+                let Some(cell) = cell else {
+                    return Err(lines.error(&format!("{keyword} outside a cell")));
+                };
+                if keyword == "name" {
+                    let name = lines.string(rest)?;
+                    doc.cells.set_name(cell, &name);
+                } else {
+                    let held = lines.value(rest)?;
+                    doc.cells.set_value(cell, held);
+                }
+            }
+            _ => return Err(lines.error(&format!("unknown line {keyword:?}"))),
+        }
+    }
+    if let Some(line) = first_difference(&write(&doc), text) {
+        return Err(format!("line {}: not in canonical form", line + 1));
+    }
+    Ok(doc)
+}
+
+/// The index of the first line where `a` and `b` part — where the
+/// shorter ends, when one is the other's prefix.
+fn first_difference(a: &str, b: &str) -> Option<usize> {
+    if a == b {
+        return None;
+    }
+    let differs = a.lines().zip(b.lines()).position(|(a, b)| a != b);
+    Some(differs.unwrap_or_else(|| a.lines().count().min(b.lines().count())))
+}
+
+/// A JSON string literal at the start of `s`, and what follows it.
+fn string_prefix(s: &str) -> Result<(String, &str), String> {
+    let mut stream = serde_json::Deserializer::from_str(s).into_iter::<String>();
+    let parsed = stream
+        .next()
+        .ok_or("expected a string")?
+        .map_err(|error| error.to_string())?;
+    Ok((parsed, &s[stream.byte_offset()..]))
+}
+
+/// The file's lines, trimmed of indentation as they're taken —
+/// blocks are delimited by their brackets, and the canonical check
+/// holds the indentation to its one spelling.
+struct Lines<'a> {
+    lines: Vec<&'a str>,
+    at: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        let line = self.lines.get(self.at).ok_or("file ends inside a value")?;
+        self.at += 1;
+        Ok(line.trim_start())
+    }
+
+    /// An error at the line last taken.
+    fn error(&self, error: &dyn std::fmt::Display) -> String {
+        format!("line {}: {error}", self.at)
+    }
+
+    fn string(&self, s: &str) -> Result<String, String> {
+        match string_prefix(s) {
+            Ok((parsed, "")) => Ok(parsed),
+            Ok(_) => Err(self.error(&"text after a string")),
+            Err(error) => Err(self.error(&error)),
+        }
+    }
+
+    fn cell(&self, s: &str) -> Result<Uuid, String> {
+        Uuid::parse_str(s).map_err(|error| self.error(&error))
+    }
+
+    /// A value whose first line's remainder is `head`, taking the
+    /// lines of its block when it opens one.
+    fn value(&mut self, head: &str) -> Result<Value, String> {
+        match head {
+            "[]" => Ok(Value::list([])),
+            "{}" => Ok(Value::record([])),
+            "[" => {
+                let mut elements = Vec::new();
+                loop {
+                    let line = self.next()?;
+                    if line == "]" {
+                        return Ok(Value::list(elements));
+                    }
+                    elements.push(self.value(line)?);
+                }
+            }
+            "{" => {
+                let mut fields = Vec::new();
+                loop {
+                    let line = self.next()?;
+                    if line == "}" {
+                        return Ok(Value::record(fields));
+                    }
+                    let (label, rest) = self.label(line)?;
+                    fields.push((label, self.value(rest)?));
+                }
+            }
+            _ => self.atom(head),
+        }
+    }
+
+    fn label<'s>(&self, line: &'s str) -> Result<(Label, &'s str), String> {
+        let (label, rest) = match line.strip_prefix('@') {
+            Some(rest) => {
+                let (cell, rest) = rest.split_once(": ").ok_or_else(|| self.error(&"no value"))?;
+                return Ok((Label::Cell(self.cell(cell)?), rest));
+            }
+            None => string_prefix(line).map_err(|error| self.error(&error))?,
+        };
+        let rest = rest.strip_prefix(": ").ok_or_else(|| self.error(&"no value"))?;
+        Ok((Label::String(label), rest))
+    }
+
+    fn atom(&self, s: &str) -> Result<Value, String> {
+        if let Some(cell) = s.strip_prefix('@') {
+            return self.cell(cell).map(Value::from);
+        }
+        if let Some(hex) = s.strip_prefix("0x") {
+            return hex_bytes(hex).map(Value::from).map_err(|error| self.error(&error));
+        }
+        self.string(s).map(Value::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use progred_graph::new_cell_id;
+
+    #[test]
+    fn one_field_edits_are_one_line_diffs() {
+        let mut doc = crate::raw::sample_document();
+        let part = new_cell_id();
+        doc.cells.set_name(part, "part \"quoted\"\nacross lines");
+        doc.cells.set_value(
+            part,
+            Value::record([
+                (Label::from("mesh"), Value::from(vec![0_u8, 255, 16])),
+                (Label::from(part), Value::list([Value::list([]), Value::record([])])),
+            ]),
+        );
+        doc.libraries.push(LibraryRef {
+            path: "shapes.progred".into(),
+            hash: Some("fnv1a64:0123".into()),
+        });
+        let before = write(&doc);
+        let read_back = read(&before).unwrap();
+        assert_eq!(write(&read_back), before);
+        assert_eq!(read_back.cells.name(part), doc.cells.name(part));
+        assert_eq!(read_back.cells.value(part), doc.cells.value(part));
+
+        // One changed field is one changed line.
+        let mut edited = doc.clone();
+        let mut fields = edited.cells.value(part).unwrap().as_record().unwrap().clone();
+        fields.insert(Label::from("mesh"), Value::from(vec![1_u8]));
+        edited.cells.set_value(part, Value::Record(fields));
+        let after = write(&edited);
+        let changed: Vec<_> = before
+            .lines()
+            .zip(after.lines())
+            .filter(|(old, new)| old != new)
+            .collect();
+        assert_eq!(before.lines().count(), after.lines().count());
+        assert_eq!(changed, [("    \"mesh\": 0x00ff10", "    \"mesh\": 0x01")]);
+
+        // Anything but the one spelling refuses, at its line.
+        let drifted = before.replacen("  name", "    name", 1);
+        assert!(read(&drifted).unwrap_err().contains("not in canonical form"));
+        assert!(read(&format!("{before}\n")).is_err());
+        assert!(read("progred text format 9\n").unwrap_err().contains("format 9"));
+    }
+}
@@ -6,9 +6,15 @@
 //! having been minted, which is the honest state. Cells are the only
 //! mutable state; values are persistent, so clones are O(1)
 //! structural sharing.
+//!
+//! The table is an ordered map, for the one thing a hash map can't
+//! do structurally: say what changed between a table and its edited
+//! successor in the size of the edit (see [`Cells::changed`]), which
+//! is what lets derived indexes follow the document cheaply.
 
 use crate::value::{CellId, Value};
-use im::HashMap;
+use im::OrdMap;
+use im::ordmap::DiffItem;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -45,7 +51,7 @@ impl Cell {
 
 #[derive(Debug, Clone, Default)]
 pub struct Cells {
-    data: HashMap<CellId, Cell>,
+    data: OrdMap<CellId, Cell>,
 }
 
 impl Cells {
@@ -139,6 +145,17 @@ impl Cells {
     pub fn ptr_eq(&self, other: &Self) -> bool {
         self.data.ptr_eq(&other.data)
     }
+
+    /// The cells whose entries differ from `before`'s — added,
+    /// removed, or restated — in id order. Subtrees the two tables
+    /// share are skipped unread, so a table compared with its own
+    /// edited successor costs the edit, not the table.
+    pub fn changed<'a>(&'a self, before: &'a Cells) -> impl Iterator<Item = CellId> + 'a {
+        before.data.diff(&self.data).map(|item| match item {
+            DiffItem::Add(cell, _) | DiffItem::Remove(cell, _) => *cell,
+            DiffItem::Update { new: (cell, _), .. } => *cell,
+        })
+    }
 }
 
 /// The file form: `{"name": …, "value": …}` with absent halves
@@ -261,6 +278,25 @@ mod tests {
         assert!(cells.ptr_eq(&before));
     }
 
+    #[test]
+    fn changed_names_exactly_the_restated_cells() {
+        let (kept, renamed, added) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let mut before = Cells::new();
+        before.set_name(kept, "kept");
+        before.set_name(renamed, "old");
+        let mut after = before.clone();
+        after.set_name(renamed, "new");
+        after.set_value(added, Value::from("v"));
+        after.set_name(kept, "kept");
+
+        let changed: Vec<CellId> = after.changed(&before).collect();
+        let mut expected = vec![renamed, added];
+        expected.sort();
+        assert_eq!(changed, expected);
+        assert_eq!(before.changed(&after).count(), 2);
+        assert_eq!(after.changed(&after).count(), 0);
+    }
+
     #[test]
     fn merge_is_left_biased_per_entry() {
         let shared = new_cell_id();
@@ -0,0 +1,283 @@
+//! Structural diff: what changed from one table (and root) to
+//! another, said in the structure's own terms rather than a file's
+//! lines. Cells are added, removed, or changed — renamed, revalued,
+//! or both — and a revalued cell's edits go down to the field and
+//! the element: records compare per label, lists align their
+//! elements by content exactly as the merge does (see
+//! [`crate::merge`]), so an insertion reads as one insertion and not
+//! as every later element changing.
+//!
+//! Edits are located by SPINE — Key and Element steps into the owning
+//! value — in the NEW value's terms: an element step carries the
+//! position it has in the new list, which is what a view of the new
+//! document is drawn from. What's gone has no new position, so
+//! removals are reported at their container, a removed element
+//! anchored after the surviving element it followed.
+
+use crate::cells::{Cell, Cells};
+use crate::merge::{Side, matching};
+use crate::position::Position;
+use crate::value::{CellId, Label, Step, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The value at the spine is new.
+    Added(Value),
+    /// The value at the spine is gone — a root or cell value only;
+    /// anything inside a value is removed from its container.
+    Removed(Value),
+    /// The value at the spine took the place of another, of another
+    /// kind or a different atom.
+    Replaced { from: Value, to: Value },
+    /// A field of the record at the spine is gone.
+    FieldRemoved(Label, Value),
+    /// An element of the list at the spine is gone; it followed the
+    /// surviving element at `after`, or led the list when None.
+    ElementRemoved { after: Option<Position>, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub spine: Vec<Step>,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellDiff {
+    Added,
+    Removed(Cell),
+    /// Said differently: the names before and after when they differ,
+    /// and the value's edits (empty when only the name changed).
+    Changed {
+        renamed: Option<(Option<String>, Option<String>)>,
+        revalued: Vec<Edit>,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Diff {
+    pub root: Vec<Edit>,
+    /// Changed cells only, in id order.
+    pub cells: BTreeMap<CellId, CellDiff>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty() && self.cells.is_empty()
+    }
+}
+
+pub fn diff(from: Side, to: Side) -> Diff {
+    let mut root = Vec::new();
+    diff_value(from.root, to.root, &mut Vec::new(), &mut root);
+    let mut cells = BTreeMap::new();
+    // A table sharing its root with the other changed nothing.
+    if !from.cells.ptr_eq(to.cells) {
+        let ids: BTreeSet<CellId> =
+            from.cells.cells().chain(to.cells.cells()).copied().collect();
+        for cell in ids {
+            if let Some(change) = diff_cell(from.cells, to.cells, cell) {
+                cells.insert(cell, change);
+            }
+        }
+    }
+    Diff { root, cells }
+}
+
+fn diff_cell(from: &Cells, to: &Cells, cell: CellId) -> Option<CellDiff> {
+    match (from.entry(cell), to.entry(cell)) {
+        (None, None) => None,
+        (None, Some(_)) => Some(CellDiff::Added),
+        (Some(gone), None) => Some(CellDiff::Removed(gone.clone())),
+        (Some(before), Some(after)) if before == after => None,
+        (Some(before), Some(after)) => {
+            let renamed = (before.name() != after.name())
+                .then(|| (before.name().map(str::to_owned), after.name().map(str::to_owned)));
+            let mut revalued = Vec::new();
+            diff_value(before.value(), after.value(), &mut Vec::new(), &mut revalued);
+            Some(CellDiff::Changed { renamed, revalued })
+        }
+    }
+}
+
+fn diff_value(
+    from: Option<&Value>,
+    to: Option<&Value>,
+    spine: &mut Vec<Step>,
+    out: &mut Vec<Edit>,
+) {
+    let edit = |out: &mut Vec<Edit>, spine: &[Step], change| {
+        out.push(Edit {
+            spine: spine.to_vec(),
+            change,
+        })
+    };
+    match (from, to) {
+        (None, None) => {}
+        _ if from == to => {}
+        (None, Some(to)) => edit(out, spine, Change::Added(to.clone())),
+        (Some(from), None) => edit(out, spine, Change::Removed(from.clone())),
+        (Some(Value::Record(before)), Some(Value::Record(after))) => {
+            let labels: BTreeSet<&Label> = before.keys().chain(after.keys()).collect();
+            for label in labels {
+                match (before.get(label), after.get(label)) {
+                    (Some(gone), None) => {
+                        edit(out, spine, Change::FieldRemoved(label.clone(), gone.clone()))
+                    }
+                    (before, after) => {
+                        spine.push(Step::Key(label.clone()));
+                        diff_value(before, after, spine, out);
+                        spine.pop();
+                    }
+                }
+            }
+        }
+        (Some(Value::List(before)), Some(Value::List(after))) => {
+            let before: Vec<&Value> = before.values().collect();
+            let after: Vec<(&Position, &Value)> = after.iter().collect();
+            diff_list(&before, &after, spine, out);
+        }
+        (Some(from), Some(to)) => edit(
+            out,
+            spine,
+            Change::Replaced {
+                from: from.clone(),
+                to: to.clone(),
+            },
+        ),
+    }
+}
+
+/// Walks the content alignment from fixed point to fixed point. A
+/// run replaced by one of the same length is taken as edited in
+/// place, pairwise, so a changed field inside an element reads as
+/// that field; any other run is its removals and its additions.
+fn diff_list(
+    before: &[&Value],
+    after: &[(&Position, &Value)],
+    spine: &mut Vec<Step>,
+    out: &mut Vec<Edit>,
+) {
+    let values: Vec<&Value> = after.iter().map(|(_, value)| *value).collect();
+    let aligned = matching(before, &values);
+    let (mut b, mut a) = (0, 0);
+    loop {
+        let fixed = (b..before.len()).find_map(|i| Some((i, aligned[i]?)));
+        let (end_b, end_a) = fixed.unwrap_or((before.len(), after.len()));
+        if end_b - b == end_a - a {
+            for (old, (position, new)) in before[b..end_b].iter().zip(&after[a..end_a]) {
+                spine.push(Step::Element((*position).clone()));
+                diff_value(Some(old), Some(new), spine, out);
+                spine.pop();
+            }
+        } else {
+            let anchor = a.checked_sub(1).map(|previous| after[previous].0.clone());
+            for old in &before[b..end_b] {
+                out.push(Edit {
+                    spine: spine.clone(),
+                    change: Change::ElementRemoved {
+                        after: anchor.clone(),
+                        value: (*old).clone(),
+                    },
+                });
+            }
+            for (position, new) in &after[a..end_a] {
+                spine.push(Step::Element((*position).clone()));
+                out.push(Edit {
+                    spine: spine.clone(),
+                    change: Change::Added((*new).clone()),
+                });
+                spine.pop();
+            }
+        }
+        let Some((i, j)) = fixed else {
+            return;
+        };
+        (b, a) = (i + 1, j + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::new_cell_id;
+
+    fn strings(items: &[&str]) -> Value {
+        Value::list(items.iter().map(|item| Value::from(*item)))
+    }
+
+    #[test]
+    fn reports_cells_fields_and_aligned_elements() {
+        let (kept, gone, added) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let mut before = Cells::new();
+        before.set_name(kept, "kept");
+        before.set_value(
+            kept,
+            Value::record([
+                (Label::from("dropped"), Value::from("x")),
+                (Label::from("tags"), strings(&["a", "b", "c"])),
+                (Label::from("size"), Value::from("1")),
+            ]),
+        );
+        before.set_name(gone, "gone");
+        let mut after = Cells::new();
+        after.set_name(kept, "renamed");
+        let tags = strings(&["z", "a", "c"]);
+        after.set_value(
+            kept,
+            Value::record([
+                (Label::from("tags"), tags.clone()),
+                (Label::from("size"), Value::list([])),
+            ]),
+        );
+        after.set_value(added, Value::from("new"));
+        let root = Value::from(kept);
+        let side = |cells| Side {
+            cells,
+            root: Some(&root),
+        };
+
+        let changes = diff(side(&before), side(&after));
+        assert!(changes.root.is_empty());
+        assert_eq!(changes.cells.len(), 3);
+        assert_eq!(changes.cells[&added], CellDiff::Added);
+        assert_eq!(changes.cells[&gone], CellDiff::Removed(before.entry(gone).unwrap().clone()));
+
+        // `z` inserted at the head and `b` dropped: one addition and
+        // one removal anchored after `a`, nothing said of `a` or `c`.
+        let positions: Vec<Position> = tags.as_list().unwrap().keys().cloned().collect();
+        let key = |label: &str| Step::Key(Label::from(label));
+        assert_eq!(
+            changes.cells[&kept],
+            CellDiff::Changed {
+                renamed: Some((Some("kept".into()), Some("renamed".into()))),
+                revalued: vec![
+                    Edit {
+                        spine: Vec::new(),
+                        change: Change::FieldRemoved(Label::from("dropped"), Value::from("x")),
+                    },
+                    Edit {
+                        spine: vec![key("size")],
+                        change: Change::Replaced {
+                            from: Value::from("1"),
+                            to: Value::list([]),
+                        },
+                    },
+                    Edit {
+                        spine: vec![key("tags"), Step::Element(positions[0].clone())],
+                        change: Change::Added(Value::from("z")),
+                    },
+                    Edit {
+                        spine: vec![key("tags")],
+                        change: Change::ElementRemoved {
+                            after: Some(positions[1].clone()),
+                            value: Value::from("b"),
+                        },
+                    },
+                ],
+            }
+        );
+        assert!(diff(side(&after), side(&after)).is_empty());
+    }
+}
@@ -1,4 +1,7 @@
 mod cells;
+pub mod diff;
+pub mod mentions;
+pub mod merge;
 pub mod position;
 pub mod spine;
 mod value;
@@ -6,4 +9,4 @@ mod value;
 pub use cells::{Cell, Cells};
 pub use position::Position;
 pub use uuid::Uuid;
-pub use value::{Atom, CellId, Label, Step, Value, hex_string, new_cell_id};
+pub use value::{Atom, CellId, Label, Step, Value, hex_bytes, hex_string, new_cell_id};
@@ -0,0 +1,194 @@
+//! The reverse-mention index: for each cell, every place its id is
+//! stated — as a value (a link), or as a record label. Identity is
+//! not naming, so "where is this used" is a question about ids alone,
+//! and the answer is this index read backwards.
+//!
+//! A mention is located the way diffs are: by OWNER — the root, or
+//! the cell whose value holds it — and the value SPINE inside it (see
+//! [`crate::diff`]). The index follows the document by re-indexing
+//! only the owners that changed: the root when it differs, and the
+//! cells [`Cells::changed`] reports, each dropping what its old value
+//! said and adding what its new one says. A sync after one edit costs
+//! that edit, however large the table.
+
+use crate::cells::Cells;
+use crate::value::{Atom, CellId, Label, Step, Value};
+use std::collections::HashMap;
+
+/// Whose value a spot is in: the document's root, or a cell's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Owner {
+    Root,
+    Cell(CellId),
+}
+
+/// How a spot mentions the cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// The value at the spine is a link to it.
+    Value,
+    /// The record at the spine has a field labelled by it.
+    Label,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mention {
+    pub owner: Owner,
+    pub spine: Vec<Step>,
+    pub role: Role,
+}
+
+/// One cell's mentions: by owner, each owner's in spine order.
+type Spots = HashMap<Owner, Vec<(Vec<Step>, Role)>>;
+
+/// The index, with the table and root it was last synced to.
+#[derive(Clone, Debug, Default)]
+pub struct Mentions {
+    cells: Cells,
+    root: Option<Value>,
+    of: HashMap<CellId, Spots>,
+}
+
+impl Mentions {
+    pub fn new(cells: &Cells, root: Option<&Value>) -> Self {
+        let mut mentions = Self::default();
+        mentions.sync(cells, root);
+        mentions
+    }
+
+    /// Brings the index up to `cells` and `root`.
+    pub fn sync(&mut self, cells: &Cells, root: Option<&Value>) {
+        if self.root.as_ref() != root {
+            let old = std::mem::replace(&mut self.root, root.cloned());
+            self.reindex(Owner::Root, old.as_ref(), root);
+        }
+        if self.cells.ptr_eq(cells) {
+            return;
+        }
+        let old = std::mem::replace(&mut self.cells, cells.clone());
+        for cell in cells.changed(&old) {
+            self.reindex(Owner::Cell(cell), old.value(cell), cells.value(cell));
+        }
+    }
+
+    fn reindex(&mut self, owner: Owner, old: Option<&Value>, new: Option<&Value>) {
+        if let Some(old) = old {
+            let mut gone = Vec::new();
+            walk(old, &mut Vec::new(), &mut |cell, _, _| gone.push(cell));
+            for cell in gone {
+                if let Some(owners) = self.of.get_mut(&cell) {
+                    owners.remove(&owner);
+                    if owners.is_empty() {
+                        self.of.remove(&cell);
+                    }
+                }
+            }
+        }
+        if let Some(new) = new {
+            walk(new, &mut Vec::new(), &mut |cell, spine, role| {
+                let spots = self.of.entry(cell).or_default().entry(owner).or_default();
+                spots.push((spine.to_vec(), role));
+            });
+        }
+    }
+
+    /// Every mention of `cell`: the root's first, then the cells' in
+    /// id order, each owner's in the order its value states them.
+    pub fn of(&self, cell: CellId) -> Vec<Mention> {
+        let Some(owners) = self.of.get(&cell) else {
+            return Vec::new();
+        };
+        let mut owners: Vec<_> = owners.iter().collect();
+        owners.sort_by_key(|(owner, _)| **owner);
+        owners
+            .into_iter()
+            .flat_map(|(owner, spots)| {
+                spots.iter().map(|(spine, role)| Mention {
+                    owner: *owner,
+                    spine: spine.clone(),
+                    role: *role,
+                })
+            })
+            .collect()
+    }
+}
+
+fn walk(value: &Value, spine: &mut Vec<Step>, found: &mut impl FnMut(CellId, &[Step], Role)) {
+    match value {
+        Value::Atom(Atom::Cell(cell)) => found(*cell, spine, Role::Value),
+        Value::Atom(_) => {}
+        Value::List(elements) => {
+            for (position, item) in elements {
+                spine.push(Step::Element(position.clone()));
+                walk(item, spine, found);
+                spine.pop();
+            }
+        }
+        Value::Record(fields) => {
+            for (label, item) in fields {
+                if let Label::Cell(cell) = label {
+                    found(*cell, spine, Role::Label);
+                }
+                spine.push(Step::Key(label.clone()));
+                walk(item, spine, found);
+                spine.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::new_cell_id;
+
+    #[test]
+    fn follows_edits_owner_by_owner() {
+        let (target, holder, field) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let mut cells = Cells::new();
+        cells.set_value(
+            holder,
+            Value::record([
+                (Label::from(target), Value::from("as label")),
+                (Label::from(field), Value::list([Value::from(target)])),
+            ]),
+        );
+        let root = Value::from(target);
+        let mut mentions = Mentions::new(&cells, Some(&root));
+
+        let record = cells.value(holder).unwrap().as_record().unwrap();
+        let list = record.get(&Label::from(field)).and_then(Value::as_list).unwrap();
+        let position = list.keys().next().unwrap().clone();
+        let found = mentions.of(target);
+        assert_eq!(found.len(), 3);
+        assert_eq!(
+            found[0],
+            Mention {
+                owner: Owner::Root,
+                spine: Vec::new(),
+                role: Role::Value,
+            }
+        );
+        // The holder's two, in its labels' order — ids, so either way.
+        assert!(found[1..].contains(&Mention {
+            owner: Owner::Cell(holder),
+            spine: Vec::new(),
+            role: Role::Label,
+        }));
+        assert!(found[1..].contains(&Mention {
+            owner: Owner::Cell(holder),
+            spine: vec![Step::Key(Label::from(field)), Step::Element(position)],
+            role: Role::Value,
+        }));
+        assert_eq!(mentions.of(field).len(), 1);
+
+        // Re-valuing the holder drops what it said; the root's
+        // mention stands untouched.
+        cells.set_value(holder, Value::from("plain"));
+        mentions.sync(&cells, Some(&root));
+        assert_eq!(mentions.of(target).len(), 1);
+        assert!(mentions.of(field).is_empty());
+        mentions.sync(&cells, None);
+        assert!(mentions.of(target).is_empty());
+    }
+}
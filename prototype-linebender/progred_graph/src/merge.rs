@@ -0,0 +1,458 @@
+//! Three-way structural merge: two tables edited apart from a common
+//! base, reconciled the way their structure allows rather than the
+//! way their file's lines happen to fall. Cells merge entry by entry
+//! — name and value separately — and values merge where they are
+//! compound: records per label, lists by aligning their elements.
+//!
+//! At every level the rule is the usual one: a side that didn't
+//! change yields to the side that did, and two sides that changed
+//! alike agree. Only two DIFFERENT changes to the same atom, label,
+//! or run of elements conflict. A conflict keeps OURS in the merged
+//! result, so the result is always a whole document, and is reported
+//! in a structured list beside it for whoever resolves it.
+//!
+//! Lists have no identity to merge by — positions are session-only,
+//! minted at load — so elements align by CONTENT: each side is
+//! matched against the base by longest common subsequence, elements
+//! all three hold in the same order are the fixed points, and the
+//! runs between them merge as units (diff3's chunks). A run that one
+//! side replaced element-for-element with the other's also changed
+//! merges recursively, so two edits to different fields of the same
+//! element both land. Two sides inserting at the same point keep
+//! both, ours first: in a list, order is the only thing that could
+//! conflict, and a guessed order beats a refusal for additions.
+//! Alignment is quadratic in the length of the changed middle (a
+//! shared prefix and suffix are trimmed first), which the lists a
+//! document holds by hand stay far below.
+
+use crate::cells::Cells;
+use crate::value::{CellId, Label, Value};
+use im::OrdMap;
+use std::collections::BTreeSet;
+
+/// One of the three tables, with the root it hangs from.
+#[derive(Clone, Copy)]
+pub struct Side<'a> {
+    pub cells: &'a Cells,
+    pub root: Option<&'a Value>,
+}
+
+/// What a conflict is in: the root, or one half of a cell's entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    Root,
+    Name(CellId),
+    Value(CellId),
+}
+
+/// A step from a part down to a conflict. Elements count in the
+/// MERGED list — positions don't survive a merge, and are not the
+/// same across sides to begin with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Field(Label),
+    Element(usize),
+}
+
+/// Two different changes to one place. A name conflict carries the
+/// names as string values; a run of list elements carries each side's
+/// run as a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub part: Part,
+    pub path: Vec<Segment>,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+pub struct Merged {
+    pub cells: Cells,
+    pub root: Option<Value>,
+    pub conflicts: Vec<Conflict>,
+}
+
+pub fn merge(base: Side, ours: Side, theirs: Side) -> Merged {
+    let mut conflicts = Vec::new();
+    let root = merge_value(
+        base.root,
+        ours.root,
+        theirs.root,
+        &mut At::new(Part::Root, &mut conflicts),
+    );
+    let ids: BTreeSet<CellId> = base
+        .cells
+        .cells()
+        .chain(ours.cells.cells())
+        .chain(theirs.cells.cells())
+        .copied()
+        .collect();
+    let mut cells = Cells::new();
+    for cell in ids {
+        let names = (
+            base.cells.name(cell),
+            ours.cells.name(cell),
+            theirs.cells.name(cell),
+        );
+        let name = match names {
+            (base, ours, theirs) if ours == theirs || base == theirs => ours,
+            (base, ours, theirs) if base == ours => theirs,
+            (base, ours, theirs) => {
+                conflicts.push(Conflict {
+                    part: Part::Name(cell),
+                    path: Vec::new(),
+                    base: base.map(Value::from),
+                    ours: ours.map(Value::from),
+                    theirs: theirs.map(Value::from),
+                });
+                ours
+            }
+        };
+        let value = merge_value(
+            base.cells.value(cell),
+            ours.cells.value(cell),
+            theirs.cells.value(cell),
+            &mut At::new(Part::Value(cell), &mut conflicts),
+        );
+        if let Some(name) = name {
+            cells.set_name(cell, name);
+        }
+        if let Some(value) = value {
+            cells.set_value(cell, value);
+        }
+    }
+    Merged {
+        cells,
+        root,
+        conflicts,
+    }
+}
+
+/// Where the merge is, and where its conflicts go.
+struct At<'a> {
+    part: Part,
+    path: Vec<Segment>,
+    conflicts: &'a mut Vec<Conflict>,
+}
+
+impl<'a> At<'a> {
+    fn new(part: Part, conflicts: &'a mut Vec<Conflict>) -> Self {
+        At {
+            part,
+            path: Vec::new(),
+            conflicts,
+        }
+    }
+
+    fn conflict(&mut self, base: Option<Value>, ours: Option<Value>, theirs: Option<Value>) {
+        self.conflicts.push(Conflict {
+            part: self.part.clone(),
+            path: self.path.clone(),
+            base,
+            ours,
+            theirs,
+        });
+    }
+}
+
+fn merge_value(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    at: &mut At,
+) -> Option<Value> {
+    if ours == theirs || base == theirs {
+        return ours.cloned();
+    }
+    if base == ours {
+        return theirs.cloned();
+    }
+    match (ours, theirs) {
+        (Some(Value::Record(ours)), Some(Value::Record(theirs))) => {
+            // A base of another kind (or none) states no fields: both
+            // sides' fields are additions.
+            let none = OrdMap::new();
+            let base = base.and_then(Value::as_record).unwrap_or(&none);
+            let labels: BTreeSet<&Label> =
+                base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+            let mut fields = OrdMap::new();
+            for label in labels {
+                at.path.push(Segment::Field(label.clone()));
+                let field = merge_value(base.get(label), ours.get(label), theirs.get(label), at);
+                at.path.pop();
+                if let Some(field) = field {
+                    fields.insert(label.clone(), field);
+                }
+            }
+            Some(Value::Record(fields))
+        }
+        (Some(Value::List(ours)), Some(Value::List(theirs))) => {
+            let base: Vec<&Value> = base
+                .and_then(Value::as_list)
+                .map(|base| base.values().collect())
+                .unwrap_or_default();
+            let ours: Vec<&Value> = ours.values().collect();
+            let theirs: Vec<&Value> = theirs.values().collect();
+            Some(Value::list(merge_list(&base, &ours, &theirs, at)))
+        }
+        _ => {
+            at.conflict(base.cloned(), ours.cloned(), theirs.cloned());
+            ours.cloned()
+        }
+    }
+}
+
+/// diff3 over content-aligned elements: walks from fixed point to
+/// fixed point, merging the run before each.
+fn merge_list(base: &[&Value], ours: &[&Value], theirs: &[&Value], at: &mut At) -> Vec<Value> {
+    let to_ours = matching(base, ours);
+    let to_theirs = matching(base, theirs);
+    let mut merged = Vec::new();
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    loop {
+        let fixed = (b..base.len()).find_map(|i| Some((i, to_ours[i]?, to_theirs[i]?)));
+        let (end_b, end_o, end_t) = fixed.unwrap_or((base.len(), ours.len(), theirs.len()));
+        merge_run(
+            &base[b..end_b],
+            &ours[o..end_o],
+            &theirs[t..end_t],
+            &mut merged,
+            at,
+        );
+        let Some((i, j, k)) = fixed else {
+            return merged;
+        };
+        merged.push(ours[j].clone());
+        (b, o, t) = (i + 1, j + 1, k + 1);
+    }
+}
+
+fn merge_run(
+    base: &[&Value],
+    ours: &[&Value],
+    theirs: &[&Value],
+    merged: &mut Vec<Value>,
+    at: &mut At,
+) {
+    let owned = |run: &[&Value]| run.iter().map(|value| (*value).clone()).collect::<Vec<_>>();
+    if ours == theirs || base == theirs {
+        merged.extend(owned(ours));
+    } else if base == ours {
+        merged.extend(owned(theirs));
+    } else if base.is_empty() {
+        merged.extend(owned(ours));
+        merged.extend(owned(theirs));
+    } else if let ([base], [ours], [theirs]) = (base, ours, theirs) {
+        at.path.push(Segment::Element(merged.len()));
+        let element = merge_value(Some(base), Some(ours), Some(theirs), at);
+        at.path.pop();
+        merged.extend(element);
+    } else {
+        at.path.push(Segment::Element(merged.len()));
+        at.conflict(
+            Some(Value::list(owned(base))),
+            Some(Value::list(owned(ours))),
+            Some(Value::list(owned(theirs))),
+        );
+        at.path.pop();
+        merged.extend(owned(ours));
+    }
+}
+
+/// For each element of `from`, the index of the element of `to` it
+/// aligns with under a longest common subsequence, if any.
+pub(crate) fn matching(from: &[&Value], to: &[&Value]) -> Vec<Option<usize>> {
+    let mut aligned = vec![None; from.len()];
+    let prefix = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    for (i, slot) in aligned.iter_mut().enumerate().take(prefix) {
+        *slot = Some(i);
+    }
+    for k in 1..=suffix {
+        aligned[from.len() - k] = Some(to.len() - k);
+    }
+    let from_mid = &from[prefix..from.len() - suffix];
+    let to_mid = &to[prefix..to.len() - suffix];
+    let (n, m) = (from_mid.len(), to_mid.len());
+    // lengths[i * (m + 1) + j]: the LCS of from_mid[i..] and to_mid[j..].
+    let mut lengths = vec![0_u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * (m + 1) + j] = if from_mid[i] == to_mid[j] {
+                lengths[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if from_mid[i] == to_mid[j] {
+            aligned[prefix + i] = Some(prefix + j);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::new_cell_id;
+
+    fn side<'a>(cells: &'a Cells, root: &'a Value) -> Side<'a> {
+        Side {
+            cells,
+            root: Some(root),
+        }
+    }
+
+    fn strings(items: &[&str]) -> Value {
+        Value::list(items.iter().map(|item| Value::from(*item)))
+    }
+
+    fn point(x: &str, y: &str) -> Value {
+        Value::record([
+            (Label::from("x"), Value::from(x)),
+            (Label::from("y"), Value::from(y)),
+        ])
+    }
+
+    #[test]
+    fn independent_edits_all_land() {
+        let (shape, gone, added) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let root = Value::from(shape);
+        let mut base = Cells::new();
+        base.set_name(shape, "shape");
+        base.set_value(
+            shape,
+            Value::record([
+                (Label::from("tags"), strings(&["a", "b", "c"])),
+                (Label::from("corner"), point("0", "0")),
+                (Label::from("points"), Value::list([point("1", "1")])),
+            ]),
+        );
+        base.set_name(gone, "scrap");
+
+        // Ours renames, inserts a tag, moves x, and edits one field of
+        // the point; theirs appends a tag, moves y, edits the point's
+        // other field, drops a cell and mints one.
+        let mut ours = base.clone();
+        ours.set_name(shape, "polygon");
+        ours.set_value(
+            shape,
+            Value::record([
+                (Label::from("tags"), strings(&["a", "x", "b", "c"])),
+                (Label::from("corner"), point("5", "0")),
+                (Label::from("points"), Value::list([point("2", "1")])),
+            ]),
+        );
+        let mut theirs = base.clone();
+        theirs.set_value(
+            shape,
+            Value::record([
+                (Label::from("tags"), strings(&["a", "b", "c", "y"])),
+                (Label::from("corner"), point("0", "7")),
+                (Label::from("points"), Value::list([point("1", "3")])),
+            ]),
+        );
+        theirs.remove(gone);
+        theirs.set_value(added, Value::from("new"));
+
+        let merged = merge(
+            side(&base, &root),
+            side(&ours, &root),
+            side(&theirs, &root),
+        );
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.root, Some(root));
+        assert_eq!(merged.cells.name(shape), Some("polygon"));
+        assert_eq!(
+            merged.cells.value(shape),
+            Some(&Value::record([
+                (Label::from("tags"), strings(&["a", "x", "b", "c", "y"])),
+                (Label::from("corner"), point("5", "7")),
+                (Label::from("points"), Value::list([point("2", "3")])),
+            ]))
+        );
+        assert_eq!(merged.cells.entry(gone), None);
+        assert_eq!(merged.cells.value(added), Some(&Value::from("new")));
+    }
+
+    #[test]
+    fn different_changes_to_one_place_conflict_keeping_ours() {
+        let cell = new_cell_id();
+        let root = Value::from(cell);
+        let mut base = Cells::new();
+        base.set_name(cell, "n");
+        base.set_value(
+            cell,
+            Value::record([
+                (Label::from("x"), Value::from("0")),
+                (Label::from("tags"), strings(&["a", "b", "c", "d"])),
+            ]),
+        );
+        let mut ours = base.clone();
+        ours.set_name(cell, "mine");
+        ours.set_value(
+            cell,
+            Value::record([
+                (Label::from("x"), Value::from("1")),
+                (Label::from("tags"), strings(&["a", "p", "q", "d"])),
+            ]),
+        );
+        let mut theirs = base.clone();
+        theirs.set_name(cell, "yours");
+        theirs.set_value(
+            cell,
+            Value::record([
+                (Label::from("x"), Value::from("2")),
+                (Label::from("tags"), strings(&["a", "r", "d"])),
+            ]),
+        );
+
+        let merged = merge(
+            side(&base, &root),
+            side(&ours, &root),
+            side(&theirs, &root),
+        );
+        assert_eq!(merged.cells.name(cell), Some("mine"));
+        assert_eq!(merged.cells.value(cell), ours.value(cell));
+        assert_eq!(
+            merged.conflicts,
+            [
+                Conflict {
+                    part: Part::Name(cell),
+                    path: Vec::new(),
+                    base: Some(Value::from("n")),
+                    ours: Some(Value::from("mine")),
+                    theirs: Some(Value::from("yours")),
+                },
+                Conflict {
+                    part: Part::Value(cell),
+                    path: vec![Segment::Field(Label::from("tags")), Segment::Element(1)],
+                    base: Some(strings(&["b", "c"])),
+                    ours: Some(strings(&["p", "q"])),
+                    theirs: Some(strings(&["r"])),
+                },
+                Conflict {
+                    part: Part::Value(cell),
+                    path: vec![Segment::Field(Label::from("x"))],
+                    base: Some(Value::from("0")),
+                    ours: Some(Value::from("1")),
+                    theirs: Some(Value::from("2")),
+                },
+            ]
+        );
+    }
+}
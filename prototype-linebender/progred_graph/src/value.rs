@@ -160,6 +160,35 @@ impl Value {
             _ => None,
         }
     }
+
+    /// The same value with every mention of a cell — links and cell
+    /// labels alike — rewritten through `f`. Positions survive, so a
+    /// rewritten list keeps its elements' identities; labels that
+    /// collide after the rewrite keep the last field in label order.
+    pub fn map_cells(&self, f: &mut impl FnMut(CellId) -> CellId) -> Value {
+        match self {
+            Value::Atom(Atom::Cell(cell)) => Value::from(f(*cell)),
+            Value::Atom(_) => self.clone(),
+            Value::List(elements) => Value::List(
+                elements
+                    .iter()
+                    .map(|(position, element)| (position.clone(), element.map_cells(f)))
+                    .collect(),
+            ),
+            Value::Record(fields) => Value::Record(
+                fields
+                    .iter()
+                    .map(|(label, field)| {
+                        let label = match label {
+                            Label::Cell(cell) => Label::Cell(f(*cell)),
+                            Label::String(_) => label.clone(),
+                        };
+                        (label, field.map_cells(f))
+                    })
+                    .collect(),
+            ),
+        }
+    }
 }
 
 impl PartialEq for Value {
@@ -267,7 +296,7 @@ pub fn hex_string(bytes: &[u8]) -> String {
 
 /// Strict reads: lowercase pairs only, so every blob has exactly one
 /// spelled form.
-fn hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+pub fn hex_bytes(s: &str) -> Result<Vec<u8>, String> {
     let digit = |c: u8| match c {
         b'0'..=b'9' => Ok(c - b'0'),
         b'a'..=b'f' => Ok(c - b'a' + 10),
@@ -571,6 +600,27 @@ mod tests {
         assert!(serde_json::from_str::<Label>(r#"{"number":1.0}"#).is_err());
     }
 
+    #[test]
+    fn map_cells_rewrites_links_and_labels_keeping_positions() {
+        let (a, b, c) = (new_cell_id(), new_cell_id(), new_cell_id());
+        let value = Value::record([
+            (Label::from(a), Value::list([Value::from(a), Value::from("a")])),
+            (Label::from("k"), Value::from(c)),
+        ]);
+        let mapped = value.map_cells(&mut |cell| if cell == a { b } else { cell });
+        assert_eq!(
+            mapped,
+            Value::record([
+                (Label::from(b), Value::list([Value::from(b), Value::from("a")])),
+                (Label::from("k"), Value::from(c)),
+            ])
+        );
+        // The list under the rewritten label kept its positions.
+        let before = value.as_record().unwrap()[&Label::from(a)].as_list().unwrap();
+        let after = mapped.as_record().unwrap()[&Label::from(b)].as_list().unwrap();
+        assert!(before.keys().eq(after.keys()));
+    }
+
     #[test]
     fn spread_positions_carry_list_construction() {
         let list = Value::list((0..100).map(|i| Value::from(i.to_string())));